@@ -1,74 +1,697 @@
-use crate::mdns::mdns_message::{MDNSMessage, MDNSMessageReceivedEvent};
-use pnet::datalink::{channel, Channel, NetworkInterface};
-use pnet::packet::ethernet::EthernetPacket;
-use pnet::packet::ip::IpNextHeaderProtocols;
+use crate::mdns::capture_filter::CaptureFilter;
+use crate::mdns::event_channel::{self, BackpressurePolicy, EventChannelMonitor, EventChannelStats, EventReceiver, EventSender};
+use crate::mdns::mdns_message::{MDNSMessage, MDNSMessageReceivedEvent, PacketMetadata, Protocol};
+use crate::mdns::parser::parse_mdns_message_with_diagnostics;
+use crate::mdns::packet_source::{FilePacketSource, LivePacketSource, PacketSource, SocketPacketSource};
+use crate::mdns::socket_listener::SocketListenerConfig;
+use crate::mdns::tcp_reassembly::{StreamKey, TcpReassembler, TcpSegment};
+use pnet::datalink::NetworkInterface;
+use pnet::packet::ip::{IpNextHeaderProtocol, IpNextHeaderProtocols};
 use pnet::packet::ipv4::Ipv4Packet;
-use pnet::packet::udp::UdpPacket;
+use pnet::packet::ipv6::Ipv6Packet;
+use pnet::packet::tcp::{TcpFlags, TcpPacket};
+use pnet::packet::udp::{self, UdpPacket};
 use pnet::packet::Packet;
-use time::{OffsetDateTime, PrimitiveDateTime};
+use pnet::util::MacAddr;
+use std::fmt::{Display, Formatter};
+use std::cmp::min;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use time::OffsetDateTime;
 
-pub fn start<F>(interface: &NetworkInterface, callback: F)
+/// How long a single blocking read may take before the capture thread checks whether it should stop.
+const READ_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// How long `CaptureHandle::stop` waits for the capture thread before detaching it.
+/// Read timeouts are not supported by every pnet backend (e.g. WinPcap), so the thread may only
+/// notice the stop request once the next frame arrives.
+const STOP_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// How often the capture thread asks the source for the frames the kernel dropped.
+const KERNEL_DROPS_INTERVAL: Duration = Duration::from_millis(500);
+
+struct CaptureState {
+    stop_requested: AtomicBool,
+    paused: AtomicBool,
+    running: AtomicBool,
+}
+
+/// What happened to the frames of a capture so far.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CaptureStats {
+    /// Frames read from the source, including those discarded while paused.
+    pub frames_seen: u64,
+    pub ipv4: u64,
+    pub ipv6: u64,
+    /// Decoded mDNS, LLMNR and unicast DNS messages.
+    pub messages: u64,
+    /// Frames which do not carry any of the decoded protocols.
+    pub other_frames: u64,
+    pub parse_errors: u64,
+    /// UDP checksum mismatches. Frames sent by this host can show up here when the network card computes checksums.
+    pub checksum_errors: u64,
+    /// Frames the kernel dropped because the capture thread fell behind, `None` where the source cannot tell.
+    pub kernel_drops: Option<u64>,
+    /// Messages waiting for the consumer and messages the event channel dropped.
+    pub backlog: EventChannelStats,
+}
+
+/// Counters updated by the capture thread and read through the capture handle.
+#[derive(Default)]
+struct StatsCounters {
+    frames_seen: AtomicU64,
+    ipv4: AtomicU64,
+    ipv6: AtomicU64,
+    messages: AtomicU64,
+    other_frames: AtomicU64,
+    parse_errors: AtomicU64,
+    checksum_errors: AtomicU64,
+    kernel_drops: AtomicU64,
+    kernel_drops_known: AtomicBool,
+}
+
+impl StatsCounters {
+    fn increment(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> CaptureStats {
+        let kernel_drops = match self.kernel_drops_known.load(Ordering::Relaxed) {
+            true => Some(self.kernel_drops.load(Ordering::Relaxed)),
+            false => None,
+        };
+        CaptureStats {
+            frames_seen: self.frames_seen.load(Ordering::Relaxed),
+            ipv4: self.ipv4.load(Ordering::Relaxed),
+            ipv6: self.ipv6.load(Ordering::Relaxed),
+            messages: self.messages.load(Ordering::Relaxed),
+            other_frames: self.other_frames.load(Ordering::Relaxed),
+            parse_errors: self.parse_errors.load(Ordering::Relaxed),
+            checksum_errors: self.checksum_errors.load(Ordering::Relaxed),
+            kernel_drops,
+            backlog: EventChannelStats::default(),
+        }
+    }
+}
+
+/// Handle to a running capture thread. Dropping the handle stops the capture.
+pub struct CaptureHandle {
+    state: Arc<CaptureState>,
+    thread: Option<JoinHandle<()>>,
+    finished: Receiver<()>,
+    kernel_filter: Option<CaptureFilter>,
+    stats: Arc<StatsCounters>,
+    channel: EventChannelMonitor<MDNSMessageReceivedEvent>,
+}
+
+impl CaptureHandle {
+    /// Requests the capture thread to stop and waits for it to finish.
+    pub fn stop(&mut self) {
+        self.state.stop_requested.store(true, Ordering::SeqCst);
+        let thread = match self.thread.take() {
+            Some(t) => t,
+            None => return,
+        };
+        match self.finished.recv_timeout(STOP_TIMEOUT) {
+            Ok(_) | Err(mpsc::RecvTimeoutError::Disconnected) => {
+                let _ = thread.join();
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                // The thread is stuck in a blocking read; it exits on its own once the read returns.
+                println!("Capture thread did not stop in time, detaching it.");
+            }
+        }
+    }
+
+    /// Paused capture keeps reading frames from the interface but discards them.
+    pub fn pause(&self) {
+        self.state.paused.store(true, Ordering::SeqCst);
+    }
+
+    pub fn resume(&self) {
+        self.state.paused.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.state.paused.load(Ordering::SeqCst)
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.state.running.load(Ordering::SeqCst)
+    }
+
+    /// Current counters of the capture. With a kernel filter attached, the frames seen are those the filter let through.
+    pub fn stats(&self) -> CaptureStats {
+        CaptureStats {
+            backlog: self.channel.stats(),
+            ..self.stats.snapshot()
+        }
+    }
+
+    /// The filter the kernel applies before frames reach the capture thread, if one is attached.
+    pub fn kernel_filter(&self) -> Option<&CaptureFilter> {
+        self.kernel_filter.as_ref()
+    }
+}
+
+impl Drop for CaptureHandle {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Controls what is captured and the queue between the capture thread and the consumer of the captured messages.
+#[derive(Debug, Clone)]
+pub struct CaptureOptions {
+    pub capacity: usize,
+    pub backpressure: BackpressurePolicy,
+    /// Also decode unicast DNS on port 53, over UDP and TCP.
+    pub unicast_dns: bool,
+    /// Filter attached to raw captures. Without one, only the decoded protocols are let through.
+    pub filter: Option<CaptureFilter>,
+}
+
+impl Default for CaptureOptions {
+    fn default() -> Self {
+        Self {
+            capacity: 10_000,
+            backpressure: BackpressurePolicy::DropOldest,
+            unicast_dns: false,
+            filter: None,
+        }
+    }
+}
+
+const ETHERNET_HEADER_LENGTH: usize = 14;
+const LINUX_SLL_HEADER_LENGTH: usize = 16;
+const LINUX_SLL2_HEADER_LENGTH: usize = 20;
+const NULL_HEADER_LENGTH: usize = 4;
+const IPV6_HEADER_LENGTH: usize = 40;
+const UDP_HEADER_LENGTH: usize = 8;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86DD;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+
+/// Framing of the frames delivered by a capture source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkType {
+    Ethernet,
+    /// Frames start directly with the IP header, e.g. on TUN and WireGuard interfaces.
+    RawIp,
+    /// Linux cooked capture header, used when capturing on the "any" device.
+    LinuxSll,
+    LinuxSll2,
+    /// BSD loopback header holding the address family of the packet.
+    Null,
+}
+
+impl LinkType {
+    /// The LINKTYPE_* value used when writing frames of this type to pcapng files.
+    pub fn to_pcap(self) -> u32 {
+        match self {
+            LinkType::Ethernet => 1,
+            LinkType::RawIp => 101,
+            LinkType::LinuxSll => 113,
+            LinkType::LinuxSll2 => 276,
+            LinkType::Null => 0,
+        }
+    }
+
+    /// Maps a LINKTYPE_* value used in pcap and pcapng files.
+    pub fn from_pcap(link_type: u32) -> Option<Self> {
+        match link_type {
+            1 => Some(LinkType::Ethernet),
+            101 | 228 | 229 => Some(LinkType::RawIp),
+            113 => Some(LinkType::LinuxSll),
+            276 => Some(LinkType::LinuxSll2),
+            // LINKTYPE_LOOP only differs in the byte order of the address family, which is not needed for decoding.
+            0 | 108 => Some(LinkType::Null),
+            _ => None,
+        }
+    }
+
+    /// Framing of the frames pnet delivers for the given interface.
+    /// Linux reports the hardware type of each interface, interfaces without a link layer header deliver raw IP.
+    #[cfg(target_os = "linux")]
+    pub fn for_interface(interface: &NetworkInterface) -> Self {
+        let hardware_type = std::fs::read_to_string(format!("/sys/class/net/{}/type", interface.name))
+            .ok()
+            .and_then(|t| t.trim().parse::<u16>().ok());
+        match hardware_type {
+            // The loopback device delivers a zeroed Ethernet header.
+            Some(libc::ARPHRD_ETHER) | Some(libc::ARPHRD_LOOPBACK) => LinkType::Ethernet,
+            Some(libc::ARPHRD_NONE) | Some(libc::ARPHRD_PPP) | Some(libc::ARPHRD_TUNNEL) | Some(libc::ARPHRD_TUNNEL6)
+            | Some(libc::ARPHRD_SIT) | Some(libc::ARPHRD_IPGRE) => LinkType::RawIp,
+            _ => match interface.mac {
+                Some(mac) if !mac.is_zero() => LinkType::Ethernet,
+                _ => LinkType::RawIp,
+            },
+        }
+    }
+
+    /// Framing of the frames pnet delivers for the given interface.
+    /// WinPcap and Npcap deliver the loopback adapter with its BSD loopback header.
+    #[cfg(windows)]
+    pub fn for_interface(interface: &NetworkInterface) -> Self {
+        match interface.is_loopback() {
+            true => LinkType::Null,
+            false => LinkType::Ethernet,
+        }
+    }
+
+    /// Framing of the frames pnet delivers for the given interface.
+    /// The BPF backend replaces loopback and tunnel headers with a zeroed Ethernet header.
+    #[cfg(not(any(target_os = "linux", windows)))]
+    pub fn for_interface(_interface: &NetworkInterface) -> Self {
+        LinkType::Ethernet
+    }
+}
+
+impl Display for LinkType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let link_type = match self {
+            LinkType::Ethernet => "Ethernet",
+            LinkType::RawIp => "Raw IP",
+            LinkType::LinuxSll => "Linux cooked (SLL)",
+            LinkType::LinuxSll2 => "Linux cooked (SLL2)",
+            LinkType::Null => "BSD loopback",
+        };
+        write!(f, "{}", link_type)
+    }
+}
+
+/// How messages are received from a network interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureBackend {
+    /// Raw datalink capture, sees all mDNS traffic on the link but needs elevated privileges.
+    RawCapture,
+    /// UDP socket joined to the mDNS groups, needs no privileges.
+    SocketListener,
+}
+
+impl Display for CaptureBackend {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let backend = match self {
+            CaptureBackend::RawCapture => "Raw capture",
+            CaptureBackend::SocketListener => "Socket listener",
+        };
+        write!(f, "{}", backend)
+    }
+}
+
+/// Starts capturing MDNS messages on the given interface in a background thread.
+/// Received messages are queued into the returned receiver, which can be consumed from any thread.
+pub fn start(interface: &NetworkInterface, options: CaptureOptions) -> io::Result<(CaptureHandle, EventReceiver<MDNSMessageReceivedEvent>)> {
+    let filter = options.filter.clone().unwrap_or_else(|| CaptureFilter::for_decoded_protocols(options.unicast_dns));
+    let source = LivePacketSource::open(interface, READ_TIMEOUT, Some(&filter))?;
+    println!("Chosen interface name: {} ({}), capture filter: {}", &interface.description, source.link_type(), filter);
+    let filter_attached = source.filter_attached();
+    let (mut handle, receiver) = capture(source, format!("capture-{}", interface.name), &interface.name, options)?;
+    if filter_attached {
+        handle.kernel_filter = Some(filter);
+    }
+    Ok((handle, receiver))
+}
+
+/// Receives MDNS and LLMNR messages on UDP sockets joined to the multicast groups of the given interface.
+/// Messages are wrapped into synthesized IP packets, so they can be exported like captured frames.
+pub fn listen(interface: &NetworkInterface, options: CaptureOptions) -> io::Result<(CaptureHandle, EventReceiver<MDNSMessageReceivedEvent>)> {
+    let source = SocketPacketSource::open(&SocketListenerConfig::for_interface(interface))?;
+    println!("Listening on interface: {}", &interface.description);
+    capture(source, format!("listen-{}", interface.name), &interface.name, options)
+}
+
+/// Reads MDNS messages from a pcap or pcapng capture file in a background thread.
+/// Messages keep the timestamps recorded in the file. Unlike live capture, pausing halts reading.
+pub fn open_file(path: &Path, options: CaptureOptions) -> io::Result<(CaptureHandle, EventReceiver<MDNSMessageReceivedEvent>)> {
+    let source = FilePacketSource::open(path)?;
+    println!("Opened capture file: {}", path.display());
+    let file_name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    capture(source, "capture-file".to_string(), &file_name, options)
+}
+
+/// Decodes the frames of any packet source in a background thread until the source is exhausted or the capture is stopped.
+/// `interface_name` is attached to every message to tell where it came from.
+pub fn capture<S>(mut source: S, thread_name: String, interface_name: &str, options: CaptureOptions) -> io::Result<(CaptureHandle, EventReceiver<MDNSMessageReceivedEvent>)>
+where
+    S: PacketSource + 'static
+{
+    let interface_name: Arc<str> = Arc::from(interface_name);
+    let mut decoder = FrameDecoder::new(options.unicast_dns);
+    let stats = decoder.counters.clone();
+    spawn(thread_name, options, decoder.counters.clone(), move |state, sender| {
+        let mut kernel_drops_read = Instant::now();
+        while !state.stop_requested.load(Ordering::SeqCst) {
+            if kernel_drops_read.elapsed() >= KERNEL_DROPS_INTERVAL {
+                kernel_drops_read = Instant::now();
+                if let Some(drops) = source.kernel_drops() {
+                    stats.kernel_drops.fetch_add(drops, Ordering::Relaxed);
+                    stats.kernel_drops_known.store(true, Ordering::Relaxed);
+                }
+            }
+            if state.paused.load(Ordering::SeqCst) && !source.is_live() {
+                thread::sleep(READ_TIMEOUT);
+                continue;
+            }
+            let frame = match source.next_frame() {
+                Ok(Some(f)) => f,
+                Ok(None) => break,
+                Err(e) if e.kind() == io::ErrorKind::TimedOut || e.kind() == io::ErrorKind::WouldBlock => continue,
+                Err(e) => {
+                    println!("Capture stopped due to read error: {}", e);
+                    break;
+                }
+            };
+            StatsCounters::increment(&stats.frames_seen);
+            if state.paused.load(Ordering::SeqCst) {
+                continue;
+            }
+            let events = decoder.decode(frame.link_type, frame.data, frame.timestamp, &interface_name);
+            // Nothing is sent anymore once nobody is listening.
+            if !events.into_iter().all(|event| sender.send(event, &state.stop_requested)) {
+                break;
+            }
+        }
+    })
+}
+
+/// Runs the capture loop in a new thread which owns the sending side of the event channel.
+fn spawn<F>(name: String, options: CaptureOptions, stats: Arc<StatsCounters>, capture_loop: F) -> io::Result<(CaptureHandle, EventReceiver<MDNSMessageReceivedEvent>)>
 where
-    F: Fn(&MDNSMessageReceivedEvent)
+    F: FnOnce(&CaptureState, &EventSender<MDNSMessageReceivedEvent>) + Send + 'static
 {
-    // Create a channel to listen for packets
-    let (_, mut rx) = match channel(&interface, Default::default()) {
-        Ok(Channel::Ethernet(tx, rx)) => (tx, rx),
-        _ => panic!("Failed to create datalink channel"),
+    let state = Arc::new(CaptureState {
+        stop_requested: AtomicBool::new(false),
+        paused: AtomicBool::new(false),
+        running: AtomicBool::new(true),
+    });
+    let (sender, receiver) = event_channel::bounded(options.capacity, options.backpressure);
+    let (finished_tx, finished_rx) = mpsc::channel();
+    let thread_state = state.clone();
+    let thread = thread::Builder::new()
+        .name(name)
+        .spawn(move || {
+            capture_loop(&thread_state, &sender);
+            thread_state.running.store(false, Ordering::SeqCst);
+            let _ = finished_tx.send(());
+        })?;
+
+    let handle = CaptureHandle {
+        state,
+        thread: Some(thread),
+        finished: finished_rx,
+        kernel_filter: None,
+        stats,
+        channel: receiver.monitor(),
     };
+    Ok((handle, receiver))
+}
 
-    println!("Chosen interface name: {}", &interface.description);
-
-    loop {
-        // There is actually no way to make the call to rx.next() non-blocking on Windows.
-        let packet = rx.next().unwrap();
-        let now = OffsetDateTime::now_utc();
-        let ethernet_packet = EthernetPacket::new(packet).unwrap();
-        let ipv4_packet = handle_ethernet_packet(&ethernet_packet);
-        match ipv4_packet {
-            Some(p) => {
-                let source_ip = p.get_source();
-                let destination_ip = p.get_destination();
-                let mdns_packet = handle_ipv4_packet(&p);
-                match mdns_packet {
-                    Some(m) => {
-                        callback(&MDNSMessageReceivedEvent {
-                            received_datetime: PrimitiveDateTime::new(now.date(), now.time()),
-                            message: m,
-                            source_ip: source_ip,
-                            destination_ip: destination_ip,
-                        });
-                    },
-                    None => {}
-                }
+/// Decodes captured frames into message events.
+/// Keeps the state needed to reassemble unicast DNS messages sent over TCP, so one decoder is used per capture.
+pub struct FrameDecoder {
+    unicast_dns: bool,
+    tcp_streams: TcpReassembler,
+    counters: Arc<StatsCounters>,
+}
+
+impl FrameDecoder {
+    /// Unicast DNS on port 53 is only decoded when `unicast_dns` is set, mDNS and LLMNR always are.
+    pub fn new(unicast_dns: bool) -> Self {
+        Self {
+            unicast_dns,
+            tcp_streams: TcpReassembler::new(),
+            counters: Arc::new(StatsCounters::default()),
+        }
+    }
+
+    /// Decodes the messages carried by the frame. A TCP segment can complete several messages or none at all.
+    pub fn decode(&mut self, link_type: LinkType, frame: &[u8], received: OffsetDateTime, interface_name: &Arc<str>) -> Vec<MDNSMessageReceivedEvent> {
+        let link_layer = strip_link_header(link_type, frame);
+        let (source_mac, destination_mac, ip_packet) = match link_layer.and_then(|(s, d, p)| Some((s, d, read_ip_packet(p)?))) {
+            Some(p) => p,
+            None => {
+                StatsCounters::increment(&self.counters.other_frames);
+                return vec![];
+            }
+        };
+        match ip_packet.source_ip {
+            IpAddr::V4(_) => StatsCounters::increment(&self.counters.ipv4),
+            IpAddr::V6(_) => StatsCounters::increment(&self.counters.ipv6),
+        }
+        let decoded = match ip_packet.next_protocol {
+            IpNextHeaderProtocols::Udp => self.handle_udp_packet(&ip_packet).into_iter().collect(),
+            IpNextHeaderProtocols::Tcp if self.unicast_dns => self.handle_tcp_segment(&ip_packet),
+            _ => {
+                StatsCounters::increment(&self.counters.other_frames);
+                vec![]
+            }
+        };
+        self.counters.messages.fetch_add(decoded.len() as u64, Ordering::Relaxed);
+        decoded
+            .into_iter()
+            .map(|decoded| MDNSMessageReceivedEvent {
+                received_datetime: received,
+                protocol: decoded.protocol,
+                message: decoded.message,
+                source_ip: ip_packet.source_ip,
+                destination_ip: ip_packet.destination_ip,
+                metadata: PacketMetadata {
+                    source_mac,
+                    destination_mac,
+                    source_port: decoded.source_port,
+                    destination_port: decoded.destination_port,
+                    frame_length: frame.len(),
+                    ..ip_packet.metadata
+                },
+                frame: Arc::from(frame),
+                link_type,
+                interface_name: interface_name.clone(),
+                diagnostics: decoded.diagnostics,
+            })
+            .collect()
+    }
+
+    fn handle_udp_packet(&self, ip_packet: &IpPayload) -> Option<DecodedMessage> {
+        let udp_packet = UdpPacket::new(ip_packet.payload);
+        let protocol = udp_packet.as_ref().and_then(|u| Protocol::from_ports(u.get_source(), u.get_destination()));
+        let (udp_packet, protocol) = match (udp_packet, protocol) {
+            (Some(u), Some(p)) if p != Protocol::UnicastDns || self.unicast_dns => (u, p),
+            _ => {
+                StatsCounters::increment(&self.counters.other_frames);
+                return None;
+            }
+        };
+        let checksum_valid = has_valid_checksum(&udp_packet, ip_packet.source_ip, ip_packet.destination_ip);
+        if !checksum_valid {
+            StatsCounters::increment(&self.counters.checksum_errors);
+        }
+        let mut decoded = self.decode_message(protocol, udp_packet.payload(), ip_packet.source_ip, udp_packet.get_source(), udp_packet.get_destination())?;
+        if !checksum_valid {
+            decoded.diagnostics.push("UDP checksum mismatch, the datagram is corrupted or was captured before the network card computed the checksum.".to_string());
+        }
+        Some(decoded)
+    }
+
+    fn handle_tcp_segment(&mut self, ip_packet: &IpPayload) -> Vec<DecodedMessage> {
+        let tcp_packet = match TcpPacket::new(ip_packet.payload) {
+            Some(p) if Protocol::from_ports(p.get_source(), p.get_destination()) == Some(Protocol::UnicastDns) => p,
+            _ => {
+                StatsCounters::increment(&self.counters.other_frames);
+                return vec![];
+            }
+        };
+        let (source_port, destination_port) = (tcp_packet.get_source(), tcp_packet.get_destination());
+        let key = StreamKey {
+            source: SocketAddr::new(ip_packet.source_ip, source_port),
+            destination: SocketAddr::new(ip_packet.destination_ip, destination_port),
+        };
+        let flags = tcp_packet.get_flags();
+        let segment = TcpSegment {
+            sequence: tcp_packet.get_sequence(),
+            syn: flags & TcpFlags::SYN != 0,
+            closes: flags & (TcpFlags::FIN | TcpFlags::RST) != 0,
+            payload: tcp_packet.payload(),
+        };
+        self.tcp_streams
+            .push(key, segment)
+            .iter()
+            .filter_map(|payload| self.decode_message(Protocol::UnicastDns, payload, ip_packet.source_ip, source_port, destination_port))
+            .map(|mut decoded| {
+                decoded.diagnostics.push("Reassembled from TCP, the frame only holds the segment which completed the message.".to_string());
+                decoded
+            })
+            .collect()
+    }
+
+    /// Parses the DNS message carried by a datagram or reassembled from a TCP stream.
+    fn decode_message(&self, protocol: Protocol, payload: &[u8], source_ip: IpAddr, source_port: u16, destination_port: u16) -> Option<DecodedMessage> {
+        // LLMNR and unicast DNS use the DNS header and record format too, so the same parser handles all of them.
+        let (message, mut diagnostics) = match parse_mdns_message_with_diagnostics(payload) {
+            Ok(m) => m,
+            Err(e) => {
+                println!("Could not parse {} message from {}: {}", protocol, source_ip, e);
+                StatsCounters::increment(&self.counters.parse_errors);
+                return None;
             }
-            None => {}
+        };
+        if protocol == Protocol::UnicastDns && message.is_local_query() {
+            diagnostics.push("Query for a .local name sent to unicast DNS, .local names are resolved with mDNS (RFC 6762 - 3).".to_string());
         }
+        Some(DecodedMessage {
+            protocol,
+            message,
+            diagnostics,
+            source_port,
+            destination_port,
+        })
     }
 }
 
-fn handle_ethernet_packet<'a>(eth_packet: &'a EthernetPacket<'a>) -> Option<Ipv4Packet<'a>> {
-    // Check if the packet is an IPv4 packet
-    let is_ipv4 = eth_packet.get_ethertype() == pnet::packet::ethernet::EtherTypes::Ipv4;
-    match is_ipv4 {
-        true => Some(Ipv4Packet::new(eth_packet.payload()).unwrap()),
-        false => None
+/// Decodes a single captured frame into an MDNS or LLMNR message event, if it carries one.
+/// A shortcut for tests, which mostly deal with single datagrams.
+#[cfg(test)]
+pub fn decode_frame(link_type: LinkType, frame: &[u8], received: OffsetDateTime, interface_name: &Arc<str>) -> Option<MDNSMessageReceivedEvent> {
+    FrameDecoder::new(false).decode(link_type, frame, received, interface_name).pop()
+}
+
+/// A UDP datagram read back from a captured frame.
+pub struct CapturedDatagram<'a> {
+    pub source: SocketAddr,
+    pub destination: SocketAddr,
+    /// IPv4 TTL or IPv6 hop limit.
+    pub ttl: Option<u8>,
+    pub payload: &'a [u8],
+}
+
+/// Reads the UDP datagram carried by a frame, used to send captured messages again.
+pub fn udp_datagram(link_type: LinkType, frame: &[u8]) -> Option<CapturedDatagram<'_>> {
+    let (_, _, packet) = strip_link_header(link_type, frame)?;
+    let ip_packet = read_ip_packet(packet)?;
+    if ip_packet.next_protocol != IpNextHeaderProtocols::Udp {
+        return None;
     }
+    let udp_packet = UdpPacket::new(ip_packet.payload)?;
+    Some(CapturedDatagram {
+        source: SocketAddr::new(ip_packet.source_ip, udp_packet.get_source()),
+        destination: SocketAddr::new(ip_packet.destination_ip, udp_packet.get_destination()),
+        ttl: ip_packet.metadata.ttl,
+        payload: ip_packet.payload.get(UDP_HEADER_LENGTH..)?,
+    })
 }
 
-fn handle_ipv4_packet(ipv4_packet: &Ipv4Packet) -> Option<MDNSMessage> {
+/// Addresses and header fields of an IP packet together with its payload.
+struct IpPayload<'a> {
+    next_protocol: IpNextHeaderProtocol,
+    source_ip: IpAddr,
+    destination_ip: IpAddr,
+    metadata: PacketMetadata,
+    payload: &'a [u8],
+}
 
+/// A message together with the transport ports it was received on.
+struct DecodedMessage {
+    protocol: Protocol,
+    message: MDNSMessage,
+    diagnostics: Vec<String>,
+    source_port: u16,
+    destination_port: u16,
+}
 
-    match ipv4_packet.get_next_level_protocol() {
-        IpNextHeaderProtocols::Udp => {
-            let udp_packet = UdpPacket::new(ipv4_packet.payload()).unwrap();
-            if udp_packet.get_source() == 5353 || udp_packet.get_destination() == 5353
-            {
-                Some(MDNSMessage::get(&udp_packet))
-            } else {
-                None
+/// Returns the source MAC, destination MAC and the IP packet carried by the frame,
+/// or `None` for frames which do not carry IP.
+fn strip_link_header(link_type: LinkType, frame: &[u8]) -> Option<(Option<MacAddr>, Option<MacAddr>, &[u8])> {
+    let read_u16 = |offset: usize| Some(u16::from_be_bytes([*frame.get(offset)?, *frame.get(offset + 1)?]));
+    let read_mac = |offset: usize| frame.get(offset..offset + 6).map(|m| MacAddr::new(m[0], m[1], m[2], m[3], m[4], m[5]));
+    let (ethertype, header_length, source_mac, destination_mac) = match link_type {
+        LinkType::Ethernet => {
+            let ethertype = read_u16(12)?;
+            match ethertype {
+                // Skip a single 802.1Q tag.
+                ETHERTYPE_VLAN => (read_u16(16)?, ETHERNET_HEADER_LENGTH + 4, read_mac(6), read_mac(0)),
+                // pnet delivers BSD loopback and tunnel frames with a zeroed Ethernet header, the IP version tells what follows.
+                0 => return Some((None, None, frame.get(ETHERNET_HEADER_LENGTH..)?)),
+                _ => (ethertype, ETHERNET_HEADER_LENGTH, read_mac(6), read_mac(0)),
             }
         }
+        // Cooked headers only hold the link layer address of the sender.
+        LinkType::LinuxSll => {
+            let source_mac = if read_u16(4)? == 6 { read_mac(6) } else { None };
+            (read_u16(14)?, LINUX_SLL_HEADER_LENGTH, source_mac, None)
+        }
+        LinkType::LinuxSll2 => {
+            let source_mac = if *frame.get(11)? == 6 { read_mac(12) } else { None };
+            (read_u16(0)?, LINUX_SLL2_HEADER_LENGTH, source_mac, None)
+        }
+        // The address family values for IPv6 differ between BSDs, the IP version is checked instead.
+        LinkType::Null => return Some((None, None, frame.get(NULL_HEADER_LENGTH..)?)),
+        LinkType::RawIp => return Some((None, None, frame)),
+    };
+    match ethertype {
+        ETHERTYPE_IPV4 | ETHERTYPE_IPV6 => Some((source_mac, destination_mac, frame.get(header_length..)?)),
+        _ => None,
+    }
+}
+
+fn read_ip_packet(packet: &[u8]) -> Option<IpPayload<'_>> {
+    // The IP version is the upper nibble of the first byte.
+    match packet.first()? >> 4 {
+        4 => {
+            let ipv4_packet = Ipv4Packet::new(packet)?;
+            let header_length = ipv4_packet.get_header_length() as usize * 4;
+            // Offloaded packets captured on the sending host may report a total length of zero, the rest of the
+            // captured packet is the payload then.
+            let payload_length = match ipv4_packet.get_total_length() as usize {
+                0 => packet.len().saturating_sub(header_length),
+                total_length => total_length.saturating_sub(header_length),
+            };
+            Some(IpPayload {
+                next_protocol: ipv4_packet.get_next_level_protocol(),
+                source_ip: IpAddr::V4(ipv4_packet.get_source()),
+                destination_ip: IpAddr::V4(ipv4_packet.get_destination()),
+                metadata: PacketMetadata {
+                    ttl: Some(ipv4_packet.get_ttl()),
+                    dscp: Some(ipv4_packet.get_dscp()),
+                    ip_identification: Some(ipv4_packet.get_identification()),
+                    ..Default::default()
+                },
+                payload: packet.get(header_length..min(header_length + payload_length, packet.len()))?,
+            })
+        }
+        6 => {
+            let ipv6_packet = Ipv6Packet::new(packet)?;
+            let payload_length = ipv6_packet.get_payload_length() as usize;
+            Some(IpPayload {
+                next_protocol: ipv6_packet.get_next_header(),
+                source_ip: IpAddr::V6(ipv6_packet.get_source()),
+                destination_ip: IpAddr::V6(ipv6_packet.get_destination()),
+                metadata: PacketMetadata {
+                    ttl: Some(ipv6_packet.get_hop_limit()),
+                    // DSCP is the upper six bits of the traffic class.
+                    dscp: Some(ipv6_packet.get_traffic_class() >> 2),
+                    ..Default::default()
+                },
+                payload: packet.get(IPV6_HEADER_LENGTH..min(IPV6_HEADER_LENGTH + payload_length, packet.len()))?,
+            })
+        }
         _ => None
     }
 }
+
+/// Verifies the UDP checksum. IPv4 senders may leave it out by sending zero.
+fn has_valid_checksum(udp_packet: &UdpPacket, source_ip: IpAddr, destination_ip: IpAddr) -> bool {
+    match (source_ip, destination_ip) {
+        (IpAddr::V4(source), IpAddr::V4(destination)) => {
+            udp_packet.get_checksum() == 0 || udp::ipv4_checksum(udp_packet, &source, &destination) == udp_packet.get_checksum()
+        }
+        (IpAddr::V6(source), IpAddr::V6(destination)) => udp::ipv6_checksum(udp_packet, &source, &destination) == udp_packet.get_checksum(),
+        _ => true,
+    }
+}
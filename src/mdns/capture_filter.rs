@@ -0,0 +1,477 @@
+use crate::mdns::capture::LinkType;
+use std::fmt::{Display, Formatter};
+use std::net::IpAddr;
+use std::str::FromStr;
+
+// Classic BPF opcodes, see linux/filter.h.
+const BPF_LD_W_ABS: u16 = 0x20;
+const BPF_LD_H_ABS: u16 = 0x28;
+const BPF_LD_B_ABS: u16 = 0x30;
+const BPF_LD_H_IND: u16 = 0x48;
+/// Loads four times the lower nibble of a byte into X, i.e. the length of the IPv4 header.
+const BPF_LDX_B_MSH: u16 = 0xb1;
+const BPF_ALU_AND_K: u16 = 0x54;
+const BPF_JMP_JA: u16 = 0x05;
+const BPF_JMP_JEQ_K: u16 = 0x15;
+const BPF_JMP_JSET_K: u16 = 0x45;
+const BPF_RET_K: u16 = 0x06;
+
+/// Offsets of the ancillary data the Linux kernel exposes to filters.
+/// Most drivers strip the VLAN tag from the frame and only report it there.
+const SKF_AD_OFF: u32 = 0xffff_f000;
+const SKF_AD_VLAN_TAG: u32 = SKF_AD_OFF + 40;
+const SKF_AD_VLAN_TAG_PRESENT: u32 = SKF_AD_OFF + 44;
+
+const ETHERTYPE_IPV4: u32 = 0x0800;
+const ETHERTYPE_IPV6: u32 = 0x86dd;
+const ETHERTYPE_VLAN: u32 = 0x8100;
+const IP_PROTOCOL_TCP: u32 = 6;
+const IP_PROTOCOL_UDP: u32 = 17;
+
+/// Bytes of an accepted frame handed to userspace, enough for any frame.
+const SNAP_LENGTH: u32 = 0x40000;
+
+/// A single classic BPF instruction, laid out like `struct sock_filter`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BpfInstruction {
+    pub code: u16,
+    pub jt: u8,
+    pub jf: u8,
+    pub k: u32,
+}
+
+/// Transport protocol a port clause applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    Udp,
+    Tcp,
+    /// Both UDP and TCP.
+    Any,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortClause {
+    pub transport: Transport,
+    /// Matches when the source or destination port is one of these.
+    pub ports: Vec<u16>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpVersion {
+    V4,
+    V6,
+}
+
+/// Error describing why a capture filter expression could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaptureFilterError(pub String);
+
+impl Display for CaptureFilterError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Decides in the kernel which frames reach the capture thread, so unrelated traffic is not copied to userspace.
+///
+/// Expressions are a small subset of the pcap filter syntax:
+/// `udp port 5353,5355`, `tcp port 53`, `port 53` (UDP or TCP), `host <address>`, `ip`, `ip6`, `vlan` and `vlan <id>`.
+/// Clauses are joined with `and`, port clauses may also be joined with `or`. A frame passes when it matches
+/// any of the port clauses and all other clauses.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CaptureFilter {
+    pub ports: Vec<PortClause>,
+    /// Source or destination address.
+    pub host: Option<IpAddr>,
+    pub ip_version: Option<IpVersion>,
+    /// `Some(None)` matches frames of any VLAN, `Some(Some(id))` only the given VLAN.
+    pub vlan: Option<Option<u16>>,
+}
+
+impl CaptureFilter {
+    /// Lets through the protocols the capture decodes: mDNS and LLMNR, and unicast DNS when requested.
+    pub fn for_decoded_protocols(unicast_dns: bool) -> Self {
+        let mut ports = vec![PortClause { transport: Transport::Udp, ports: vec![5353, 5355] }];
+        if unicast_dns {
+            ports.push(PortClause { transport: Transport::Any, ports: vec![53] });
+        }
+        Self { ports, ..Default::default() }
+    }
+
+    /// Compiles the filter for frames of the given link type.
+    /// Only Ethernet and raw IP frames are supported, those are what Linux packet sockets deliver.
+    pub fn compile(&self, link_type: LinkType) -> Result<Vec<BpfInstruction>, CaptureFilterError> {
+        let mut program = Assembler::new();
+        let accept = program.label();
+        let reject = program.label();
+        match link_type {
+            LinkType::Ethernet => self.emit_ethernet(&mut program, accept, reject),
+            LinkType::RawIp => self.emit_raw_ip(&mut program, accept, reject),
+            _ => return Err(CaptureFilterError(format!("Capture filters are not supported for {} frames.", link_type))),
+        }
+        program.place(accept);
+        program.statement(BPF_RET_K, SNAP_LENGTH);
+        program.place(reject);
+        program.statement(BPF_RET_K, 0);
+        program.finish()
+    }
+
+    fn emit_ethernet(&self, program: &mut Assembler, accept: usize, reject: usize) {
+        let tagged = program.label();
+        match self.vlan {
+            Some(id) => {
+                // The tag is either reported as ancillary data or still part of the frame.
+                program.statement(BPF_LD_W_ABS, SKF_AD_VLAN_TAG_PRESENT);
+                program.jump(BPF_JMP_JEQ_K, 0, Target::Label(tagged), Target::Next);
+                if let Some(id) = id {
+                    program.statement(BPF_LD_W_ABS, SKF_AD_VLAN_TAG);
+                    program.statement(BPF_ALU_AND_K, 0x0fff);
+                    program.jump(BPF_JMP_JEQ_K, id as u32, Target::Next, Target::Label(reject));
+                }
+                program.statement(BPF_LD_H_ABS, 12);
+                self.emit_ethertype_dispatch(program, 14, accept, reject);
+                program.place(tagged);
+                program.statement(BPF_LD_H_ABS, 12);
+                program.jump(BPF_JMP_JEQ_K, ETHERTYPE_VLAN, Target::Next, Target::Label(reject));
+                if let Some(id) = id {
+                    program.statement(BPF_LD_H_ABS, 14);
+                    program.statement(BPF_ALU_AND_K, 0x0fff);
+                    program.jump(BPF_JMP_JEQ_K, id as u32, Target::Next, Target::Label(reject));
+                }
+            }
+            None => {
+                program.statement(BPF_LD_H_ABS, 12);
+                program.jump(BPF_JMP_JEQ_K, ETHERTYPE_VLAN, Target::Label(tagged), Target::Next);
+                self.emit_ethertype_dispatch(program, 14, accept, reject);
+                program.place(tagged);
+            }
+        }
+        // A single 802.1Q tag in front of the ethertype.
+        program.statement(BPF_LD_H_ABS, 16);
+        self.emit_ethertype_dispatch(program, 18, accept, reject);
+    }
+
+    fn emit_raw_ip(&self, program: &mut Assembler, accept: usize, reject: usize) {
+        if let Some(id) = self.vlan {
+            program.statement(BPF_LD_W_ABS, SKF_AD_VLAN_TAG_PRESENT);
+            program.jump(BPF_JMP_JEQ_K, 0, Target::Label(reject), Target::Next);
+            if let Some(id) = id {
+                program.statement(BPF_LD_W_ABS, SKF_AD_VLAN_TAG);
+                program.statement(BPF_ALU_AND_K, 0x0fff);
+                program.jump(BPF_JMP_JEQ_K, id as u32, Target::Next, Target::Label(reject));
+            }
+        }
+        // The IP version is the upper nibble of the first byte.
+        program.statement(BPF_LD_B_ABS, 0);
+        program.statement(BPF_ALU_AND_K, 0xf0);
+        self.emit_version_dispatch(program, 0x40, 0x60, 0, accept, reject);
+    }
+
+    /// Expects the ethertype in the accumulator and continues with the IP header at `offset`.
+    fn emit_ethertype_dispatch(&self, program: &mut Assembler, offset: u32, accept: usize, reject: usize) {
+        self.emit_version_dispatch(program, ETHERTYPE_IPV4, ETHERTYPE_IPV6, offset, accept, reject);
+    }
+
+    fn emit_version_dispatch(&self, program: &mut Assembler, ipv4_value: u32, ipv6_value: u32, offset: u32, accept: usize, reject: usize) {
+        let ipv6 = program.label();
+        let allows_ipv4 = self.ip_version != Some(IpVersion::V6) && !matches!(self.host, Some(IpAddr::V6(_)));
+        let allows_ipv6 = self.ip_version != Some(IpVersion::V4) && !matches!(self.host, Some(IpAddr::V4(_)));
+        if allows_ipv6 {
+            program.jump(BPF_JMP_JEQ_K, ipv6_value, Target::Label(ipv6), Target::Next);
+        }
+        if allows_ipv4 {
+            program.jump(BPF_JMP_JEQ_K, ipv4_value, Target::Next, Target::Label(reject));
+            self.emit_ipv4(program, offset, accept, reject);
+        } else {
+            program.jump_always(reject);
+        }
+        program.place(ipv6);
+        if allows_ipv6 {
+            self.emit_ipv6(program, offset, accept, reject);
+        }
+    }
+
+    fn emit_ipv4(&self, program: &mut Assembler, offset: u32, accept: usize, reject: usize) {
+        if let Some(IpAddr::V4(host)) = self.host {
+            let host_matched = program.label();
+            program.statement(BPF_LD_W_ABS, offset + 12);
+            program.jump(BPF_JMP_JEQ_K, u32::from(host), Target::Label(host_matched), Target::Next);
+            program.statement(BPF_LD_W_ABS, offset + 16);
+            program.jump(BPF_JMP_JEQ_K, u32::from(host), Target::Next, Target::Label(reject));
+            program.place(host_matched);
+        }
+        if self.ports.is_empty() {
+            program.jump_always(accept);
+            return;
+        }
+        // Only the first fragment carries the transport header.
+        program.statement(BPF_LD_H_ABS, offset + 6);
+        program.jump(BPF_JMP_JSET_K, 0x1fff, Target::Label(reject), Target::Next);
+        program.statement(BPF_LDX_B_MSH, offset);
+        program.statement(BPF_LD_B_ABS, offset + 9);
+        self.emit_transport_dispatch(program, |program, ports| {
+            Self::emit_port_checks(program, BPF_LD_H_IND, offset, ports, accept, reject);
+        }, reject);
+    }
+
+    fn emit_ipv6(&self, program: &mut Assembler, offset: u32, accept: usize, reject: usize) {
+        if let Some(IpAddr::V6(host)) = self.host {
+            let host_matched = program.label();
+            let check_destination = program.label();
+            let words: Vec<u32> = host.octets().chunks(4).map(|w| u32::from_be_bytes([w[0], w[1], w[2], w[3]])).collect();
+            for (address_offset, mismatch) in [(offset + 8, check_destination), (offset + 24, reject)] {
+                for (index, word) in words.iter().enumerate() {
+                    program.statement(BPF_LD_W_ABS, address_offset + 4 * index as u32);
+                    program.jump(BPF_JMP_JEQ_K, *word, Target::Next, Target::Label(mismatch));
+                }
+                program.jump_always(host_matched);
+                if mismatch == check_destination {
+                    program.place(check_destination);
+                }
+            }
+            program.place(host_matched);
+        }
+        if self.ports.is_empty() {
+            program.jump_always(accept);
+            return;
+        }
+        // Extension headers are not followed, mDNS and LLMNR packets do not use them.
+        program.statement(BPF_LD_B_ABS, offset + 6);
+        self.emit_transport_dispatch(program, |program, ports| {
+            Self::emit_port_checks(program, BPF_LD_H_ABS, offset + 40, ports, accept, reject);
+        }, reject);
+    }
+
+    /// Expects the IP protocol number in the accumulator and emits the port checks of each transport.
+    fn emit_transport_dispatch<F>(&self, program: &mut Assembler, mut emit_ports: F, reject: usize)
+    where
+        F: FnMut(&mut Assembler, &[u16])
+    {
+        let udp_ports = self.ports_for(Transport::Udp);
+        let tcp_ports = self.ports_for(Transport::Tcp);
+        let udp = program.label();
+        let tcp = program.label();
+        if !udp_ports.is_empty() {
+            program.jump(BPF_JMP_JEQ_K, IP_PROTOCOL_UDP, Target::Label(udp), Target::Next);
+        }
+        if !tcp_ports.is_empty() {
+            program.jump(BPF_JMP_JEQ_K, IP_PROTOCOL_TCP, Target::Label(tcp), Target::Next);
+        }
+        program.jump_always(reject);
+        program.place(udp);
+        if !udp_ports.is_empty() {
+            emit_ports(program, &udp_ports);
+        }
+        program.place(tcp);
+        if !tcp_ports.is_empty() {
+            emit_ports(program, &tcp_ports);
+        }
+    }
+
+    /// Accepts when the source or destination port, at `offset` and `offset + 2`, is one of the ports.
+    fn emit_port_checks(program: &mut Assembler, load: u16, offset: u32, ports: &[u16], accept: usize, reject: usize) {
+        for port_offset in [offset, offset + 2] {
+            program.statement(load, port_offset);
+            for port in ports {
+                program.jump(BPF_JMP_JEQ_K, *port as u32, Target::Label(accept), Target::Next);
+            }
+        }
+        program.jump_always(reject);
+    }
+
+    fn ports_for(&self, transport: Transport) -> Vec<u16> {
+        let mut ports: Vec<u16> = self.ports.iter()
+            .filter(|c| c.transport == transport || c.transport == Transport::Any)
+            .flat_map(|c| c.ports.iter().copied())
+            .collect();
+        ports.sort();
+        ports.dedup();
+        ports
+    }
+}
+
+impl FromStr for CaptureFilter {
+    type Err = CaptureFilterError;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        let lowercase = expression.to_ascii_lowercase();
+        let mut tokens = lowercase.split_whitespace().peekable();
+        if tokens.peek().is_none() {
+            return Err(CaptureFilterError("The filter is empty.".to_string()));
+        }
+        let mut filter = CaptureFilter::default();
+        let mut joined_with_or = false;
+        loop {
+            let is_port_clause = matches!(tokens.peek(), Some(&"udp") | Some(&"tcp") | Some(&"port"));
+            if joined_with_or && !is_port_clause {
+                return Err(CaptureFilterError("'or' can only join port clauses.".to_string()));
+            }
+            let token = tokens.next().ok_or_else(|| CaptureFilterError("The filter ends with a connective.".to_string()))?;
+            match token {
+                "udp" | "tcp" | "port" => {
+                    let transport = match token {
+                        "udp" => Transport::Udp,
+                        "tcp" => Transport::Tcp,
+                        _ => Transport::Any,
+                    };
+                    if token != "port" && tokens.next() != Some("port") {
+                        return Err(CaptureFilterError(format!("Expected 'port' after '{}'.", token)));
+                    }
+                    let ports = tokens.next().ok_or_else(|| CaptureFilterError("Expected port numbers.".to_string()))?;
+                    let ports = ports
+                        .split(',')
+                        .map(|p| p.parse::<u16>().map_err(|_| CaptureFilterError(format!("Invalid port: '{}'.", p))))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    filter.ports.push(PortClause { transport, ports });
+                }
+                "host" => {
+                    let address = tokens.next().ok_or_else(|| CaptureFilterError("Expected an address after 'host'.".to_string()))?;
+                    let address = address.parse::<IpAddr>().map_err(|_| CaptureFilterError(format!("Invalid address: '{}'.", address)))?;
+                    if filter.host.replace(address).is_some() {
+                        return Err(CaptureFilterError("Only one host clause is supported.".to_string()));
+                    }
+                }
+                "ip" | "ip6" => {
+                    let version = if token == "ip" { IpVersion::V4 } else { IpVersion::V6 };
+                    if filter.ip_version.replace(version).is_some_and(|v| v != version) {
+                        return Err(CaptureFilterError("A frame cannot be both IPv4 and IPv6.".to_string()));
+                    }
+                }
+                "vlan" => {
+                    let id = match tokens.peek().map(|t| t.parse::<u16>()) {
+                        Some(Ok(id)) if id < 4096 => {
+                            tokens.next();
+                            Some(id)
+                        }
+                        Some(Ok(id)) => return Err(CaptureFilterError(format!("Invalid VLAN id: {}.", id))),
+                        _ => None,
+                    };
+                    filter.vlan = Some(id);
+                }
+                _ => return Err(CaptureFilterError(format!("Unknown clause: '{}'.", token))),
+            }
+            joined_with_or = match tokens.next() {
+                None => break,
+                Some("and") => false,
+                Some("or") if is_port_clause => true,
+                Some("or") => return Err(CaptureFilterError("'or' can only join port clauses.".to_string())),
+                Some(token) => return Err(CaptureFilterError(format!("Expected 'and' or 'or' before '{}'.", token))),
+            };
+        }
+        let host_version = match filter.host {
+            Some(IpAddr::V4(_)) => Some(IpVersion::V4),
+            Some(IpAddr::V6(_)) => Some(IpVersion::V6),
+            None => None,
+        };
+        if let (Some(host_version), Some(ip_version)) = (host_version, filter.ip_version)
+            && host_version != ip_version {
+            return Err(CaptureFilterError("The host address does not match the IP version.".to_string()));
+        }
+        Ok(filter)
+    }
+}
+
+impl Display for CaptureFilter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let ports = self.ports.iter().map(|clause| {
+            let ports = clause.ports.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(",");
+            match clause.transport {
+                Transport::Udp => format!("udp port {}", ports),
+                Transport::Tcp => format!("tcp port {}", ports),
+                Transport::Any => format!("port {}", ports),
+            }
+        });
+        let mut clauses = vec![];
+        let ports = ports.collect::<Vec<_>>().join(" or ");
+        if !ports.is_empty() {
+            clauses.push(ports);
+        }
+        if let Some(host) = self.host {
+            clauses.push(format!("host {}", host));
+        }
+        match self.ip_version {
+            Some(IpVersion::V4) => clauses.push("ip".to_string()),
+            Some(IpVersion::V6) => clauses.push("ip6".to_string()),
+            None => {}
+        }
+        match self.vlan {
+            Some(Some(id)) => clauses.push(format!("vlan {}", id)),
+            Some(None) => clauses.push("vlan".to_string()),
+            None => {}
+        }
+        write!(f, "{}", clauses.join(" and "))
+    }
+}
+
+/// Where a jump continues, resolved to a relative offset once the program is complete.
+#[derive(Debug, Clone, Copy)]
+enum Target {
+    Next,
+    Label(usize),
+}
+
+/// Collects instructions with symbolic jump targets.
+struct Assembler {
+    instructions: Vec<(u16, Target, Target, u32)>,
+    /// Index of the instruction each label points at, once placed.
+    labels: Vec<Option<usize>>,
+}
+
+impl Assembler {
+    fn new() -> Self {
+        Self { instructions: vec![], labels: vec![] }
+    }
+
+    fn label(&mut self) -> usize {
+        self.labels.push(None);
+        self.labels.len() - 1
+    }
+
+    fn place(&mut self, label: usize) {
+        self.labels[label] = Some(self.instructions.len());
+    }
+
+    fn statement(&mut self, code: u16, k: u32) {
+        self.instructions.push((code, Target::Next, Target::Next, k));
+    }
+
+    fn jump(&mut self, code: u16, k: u32, jump_true: Target, jump_false: Target) {
+        self.instructions.push((code, jump_true, jump_false, k));
+    }
+
+    fn jump_always(&mut self, label: usize) {
+        self.instructions.push((BPF_JMP_JA, Target::Label(label), Target::Next, 0));
+    }
+
+    fn finish(self) -> Result<Vec<BpfInstruction>, CaptureFilterError> {
+        let too_long = || CaptureFilterError("The filter is too complex.".to_string());
+        let offset = |index: usize, target: Target| -> Result<u32, CaptureFilterError> {
+            match target {
+                Target::Next => Ok(0),
+                Target::Label(label) => {
+                    let destination = self.labels[label].ok_or_else(too_long)?;
+                    Ok((destination - index - 1) as u32)
+                }
+            }
+        };
+        self.instructions
+            .iter()
+            .enumerate()
+            .map(|(index, (code, jump_true, jump_false, k))| {
+                let jump_true = offset(index, *jump_true)?;
+                let jump_false = offset(index, *jump_false)?;
+                // Unconditional jumps take their offset from k, conditional ones are limited to 255 instructions.
+                if *code == BPF_JMP_JA {
+                    return Ok(BpfInstruction { code: *code, jt: 0, jf: 0, k: jump_true });
+                }
+                Ok(BpfInstruction {
+                    code: *code,
+                    jt: u8::try_from(jump_true).map_err(|_| too_long())?,
+                    jf: u8::try_from(jump_false).map_err(|_| too_long())?,
+                    k: *k,
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,385 @@
+use crate::mdns::encoder::{encode_mdns_message, encode_rdata};
+use crate::mdns::mdns_message::MDNSMessage;
+use crate::mdns::parser::parse_mdns_message;
+use crate::mdns::query_sender::QuerySenderConfig;
+use crate::mdns::responder::open_group_sockets;
+use crate::mdns::socket_listener::RECEIVE_BUFFER_LENGTH;
+use crate::mdns::types::{MDNSAnswer, MDNSMessageHeader, MDNSQuestion, MDNSRData, MDNSRecordType};
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+
+/// How long the target is observed when nothing else is chosen.
+pub const DEFAULT_TEST_DURATION: Duration = Duration::from_secs(10);
+
+/// A host must go back to probing right after a conflicting response (RFC 6762 - 9). Leaves room for the
+/// random delay of up to 250 ms before the first probe (RFC 6762 - 8.1) and for slow devices.
+const REPROBE_DEADLINE: Duration = Duration::from_secs(1);
+/// Probes are sent 250 ms apart (RFC 6762 - 8.1), less some slack for timer jitter.
+const MIN_PROBE_GAP: Duration = Duration::from_millis(225);
+const PROBE_COUNT: usize = 3;
+/// Answers to probes are not delayed (RFC 6762 - 6), so the owner of a name defends it within a probe interval.
+const DEFENSE_DEADLINE: Duration = Duration::from_millis(250);
+
+const RESPONSE_FLAGS: u16 = 0x8400;
+/// How long each socket waits for a datagram before the test duration is checked.
+const RECEIVE_TIMEOUT: Duration = Duration::from_millis(20);
+
+/// How the conflicting record is put on the network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictMode {
+    /// An unsolicited response claiming the name. The target has to probe again and rename (RFC 6762 - 9).
+    Announcement,
+    /// A probe for the name. The target owns it, so it has to defend it and keep it (RFC 6762 - 8.1).
+    Probe,
+}
+
+impl Display for ConflictMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mode = match self {
+            ConflictMode::Announcement => "Conflicting announcement",
+            ConflictMode::Probe => "Conflicting probe",
+        };
+        write!(f, "{}", mode)
+    }
+}
+
+/// What to test: a unique record the target device was seen announcing.
+#[derive(Clone)]
+pub struct ConflictTestConfig {
+    /// Addresses of the device under test, only their messages are evaluated. A device defends or renames its name
+    /// from any of its addresses, e.g. over IPv6 while it was tested over IPv4.
+    pub targets: Vec<IpAddr>,
+    pub record: MDNSAnswer,
+    pub mode: ConflictMode,
+    /// How long the target is observed. An announcement test ends earlier once the new name is announced.
+    pub duration: Duration,
+}
+
+/// One requirement of the report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConflictCheck {
+    pub requirement: String,
+    pub passed: bool,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConflictReport {
+    pub mode: ConflictMode,
+    pub record: String,
+    pub checks: Vec<ConflictCheck>,
+    /// The name the target moved its record to.
+    pub new_name: Option<String>,
+    /// What was sent and what the target did, with the time since the conflict was sent.
+    pub timeline: Vec<String>,
+}
+
+impl ConflictReport {
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|c| c.passed)
+    }
+}
+
+/// Evaluates what the target does after the conflict, without any I/O. Times are passed in as the time since
+/// the conflicting message was sent.
+pub struct ConflictObserver {
+    config: ConflictTestConfig,
+    conflicting: MDNSAnswer,
+    /// Probes of the target for a record of the tested type, with the probed name.
+    probes: Vec<(Duration, String)>,
+    /// Responses of the target with a record of the tested type under another name, with the cache flush bit.
+    announcements: Vec<(Duration, String, bool)>,
+    /// When the target first repeated the original record.
+    defended: Option<Duration>,
+    timeline: Vec<String>,
+}
+
+impl ConflictObserver {
+    pub fn new(config: ConflictTestConfig) -> Self {
+        let conflicting = MDNSAnswer {
+            cache_flush: true,
+            rdata: conflicting_rdata(&config.record.rdata),
+            ..config.record.clone()
+        };
+        Self {
+            config,
+            conflicting,
+            probes: vec![],
+            announcements: vec![],
+            defended: None,
+            timeline: vec![],
+        }
+    }
+
+    /// The message which starts the test.
+    pub fn injection(&mut self) -> MDNSMessage {
+        self.log(Duration::ZERO, format!("Sent {}: {} => {}", self.config.mode.to_string().to_lowercase(), self.conflicting.name, self.conflicting.rdata));
+        match self.config.mode {
+            ConflictMode::Announcement => response(vec![self.conflicting.clone()]),
+            // A multicast probe, so the defense of the target is multicast too and shows up in the capture.
+            ConflictMode::Probe => message(0, vec![MDNSQuestion {
+                name: self.conflicting.name.clone(),
+                question_type: MDNSRecordType::ANY,
+                question_class: self.conflicting.answer_class.clone(),
+                unicast_response: false,
+            }], vec![], vec![self.conflicting.clone()]),
+        }
+    }
+
+    /// Takes note of a message received `elapsed` after the conflict. Returns the response to send when the target
+    /// probes for the name we claimed, which we defend so the target has to rename.
+    pub fn observe(&mut self, received: &MDNSMessage, source: IpAddr, elapsed: Duration) -> Option<MDNSMessage> {
+        if !self.config.targets.contains(&source) {
+            return None;
+        }
+        let record_type = self.config.record.answer_type;
+        let original = self.config.record.name.clone();
+        if !received.header.is_response() {
+            let mut defense = None;
+            let probed: Vec<String> = received.authorities.iter().filter(|r| r.answer_type == record_type).map(|r| r.name.clone()).collect();
+            for name in probed {
+                self.log(elapsed, format!("Target probed for {}", name));
+                if name.eq_ignore_ascii_case(&original) && self.config.mode == ConflictMode::Announcement {
+                    self.log(elapsed, format!("Defended {}", name));
+                    defense = Some(response(vec![self.conflicting.clone()]));
+                }
+                self.probes.push((elapsed, name));
+            }
+            return defense;
+        }
+        let records: Vec<MDNSAnswer> = received.answers.iter().chain(received.additionals.iter())
+            .filter(|r| r.answer_type == record_type && r.ttl_seconds > 0)
+            .cloned()
+            .collect();
+        for record in records {
+            if record.name.eq_ignore_ascii_case(&original) {
+                if self.defended.is_none() && same_rdata(&record.rdata, &self.config.record.rdata) {
+                    self.log(elapsed, format!("Target answered with its record {} => {}", record.name, record.rdata));
+                    self.defended = Some(elapsed);
+                }
+                continue;
+            }
+            if !self.announcements.iter().any(|(_, name, _)| name.eq_ignore_ascii_case(&record.name)) {
+                self.log(elapsed, format!("Target announced {} => {}", record.name, record.rdata));
+            }
+            self.announcements.push((elapsed, record.name.clone(), record.cache_flush));
+        }
+        None
+    }
+
+    /// Whether the outcome is known before the test duration is over.
+    pub fn is_complete(&self) -> bool {
+        match self.config.mode {
+            ConflictMode::Announcement => self.new_name().is_some_and(|n| self.announcements.iter().any(|(_, name, _)| *name == n)),
+            // The target has to keep its name for the whole duration.
+            ConflictMode::Probe => false,
+        }
+    }
+
+    /// The first other name the target probed or announced the record under.
+    fn new_name(&self) -> Option<String> {
+        let probed = self.probes.iter().map(|(at, name)| (*at, name));
+        let announced = self.announcements.iter().map(|(at, name, _)| (*at, name));
+        probed.chain(announced)
+            .filter(|(_, name)| !name.eq_ignore_ascii_case(&self.config.record.name))
+            .min_by_key(|(at, _)| *at)
+            .map(|(_, name)| name.clone())
+    }
+
+    pub fn report(&self) -> ConflictReport {
+        let new_name = self.new_name();
+        let checks = match self.config.mode {
+            ConflictMode::Announcement => self.announcement_checks(new_name.as_deref()),
+            ConflictMode::Probe => self.probe_checks(new_name.as_deref()),
+        };
+        ConflictReport {
+            mode: self.config.mode,
+            record: format!("{} {} => {}", self.config.record.answer_type, self.config.record.name, self.config.record.rdata),
+            checks,
+            new_name,
+            timeline: self.timeline.clone(),
+        }
+    }
+
+    fn announcement_checks(&self, new_name: Option<&str>) -> Vec<ConflictCheck> {
+        let first_probe = self.probes.first().map(|(at, _)| *at);
+        let mut checks = vec![
+            check(
+                format!("Probes again within {} ms of the conflict (RFC 6762 - 9)", REPROBE_DEADLINE.as_millis()),
+                first_probe.is_some_and(|at| at <= REPROBE_DEADLINE),
+                first_probe.map_or("No probe was seen.".to_string(), |at| format!("First probe after {}.", milliseconds(at))),
+            ),
+            check(
+                "Picks a new name".to_string(),
+                new_name.is_some(),
+                new_name.map_or("The record was not moved to another name.".to_string(), |n| format!("Renamed to {}.", n)),
+            ),
+        ];
+        let new_name = match new_name {
+            Some(n) => n,
+            None => return checks,
+        };
+        let first_announcement = self.announcements.iter().find(|(_, name, _)| name.eq_ignore_ascii_case(new_name));
+        let announced_at = first_announcement.map_or(Duration::MAX, |(at, _, _)| *at);
+        let probe_times: Vec<Duration> = self.probes.iter()
+            .filter(|(at, name)| name.eq_ignore_ascii_case(new_name) && *at < announced_at)
+            .map(|(at, _)| *at)
+            .collect();
+        let shortest_gap = probe_times.windows(2).map(|w| w[1] - w[0]).min();
+        checks.push(check(
+            format!("Probes the new name {} times, {} ms apart (RFC 6762 - 8.1)", PROBE_COUNT, 250),
+            probe_times.len() >= PROBE_COUNT && shortest_gap.is_some_and(|g| g >= MIN_PROBE_GAP),
+            match shortest_gap {
+                Some(gap) => format!("{} probes, the shortest gap was {}.", probe_times.len(), milliseconds(gap)),
+                None => format!("{} probes.", probe_times.len()),
+            },
+        ));
+        checks.push(match first_announcement {
+            Some((at, _, true)) => check(announce_requirement(), true, format!("Announced after {}.", milliseconds(*at))),
+            Some((at, _, false)) => check(announce_requirement(), false, format!("Announced after {} without the cache flush bit.", milliseconds(*at))),
+            None => check(announce_requirement(), false, "The new name was not announced.".to_string()),
+        });
+        checks
+    }
+
+    fn probe_checks(&self, new_name: Option<&str>) -> Vec<ConflictCheck> {
+        vec![
+            check(
+                format!("Defends the record within {} ms (RFC 6762 - 8.1)", DEFENSE_DEADLINE.as_millis()),
+                self.defended.is_some_and(|at| at <= DEFENSE_DEADLINE),
+                self.defended.map_or("The target did not answer the probe.".to_string(), |at| format!("Answered after {}.", milliseconds(at))),
+            ),
+            check(
+                "Keeps its name".to_string(),
+                new_name.is_none(),
+                new_name.map_or("The record stayed under its name.".to_string(), |n| format!("Moved the record to {}.", n)),
+            ),
+        ]
+    }
+
+    fn log(&mut self, elapsed: Duration, event: String) {
+        self.timeline.push(format!("{:>9}  {}", milliseconds(elapsed), event));
+    }
+}
+
+/// Addresses of the device which sent the message: the source address and the addresses its response gives.
+pub fn target_addresses(source: IpAddr, message: &MDNSMessage) -> Vec<IpAddr> {
+    let mut addresses = vec![source];
+    for record in message.answers.iter().chain(message.additionals.iter()).filter(|r| r.ttl_seconds > 0) {
+        let address = match record.rdata {
+            MDNSRData::A { ipv4_address } => IpAddr::V4(ipv4_address),
+            MDNSRData::AAAA { ipv6_addr } => IpAddr::V6(ipv6_addr),
+            _ => continue
+        };
+        if !addresses.contains(&address) {
+            addresses.push(address);
+        }
+    }
+    addresses
+}
+
+/// Sends the conflict on the interface and observes the target for the configured duration.
+/// Blocks until the test is over.
+pub fn run_conflict_test(config: &QuerySenderConfig, test: ConflictTestConfig) -> io::Result<ConflictReport> {
+    let sockets = open_group_sockets(config, RECEIVE_TIMEOUT)?;
+    let duration = test.duration;
+    let mut observer = ConflictObserver::new(test);
+    // Our own messages are looped back, they are recognized by their payload.
+    let mut sent: Vec<Vec<u8>> = vec![];
+    let injection = observer.injection();
+    let started = Instant::now();
+    send_multicast(&sockets, &injection, &mut sent)?;
+
+    let mut buffer = vec![0u8; RECEIVE_BUFFER_LENGTH];
+    while started.elapsed() < duration && !observer.is_complete() {
+        for (socket, _) in sockets.iter() {
+            let (length, source) = match socket.recv_from(&mut buffer) {
+                Ok(r) => r,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut => continue,
+                Err(e) => return Err(e),
+            };
+            let payload = &buffer[..length];
+            if sent.iter().any(|s| s == payload) {
+                continue;
+            }
+            let message = match parse_mdns_message(payload) {
+                Ok(m) => m,
+                Err(_) => continue
+            };
+            if let Some(defense) = observer.observe(&message, source.ip(), started.elapsed()) {
+                send_multicast(&sockets, &defense, &mut sent)?;
+            }
+        }
+    }
+    Ok(observer.report())
+}
+
+/// Sends the message to the group and remembers its payload.
+fn send_multicast(sockets: &[(UdpSocket, SocketAddr)], message: &MDNSMessage, sent: &mut Vec<Vec<u8>>) -> io::Result<()> {
+    let payload = encode_mdns_message(message).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+    for (socket, group) in sockets.iter() {
+        socket.send_to(&payload, group)?;
+    }
+    sent.push(payload);
+    Ok(())
+}
+
+/// Same type, other data. Addresses and ports are incremented, names and TXT data get a marker.
+fn conflicting_rdata(rdata: &MDNSRData) -> MDNSRData {
+    match rdata {
+        MDNSRData::A { ipv4_address } => MDNSRData::A { ipv4_address: Ipv4Addr::from(u32::from(*ipv4_address).wrapping_add(1)) },
+        MDNSRData::AAAA { ipv6_addr } => MDNSRData::AAAA { ipv6_addr: Ipv6Addr::from(u128::from(*ipv6_addr).wrapping_add(1)) },
+        MDNSRData::PTR { domain_name } => MDNSRData::PTR { domain_name: format!("conflict-{}", domain_name) },
+        MDNSRData::SRV { priority, weight, port, target_domain_name } => MDNSRData::SRV {
+            priority: *priority,
+            weight: *weight,
+            port: port.wrapping_add(1),
+            target_domain_name: target_domain_name.clone(),
+        },
+        MDNSRData::TXT { text } if text.is_empty() => MDNSRData::TXT { text: "conflict=1".to_string() },
+        MDNSRData::TXT { text } => MDNSRData::TXT { text: format!("{}\nconflict=1", text) },
+        MDNSRData::CNAME { canonical_domain_name } => MDNSRData::CNAME { canonical_domain_name: format!("conflict-{}", canonical_domain_name) },
+        MDNSRData::NSEC { raw } => MDNSRData::NSEC { raw: [raw.as_slice(), &[0]].concat() },
+        MDNSRData::ANY { raw } => MDNSRData::ANY { raw: [raw.as_slice(), &[0]].concat() },
+        MDNSRData::OTHER { raw } => MDNSRData::OTHER { raw: [raw.as_slice(), &[0]].concat() },
+    }
+}
+
+fn same_rdata(a: &MDNSRData, b: &MDNSRData) -> bool {
+    encode_rdata(a).ok() == encode_rdata(b).ok()
+}
+
+fn announce_requirement() -> String {
+    "Announces the new name with the cache flush bit (RFC 6762 - 8.3)".to_string()
+}
+
+fn check(requirement: String, passed: bool, detail: String) -> ConflictCheck {
+    ConflictCheck { requirement, passed, detail }
+}
+
+fn milliseconds(duration: Duration) -> String {
+    format!("{:.1} ms", duration.as_secs_f64() * 1000.0)
+}
+
+fn response(answers: Vec<MDNSAnswer>) -> MDNSMessage {
+    message(RESPONSE_FLAGS, vec![], answers, vec![])
+}
+
+fn message(flags: u16, questions: Vec<MDNSQuestion>, answers: Vec<MDNSAnswer>, authorities: Vec<MDNSAnswer>) -> MDNSMessage {
+    MDNSMessage {
+        header: MDNSMessageHeader {
+            query_identifier: 0,
+            flags,
+            question_count: questions.len() as u16,
+            answer_count: answers.len() as u16,
+            authority_count: authorities.len() as u16,
+            additional_count: 0,
+        },
+        questions,
+        answers,
+        authorities,
+        additionals: vec![],
+    }
+}
@@ -0,0 +1,186 @@
+use crate::mdns::mdns_message::MDNSMessage;
+use crate::mdns::parser::escape_label;
+use crate::mdns::types::{MDNSAnswer, MDNSQuestion, MDNSRData};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+/// Longest label allowed by RFC 1035 - 2.3.4.
+const MAX_LABEL_LENGTH: usize = 63;
+/// Longest name on the wire, including the length bytes and the trailing 0.
+const MAX_NAME_LENGTH: usize = 255;
+/// Compression pointers have 14 bits, names further into the message cannot be pointed to.
+const MAX_POINTER_OFFSET: usize = 0x3fff;
+/// The top bit of the class, used as QU bit in questions and cache flush bit in answers.
+const CLASS_TOP_BIT: u16 = 0x8000;
+
+/// Reason why a message could not be encoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncodeError {
+    /// A name with two consecutive dots or a leading dot.
+    EmptyLabel(String),
+    LabelTooLong(String),
+    NameTooLong(String),
+    /// A TXT string longer than 255 bytes.
+    TextTooLong(String),
+    /// More questions or answers than the 16 bit count of a section can hold.
+    TooManyRecords,
+    /// Record data longer than the 16 bit record length can hold.
+    RecordTooLong(String),
+}
+
+impl Display for EncodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EncodeError::EmptyLabel(name) => write!(f, "Name {} contains an empty label.", name),
+            EncodeError::LabelTooLong(name) => write!(f, "Name {} contains a label longer than {} bytes.", name, MAX_LABEL_LENGTH),
+            EncodeError::NameTooLong(name) => write!(f, "Name {} is longer than {} bytes.", name, MAX_NAME_LENGTH),
+            EncodeError::TextTooLong(text) => write!(f, "TXT string {} is longer than 255 bytes.", text),
+            EncodeError::TooManyRecords => write!(f, "Too many records for a single message."),
+            EncodeError::RecordTooLong(name) => write!(f, "Record data of {} is longer than 65535 bytes.", name),
+        }
+    }
+}
+
+/// Encodes the message into the DNS wire format. The counts in the header are taken from the sections.
+/// Names are compressed (RFC 1035 - 4.1.4), also inside PTR, SRV and CNAME data as RFC 6762 - 18.14 allows.
+pub fn encode_mdns_message(message: &MDNSMessage) -> Result<Vec<u8>, EncodeError> {
+    let question_count = u16::try_from(message.questions.len()).map_err(|_| EncodeError::TooManyRecords)?;
+    let record_sections = [&message.answers, &message.authorities, &message.additionals];
+    let mut writer = MessageWriter::default();
+    writer.write_u16(message.header.query_identifier);
+    writer.write_u16(message.header.flags);
+    writer.write_u16(question_count);
+    for records in record_sections {
+        writer.write_u16(u16::try_from(records.len()).map_err(|_| EncodeError::TooManyRecords)?);
+    }
+    for question in message.questions.iter() {
+        writer.write_question(question)?;
+    }
+    for answer in record_sections.into_iter().flatten() {
+        writer.write_answer(answer)?;
+    }
+    Ok(writer.bytes)
+}
+
+/// Encodes record data on its own, without name compression. Used to compare records by their data (RFC 6762 - 8.2).
+pub fn encode_rdata(rdata: &MDNSRData) -> Result<Vec<u8>, EncodeError> {
+    let mut writer = MessageWriter::default();
+    writer.write_rdata(rdata)?;
+    Ok(writer.bytes)
+}
+
+#[derive(Default)]
+struct MessageWriter {
+    bytes: Vec<u8>,
+    /// Offsets of the names written so far, keyed by every suffix of them.
+    names: HashMap<String, usize>,
+}
+
+impl MessageWriter {
+    fn write_u16(&mut self, value: u16) {
+        self.bytes.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn write_u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn write_question(&mut self, question: &MDNSQuestion) -> Result<(), EncodeError> {
+        self.write_name(&question.name)?;
+        self.write_u16(question.question_type as u16);
+        let unicast_response = if question.unicast_response { CLASS_TOP_BIT } else { 0 };
+        self.write_u16(question.question_class.clone() as u16 | unicast_response);
+        Ok(())
+    }
+
+    fn write_answer(&mut self, answer: &MDNSAnswer) -> Result<(), EncodeError> {
+        self.write_name(&answer.name)?;
+        self.write_u16(answer.answer_type as u16);
+        let cache_flush = if answer.cache_flush { CLASS_TOP_BIT } else { 0 };
+        self.write_u16(answer.answer_class.clone() as u16 | cache_flush);
+        self.write_u32(answer.ttl_seconds);
+        // The length is only known once the data is written, `rd_length` of the answer is ignored.
+        let length_offset = self.bytes.len();
+        self.write_u16(0);
+        self.write_rdata(&answer.rdata)?;
+        let length = u16::try_from(self.bytes.len() - length_offset - 2).map_err(|_| EncodeError::RecordTooLong(answer.name.clone()))?;
+        self.bytes[length_offset..length_offset + 2].copy_from_slice(&length.to_be_bytes());
+        Ok(())
+    }
+
+    fn write_rdata(&mut self, rdata: &MDNSRData) -> Result<(), EncodeError> {
+        match rdata {
+            MDNSRData::A { ipv4_address } => self.bytes.extend_from_slice(&ipv4_address.octets()),
+            MDNSRData::AAAA { ipv6_addr } => self.bytes.extend_from_slice(&ipv6_addr.octets()),
+            MDNSRData::PTR { domain_name } => self.write_name(domain_name)?,
+            MDNSRData::SRV { priority, weight, port, target_domain_name } => {
+                self.write_u16(*priority);
+                self.write_u16(*weight);
+                self.write_u16(*port);
+                self.write_name(target_domain_name)?;
+            }
+            MDNSRData::TXT { text } => self.write_text(text)?,
+            MDNSRData::CNAME { canonical_domain_name } => self.write_name(canonical_domain_name)?,
+            MDNSRData::NSEC { raw } | MDNSRData::ANY { raw } | MDNSRData::OTHER { raw } => self.bytes.extend_from_slice(raw),
+        }
+        Ok(())
+    }
+
+    /// Every line of the text becomes one character string. An empty text is a single empty string (RFC 6763 - 6.1).
+    fn write_text(&mut self, text: &str) -> Result<(), EncodeError> {
+        for line in text.split('\n') {
+            let length = u8::try_from(line.len()).map_err(|_| EncodeError::TextTooLong(line.to_string()))?;
+            self.bytes.push(length);
+            self.bytes.extend_from_slice(line.as_bytes());
+        }
+        Ok(())
+    }
+
+    fn write_name(&mut self, name: &str) -> Result<(), EncodeError> {
+        let trimmed = match name.strip_suffix('.') {
+            Some(t) if !t.ends_with('\\') => t,
+            _ => name,
+        };
+        let labels = split_labels(trimmed);
+        if labels.iter().map(|l| l.len() + 1).sum::<usize>() + 1 > MAX_NAME_LENGTH {
+            return Err(EncodeError::NameTooLong(name.to_string()));
+        }
+        for (index, label) in labels.iter().enumerate() {
+            if label.is_empty() {
+                return Err(EncodeError::EmptyLabel(name.to_string()));
+            }
+            if label.len() > MAX_LABEL_LENGTH {
+                return Err(EncodeError::LabelTooLong(name.to_string()));
+            }
+            let suffix = labels[index..].iter().map(|l| escape_label(l)).collect::<Vec<String>>().join(".");
+            if let Some(offset) = self.names.get(&suffix) {
+                self.write_u16(0xc000 | *offset as u16);
+                return Ok(());
+            }
+            if self.bytes.len() <= MAX_POINTER_OFFSET {
+                self.names.insert(suffix, self.bytes.len());
+            }
+            self.bytes.push(label.len() as u8);
+            self.bytes.extend_from_slice(label.as_bytes());
+        }
+        self.bytes.push(0);
+        Ok(())
+    }
+}
+
+/// Splits a name at the dots which are not escaped, a label may contain a dot written as `\.` (RFC 6763 - 4.3).
+fn split_labels(name: &str) -> Vec<String> {
+    if name.is_empty() {
+        return vec![];
+    }
+    let mut labels = vec![String::new()];
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => labels.last_mut().unwrap().extend(chars.next()),
+            '.' => labels.push(String::new()),
+            _ => labels.last_mut().unwrap().push(c),
+        }
+    }
+    labels
+}
@@ -0,0 +1,197 @@
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+/// What the sending side does when the channel is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackpressurePolicy {
+    /// Wait until the receiver makes room. Nothing is dropped, but the producer is slowed down.
+    Block,
+    /// Evict the oldest queued event to make room for the new one.
+    DropOldest,
+    /// Discard the new event and keep the queued ones.
+    DropNewest,
+}
+
+struct Shared<T> {
+    queue: Mutex<VecDeque<T>>,
+    not_empty: Condvar,
+    not_full: Condvar,
+    capacity: usize,
+    policy: BackpressurePolicy,
+    dropped: AtomicU64,
+    sent: AtomicU64,
+    sender_alive: AtomicBool,
+    receiver_alive: AtomicBool,
+}
+
+/// Producing half of a bounded event channel.
+pub struct EventSender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+/// Consuming half of a bounded event channel.
+pub struct EventReceiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+/// Reads the counters of an event channel without being able to send or receive.
+pub struct EventChannelMonitor<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> EventChannelMonitor<T> {
+    pub fn stats(&self) -> EventChannelStats {
+        stats(&self.shared)
+    }
+}
+
+/// Shared counters of an event channel which can be read from any thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct EventChannelStats {
+    pub sent: u64,
+    pub dropped: u64,
+    pub queued: usize,
+}
+
+/// Creates a bounded channel which applies the given policy once `capacity` events are queued.
+pub fn bounded<T>(capacity: usize, policy: BackpressurePolicy) -> (EventSender<T>, EventReceiver<T>) {
+    let shared = Arc::new(Shared {
+        queue: Mutex::new(VecDeque::with_capacity(capacity.min(1024))),
+        not_empty: Condvar::new(),
+        not_full: Condvar::new(),
+        capacity: capacity.max(1),
+        policy,
+        dropped: AtomicU64::new(0),
+        sent: AtomicU64::new(0),
+        sender_alive: AtomicBool::new(true),
+        receiver_alive: AtomicBool::new(true),
+    });
+    (EventSender { shared: shared.clone() }, EventReceiver { shared })
+}
+
+impl Display for BackpressurePolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let policy = match self {
+            BackpressurePolicy::Block => "Block capture",
+            BackpressurePolicy::DropOldest => "Drop oldest",
+            BackpressurePolicy::DropNewest => "Drop newest",
+        };
+        write!(f, "{}", policy)
+    }
+}
+
+impl<T> EventSender<T> {
+    /// Queues the event according to the backpressure policy.
+    /// Returns false when the receiver is gone or `stop` was set while waiting for room, and the event was discarded.
+    pub fn send(&self, event: T, stop: &AtomicBool) -> bool {
+        let shared = &self.shared;
+        if !shared.receiver_alive.load(Ordering::SeqCst) {
+            return false;
+        }
+        let mut queue = shared.queue.lock().unwrap();
+        if queue.len() >= shared.capacity {
+            match shared.policy {
+                BackpressurePolicy::Block => {
+                    while queue.len() >= shared.capacity {
+                        if !shared.receiver_alive.load(Ordering::SeqCst) || stop.load(Ordering::SeqCst) {
+                            return false;
+                        }
+                        // Wake up periodically so a receiver dropped or a stop requested while we wait is noticed.
+                        queue = shared.not_full.wait_timeout(queue, Duration::from_millis(100)).unwrap().0;
+                    }
+                }
+                BackpressurePolicy::DropOldest => {
+                    queue.pop_front();
+                    shared.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+                BackpressurePolicy::DropNewest => {
+                    shared.dropped.fetch_add(1, Ordering::Relaxed);
+                    return true;
+                }
+            }
+        }
+        queue.push_back(event);
+        shared.sent.fetch_add(1, Ordering::Relaxed);
+        shared.not_empty.notify_one();
+        true
+    }
+}
+
+impl<T> Drop for EventSender<T> {
+    fn drop(&mut self) {
+        self.shared.sender_alive.store(false, Ordering::SeqCst);
+        let _guard = self.shared.queue.lock().unwrap();
+        self.shared.not_empty.notify_all();
+    }
+}
+
+impl<T> EventReceiver<T> {
+    /// Returns the next queued event without waiting.
+    pub fn try_recv(&self) -> Option<T> {
+        let event = self.shared.queue.lock().unwrap().pop_front();
+        if event.is_some() {
+            self.shared.not_full.notify_one();
+        }
+        event
+    }
+
+    /// Waits for the next event. Returns `None` on timeout or once the sender is gone and the queue is drained.
+    #[cfg(test)]
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<T> {
+        use std::time::Instant;
+        // A deadline too far in the future to be represented means waiting without a timeout.
+        let deadline = Instant::now().checked_add(timeout);
+        let mut queue = self.shared.queue.lock().unwrap();
+        loop {
+            if let Some(event) = queue.pop_front() {
+                self.shared.not_full.notify_one();
+                return Some(event);
+            }
+            if !self.shared.sender_alive.load(Ordering::SeqCst) {
+                return None;
+            }
+            queue = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return None;
+                    }
+                    self.shared.not_empty.wait_timeout(queue, deadline - now).unwrap().0
+                }
+                None => self.shared.not_empty.wait(queue).unwrap(),
+            };
+        }
+    }
+
+    /// Non-blocking iterator over the currently queued events.
+    pub fn try_iter(&self) -> impl Iterator<Item = T> + '_ {
+        std::iter::from_fn(move || self.try_recv())
+    }
+
+    pub fn stats(&self) -> EventChannelStats {
+        stats(&self.shared)
+    }
+
+    pub fn monitor(&self) -> EventChannelMonitor<T> {
+        EventChannelMonitor { shared: self.shared.clone() }
+    }
+}
+
+fn stats<T>(shared: &Shared<T>) -> EventChannelStats {
+    EventChannelStats {
+        sent: shared.sent.load(Ordering::Relaxed),
+        dropped: shared.dropped.load(Ordering::Relaxed),
+        queued: shared.queue.lock().unwrap().len(),
+    }
+}
+
+impl<T> Drop for EventReceiver<T> {
+    fn drop(&mut self) {
+        self.shared.receiver_alive.store(false, Ordering::SeqCst);
+        let _guard = self.shared.queue.lock().unwrap();
+        self.shared.not_full.notify_all();
+    }
+}
@@ -0,0 +1,272 @@
+use crate::mdns::encoder::encode_mdns_message;
+use crate::mdns::mdns_message::MDNSMessage;
+use crate::mdns::query_sender::{open_group_senders, send_to_groups, QuerySenderConfig};
+use crate::mdns::types::{MDNSAnswer, MDNSMessageHeader, MDNSQueryClass, MDNSQuestion, MDNSRData, MDNSRecordType};
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Placeholder in generated names, replaced by the number of the message so every message asks for another name.
+pub const MESSAGE_NUMBER_PLACEHOLDER: &str = "{n}";
+/// TTL of the generated records, the one RFC 6762 - 10 recommends for records naming a host.
+const RECORD_TTL: u32 = 120;
+const RESPONSE_FLAGS: u16 = 0x8400;
+/// Target of generated SRV and CNAME records.
+const TARGET_HOST: &str = "loadgen.local";
+/// How often the sending thread checks for messages which are due.
+const SEND_INTERVAL: Duration = Duration::from_millis(1);
+/// Period over which the current rate is measured.
+const RATE_WINDOW: Duration = Duration::from_millis(250);
+
+/// A record type of the traffic mix and how often it is picked relative to the others.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MixEntry {
+    pub record_type: MDNSRecordType,
+    pub weight: u32,
+}
+
+/// The traffic a load run sends.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadProfile {
+    /// Messages per second.
+    pub rate: f64,
+    /// The run stops after this long.
+    pub duration: Duration,
+    /// Percentage of announcements, the other messages are queries.
+    pub announcement_percent: u8,
+    pub mix: Vec<MixEntry>,
+    /// Names used in turn. `{n}` is replaced by the number of the message.
+    pub names: Vec<String>,
+}
+
+/// Builds the messages of a load run. The mix is followed exactly rather than randomly, so runs are repeatable.
+pub struct LoadGenerator {
+    profile: LoadProfile,
+    /// Number of messages generated so far.
+    count: u64,
+    /// Running weights of the smooth weighted round robin over the mix.
+    current_weights: Vec<i64>,
+}
+
+impl LoadGenerator {
+    pub fn new(profile: LoadProfile) -> Result<Self, String> {
+        if profile.names.iter().all(|n| n.trim().is_empty()) {
+            return Err("Enter at least one name.".to_string());
+        }
+        if profile.mix.iter().all(|e| e.weight == 0) {
+            return Err("Pick at least one record type.".to_string());
+        }
+        if profile.rate <= 0.0 {
+            return Err("The rate must be above zero.".to_string());
+        }
+        let current_weights = vec![0; profile.mix.len()];
+        Ok(Self { profile, count: 0, current_weights })
+    }
+
+    /// Builds the next query or announcement.
+    pub fn next_message(&mut self) -> MDNSMessage {
+        let number = self.count;
+        self.count += 1;
+        let record_type = self.next_record_type();
+        let names: Vec<&String> = self.profile.names.iter().filter(|n| !n.trim().is_empty()).collect();
+        let name = names[(number % names.len() as u64) as usize].trim().replace(MESSAGE_NUMBER_PLACEHOLDER, &number.to_string());
+        // Spreads the announcements evenly over every hundred messages.
+        let percent = self.profile.announcement_percent.min(100) as u64;
+        let announce = (number % 100 + 1) * percent / 100 > (number % 100) * percent / 100;
+        // Types without made-up data, like ANY, are only asked for.
+        match announce.then(|| announcement_rdata(&name, record_type, number)).flatten() {
+            Some(rdata) => announcement(&name, record_type, rdata),
+            None => query(&name, record_type),
+        }
+    }
+
+    /// Number of messages which are due `elapsed` after the start of the run: one right away and then one per
+    /// `1 / rate` seconds, up to the messages of the whole duration.
+    pub fn messages_due(&self, elapsed: Duration) -> u64 {
+        let total = (self.profile.duration.as_secs_f64() * self.profile.rate).ceil() as u64;
+        ((elapsed.as_secs_f64() * self.profile.rate) as u64 + 1).min(total)
+    }
+
+    /// Smooth weighted round robin: every entry gains its weight, the leader is picked and loses the total.
+    fn next_record_type(&mut self) -> MDNSRecordType {
+        let total: i64 = self.profile.mix.iter().map(|e| e.weight as i64).sum();
+        let mut picked = 0;
+        for (index, entry) in self.profile.mix.iter().enumerate() {
+            self.current_weights[index] += entry.weight as i64;
+            if self.current_weights[index] > self.current_weights[picked] {
+                picked = index;
+            }
+        }
+        self.current_weights[picked] -= total;
+        self.profile.mix[picked].record_type
+    }
+}
+
+fn query(name: &str, record_type: MDNSRecordType) -> MDNSMessage {
+    MDNSMessage {
+        header: MDNSMessageHeader {
+            query_identifier: 0,
+            flags: 0,
+            question_count: 1,
+            answer_count: 0,
+            authority_count: 0,
+            additional_count: 0,
+        },
+        questions: vec![MDNSQuestion {
+            name: name.to_string(),
+            question_type: record_type,
+            question_class: MDNSQueryClass::IN,
+            unicast_response: false,
+        }],
+        answers: vec![],
+        authorities: vec![],
+        additionals: vec![],
+    }
+}
+
+/// Made-up data of an announced record. Addresses come from the documentation ranges (RFC 5737, RFC 3849).
+/// Other types, like ANY, which is no record type at all, have none.
+fn announcement_rdata(name: &str, record_type: MDNSRecordType, number: u64) -> Option<MDNSRData> {
+    let rdata = match record_type {
+        MDNSRecordType::A => MDNSRData::A { ipv4_address: Ipv4Addr::new(192, 0, 2, (number % 254 + 1) as u8) },
+        MDNSRecordType::AAAA => MDNSRData::AAAA { ipv6_addr: Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, (number % 0xffff + 1) as u16) },
+        MDNSRecordType::PTR => MDNSRData::PTR { domain_name: format!("Load {}.{}", number, name) },
+        MDNSRecordType::SRV => MDNSRData::SRV { priority: 0, weight: 0, port: 9, target_domain_name: TARGET_HOST.to_string() },
+        MDNSRecordType::TXT => MDNSRData::TXT { text: format!("n={}", number) },
+        MDNSRecordType::CNAME => MDNSRData::CNAME { canonical_domain_name: TARGET_HOST.to_string() },
+        _ => return None,
+    };
+    Some(rdata)
+}
+
+/// An unsolicited response with a record of the given type.
+fn announcement(name: &str, record_type: MDNSRecordType, rdata: MDNSRData) -> MDNSMessage {
+    MDNSMessage {
+        header: MDNSMessageHeader {
+            query_identifier: 0,
+            flags: RESPONSE_FLAGS,
+            question_count: 0,
+            answer_count: 1,
+            authority_count: 0,
+            additional_count: 0,
+        },
+        questions: vec![],
+        answers: vec![MDNSAnswer {
+            name: name.to_string(),
+            answer_type: record_type,
+            answer_class: MDNSQueryClass::IN,
+            // PTR records are shared, every other record is announced as unique (RFC 6762 - 10.2).
+            cache_flush: record_type != MDNSRecordType::PTR,
+            ttl_seconds: RECORD_TTL,
+            rd_length: 0,
+            rdata,
+        }],
+        authorities: vec![],
+        additionals: vec![],
+    }
+}
+
+/// Progress of a load run.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LoadStats {
+    pub queries: u64,
+    pub announcements: u64,
+    /// Messages which could not be encoded or sent.
+    pub errors: u64,
+    pub elapsed: Duration,
+    /// Messages per second sent over the last quarter second.
+    pub current_rate: f64,
+    pub finished: bool,
+    pub last_error: Option<String>,
+}
+
+impl LoadStats {
+    pub fn sent(&self) -> u64 {
+        self.queries + self.announcements
+    }
+}
+
+/// Sends the messages of a load generator to the multicast group on a background thread until the duration passes or it is stopped.
+pub struct LoadRun {
+    stats: Arc<Mutex<LoadStats>>,
+    stop_requested: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl LoadRun {
+    pub fn start(config: &QuerySenderConfig, profile: LoadProfile) -> io::Result<Self> {
+        let mut generator = LoadGenerator::new(profile.clone()).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let sockets = open_group_senders(config)?;
+        let stats = Arc::new(Mutex::new(LoadStats::default()));
+        let stop_requested = Arc::new(AtomicBool::new(false));
+        let thread_stats = stats.clone();
+        let thread_stop_requested = stop_requested.clone();
+        let thread = thread::Builder::new()
+            .name("mDNS load generator".to_string())
+            .spawn(move || {
+                let started = Instant::now();
+                let mut generated: u64 = 0;
+                let (mut window_start, mut window_generated) = (started, 0);
+                while !thread_stop_requested.load(Ordering::SeqCst) && started.elapsed() < profile.duration {
+                    // Messages which fell due while sleeping are sent in a burst, so the rate holds above the sleep granularity.
+                    let due = generator.messages_due(started.elapsed());
+                    while generated < due {
+                        let message = generator.next_message();
+                        let result = send(&sockets, &message);
+                        generated += 1;
+                        let mut stats = thread_stats.lock().unwrap();
+                        match result {
+                            Ok(_) if message.header.is_response() => stats.announcements += 1,
+                            Ok(_) => stats.queries += 1,
+                            Err(e) => {
+                                stats.errors += 1;
+                                stats.last_error = Some(e.to_string());
+                            }
+                        }
+                    }
+                    let mut stats = thread_stats.lock().unwrap();
+                    stats.elapsed = started.elapsed();
+                    if window_start.elapsed() >= RATE_WINDOW {
+                        stats.current_rate = (generated - window_generated) as f64 / window_start.elapsed().as_secs_f64();
+                        (window_start, window_generated) = (Instant::now(), generated);
+                    }
+                    drop(stats);
+                    thread::sleep(SEND_INTERVAL);
+                }
+                let mut stats = thread_stats.lock().unwrap();
+                stats.elapsed = started.elapsed();
+                stats.current_rate = 0.0;
+                stats.finished = true;
+            })?;
+        Ok(Self {
+            stats,
+            stop_requested,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn stats(&self) -> LoadStats {
+        self.stats.lock().unwrap().clone()
+    }
+
+    pub fn stop(&mut self) {
+        self.stop_requested.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for LoadRun {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn send(sockets: &[(UdpSocket, SocketAddr)], message: &MDNSMessage) -> io::Result<()> {
+    let payload = encode_mdns_message(message).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+    send_to_groups(sockets, &payload)
+}
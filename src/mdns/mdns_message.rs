@@ -1,29 +1,100 @@
-use std::net::{IpAddr, Ipv4Addr};
-use crate::mdns::parser::parse_mdns_message;
+use std::fmt::{Display, Formatter};
+use std::net::IpAddr;
+use std::sync::Arc;
+use crate::mdns::capture::LinkType;
 use crate::mdns::types::{MDNSAnswer, MDNSMessageHeader, MDNSQuestion};
-use pnet::packet::udp::UdpPacket;
-use pnet::packet::Packet;
-use time::PrimitiveDateTime;
+use pnet::util::MacAddr;
+use time::OffsetDateTime;
 
 pub struct MDNSMessageReceivedEvent {
-    pub received_datetime: PrimitiveDateTime,
+    /// When the frame was received, as reported by the capture source where possible.
+    pub received_datetime: OffsetDateTime,
+    pub protocol: Protocol,
     pub message: MDNSMessage,
-    pub source_ip: Ipv4Addr,
-    pub destination_ip: Ipv4Addr
+    pub source_ip: IpAddr,
+    pub destination_ip: IpAddr,
+    pub metadata: PacketMetadata,
+    /// The complete captured frame the message was decoded from.
+    pub frame: Arc<[u8]>,
+    pub link_type: LinkType,
+    /// Name of the interface or capture file the frame was read from.
+    pub interface_name: Arc<str>,
+    /// Non-fatal findings of the parser, such as undecoded sections.
+    pub diagnostics: Vec<String>
+}
+
+/// Name resolution protocols sharing the DNS wire format which are decoded by the parser.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Protocol {
+    Mdns,
+    /// Link-Local Multicast Name Resolution (RFC 4795), used by Windows hosts.
+    Llmnr,
+    /// Ordinary DNS on port 53, only decoded on request.
+    UnicastDns,
+}
+
+impl Protocol {
+    pub const ALL: [Protocol; 3] = [Protocol::Mdns, Protocol::Llmnr, Protocol::UnicastDns];
+
+    pub fn port(self) -> u16 {
+        match self {
+            Protocol::Mdns => 5353,
+            Protocol::Llmnr => 5355,
+            Protocol::UnicastDns => 53,
+        }
+    }
+
+    /// The protocol of a UDP datagram, recognized by its well-known port on either side.
+    pub fn from_ports(source_port: u16, destination_port: u16) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| source_port == p.port() || destination_port == p.port())
+    }
+}
+
+impl Display for Protocol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let protocol = match self {
+            Protocol::Mdns => "mDNS",
+            Protocol::Llmnr => "LLMNR",
+            Protocol::UnicastDns => "DNS",
+        };
+        write!(f, "{}", protocol)
+    }
 }
 
 #[derive(Clone)]
 pub struct MDNSMessage {
     pub header: MDNSMessageHeader,
     pub questions: Vec<MDNSQuestion>,
-    pub answers: Vec<MDNSAnswer>
+    pub answers: Vec<MDNSAnswer>,
+    /// Records of the authority section, e.g. the records a host is probing for (RFC 6762 - 8.2).
+    pub authorities: Vec<MDNSAnswer>,
+    /// Records of the additional section, e.g. the SRV, TXT and address records sent along with a PTR answer.
+    pub additionals: Vec<MDNSAnswer>
 }
 
 impl MDNSMessage {
-    pub fn get(udp_packet: &UdpPacket) -> Self
-    {
-        let udp_payload = udp_packet.payload();
-        let parser = parse_mdns_message(udp_payload);
-        parser.unwrap()
+    /// Whether the message is a query asking for a name in the `.local` domain.
+    pub fn is_local_query(&self) -> bool {
+        !self.header.is_response() && self.questions.iter().any(|q| {
+            let name = q.name.trim_end_matches('.').to_ascii_lowercase();
+            name == "local" || name.ends_with(".local")
+        })
     }
-}
\ No newline at end of file
+}
+
+/// Link layer, IP and UDP header fields of the packet a message was received in.
+/// Fields are `None` where the capture source does not expose them, e.g. MAC addresses for the socket listener.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PacketMetadata {
+    pub source_mac: Option<MacAddr>,
+    pub destination_mac: Option<MacAddr>,
+    /// IPv4 TTL or IPv6 hop limit.
+    pub ttl: Option<u8>,
+    pub dscp: Option<u8>,
+    /// Identification field of the IPv4 header, IPv6 headers have none.
+    pub ip_identification: Option<u16>,
+    pub source_port: u16,
+    pub destination_port: u16,
+    /// Length of the frame including the link layer header.
+    pub frame_length: usize,
+}
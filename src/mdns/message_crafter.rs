@@ -0,0 +1,190 @@
+use crate::mdns::encoder::{encode_mdns_message, encode_rdata};
+use crate::mdns::mdns_message::MDNSMessage;
+use crate::mdns::query_sender::{open_group_senders, send_to_groups, QuerySenderConfig};
+use crate::mdns::types::{MDNSAnswer, MDNSHeaderFlags, MDNSMessageHeader, MDNSQueryClass, MDNSQuestion, MDNSRData, MDNSRecordType};
+use std::fmt::{Display, Formatter};
+use std::io;
+
+/// Bytes per line of formatted hex.
+const HEX_LINE_LENGTH: usize = 16;
+
+/// Section of the message a crafted record goes into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordSection {
+    Answer,
+    Authority,
+    Additional,
+}
+
+impl RecordSection {
+    pub const ALL: [RecordSection; 3] = [RecordSection::Answer, RecordSection::Authority, RecordSection::Additional];
+}
+
+impl Display for RecordSection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let section = match self {
+            RecordSection::Answer => "Answer",
+            RecordSection::Authority => "Authority",
+            RecordSection::Additional => "Additional",
+        };
+        write!(f, "{}", section)
+    }
+}
+
+/// A record as edited in the crafter. The data is text as `MDNSRData::from_text` reads it, or hex put on the wire as is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CraftedRecord {
+    pub section: RecordSection,
+    pub name: String,
+    pub record_type: MDNSRecordType,
+    pub record_class: MDNSQueryClass,
+    pub cache_flush: bool,
+    pub ttl_seconds: u32,
+    pub data: String,
+    /// Whether `data` is hex rather than text.
+    pub hex: bool,
+}
+
+impl CraftedRecord {
+    /// Text where the data can be written as text, hex otherwise.
+    pub fn from_answer(section: RecordSection, answer: &MDNSAnswer) -> Self {
+        let (data, hex) = match answer.rdata.to_text() {
+            Some(text) => (text, false),
+            None => (encode_rdata(&answer.rdata).map(|raw| format_hex(&raw)).unwrap_or_default(), true),
+        };
+        Self {
+            section,
+            name: answer.name.clone(),
+            record_type: answer.answer_type,
+            record_class: answer.answer_class.clone(),
+            cache_flush: answer.cache_flush,
+            ttl_seconds: answer.ttl_seconds,
+            data,
+            hex,
+        }
+    }
+
+    pub fn to_answer(&self) -> Result<MDNSAnswer, String> {
+        let rdata = match self.hex {
+            true => MDNSRData::OTHER { raw: parse_hex(&self.data)? },
+            false => MDNSRData::from_text(self.record_type, &self.data)?,
+        };
+        Ok(MDNSAnswer {
+            name: self.name.trim().to_string(),
+            answer_type: self.record_type,
+            answer_class: self.record_class.clone(),
+            cache_flush: self.cache_flush,
+            ttl_seconds: self.ttl_seconds,
+            rd_length: 0,
+            rdata,
+        })
+    }
+}
+
+/// A message as edited in the crafter. The section counts of the header follow the questions and records.
+#[derive(Clone, PartialEq)]
+pub struct CraftedMessage {
+    pub id: u16,
+    pub flags: MDNSHeaderFlags,
+    pub questions: Vec<MDNSQuestion>,
+    pub records: Vec<CraftedRecord>,
+}
+
+impl CraftedMessage {
+    /// A standard multicast query for the name.
+    pub fn query(name: &str, record_type: MDNSRecordType) -> Self {
+        Self {
+            id: 0,
+            flags: MDNSHeaderFlags::default(),
+            questions: vec![MDNSQuestion {
+                name: name.to_string(),
+                question_type: record_type,
+                question_class: MDNSQueryClass::IN,
+                unicast_response: false,
+            }],
+            records: vec![],
+        }
+    }
+
+    pub fn from_message(message: &MDNSMessage) -> Self {
+        let sections = [
+            (RecordSection::Answer, &message.answers),
+            (RecordSection::Authority, &message.authorities),
+            (RecordSection::Additional, &message.additionals),
+        ];
+        Self {
+            id: message.header.query_identifier,
+            flags: MDNSHeaderFlags::from_u16(message.header.flags),
+            questions: message.questions.clone(),
+            records: sections.into_iter()
+                .flat_map(|(section, records)| records.iter().map(move |r| CraftedRecord::from_answer(section, r)))
+                .collect(),
+        }
+    }
+
+    pub fn to_message(&self) -> Result<MDNSMessage, String> {
+        let mut message = MDNSMessage {
+            header: MDNSMessageHeader {
+                query_identifier: self.id,
+                flags: self.flags.to_u16(),
+                question_count: self.questions.len() as u16,
+                answer_count: 0,
+                authority_count: 0,
+                additional_count: 0,
+            },
+            questions: self.questions.iter()
+                .map(|q| MDNSQuestion { name: q.name.trim().to_string(), ..q.clone() })
+                .collect(),
+            answers: vec![],
+            authorities: vec![],
+            additionals: vec![],
+        };
+        for (index, record) in self.records.iter().enumerate() {
+            let answer = record.to_answer().map_err(|e| format!("Record {} ({}): {}", index + 1, record.name.trim(), e))?;
+            match record.section {
+                RecordSection::Answer => message.answers.push(answer),
+                RecordSection::Authority => message.authorities.push(answer),
+                RecordSection::Additional => message.additionals.push(answer),
+            }
+        }
+        message.header.answer_count = message.answers.len() as u16;
+        message.header.authority_count = message.authorities.len() as u16;
+        message.header.additional_count = message.additionals.len() as u16;
+        Ok(message)
+    }
+
+    /// The message in wire format.
+    pub fn encode(&self) -> Result<Vec<u8>, String> {
+        encode_mdns_message(&self.to_message()?).map_err(|e| e.to_string())
+    }
+}
+
+/// Reads hex bytes, ignoring whitespace, `:` and `-` separators and `0x` prefixes, as copied from most hex dumps.
+pub fn parse_hex(text: &str) -> Result<Vec<u8>, String> {
+    let digits: String = text.split(|c: char| c.is_whitespace() || c == ':' || c == '-' || c == ',')
+        .map(|token| token.strip_prefix("0x").or_else(|| token.strip_prefix("0X")).unwrap_or(token))
+        .collect();
+    if let Some(c) = digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(format!("{} is not a hex digit.", c));
+    }
+    if !digits.len().is_multiple_of(2) {
+        return Err("The hex has an odd number of digits.".to_string());
+    }
+    (0..digits.len()).step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).map_err(|e| e.to_string()))
+        .collect()
+}
+
+/// Writes the bytes as space separated hex, sixteen bytes per line.
+pub fn format_hex(bytes: &[u8]) -> String {
+    bytes.chunks(HEX_LINE_LENGTH)
+        .map(|line| line.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(" "))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Sends the payload to the multicast group as is, whatever it contains.
+pub fn send_crafted(config: &QuerySenderConfig, payload: &[u8]) -> io::Result<()> {
+    let sockets = open_group_senders(config)?;
+    send_to_groups(&sockets, payload)
+}
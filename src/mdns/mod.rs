@@ -1,5 +1,22 @@
 pub mod capture;
+pub mod capture_filter;
+pub mod conflict_test;
+pub mod encoder;
+pub mod event_channel;
+pub mod load_generator;
 pub mod types;
 pub mod mdns_message;
+pub mod message_crafter;
+pub mod packet_builder;
+pub mod packet_source;
 pub mod parser;
-mod tests;
\ No newline at end of file
+pub mod pcap_reader;
+pub mod pcapng_writer;
+pub mod query_sender;
+pub mod replay;
+pub mod resolver;
+pub mod responder;
+pub mod service_browser;
+pub mod socket_listener;
+pub mod tcp_reassembly;
+pub(crate) mod tests;
\ No newline at end of file
@@ -0,0 +1,63 @@
+use pnet::packet::ip::IpNextHeaderProtocols;
+use pnet::packet::ipv4::{self, MutableIpv4Packet};
+use pnet::packet::ipv6::MutableIpv6Packet;
+use pnet::packet::udp::{self, MutableUdpPacket};
+use std::net::{IpAddr, SocketAddr};
+
+const IPV4_HEADER_LENGTH: usize = 20;
+const IPV6_HEADER_LENGTH: usize = 40;
+const UDP_HEADER_LENGTH: usize = 8;
+
+/// Wraps a UDP payload into an IPv4 or IPv6 packet (LINKTYPE_RAW framing).
+/// Used where only the datagram is known, e.g. for messages received on a plain UDP socket.
+/// Returns `None` when source and destination are not of the same address family.
+pub fn build_udp_packet(source: SocketAddr, destination: SocketAddr, ttl: u8, payload: &[u8]) -> Option<Vec<u8>> {
+    let udp_length = UDP_HEADER_LENGTH + payload.len();
+    match (source.ip(), destination.ip()) {
+        (IpAddr::V4(source_ip), IpAddr::V4(destination_ip)) => {
+            let mut buffer = vec![0u8; IPV4_HEADER_LENGTH + udp_length];
+            {
+                let mut udp_packet = MutableUdpPacket::new(&mut buffer[IPV4_HEADER_LENGTH..])?;
+                fill_udp_header(&mut udp_packet, source.port(), destination.port(), udp_length, payload);
+                let checksum = udp::ipv4_checksum(&udp_packet.to_immutable(), &source_ip, &destination_ip);
+                udp_packet.set_checksum(checksum);
+            }
+            let mut ip_packet = MutableIpv4Packet::new(&mut buffer)?;
+            ip_packet.set_version(4);
+            ip_packet.set_header_length((IPV4_HEADER_LENGTH / 4) as u8);
+            ip_packet.set_total_length((IPV4_HEADER_LENGTH + udp_length) as u16);
+            ip_packet.set_ttl(ttl);
+            ip_packet.set_next_level_protocol(IpNextHeaderProtocols::Udp);
+            ip_packet.set_source(source_ip);
+            ip_packet.set_destination(destination_ip);
+            let checksum = ipv4::checksum(&ip_packet.to_immutable());
+            ip_packet.set_checksum(checksum);
+            Some(buffer)
+        }
+        (IpAddr::V6(source_ip), IpAddr::V6(destination_ip)) => {
+            let mut buffer = vec![0u8; IPV6_HEADER_LENGTH + udp_length];
+            {
+                let mut udp_packet = MutableUdpPacket::new(&mut buffer[IPV6_HEADER_LENGTH..])?;
+                fill_udp_header(&mut udp_packet, source.port(), destination.port(), udp_length, payload);
+                let checksum = udp::ipv6_checksum(&udp_packet.to_immutable(), &source_ip, &destination_ip);
+                udp_packet.set_checksum(checksum);
+            }
+            let mut ip_packet = MutableIpv6Packet::new(&mut buffer)?;
+            ip_packet.set_version(6);
+            ip_packet.set_payload_length(udp_length as u16);
+            ip_packet.set_next_header(IpNextHeaderProtocols::Udp);
+            ip_packet.set_hop_limit(ttl);
+            ip_packet.set_source(source_ip);
+            ip_packet.set_destination(destination_ip);
+            Some(buffer)
+        }
+        _ => None,
+    }
+}
+
+fn fill_udp_header(udp_packet: &mut MutableUdpPacket, source_port: u16, destination_port: u16, udp_length: usize, payload: &[u8]) {
+    udp_packet.set_source(source_port);
+    udp_packet.set_destination(destination_port);
+    udp_packet.set_length(udp_length as u16);
+    udp_packet.set_payload(payload);
+}
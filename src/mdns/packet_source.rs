@@ -0,0 +1,271 @@
+use crate::mdns::capture::LinkType;
+use crate::mdns::capture_filter::CaptureFilter;
+use crate::mdns::packet_builder::build_udp_packet;
+use crate::mdns::pcap_reader::PcapReader;
+use crate::mdns::socket_listener::{SocketListener, SocketListenerConfig};
+use pnet::datalink::{channel, Channel, Config, DataLinkReceiver, NetworkInterface};
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+use std::time::Duration;
+use time::OffsetDateTime;
+
+/// A frame handed out by a packet source. The data is only borrowed until the next frame is requested.
+pub struct SourceFrame<'a> {
+    pub data: &'a [u8],
+    pub timestamp: OffsetDateTime,
+    pub link_type: LinkType,
+}
+
+/// Anything frames can be read from. The capture thread decodes the frames of any source the same way.
+pub trait PacketSource: Send {
+    /// Returns the next frame, or `None` once the source is exhausted.
+    /// Sources which wait for traffic return an error of kind `TimedOut` or `WouldBlock` when nothing arrived in time.
+    fn next_frame(&mut self) -> io::Result<Option<SourceFrame<'_>>>;
+
+    /// Live sources keep reading and discard frames while the capture is paused, recorded sources stop reading.
+    fn is_live(&self) -> bool;
+
+    /// Frames the kernel dropped since the last call because the source did not read them fast enough.
+    /// `None` where the source cannot tell.
+    fn kernel_drops(&mut self) -> Option<u64> {
+        None
+    }
+}
+
+/// Raw datalink capture on a network interface through pnet.
+pub struct LivePacketSource {
+    receiver: Box<dyn DataLinkReceiver>,
+    link_type: LinkType,
+    /// The packet socket, when it was created here rather than by pnet. pnet closes it with the receiver.
+    socket: Option<i32>,
+}
+
+impl LivePacketSource {
+    /// Opens the interface. On Linux the filter is attached to the packet socket, so the kernel drops
+    /// frames it rejects before they are copied to the capture thread. Elsewhere the filter is ignored.
+    pub fn open(interface: &NetworkInterface, read_timeout: Duration, filter: Option<&CaptureFilter>) -> io::Result<Self> {
+        let link_type = LinkType::for_interface(interface);
+        let socket_fd = match filter {
+            Some(filter) => open_filtered_socket(filter, link_type)?,
+            None => None,
+        };
+        let config = Config {
+            read_timeout: Some(read_timeout),
+            socket_fd,
+            ..Default::default()
+        };
+        let receiver = match channel(interface, config)? {
+            Channel::Ethernet(_, rx) => rx,
+            _ => return Err(io::Error::new(io::ErrorKind::Unsupported, "Unsupported datalink channel type.")),
+        };
+        Ok(LivePacketSource {
+            receiver,
+            link_type,
+            socket: socket_fd,
+        })
+    }
+
+    pub fn link_type(&self) -> LinkType {
+        self.link_type
+    }
+
+    /// Whether the kernel filters the frames, so every frame read passed the filter.
+    pub fn filter_attached(&self) -> bool {
+        self.socket.is_some()
+    }
+}
+
+/// Creates a packet socket with the compiled filter attached, which pnet then binds to the interface.
+#[cfg(target_os = "linux")]
+fn open_filtered_socket(filter: &CaptureFilter, link_type: LinkType) -> io::Result<Option<i32>> {
+    let program = filter.compile(link_type).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+    // Protocol 0 receives nothing until the socket is bound, so no frame is queued before the filter is attached.
+    let socket = unsafe { libc::socket(libc::AF_PACKET, libc::SOCK_RAW, 0) };
+    if socket < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let program = libc::sock_fprog {
+        len: program.len() as u16,
+        filter: program.as_ptr() as *mut libc::sock_filter,
+    };
+    let result = unsafe {
+        libc::setsockopt(
+            socket,
+            libc::SOL_SOCKET,
+            libc::SO_ATTACH_FILTER,
+            &program as *const libc::sock_fprog as *const libc::c_void,
+            size_of::<libc::sock_fprog>() as libc::socklen_t,
+        )
+    };
+    if result != 0 {
+        let error = io::Error::last_os_error();
+        unsafe { libc::close(socket) };
+        return Err(error);
+    }
+    Ok(Some(socket))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn open_filtered_socket(_filter: &CaptureFilter, _link_type: LinkType) -> io::Result<Option<i32>> {
+    println!("Capture filters are only applied on Linux, filtering happens after capture.");
+    Ok(None)
+}
+
+impl PacketSource for LivePacketSource {
+    fn next_frame(&mut self) -> io::Result<Option<SourceFrame<'_>>> {
+        let data = self.receiver.next()?;
+        Ok(Some(SourceFrame {
+            data,
+            timestamp: OffsetDateTime::now_utc(),
+            link_type: self.link_type,
+        }))
+    }
+
+    fn is_live(&self) -> bool {
+        true
+    }
+
+    fn kernel_drops(&mut self) -> Option<u64> {
+        read_kernel_drops(self.socket?)
+    }
+}
+
+/// Reads and resets the drop counter of a packet socket.
+#[cfg(target_os = "linux")]
+fn read_kernel_drops(socket: i32) -> Option<u64> {
+    let mut stats: libc::tpacket_stats = unsafe { std::mem::zeroed() };
+    let mut length = size_of::<libc::tpacket_stats>() as libc::socklen_t;
+    let result = unsafe {
+        libc::getsockopt(
+            socket,
+            libc::SOL_PACKET,
+            libc::PACKET_STATISTICS,
+            &mut stats as *mut libc::tpacket_stats as *mut libc::c_void,
+            &mut length,
+        )
+    };
+    match result {
+        0 => Some(stats.tp_drops as u64),
+        _ => None,
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_kernel_drops(_socket: i32) -> Option<u64> {
+    None
+}
+
+/// Frames recorded in a pcap or pcapng file. Frames of unsupported link types are skipped.
+pub struct FilePacketSource {
+    reader: PcapReader<BufReader<File>>,
+    data: Vec<u8>,
+}
+
+impl FilePacketSource {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Ok(FilePacketSource {
+            reader: PcapReader::open(path)?,
+            data: vec![],
+        })
+    }
+}
+
+impl PacketSource for FilePacketSource {
+    fn next_frame(&mut self) -> io::Result<Option<SourceFrame<'_>>> {
+        loop {
+            let frame = match self.reader.next_frame()? {
+                Some(f) => f,
+                None => return Ok(None),
+            };
+            if let Some(link_type) = LinkType::from_pcap(frame.link_type) {
+                self.data = frame.data;
+                return Ok(Some(SourceFrame {
+                    data: &self.data,
+                    timestamp: frame.timestamp.unwrap_or_else(OffsetDateTime::now_utc),
+                    link_type,
+                }));
+            }
+        }
+    }
+
+    fn is_live(&self) -> bool {
+        false
+    }
+}
+
+/// Datagrams received by the socket listener, wrapped into synthesized IP packets.
+pub struct SocketPacketSource {
+    listener: SocketListener,
+    data: Vec<u8>,
+}
+
+impl SocketPacketSource {
+    pub fn open(config: &SocketListenerConfig) -> io::Result<Self> {
+        Ok(SocketPacketSource {
+            listener: SocketListener::open(config)?,
+            data: vec![],
+        })
+    }
+}
+
+impl PacketSource for SocketPacketSource {
+    fn next_frame(&mut self) -> io::Result<Option<SourceFrame<'_>>> {
+        let datagram = match self.listener.receive()? {
+            Some(d) => d,
+            None => return Err(io::Error::from(io::ErrorKind::TimedOut)),
+        };
+        // The TTL is reported on Linux only, elsewhere the value mDNS requires senders to use is assumed.
+        self.data = build_udp_packet(datagram.source, datagram.destination, datagram.ttl.unwrap_or(255), &datagram.payload)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Source and destination address families differ."))?;
+        Ok(Some(SourceFrame {
+            data: &self.data,
+            timestamp: OffsetDateTime::now_utc(),
+            link_type: LinkType::RawIp,
+        }))
+    }
+
+    fn is_live(&self) -> bool {
+        true
+    }
+}
+
+/// Hands out a prepared list of frames, used to feed the capture pipeline in tests.
+#[cfg(test)]
+#[derive(Default)]
+pub struct ScriptedPacketSource {
+    frames: std::collections::VecDeque<(LinkType, Vec<u8>, OffsetDateTime)>,
+    current: Vec<u8>,
+}
+
+#[cfg(test)]
+impl ScriptedPacketSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_frame(mut self, link_type: LinkType, data: Vec<u8>, timestamp: OffsetDateTime) -> Self {
+        self.frames.push_back((link_type, data, timestamp));
+        self
+    }
+}
+
+#[cfg(test)]
+impl PacketSource for ScriptedPacketSource {
+    fn next_frame(&mut self) -> io::Result<Option<SourceFrame<'_>>> {
+        let (link_type, data, timestamp) = match self.frames.pop_front() {
+            Some(f) => f,
+            None => return Ok(None),
+        };
+        self.current = data;
+        Ok(Some(SourceFrame {
+            data: &self.current,
+            timestamp,
+            link_type,
+        }))
+    }
+
+    fn is_live(&self) -> bool {
+        false
+    }
+}
@@ -1,9 +1,8 @@
 use crate::mdns::mdns_message::MDNSMessage;
 use crate::mdns::types::{MDNSAnswer, MDNSMessageHeader, MDNSQueryClass, MDNSQuestion, MDNSRData, MDNSRecordType};
-use std::fmt::{Debug, Display};
+use std::fmt::{Display, Formatter};
 use std::net::{Ipv4Addr, Ipv6Addr};
 use std::str::from_utf8;
-use std::string::ParseError;
 
 pub struct ByteReader {
     pub bytes: Vec<u8>,
@@ -55,20 +54,72 @@ impl ByteReader {
     }
 }
 
+/// Reason why a message could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// The message ended while the named field was being read.
+    UnexpectedEnd(&'static str),
+    UnknownRecordType(u16),
+    UnknownQueryClass(u16),
+    InvalidLabel(Vec<u8>),
+    /// A name compression pointer which does not point before the name it is part of.
+    InvalidPointer(usize),
+    EmptyName,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::UnexpectedEnd(field) => write!(f, "Message ended while reading {}.", field),
+            ParseError::UnknownRecordType(record_type) => write!(f, "Unknown record type: {}.", record_type),
+            ParseError::UnknownQueryClass(class) => write!(f, "Unknown class: {}.", class),
+            ParseError::InvalidLabel(label) => write!(f, "Could not read label data: {:?}.", label),
+            ParseError::InvalidPointer(pointer) => write!(f, "Invalid name pointer: {}.", pointer),
+            ParseError::EmptyName => write!(f, "Empty name."),
+        }
+    }
+}
+
 pub fn parse_mdns_message(bytes: &[u8]) -> Result<MDNSMessage, ParseError>
+{
+    parse_mdns_message_with_diagnostics(bytes).map(|(message, _)| message)
+}
+
+/// Parses the message and additionally describes parts of it which were not understood.
+pub fn parse_mdns_message_with_diagnostics(bytes: &[u8]) -> Result<(MDNSMessage, Vec<String>), ParseError>
 {
     let mut byte_reader = ByteReader{
         bytes: bytes.to_vec(),
         byte_index: 0
     };
-    let header = parse_mdns_header(&mut byte_reader).expect("Could not parse MDNS header.");
-    let questions = parse_mdns_questions(&mut byte_reader, header.question_count as usize);
-    let answers = parse_mdns_answers(&mut byte_reader, header.answer_count as usize);
-    Ok(MDNSMessage {
+    let header = parse_mdns_header(&mut byte_reader).ok_or(ParseError::UnexpectedEnd("header"))?;
+    let questions = parse_mdns_questions(&mut byte_reader, header.question_count as usize)?;
+    let answers = parse_mdns_answers(&mut byte_reader, header.answer_count as usize)?;
+
+    // Questions and answers are what the message is about, so broken authority or additional records only get reported.
+    let mut diagnostics = vec![];
+    let other_sections = parse_mdns_answers(&mut byte_reader, header.authority_count as usize)
+        .and_then(|authorities| Ok((authorities, parse_mdns_answers(&mut byte_reader, header.additional_count as usize)?)));
+    let (authorities, additionals) = match other_sections {
+        Ok(sections) => {
+            if byte_reader.byte_index < bytes.len() {
+                diagnostics.push(format!("{} trailing bytes after the last record.", bytes.len() - byte_reader.byte_index));
+            }
+            sections
+        }
+        Err(e) => {
+            diagnostics.push(format!("{} authority and {} additional records were not decoded: {}", header.authority_count, header.additional_count, e));
+            (vec![], vec![])
+        }
+    };
+    let message = MDNSMessage {
         header,
         questions,
-        answers
-    })
+        answers,
+        authorities,
+        additionals
+    };
+    Ok((message, diagnostics))
 }
 
 pub fn parse_mdns_header(reader: &mut ByteReader) -> Option<MDNSMessageHeader> {
@@ -90,129 +141,169 @@ pub fn parse_mdns_header(reader: &mut ByteReader) -> Option<MDNSMessageHeader> {
     Some(packet)
 }
 
-pub fn parse_mdns_questions(reader: &mut ByteReader, question_count: usize) -> Vec<MDNSQuestion>
+pub fn parse_mdns_questions(reader: &mut ByteReader, question_count: usize) -> Result<Vec<MDNSQuestion>, ParseError>
 {
-    let mut questions = Vec::with_capacity(question_count);
+    let mut questions = Vec::with_capacity(question_count.min(reader.bytes.len()));
     for _ in 0..question_count
     {
-        let name = parse_name(reader);
+        let name = parse_name(reader)?;
 
-        let question_type = reader.read_u16().expect("Could not read question type.");
-        // disregard the 3rd byte of the sequence -> class is either 1 or 255 (IN or ANY)
-        // The first byte can contain cache flush flag which is not relevant to MDNS as per RFC 6762 - 10.2
-        _ = reader.read_byte().expect("Could not read next byte.");
-        let question_class = reader.read_byte().expect("Could not read question class.") as u16;
+        let question_type = reader.read_u16().ok_or(ParseError::UnexpectedEnd("question type"))?;
+        // The top bit of the class is the QU bit (RFC 6762 - 5.4), the rest is either 1 or 255 (IN or ANY).
+        let class_high_byte = reader.read_byte().ok_or(ParseError::UnexpectedEnd("question class"))?;
+        let question_class = reader.read_byte().ok_or(ParseError::UnexpectedEnd("question class"))? as u16;
 
         questions.push(MDNSQuestion {
             name: name,
-            question_type: MDNSRecordType::from_u16(question_type).expect(format!("Could not read question type: {}", question_type).as_str()),
-            question_class: MDNSQueryClass::from_u16(question_class).expect(format!("Could not read question class: {}", question_class).as_str()),
+            question_type: MDNSRecordType::from_u16(question_type).ok_or(ParseError::UnknownRecordType(question_type))?,
+            question_class: MDNSQueryClass::from_u16(question_class).ok_or(ParseError::UnknownQueryClass(question_class))?,
+            unicast_response: class_high_byte & 0x80 != 0,
         });
     }
-    return questions
+    return Ok(questions)
 }
 
-pub fn parse_mdns_answers(reader: &mut ByteReader, answer_count: usize) -> Vec<MDNSAnswer>
+pub fn parse_mdns_answers(reader: &mut ByteReader, answer_count: usize) -> Result<Vec<MDNSAnswer>, ParseError>
 {
-    let mut answers = Vec::with_capacity(answer_count);
-    for i in 0..answer_count
+    let mut answers = Vec::with_capacity(answer_count.min(reader.bytes.len()));
+    for _ in 0..answer_count
     {
-        let name = parse_name(reader);
+        let name = parse_name(reader)?;
 
-        let answer_type = reader.read_u16().expect("Could not read answer type.");
+        let answer_type = reader.read_u16().ok_or(ParseError::UnexpectedEnd("answer type"))?;
 
-        // disregard the 1st byte of the sequence -> class is either 1 or 255 (IN or ANY)
-        // The first byte can contain cache flush flag which is not relevant to MDNS as per RFC 6762 - 10.2
-        _ = reader.read_byte().expect("Could not read next byte.");
-        let answer_class = reader.read_byte().expect("Could not read question class.") as u16;
-        let ttl = reader.read_u32().expect("Could not read ttl.");
-        let rd_length = reader.read_u16().expect("Could not read record length.");
+        // The top bit of the class is the cache flush bit (RFC 6762 - 10.2), the rest is either 1 or 255 (IN or ANY).
+        let class_high_byte = reader.read_byte().ok_or(ParseError::UnexpectedEnd("answer class"))?;
+        let answer_class = reader.read_byte().ok_or(ParseError::UnexpectedEnd("answer class"))? as u16;
+        let ttl = reader.read_u32().ok_or(ParseError::UnexpectedEnd("ttl"))?;
+        let rd_length = reader.read_u16().ok_or(ParseError::UnexpectedEnd("record length"))?;
 
-        let record_type = MDNSRecordType::from_u16(answer_type).expect("Invalid answer type.");
-        let rdata = parse_rdata(reader, record_type, rd_length);
+        let record_type = MDNSRecordType::from_u16(answer_type).ok_or(ParseError::UnknownRecordType(answer_type))?;
+        // The class of an OPT record is the UDP payload size of the sender (RFC 6891 - 6.1.2), which is not kept.
+        let (answer_class, cache_flush) = match record_type {
+            MDNSRecordType::OPT => (MDNSQueryClass::IN, false),
+            _ => (
+                MDNSQueryClass::from_u16(answer_class).ok_or(ParseError::UnknownQueryClass(answer_class))?,
+                class_high_byte & 0x80 != 0,
+            ),
+        };
+        let rdata_start = reader.byte_index;
+        let rdata_end = rdata_start + rd_length as usize;
+        if rdata_end > reader.bytes.len() {
+            return Err(ParseError::UnexpectedEnd("record data"));
+        }
+        let rdata = parse_rdata(reader, record_type, rd_length)?;
+        // Always continue right after the record data, even if the typed parser did not consume all of it.
+        reader.byte_index = rdata_end;
 
         answers.push(MDNSAnswer {
             name: name,
             answer_type: record_type,
-            answer_class: MDNSQueryClass::from_u16(answer_class).expect("Invalid answer class."),
+            answer_class,
+            cache_flush,
             ttl_seconds: ttl,
             rd_length: rd_length,
             rdata: rdata
         });
     }
-    return answers
+    return Ok(answers)
 }
 
-pub fn parse_rdata(reader: &mut ByteReader, record_type: MDNSRecordType, rd_length: u16) -> MDNSRData
+pub fn parse_rdata(reader: &mut ByteReader, record_type: MDNSRecordType, rd_length: u16) -> Result<MDNSRData, ParseError>
 {
-    match record_type {
+    let rdata = match record_type {
         MDNSRecordType::A => MDNSRData::A {
-            ipv4_address: Ipv4Addr::from(reader.read_u32().expect("Could not read ipv4 address.")),
+            ipv4_address: Ipv4Addr::from(reader.read_u32().ok_or(ParseError::UnexpectedEnd("ipv4 address"))?),
         },
         MDNSRecordType::NS => MDNSRData::OTHER {
-            raw: reader.read_n(rd_length as usize).expect("Could not read other data."),
+            raw: reader.read_n(rd_length as usize).ok_or(ParseError::UnexpectedEnd("record data"))?,
         },
         MDNSRecordType::CNAME => MDNSRData::CNAME {
-            canonical_domain_name: parse_name(reader),
+            canonical_domain_name: parse_name(reader)?,
         },
         MDNSRecordType::SOA => MDNSRData::OTHER {
-            raw: reader.read_n(rd_length as usize).expect("Could not read other data."),
+            raw: reader.read_n(rd_length as usize).ok_or(ParseError::UnexpectedEnd("record data"))?,
         },
         MDNSRecordType::PTR => MDNSRData::PTR {
-            domain_name: parse_name(reader),
+            domain_name: parse_name(reader)?,
         },
         MDNSRecordType::MX => MDNSRData::OTHER {
-            raw: reader.read_n(rd_length as usize).expect("Could not read other data."),
+            raw: reader.read_n(rd_length as usize).ok_or(ParseError::UnexpectedEnd("record data"))?,
         },
         MDNSRecordType::TXT => MDNSRData::TXT {
-            text: "".to_string(),
+            text: parse_text(&reader.read_n(rd_length as usize).ok_or(ParseError::UnexpectedEnd("record data"))?)?,
         },
         MDNSRecordType::AAAA => {
-            let ip_bytes_dynamic = reader.read_n(16).expect("Could not read ipv6 address.");
-            let ip_bytes_static: Result<[u8; 16], Vec<u8>> = <[u8; 16]>::try_from(ip_bytes_dynamic);
+            let ip_bytes_dynamic = reader.read_n(16).ok_or(ParseError::UnexpectedEnd("ipv6 address"))?;
+            let ip_bytes_static: [u8; 16] = <[u8; 16]>::try_from(ip_bytes_dynamic).map_err(|_| ParseError::UnexpectedEnd("ipv6 address"))?;
             MDNSRData::AAAA {
-                ipv6_addr: Ipv6Addr::from(ip_bytes_static.expect("Could not read ipv6 address.")),
+                ipv6_addr: Ipv6Addr::from(ip_bytes_static),
             }
         },
         MDNSRecordType::SRV => MDNSRData::SRV {
-            priority: reader.read_u16().expect("Could not read priority."),
-            weight: reader.read_u16().expect("Could not read weight."),
-            port: reader.read_u16().expect("Could not read port."),
-            target_domain_name: parse_name(reader)
+            priority: reader.read_u16().ok_or(ParseError::UnexpectedEnd("priority"))?,
+            weight: reader.read_u16().ok_or(ParseError::UnexpectedEnd("weight"))?,
+            port: reader.read_u16().ok_or(ParseError::UnexpectedEnd("port"))?,
+            target_domain_name: parse_name(reader)?
         },
         MDNSRecordType::NSEC | MDNSRecordType::OPT | MDNSRecordType::ANY | MDNSRecordType::AXFR | MDNSRecordType::MAILB | MDNSRecordType::MAILA => MDNSRData::OTHER {
-            raw: reader.read_n(rd_length as usize).expect("Could not read other data."),
+            raw: reader.read_n(rd_length as usize).ok_or(ParseError::UnexpectedEnd("record data"))?,
         }
+    };
+    Ok(rdata)
+}
+
+/// Reads the character strings of TXT data, one line per string.
+pub fn parse_text(data: &[u8]) -> Result<String, ParseError>
+{
+    let mut strings = vec![];
+    let mut index = 0;
+    while index < data.len() {
+        let length = data[index] as usize;
+        let string = data.get(index + 1..index + 1 + length).ok_or(ParseError::UnexpectedEnd("text"))?;
+        strings.push(String::from_utf8_lossy(string).into_owned());
+        index += 1 + length;
     }
+    Ok(strings.join("\n"))
 }
 
-pub fn parse_label(reader: &mut ByteReader) -> String
+pub fn parse_label(reader: &mut ByteReader) -> Result<String, ParseError>
 {
-    let length = reader.read_byte().expect("Could not read label length.");
-    let label_raw = reader.read_n(length as usize).expect("Could not read label.");
-    return from_utf8(&label_raw).expect(format!("Could not read label data: {:?}.", label_raw).as_str()).to_string();
+    let length = reader.read_byte().ok_or(ParseError::UnexpectedEnd("label length"))?;
+    let label_raw = reader.read_n(length as usize).ok_or(ParseError::UnexpectedEnd("label"))?;
+    match from_utf8(&label_raw) {
+        Ok(label) => Ok(escape_label(label)),
+        Err(_) => Err(ParseError::InvalidLabel(label_raw))
+    }
+}
+
+/// Escapes the dots and backslashes of a label, so a dot in an instance name is not taken for a label separator (RFC 6763 - 4.3).
+pub fn escape_label(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('.', "\\.")
 }
 
-pub fn parse_name(reader: &mut ByteReader) -> String
+pub fn parse_name(reader: &mut ByteReader) -> Result<String, ParseError>
 {
+    let name_start = reader.byte_index;
     let mut labels: Vec<String> = vec![];
     let mut peaked_byte = reader.peak_byte();
     let mut referenced_name: Option<String> = None;
     while peaked_byte.is_some() && peaked_byte.unwrap() != 0 && referenced_name.is_none() {
         if is_label_pointer(peaked_byte.unwrap()) {
-            let pointer = get_pointer(reader.read_u16().expect("Could not read pointer.")) as usize;
+            let pointer = get_pointer(reader.read_u16().ok_or(ParseError::UnexpectedEnd("pointer"))?) as usize;
+            // Pointers may only refer to data preceding the name, which also rules out pointer loops.
+            if pointer >= name_start {
+                return Err(ParseError::InvalidPointer(pointer));
+            }
             let mut new_reader = ByteReader {
-                bytes: reader.bytes.to_vec(),
+                bytes: reader.bytes[..name_start].to_vec(),
                 byte_index: pointer
             };
-            let r_name = parse_name(&mut new_reader);
-            if r_name.is_empty() {
-                panic!("Empty referenced name at pointer {}", pointer);
-            }
+            let r_name = parse_name(&mut new_reader)?;
             referenced_name = Some(r_name);
         }
         else {
-            let label = parse_label(reader);
+            let label = parse_label(reader)?;
             labels.push(label);
         }
         peaked_byte = reader.peak_byte();
@@ -221,13 +312,13 @@ pub fn parse_name(reader: &mut ByteReader) -> String
     // When name does not contain a pointer, the name always ends with 0 byte.
     if referenced_name.is_none()
     {
-        _ = reader.read_byte().expect("Could not read the trailing 0.");
+        _ = reader.read_byte().ok_or(ParseError::UnexpectedEnd("the trailing 0"))?;
     }
 
     let name = match !labels.is_empty() {
         true => {
             if labels.iter().any(|l| l.is_empty()) {
-                panic!("Empty label found.")
+                return Err(ParseError::EmptyName);
             }
             Some(labels.join("."))
         },
@@ -243,7 +334,7 @@ pub fn parse_name(reader: &mut ByteReader) -> String
             None => None
         }
     };
-    return result_name.expect("Could not read name.");
+    return result_name.ok_or(ParseError::EmptyName);
 }
 
 fn is_label_pointer(byte: u8) -> bool
@@ -0,0 +1,284 @@
+use std::fs::File;
+use std::io::{self, BufReader, ErrorKind, Read};
+use std::path::Path;
+use time::OffsetDateTime;
+
+const PCAP_MAGIC_MICROSECONDS: u32 = 0xA1B2_C3D4;
+const PCAP_MAGIC_NANOSECONDS: u32 = 0xA1B2_3C4D;
+const PCAP_HEADER_LENGTH: usize = 24;
+const PCAP_RECORD_HEADER_LENGTH: usize = 16;
+
+const PCAPNG_SECTION_HEADER_BLOCK: u32 = 0x0A0D_0D0A;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+const PCAPNG_INTERFACE_DESCRIPTION_BLOCK: u32 = 1;
+const PCAPNG_PACKET_BLOCK: u32 = 2;
+const PCAPNG_SIMPLE_PACKET_BLOCK: u32 = 3;
+const PCAPNG_ENHANCED_PACKET_BLOCK: u32 = 6;
+const PCAPNG_OPTION_END: u16 = 0;
+const PCAPNG_OPTION_IF_TSRESOL: u16 = 9;
+
+/// Upper bound for a single block or record, protects against allocating garbage lengths from corrupted files.
+const MAX_BLOCK_LENGTH: usize = 16 * 1024 * 1024;
+
+/// A single frame read from a capture file.
+pub struct CapturedFrame {
+    /// Capture time of the frame. Simple packet blocks in pcapng files carry no timestamp.
+    pub timestamp: Option<OffsetDateTime>,
+    /// The LINKTYPE_* value of the interface the frame was captured on.
+    pub link_type: u32,
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, Copy)]
+struct Endianness {
+    big_endian: bool,
+}
+
+impl Endianness {
+    fn u16(&self, bytes: &[u8], offset: usize) -> u16 {
+        let b = [bytes[offset], bytes[offset + 1]];
+        if self.big_endian { u16::from_be_bytes(b) } else { u16::from_le_bytes(b) }
+    }
+
+    fn u32(&self, bytes: &[u8], offset: usize) -> u32 {
+        let b = [bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]];
+        if self.big_endian { u32::from_be_bytes(b) } else { u32::from_le_bytes(b) }
+    }
+}
+
+struct PcapNgInterface {
+    link_type: u32,
+    ticks_per_second: u64,
+}
+
+enum Format {
+    Pcap { endianness: Endianness, nanoseconds: bool, link_type: u32 },
+    PcapNg { endianness: Endianness, interfaces: Vec<PcapNgInterface> },
+}
+
+/// Reads frames from classic pcap and pcapng capture files.
+pub struct PcapReader<R: Read> {
+    reader: R,
+    format: Format,
+}
+
+impl PcapReader<BufReader<File>> {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> PcapReader<R> {
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+
+        // The section header block type is a palindrome, so it reads the same in either byte order.
+        if u32::from_le_bytes(magic) == PCAPNG_SECTION_HEADER_BLOCK {
+            let mut pcap_reader = PcapReader {
+                reader,
+                format: Format::PcapNg { endianness: Endianness { big_endian: false }, interfaces: vec![] },
+            };
+            pcap_reader.read_section_header()?;
+            return Ok(pcap_reader);
+        }
+
+        let (endianness, nanoseconds) = match (u32::from_le_bytes(magic), u32::from_be_bytes(magic)) {
+            (PCAP_MAGIC_MICROSECONDS, _) => (Endianness { big_endian: false }, false),
+            (PCAP_MAGIC_NANOSECONDS, _) => (Endianness { big_endian: false }, true),
+            (_, PCAP_MAGIC_MICROSECONDS) => (Endianness { big_endian: true }, false),
+            (_, PCAP_MAGIC_NANOSECONDS) => (Endianness { big_endian: true }, true),
+            _ => return Err(invalid_data("Not a pcap or pcapng file.")),
+        };
+        let mut header = [0u8; PCAP_HEADER_LENGTH - 4];
+        reader.read_exact(&mut header)?;
+        // The upper bits of the link type field may carry FCS information.
+        let link_type = endianness.u32(&header, 16) & 0xFFFF;
+        Ok(PcapReader {
+            reader,
+            format: Format::Pcap { endianness, nanoseconds, link_type },
+        })
+    }
+
+    /// Returns the next frame or `None` at the end of the file.
+    pub fn next_frame(&mut self) -> io::Result<Option<CapturedFrame>> {
+        match self.format {
+            Format::Pcap { endianness, nanoseconds, link_type } => self.next_pcap_frame(endianness, nanoseconds, link_type),
+            Format::PcapNg { .. } => self.next_pcapng_frame(),
+        }
+    }
+
+    fn next_pcap_frame(&mut self, endianness: Endianness, nanoseconds: bool, link_type: u32) -> io::Result<Option<CapturedFrame>> {
+        let mut record = [0u8; PCAP_RECORD_HEADER_LENGTH];
+        if !read_exact_or_eof(&mut self.reader, &mut record)? {
+            return Ok(None);
+        }
+        let seconds = endianness.u32(&record, 0) as i128;
+        let fraction = endianness.u32(&record, 4) as i128;
+        let captured_length = endianness.u32(&record, 8) as usize;
+        if captured_length > MAX_BLOCK_LENGTH {
+            return Err(invalid_data("Record length is too large."));
+        }
+        let mut data = vec![0u8; captured_length];
+        self.reader.read_exact(&mut data)?;
+
+        let nanos = seconds * 1_000_000_000 + if nanoseconds { fraction } else { fraction * 1_000 };
+        Ok(Some(CapturedFrame {
+            timestamp: OffsetDateTime::from_unix_timestamp_nanos(nanos).ok(),
+            link_type,
+            data,
+        }))
+    }
+
+    fn next_pcapng_frame(&mut self) -> io::Result<Option<CapturedFrame>> {
+        loop {
+            let mut block_type_bytes = [0u8; 4];
+            if !read_exact_or_eof(&mut self.reader, &mut block_type_bytes)? {
+                return Ok(None);
+            }
+            if u32::from_le_bytes(block_type_bytes) == PCAPNG_SECTION_HEADER_BLOCK {
+                self.read_section_header()?;
+                continue;
+            }
+            let (endianness, block_type, body) = {
+                let endianness = match &self.format {
+                    Format::PcapNg { endianness, .. } => *endianness,
+                    Format::Pcap { .. } => unreachable!(),
+                };
+                let block_type = endianness.u32(&block_type_bytes, 0);
+                let mut length_bytes = [0u8; 4];
+                self.reader.read_exact(&mut length_bytes)?;
+                let block_length = endianness.u32(&length_bytes, 0) as usize;
+                let body = self.read_block_body(block_length)?;
+                (endianness, block_type, body)
+            };
+            let interfaces = match &mut self.format {
+                Format::PcapNg { interfaces, .. } => interfaces,
+                Format::Pcap { .. } => unreachable!(),
+            };
+
+            match block_type {
+                PCAPNG_INTERFACE_DESCRIPTION_BLOCK => {
+                    interfaces.push(parse_interface_description(endianness, &body)?);
+                }
+                PCAPNG_ENHANCED_PACKET_BLOCK => {
+                    if body.len() < 20 {
+                        return Err(invalid_data("Enhanced packet block is too short."));
+                    }
+                    let interface_id = endianness.u32(&body, 0) as usize;
+                    let ticks = ((endianness.u32(&body, 4) as u64) << 32) | endianness.u32(&body, 8) as u64;
+                    let captured_length = endianness.u32(&body, 12) as usize;
+                    return frame_from_block(interfaces, interface_id, Some(ticks), &body, 20, captured_length).map(Some);
+                }
+                PCAPNG_PACKET_BLOCK => {
+                    if body.len() < 20 {
+                        return Err(invalid_data("Packet block is too short."));
+                    }
+                    let interface_id = endianness.u16(&body, 0) as usize;
+                    let ticks = ((endianness.u32(&body, 4) as u64) << 32) | endianness.u32(&body, 8) as u64;
+                    let captured_length = endianness.u32(&body, 12) as usize;
+                    return frame_from_block(interfaces, interface_id, Some(ticks), &body, 20, captured_length).map(Some);
+                }
+                PCAPNG_SIMPLE_PACKET_BLOCK => {
+                    if body.len() < 4 {
+                        return Err(invalid_data("Simple packet block is too short."));
+                    }
+                    let original_length = endianness.u32(&body, 0) as usize;
+                    let captured_length = original_length.min(body.len() - 4);
+                    return frame_from_block(interfaces, 0, None, &body, 4, captured_length).map(Some);
+                }
+                // Name resolution, statistics and custom blocks carry nothing we display.
+                _ => {}
+            }
+        }
+    }
+
+    /// Reads the section header block which follows the already consumed block type and resets the interfaces.
+    fn read_section_header(&mut self) -> io::Result<()> {
+        let mut header = [0u8; 8];
+        self.reader.read_exact(&mut header)?;
+        let endianness = match (u32::from_le_bytes([header[4], header[5], header[6], header[7]]), u32::from_be_bytes([header[4], header[5], header[6], header[7]])) {
+            (PCAPNG_BYTE_ORDER_MAGIC, _) => Endianness { big_endian: false },
+            (_, PCAPNG_BYTE_ORDER_MAGIC) => Endianness { big_endian: true },
+            _ => return Err(invalid_data("Invalid pcapng byte order magic.")),
+        };
+        let block_length = endianness.u32(&header, 0) as usize;
+        if !(28..=MAX_BLOCK_LENGTH).contains(&block_length) || !block_length.is_multiple_of(4) {
+            return Err(invalid_data("Invalid section header block length."));
+        }
+        let mut rest = vec![0u8; block_length - 12];
+        self.reader.read_exact(&mut rest)?;
+        self.format = Format::PcapNg { endianness, interfaces: vec![] };
+        Ok(())
+    }
+
+    /// Reads the block body including the trailing block length, which is dropped.
+    fn read_block_body(&mut self, block_length: usize) -> io::Result<Vec<u8>> {
+        if !(12..=MAX_BLOCK_LENGTH).contains(&block_length) || !block_length.is_multiple_of(4) {
+            return Err(invalid_data("Invalid block length."));
+        }
+        let mut body = vec![0u8; block_length - 8];
+        self.reader.read_exact(&mut body)?;
+        body.truncate(block_length - 12);
+        Ok(body)
+    }
+}
+
+fn parse_interface_description(endianness: Endianness, body: &[u8]) -> io::Result<PcapNgInterface> {
+    if body.len() < 8 {
+        return Err(invalid_data("Interface description block is too short."));
+    }
+    let link_type = endianness.u16(body, 0) as u32;
+    let mut ticks_per_second = 1_000_000u64;
+    let mut offset = 8;
+    while offset + 4 <= body.len() {
+        let code = endianness.u16(body, offset);
+        let length = endianness.u16(body, offset + 2) as usize;
+        let value_offset = offset + 4;
+        if code == PCAPNG_OPTION_END || value_offset + length > body.len() {
+            break;
+        }
+        if code == PCAPNG_OPTION_IF_TSRESOL && length >= 1 {
+            let resolution = body[value_offset];
+            let exponent = (resolution & 0x7F) as u32;
+            // The most significant bit selects a power of two instead of a power of ten.
+            let ticks = if resolution & 0x80 != 0 { 2u64.checked_pow(exponent) } else { 10u64.checked_pow(exponent) };
+            ticks_per_second = ticks.ok_or_else(|| invalid_data("Unsupported timestamp resolution."))?;
+        }
+        offset = value_offset + length.div_ceil(4) * 4;
+    }
+    Ok(PcapNgInterface { link_type, ticks_per_second })
+}
+
+fn frame_from_block(interfaces: &[PcapNgInterface], interface_id: usize, ticks: Option<u64>, body: &[u8], data_offset: usize, captured_length: usize) -> io::Result<CapturedFrame> {
+    let interface = interfaces.get(interface_id).ok_or_else(|| invalid_data("Packet refers to an unknown interface."))?;
+    let data = body.get(data_offset..data_offset + captured_length).ok_or_else(|| invalid_data("Packet data exceeds the block."))?;
+    let timestamp = ticks.and_then(|t| {
+        let nanos = t as i128 * 1_000_000_000 / interface.ticks_per_second.max(1) as i128;
+        OffsetDateTime::from_unix_timestamp_nanos(nanos).ok()
+    });
+    Ok(CapturedFrame {
+        timestamp,
+        link_type: interface.link_type,
+        data: data.to_vec(),
+    })
+}
+
+/// Like `read_exact`, but returns false instead of failing when the reader is already at its end.
+fn read_exact_or_eof<R: Read>(reader: &mut R, buffer: &mut [u8]) -> io::Result<bool> {
+    let mut read = 0;
+    while read < buffer.len() {
+        match reader.read(&mut buffer[read..]) {
+            Ok(0) if read == 0 => return Ok(false),
+            Ok(0) => return Err(io::Error::new(ErrorKind::UnexpectedEof, "Capture file is truncated.")),
+            Ok(n) => read += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(true)
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message.to_string())
+}
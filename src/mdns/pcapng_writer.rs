@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use time::OffsetDateTime;
+
+const SECTION_HEADER_BLOCK: u32 = 0x0A0D_0D0A;
+const BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+const INTERFACE_DESCRIPTION_BLOCK: u32 = 1;
+const ENHANCED_PACKET_BLOCK: u32 = 6;
+
+const OPTION_END: u16 = 0;
+const OPTION_COMMENT: u16 = 1;
+const OPTION_SHB_USERAPPL: u16 = 4;
+const OPTION_IF_NAME: u16 = 2;
+const OPTION_IF_TSRESOL: u16 = 9;
+
+/// Timestamps are written with nanosecond resolution (if_tsresol = 10^-9).
+const TIMESTAMP_RESOLUTION: u8 = 9;
+const SNAP_LENGTH: u32 = 262_144;
+
+/// Writes frames into a pcapng file, which can be opened by Wireshark or by MDNS Explorer itself.
+/// Interface description blocks are emitted on demand, one per distinct interface and link type.
+pub struct PcapNgWriter<W: Write> {
+    writer: W,
+    interfaces: HashMap<(u32, String), u32>,
+}
+
+impl PcapNgWriter<BufWriter<File>> {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<W: Write> PcapNgWriter<W> {
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        let mut body = vec![];
+        body.extend_from_slice(&BYTE_ORDER_MAGIC.to_le_bytes());
+        body.extend_from_slice(&1u16.to_le_bytes());
+        body.extend_from_slice(&0u16.to_le_bytes());
+        // Section length is not known up front.
+        body.extend_from_slice(&(-1i64).to_le_bytes());
+        push_option(&mut body, OPTION_SHB_USERAPPL, b"MDNS Explorer");
+        push_option(&mut body, OPTION_END, &[]);
+        write_block(&mut writer, SECTION_HEADER_BLOCK, &body)?;
+        Ok(PcapNgWriter {
+            writer,
+            interfaces: HashMap::new(),
+        })
+    }
+
+    /// Writes one frame. `link_type` is a LINKTYPE_* value, `comment` ends up as the packet comment.
+    pub fn write_packet(&mut self, link_type: u32, interface_name: &str, timestamp: OffsetDateTime, data: &[u8], comment: Option<&str>) -> io::Result<()> {
+        let interface_id = self.interface_id(link_type, interface_name)?;
+        let ticks = timestamp.unix_timestamp_nanos().max(0) as u64;
+
+        let mut body = vec![];
+        body.extend_from_slice(&interface_id.to_le_bytes());
+        body.extend_from_slice(&((ticks >> 32) as u32).to_le_bytes());
+        body.extend_from_slice(&(ticks as u32).to_le_bytes());
+        body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        body.extend_from_slice(data);
+        pad(&mut body);
+        if let Some(comment) = comment {
+            push_option(&mut body, OPTION_COMMENT, comment.as_bytes());
+            push_option(&mut body, OPTION_END, &[]);
+        }
+        write_block(&mut self.writer, ENHANCED_PACKET_BLOCK, &body)
+    }
+
+    /// Flushes buffered blocks, so readers of the file see every packet written so far.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Flushes buffered blocks and returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn interface_id(&mut self, link_type: u32, interface_name: &str) -> io::Result<u32> {
+        let key = (link_type, interface_name.to_string());
+        if let Some(id) = self.interfaces.get(&key) {
+            return Ok(*id);
+        }
+        let mut body = vec![];
+        body.extend_from_slice(&(link_type as u16).to_le_bytes());
+        body.extend_from_slice(&0u16.to_le_bytes());
+        body.extend_from_slice(&SNAP_LENGTH.to_le_bytes());
+        if !interface_name.is_empty() {
+            push_option(&mut body, OPTION_IF_NAME, interface_name.as_bytes());
+        }
+        push_option(&mut body, OPTION_IF_TSRESOL, &[TIMESTAMP_RESOLUTION]);
+        push_option(&mut body, OPTION_END, &[]);
+        write_block(&mut self.writer, INTERFACE_DESCRIPTION_BLOCK, &body)?;
+
+        let id = self.interfaces.len() as u32;
+        self.interfaces.insert(key, id);
+        Ok(id)
+    }
+}
+
+/// Option values longer than the 16 bit length field are truncated to fit it.
+fn push_option(body: &mut Vec<u8>, code: u16, value: &[u8]) {
+    let value = &value[..value.len().min(u16::MAX as usize)];
+    body.extend_from_slice(&code.to_le_bytes());
+    body.extend_from_slice(&(value.len() as u16).to_le_bytes());
+    body.extend_from_slice(value);
+    pad(body);
+}
+
+fn pad(body: &mut Vec<u8>) {
+    while !body.len().is_multiple_of(4) {
+        body.push(0);
+    }
+}
+
+fn write_block<W: Write>(writer: &mut W, block_type: u32, body: &[u8]) -> io::Result<()> {
+    let length = (body.len() + 12) as u32;
+    writer.write_all(&block_type.to_le_bytes())?;
+    writer.write_all(&length.to_le_bytes())?;
+    writer.write_all(body)?;
+    writer.write_all(&length.to_le_bytes())
+}
@@ -0,0 +1,340 @@
+use crate::mdns::encoder::{encode_mdns_message, EncodeError};
+use crate::mdns::mdns_message::MDNSMessage;
+use crate::mdns::socket_listener::{MulticastGroup, SocketListener, SocketListenerConfig, RECEIVE_BUFFER_LENGTH};
+use crate::mdns::types::{MDNSAnswer, MDNSMessageHeader, MDNSQueryClass, MDNSQuestion, MDNSRecordType};
+use pnet::datalink::NetworkInterface;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::collections::VecDeque;
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use time::OffsetDateTime;
+
+/// Responses received this long after a query was last sent are not considered replies to it.
+/// Responders delay their answers by at most 500 ms (RFC 6762 - 6), the rest is slack for busy networks.
+const REPLY_WINDOW: time::Duration = time::Duration::seconds(2);
+
+/// Number of sent queries remembered for tagging replies.
+const MAX_LOGGED_QUERIES: usize = 256;
+
+/// How often a continuous query checks whether it should stop.
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// An mDNS query as it is put on the wire.
+#[derive(Clone)]
+pub struct Query {
+    pub questions: Vec<MDNSQuestion>,
+    /// Answers the querier already knows, responders do not repeat them (RFC 6762 - 7.1).
+    pub known_answers: Vec<MDNSAnswer>,
+}
+
+impl Query {
+    /// A query with a single question of class IN.
+    pub fn new(name: &str, record_type: MDNSRecordType, unicast_response: bool) -> Self {
+        Self {
+            questions: vec![MDNSQuestion {
+                name: name.to_string(),
+                question_type: record_type,
+                question_class: MDNSQueryClass::IN,
+                unicast_response,
+            }],
+            known_answers: vec![],
+        }
+    }
+
+    /// The query message. Multicast queries use id 0 and no flags (RFC 6762 - 18).
+    pub fn to_message(&self) -> MDNSMessage {
+        MDNSMessage {
+            header: MDNSMessageHeader {
+                query_identifier: 0,
+                flags: 0,
+                question_count: self.questions.len() as u16,
+                answer_count: self.known_answers.len() as u16,
+                authority_count: 0,
+                additional_count: 0,
+            },
+            questions: self.questions.clone(),
+            answers: self.known_answers.clone(),
+            authorities: vec![],
+            additionals: vec![],
+        }
+    }
+}
+
+/// How a captured message relates to the queries sent by this application.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryTag {
+    /// The query itself, looped back to the capture.
+    Sent(u32),
+    /// A response answering one of its questions.
+    Reply(u32),
+}
+
+struct SentQuery {
+    id: u32,
+    questions: Vec<(String, MDNSRecordType)>,
+    last_sent: OffsetDateTime,
+}
+
+#[derive(Default)]
+struct SentQueries {
+    next_id: u32,
+    queries: VecDeque<SentQuery>,
+}
+
+/// Remembers the queries sent by this application, so captured responses can be recognized as replies to them.
+/// Shared between the senders and the UI.
+#[derive(Clone, Default)]
+pub struct SentQueryLog {
+    inner: Arc<Mutex<SentQueries>>,
+}
+
+impl SentQueryLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a query and returns the id it is tagged with.
+    fn register(&self, query: &Query) -> u32 {
+        let mut inner = self.inner.lock().unwrap();
+        inner.next_id += 1;
+        let id = inner.next_id;
+        if inner.queries.len() >= MAX_LOGGED_QUERIES {
+            inner.queries.pop_front();
+        }
+        inner.queries.push_back(SentQuery {
+            id,
+            questions: query.questions.iter().map(|q| (q.name.to_ascii_lowercase(), q.question_type)).collect(),
+            last_sent: OffsetDateTime::now_utc(),
+        });
+        id
+    }
+
+    fn mark_sent(&self, id: u32, sent: OffsetDateTime) {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(query) = inner.queries.iter_mut().find(|q| q.id == id) {
+            query.last_sent = sent;
+        }
+    }
+
+    /// Tags a message received at the given time. Responses are matched by the names and types of their answers,
+    /// as mDNS responses carry neither the query id nor, in general, the questions.
+    pub fn tag(&self, message: &MDNSMessage, received: OffsetDateTime) -> Option<QueryTag> {
+        let inner = self.inner.lock().unwrap();
+        let recent = inner.queries.iter().rev().filter(|q| {
+            // The capture may timestamp our own query slightly before the sender does.
+            received >= q.last_sent - REPLY_WINDOW && received <= q.last_sent + REPLY_WINDOW
+        });
+        for query in recent {
+            if message.header.is_response() {
+                let answered = message.answers.iter().any(|a| {
+                    query.questions.iter().any(|(name, record_type)| {
+                        a.name.eq_ignore_ascii_case(name) && (*record_type == MDNSRecordType::ANY || *record_type == a.answer_type)
+                    })
+                });
+                if answered {
+                    return Some(QueryTag::Reply(query.id));
+                }
+            } else {
+                let same_questions = message.questions.len() == query.questions.len()
+                    && message.questions.iter().zip(query.questions.iter()).all(|(q, (name, record_type))| {
+                        q.name.eq_ignore_ascii_case(name) && q.question_type == *record_type
+                    });
+                if same_questions {
+                    return Some(QueryTag::Sent(query.id));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Where queries are sent.
+#[derive(Debug, Clone)]
+pub struct QuerySenderConfig {
+    pub group: MulticastGroup,
+    /// Address of the interface to send IPv4 queries from. No IPv4 query is sent when missing.
+    pub ipv4_interface: Option<Ipv4Addr>,
+    /// Index of the interface to send IPv6 queries from. No IPv6 query is sent when missing.
+    pub ipv6_interface_index: Option<u32>,
+}
+
+impl QuerySenderConfig {
+    /// Sends mDNS queries on the interface, over both address families where it has addresses.
+    pub fn for_interface(interface: &NetworkInterface) -> Self {
+        let listener_config = SocketListenerConfig::for_interface(interface);
+        Self {
+            group: MulticastGroup::MDNS,
+            ipv4_interface: listener_config.ipv4_interface,
+            ipv6_interface_index: listener_config.ipv6_interface_index,
+        }
+    }
+}
+
+/// Sends mDNS queries to the multicast group of an interface.
+/// Queries are sent from an ephemeral port, so the sockets do not take unicast datagrams meant for the mDNS stack
+/// of the host. Responders answer them by unicast to that port (RFC 6762 - 6.7), see `receive`.
+pub struct QuerySender {
+    sockets: Vec<(UdpSocket, SocketAddr)>,
+    log: SentQueryLog,
+}
+
+impl QuerySender {
+    pub fn open(config: &QuerySenderConfig, log: SentQueryLog) -> io::Result<Self> {
+        Ok(Self { sockets: open_group_senders(config)?, log })
+    }
+
+    /// Opens a socket sending to the group from the local port, returns it with the group address.
+    /// Port 0 binds an ephemeral port, any other port is shared with the sockets of other processes.
+    pub fn open_ipv4(group: &MulticastGroup, interface: Ipv4Addr, local_port: u16) -> io::Result<(UdpSocket, SocketAddr)> {
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+        if local_port != 0 {
+            SocketListener::set_reuse(&socket)?;
+        }
+        socket.bind(&SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, local_port)).into())?;
+        socket.set_multicast_if_v4(&interface)?;
+        // The capture on this host should see the query too.
+        socket.set_multicast_loop_v4(true)?;
+        socket.set_multicast_ttl_v4(255)?;
+        Ok((socket.into(), SocketAddr::V4(SocketAddrV4::new(group.ipv4, group.port))))
+    }
+
+    /// Opens a socket sending to the IPv6 group from the local port, returns it with the group address.
+    pub fn open_ipv6(group: &MulticastGroup, interface_index: u32, local_port: u16) -> io::Result<(UdpSocket, SocketAddr)> {
+        let socket = Socket::new(Domain::IPV6, Type::DGRAM, Some(Protocol::UDP))?;
+        socket.set_only_v6(true)?;
+        if local_port != 0 {
+            SocketListener::set_reuse(&socket)?;
+        }
+        socket.bind(&SocketAddr::V6(SocketAddrV6::new(Ipv6Addr::UNSPECIFIED, local_port, 0, 0)).into())?;
+        socket.set_multicast_if_v6(interface_index)?;
+        socket.set_multicast_loop_v6(true)?;
+        socket.set_multicast_hops_v6(255)?;
+        Ok((socket.into(), SocketAddr::V6(SocketAddrV6::new(group.ipv6, group.port, 0, interface_index))))
+    }
+
+    /// Sends the query once and returns the id its replies are tagged with.
+    pub fn send(&self, query: &Query) -> io::Result<u32> {
+        let payload = encode(query)?;
+        let id = self.log.register(query);
+        self.send_payload(&payload)?;
+        Ok(id)
+    }
+
+    /// Receives a unicast response to the queries, waiting up to the timeout on every socket.
+    /// Returns the payload with its source, `None` when nothing arrived.
+    pub fn receive(&self, timeout: Duration) -> io::Result<Option<(Vec<u8>, SocketAddr)>> {
+        let mut buffer = vec![0u8; RECEIVE_BUFFER_LENGTH];
+        for (socket, _) in self.sockets.iter() {
+            socket.set_read_timeout(Some(timeout))?;
+            match socket.recv_from(&mut buffer) {
+                Ok((length, source)) => return Ok(Some((buffer[..length].to_vec(), source))),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(None)
+    }
+
+    fn send_payload(&self, payload: &[u8]) -> io::Result<()> {
+        send_to_groups(&self.sockets, payload)
+    }
+}
+
+/// Opens a socket sending to the group from an ephemeral port for every address family the config has an interface for.
+pub fn open_group_senders(config: &QuerySenderConfig) -> io::Result<Vec<(UdpSocket, SocketAddr)>> {
+    let mut sockets = vec![];
+    if let Some(interface) = config.ipv4_interface {
+        sockets.push(QuerySender::open_ipv4(&config.group, interface, 0)?);
+    }
+    if let Some(interface_index) = config.ipv6_interface_index {
+        sockets.push(QuerySender::open_ipv6(&config.group, interface_index, 0)?);
+    }
+    if sockets.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::AddrNotAvailable, "The interface has no address to send from."));
+    }
+    Ok(sockets)
+}
+
+/// Sends the payload to the group of every socket. Fails only when it could not be sent on any of them.
+pub fn send_to_groups(sockets: &[(UdpSocket, SocketAddr)], payload: &[u8]) -> io::Result<()> {
+    let mut first_error = None;
+    let mut sent = false;
+    for (socket, group) in sockets.iter() {
+        match socket.send_to(payload, group) {
+            Ok(_) => sent = true,
+            Err(e) => {
+                println!("Could not send to {}: {}", group, e);
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    match (sent, first_error) {
+        (false, Some(e)) => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// A query sent repeatedly by a background thread until the handle is dropped or stopped.
+pub struct ContinuousQuery {
+    id: u32,
+    stop_requested: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ContinuousQuery {
+    /// Sends the query right away and then every `interval`.
+    pub fn start(sender: QuerySender, query: &Query, interval: Duration) -> io::Result<Self> {
+        let payload = encode(query)?;
+        let id = sender.log.register(query);
+        sender.send_payload(&payload)?;
+        let stop_requested = Arc::new(AtomicBool::new(false));
+        let thread_stop_requested = stop_requested.clone();
+        let thread = thread::Builder::new()
+            .name(format!("mDNS query {}", id))
+            .spawn(move || {
+                let mut last_sent = Instant::now();
+                while !thread_stop_requested.load(Ordering::SeqCst) {
+                    thread::sleep(STOP_POLL_INTERVAL.min(interval));
+                    if last_sent.elapsed() < interval {
+                        continue;
+                    }
+                    last_sent = Instant::now();
+                    sender.log.mark_sent(id, OffsetDateTime::now_utc());
+                    if let Err(e) = sender.send_payload(&payload) {
+                        println!("Could not repeat query {}: {}", id, e);
+                    }
+                }
+            })?;
+        Ok(Self {
+            id,
+            stop_requested,
+            thread: Some(thread),
+        })
+    }
+
+    /// Id the replies to the query are tagged with.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn stop(&mut self) {
+        self.stop_requested.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for ContinuousQuery {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn encode(query: &Query) -> io::Result<Vec<u8>> {
+    encode_mdns_message(&query.to_message()).map_err(|e: EncodeError| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))
+}
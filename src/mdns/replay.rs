@@ -0,0 +1,385 @@
+use crate::mdns::capture::{udp_datagram, LinkType};
+use crate::mdns::packet_builder::build_udp_packet;
+use crate::mdns::packet_source::{FilePacketSource, PacketSource};
+use crate::mdns::query_sender::{open_group_senders, QuerySenderConfig};
+use crate::mdns::socket_listener::{MulticastGroup, MDNS_PORT};
+use crate::mdns::types::MDNSRecordType;
+use pnet::datalink::{channel, Channel, Config, DataLinkSender, NetworkInterface};
+use pnet::util::MacAddr;
+use socket2::SockRef;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use time::OffsetDateTime;
+
+/// How often a waiting replay checks whether it was stopped.
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// IP TTL of raw packets when neither the capture nor the options give one, as mDNS senders use (RFC 6762 - 11).
+const DEFAULT_IP_TTL: u8 = 255;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86DD;
+/// Length of the DNS header, the questions follow it (RFC 1035 - 4.1.1).
+const HEADER_LENGTH: usize = 12;
+
+/// A captured mDNS datagram queued for replay.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayPacket {
+    /// Time since the first packet of the recording was captured.
+    pub offset: Duration,
+    pub source: SocketAddr,
+    /// IPv4 TTL or IPv6 hop limit it was captured with.
+    pub ttl: Option<u8>,
+    pub payload: Vec<u8>,
+}
+
+/// Collects the mDNS datagrams of captured frames in the order they are pushed.
+#[derive(Default)]
+pub struct ReplayRecording {
+    first_timestamp: Option<OffsetDateTime>,
+    packets: Vec<ReplayPacket>,
+}
+
+impl ReplayRecording {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keeps the frame when it carries a UDP datagram from or to the mDNS port, returns whether it was kept.
+    pub fn push_frame(&mut self, link_type: LinkType, frame: &[u8], timestamp: OffsetDateTime) -> bool {
+        let datagram = match udp_datagram(link_type, frame) {
+            Some(d) if d.source.port() == MDNS_PORT || d.destination.port() == MDNS_PORT => d,
+            _ => return false
+        };
+        let first_timestamp = *self.first_timestamp.get_or_insert(timestamp);
+        // Frames are replayed in the order they were pushed, a timestamp going back in time does not reorder them.
+        let previous = self.packets.last().map(|p| p.offset).unwrap_or_default();
+        let offset = Duration::try_from(timestamp - first_timestamp).unwrap_or_default().max(previous);
+        self.packets.push(ReplayPacket {
+            offset,
+            source: datagram.source,
+            ttl: datagram.ttl,
+            payload: datagram.payload.to_vec(),
+        });
+        true
+    }
+
+    pub fn into_packets(self) -> Vec<ReplayPacket> {
+        self.packets
+    }
+}
+
+/// Reads the mDNS datagrams recorded in a pcap or pcapng file.
+pub fn read_replay_file(path: &Path) -> io::Result<Vec<ReplayPacket>> {
+    let mut source = FilePacketSource::open(path)?;
+    let mut recording = ReplayRecording::new();
+    while let Some(frame) = source.next_frame()? {
+        recording.push_frame(frame.link_type, frame.data, frame.timestamp);
+    }
+    Ok(recording.into_packets())
+}
+
+/// When the packets of a replay are sent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplayTiming {
+    /// Keeps the gaps between the packets as they were captured.
+    Original,
+    /// Sends the given number of packets per second.
+    FixedRate(f64),
+}
+
+impl Display for ReplayTiming {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let timing = match self {
+            ReplayTiming::Original => "Original timing".to_string(),
+            ReplayTiming::FixedRate(rate) => format!("{} packets/s", rate),
+        };
+        write!(f, "{}", timing)
+    }
+}
+
+/// Source address of the replayed packets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplaySource {
+    /// Sends from the mDNS socket of the interface, like any other responder on the host.
+    Interface,
+    /// Keeps the captured source address, the packet is written to the link layer.
+    Original,
+    /// Replaces the source of packets of the same address family, the packet is written to the link layer.
+    /// Packets of the other family are skipped.
+    Address(IpAddr),
+}
+
+impl Display for ReplaySource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let source = match self {
+            ReplaySource::Interface => "Interface address".to_string(),
+            ReplaySource::Original => "Captured address".to_string(),
+            ReplaySource::Address(address) => address.to_string(),
+        };
+        write!(f, "{}", source)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReplayOptions {
+    pub timing: ReplayTiming,
+    pub source: ReplaySource,
+    /// IP TTL or hop limit of the sent packets. Raw packets keep the captured one when missing.
+    pub ip_ttl: Option<u8>,
+    /// Replaces the TTL of every record. Records keep their TTL when missing.
+    pub record_ttl: Option<u32>,
+}
+
+/// Time since the start of the replay at which each packet is sent.
+pub fn schedule(packets: &[ReplayPacket], timing: ReplayTiming) -> Vec<Duration> {
+    match timing {
+        ReplayTiming::Original => packets.iter().map(|p| p.offset).collect(),
+        ReplayTiming::FixedRate(rate) if rate > 0.0 => (0..packets.len()).map(|i| Duration::from_secs_f64(i as f64 / rate)).collect(),
+        ReplayTiming::FixedRate(_) => vec![Duration::ZERO; packets.len()],
+    }
+}
+
+/// Sets the TTL of every record of the payload. EDNS OPT records are left alone, their TTL field holds flags (RFC 6891 - 6.1.3).
+/// The TTL fields are patched in place, every other byte is replayed as it was captured.
+/// Payloads whose records cannot be walked are sent unchanged.
+pub fn rewrite_payload(payload: &[u8], record_ttl: Option<u32>) -> Vec<u8> {
+    let ttl_seconds = match record_ttl {
+        Some(t) => t,
+        None => return payload.to_vec()
+    };
+    let mut rewritten = payload.to_vec();
+    match patch_record_ttls(&mut rewritten, ttl_seconds) {
+        Some(()) => rewritten,
+        None => {
+            println!("Replaying a payload whose records could not be walked unchanged.");
+            payload.to_vec()
+        }
+    }
+}
+
+/// Walks the questions and records of the payload and writes the TTL of each record (RFC 1035 - 4.1).
+fn patch_record_ttls(payload: &mut [u8], ttl_seconds: u32) -> Option<()> {
+    let question_count = read_u16(payload, 4)?;
+    let record_count = read_u16(payload, 6)? + read_u16(payload, 8)? + read_u16(payload, 10)?;
+    let mut index = HEADER_LENGTH;
+    for _ in 0..question_count {
+        // Type and class.
+        index = skip_name(payload, index)? + 4;
+    }
+    for _ in 0..record_count {
+        index = skip_name(payload, index)?;
+        let record_type = read_u16(payload, index)?;
+        let rd_length = read_u16(payload, index + 8)?;
+        if record_type != MDNSRecordType::OPT as usize {
+            payload.get_mut(index + 4..index + 8)?.copy_from_slice(&ttl_seconds.to_be_bytes());
+        }
+        // Type, class, TTL, data length and the data.
+        index += 10 + rd_length;
+    }
+    match index <= payload.len() {
+        true => Some(()),
+        false => None,
+    }
+}
+
+fn read_u16(payload: &[u8], index: usize) -> Option<usize> {
+    Some(u16::from_be_bytes([*payload.get(index)?, *payload.get(index + 1)?]) as usize)
+}
+
+/// Index right after the name starting at `index`. A compression pointer ends the name (RFC 1035 - 4.1.4).
+fn skip_name(payload: &[u8], mut index: usize) -> Option<usize> {
+    loop {
+        let length = *payload.get(index)? as usize;
+        match length {
+            0 => return Some(index + 1),
+            l if l & 0xc0 == 0xc0 => return Some(index + 2),
+            l => index += 1 + l,
+        }
+    }
+}
+
+/// Wraps a UDP datagram to the multicast group into a frame of the given link type, ready to be written to the interface.
+pub fn replay_frame(link_type: LinkType, source_mac: MacAddr, source: SocketAddr, destination: SocketAddr, ttl: u8, payload: &[u8]) -> io::Result<Vec<u8>> {
+    let packet = build_udp_packet(source, destination, ttl, payload)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Source and destination are of different address families."))?;
+    match link_type {
+        LinkType::Ethernet => {
+            let (destination_mac, ethertype) = match destination.ip() {
+                // RFC 1112 - 6.4, the low 23 bits of the group go into 01:00:5e:00:00:00.
+                IpAddr::V4(group) => {
+                    let o = group.octets();
+                    (MacAddr::new(0x01, 0x00, 0x5e, o[1] & 0x7f, o[2], o[3]), ETHERTYPE_IPV4)
+                }
+                // RFC 2464 - 7, the low 32 bits of the group go into 33:33:00:00:00:00.
+                IpAddr::V6(group) => {
+                    let o = group.octets();
+                    (MacAddr::new(0x33, 0x33, o[12], o[13], o[14], o[15]), ETHERTYPE_IPV6)
+                }
+            };
+            let mut frame = Vec::with_capacity(14 + packet.len());
+            frame.extend_from_slice(&destination_mac.octets());
+            frame.extend_from_slice(&source_mac.octets());
+            frame.extend_from_slice(&ethertype.to_be_bytes());
+            frame.extend_from_slice(&packet);
+            Ok(frame)
+        }
+        LinkType::RawIp => Ok(packet),
+        _ => Err(io::Error::new(io::ErrorKind::Unsupported, "Packets can only be written to Ethernet and raw IP interfaces.")),
+    }
+}
+
+/// Where replayed packets are written to.
+enum ReplaySender {
+    /// mDNS sockets of the interface with the group address they send to.
+    Sockets(Vec<(UdpSocket, SocketAddr)>),
+    /// The link layer of the interface, for packets with a rewritten source.
+    Datalink {
+        sender: Box<dyn DataLinkSender>,
+        link_type: LinkType,
+        mac: MacAddr,
+        group: MulticastGroup,
+    },
+}
+
+impl ReplaySender {
+    fn open(interface: &NetworkInterface, group: MulticastGroup, options: &ReplayOptions) -> io::Result<Self> {
+        if options.source != ReplaySource::Interface {
+            let link_type = LinkType::for_interface(interface);
+            let sender = match channel(interface, Config::default())? {
+                Channel::Ethernet(sender, _) => sender,
+                _ => return Err(io::Error::new(io::ErrorKind::Unsupported, "The interface does not support writing packets.")),
+            };
+            return Ok(ReplaySender::Datalink {
+                sender,
+                link_type,
+                mac: interface.mac.unwrap_or(MacAddr::zero()),
+                group,
+            });
+        }
+        let config = QuerySenderConfig { group, ..QuerySenderConfig::for_interface(interface) };
+        let sockets = open_group_senders(&config)?;
+        if let Some(ttl) = options.ip_ttl {
+            for (socket, group) in sockets.iter() {
+                match group.is_ipv4() {
+                    true => socket.set_multicast_ttl_v4(ttl as u32)?,
+                    false => SockRef::from(socket).set_multicast_hops_v6(ttl as u32)?,
+                }
+            }
+        }
+        Ok(ReplaySender::Sockets(sockets))
+    }
+
+    /// Returns `false` when the packet was skipped.
+    fn send(&mut self, packet: &ReplayPacket, payload: &[u8], options: &ReplayOptions) -> io::Result<bool> {
+        match self {
+            // Packets go out on the socket of their address family, or on the one the interface has.
+            ReplaySender::Sockets(sockets) => {
+                let (socket, group) = sockets.iter()
+                    .find(|(_, group)| group.is_ipv4() == packet.source.is_ipv4())
+                    .unwrap_or(&sockets[0]);
+                socket.send_to(payload, group)?;
+                Ok(true)
+            }
+            ReplaySender::Datalink { sender, link_type, mac, group } => {
+                let source = match options.source {
+                    ReplaySource::Address(address) if address.is_ipv4() != packet.source.is_ipv4() => return Ok(false),
+                    ReplaySource::Address(address) => SocketAddr::new(address, packet.source.port()),
+                    _ => packet.source,
+                };
+                let destination = match source.ip() {
+                    IpAddr::V4(_) => SocketAddr::new(IpAddr::V4(group.ipv4), group.port),
+                    IpAddr::V6(_) => SocketAddr::new(IpAddr::V6(group.ipv6), group.port),
+                };
+                let ttl = options.ip_ttl.or(packet.ttl).unwrap_or(DEFAULT_IP_TTL);
+                let frame = replay_frame(*link_type, *mac, source, destination, ttl, payload)?;
+                match sender.send_to(&frame, None) {
+                    Some(result) => result.map(|_| true),
+                    None => Err(io::Error::other("The interface did not accept the packet.")),
+                }
+            }
+        }
+    }
+}
+
+/// Progress of a running replay.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReplayStatus {
+    pub total: usize,
+    pub sent: usize,
+    /// Packets of an address family the rewritten source does not have.
+    pub skipped: usize,
+    pub finished: bool,
+    pub last_error: Option<String>,
+}
+
+/// Sends captured mDNS payloads to the multicast group of an interface on a background thread.
+pub struct Replay {
+    status: Arc<Mutex<ReplayStatus>>,
+    stop_requested: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Replay {
+    /// Opens the interface and starts sending. Fails when the interface cannot be written to.
+    pub fn start(interface: &NetworkInterface, group: MulticastGroup, packets: Vec<ReplayPacket>, options: ReplayOptions) -> io::Result<Self> {
+        let mut sender = ReplaySender::open(interface, group, &options)?;
+        let times = schedule(&packets, options.timing);
+        let status = Arc::new(Mutex::new(ReplayStatus { total: packets.len(), ..Default::default() }));
+        let stop_requested = Arc::new(AtomicBool::new(false));
+        let thread_status = status.clone();
+        let thread_stop_requested = stop_requested.clone();
+        let thread = thread::Builder::new()
+            .name("mDNS replay".to_string())
+            .spawn(move || {
+                let started = Instant::now();
+                for (packet, at) in packets.iter().zip(times) {
+                    while started.elapsed() < at && !thread_stop_requested.load(Ordering::SeqCst) {
+                        thread::sleep((at - started.elapsed()).min(STOP_POLL_INTERVAL));
+                    }
+                    if thread_stop_requested.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let payload = rewrite_payload(&packet.payload, options.record_ttl);
+                    let result = sender.send(packet, &payload, &options);
+                    let mut status = thread_status.lock().unwrap();
+                    match result {
+                        Ok(true) => status.sent += 1,
+                        Ok(false) => status.skipped += 1,
+                        Err(e) => {
+                            println!("Could not replay a packet: {}", e);
+                            status.last_error = Some(e.to_string());
+                        }
+                    }
+                }
+                thread_status.lock().unwrap().finished = true;
+            })?;
+        Ok(Self {
+            status,
+            stop_requested,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn status(&self) -> ReplayStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    pub fn stop(&mut self) {
+        self.stop_requested.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Replay {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
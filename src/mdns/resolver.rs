@@ -0,0 +1,114 @@
+use crate::mdns::mdns_message::MDNSMessage;
+use crate::mdns::parser::parse_mdns_message;
+use crate::mdns::query_sender::{Query, QuerySender, QuerySenderConfig, SentQueryLog};
+use crate::mdns::socket_listener::{SocketListener, SocketListenerConfig};
+use crate::mdns::types::{MDNSQueryClass, MDNSQuestion, MDNSRData, MDNSRecordType};
+use std::io;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+use time::OffsetDateTime;
+
+/// How long the listener waits for a datagram before checking the timeout.
+const RECEIVE_TIMEOUT: Duration = Duration::from_millis(20);
+
+/// An address a responder gave for the name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedAddress {
+    pub address: IpAddr,
+    pub ttl_seconds: u32,
+    /// Source address of the response.
+    pub responder: IpAddr,
+    /// Time from sending the query to receiving the response.
+    pub latency: Duration,
+}
+
+/// Outcome of resolving a name.
+#[derive(Debug, Clone)]
+pub struct Resolution {
+    pub name: String,
+    /// Id of the query in the `SentQueryLog`.
+    pub query_id: u32,
+    /// When the query was sent.
+    pub sent: OffsetDateTime,
+    /// How long the answers were collected after the query was sent.
+    pub timeout: Duration,
+    /// Addresses in the order they arrived. An address given by several responders is listed for each of them.
+    pub addresses: Vec<ResolvedAddress>,
+}
+
+impl Resolution {
+    /// Whether a captured message is one of the responses: it arrived while the answers were collected and gives an address for the name.
+    /// The reply tags of the `SentQueryLog` cover only the first seconds, the timeout may be much longer.
+    pub fn is_answered_by(&self, message: &MDNSMessage, received: OffsetDateTime) -> bool {
+        received >= self.sent
+            && received <= self.sent + self.timeout
+            && message.header.is_response()
+            && addresses_for(message, &self.name).next().is_some()
+    }
+}
+
+/// Addresses and their TTLs the message gives for the name. Goodbye records withdraw an address rather than giving one.
+fn addresses_for<'a>(message: &'a MDNSMessage, name: &'a str) -> impl Iterator<Item = (IpAddr, u32)> + 'a {
+    message.answers.iter().chain(message.additionals.iter())
+        .filter(move |r| r.name.eq_ignore_ascii_case(name) && r.ttl_seconds > 0)
+        .filter_map(|r| match r.rdata {
+            MDNSRData::A { ipv4_address } => Some((IpAddr::V4(ipv4_address), r.ttl_seconds)),
+            MDNSRData::AAAA { ipv6_addr } => Some((IpAddr::V6(ipv6_addr), r.ttl_seconds)),
+            _ => None
+        })
+}
+
+/// Queries A and AAAA records of the name and collects the answers until the timeout passes, like `avahi-resolve` does.
+/// Blocks for the whole timeout, so every responder is heard.
+pub fn resolve(config: &QuerySenderConfig, log: SentQueryLog, name: &str, timeout: Duration) -> io::Result<Resolution> {
+    // Listen before sending, so no early response is missed.
+    let mut listener = SocketListener::open(&SocketListenerConfig {
+        groups: vec![config.group],
+        ipv4_interface: config.ipv4_interface,
+        ipv6_interface_index: config.ipv6_interface_index,
+        read_timeout: RECEIVE_TIMEOUT,
+    })?;
+    let sender = QuerySender::open(config, log)?;
+    let mut query = Query::new(name, MDNSRecordType::A, false);
+    query.questions.push(MDNSQuestion {
+        name: name.to_string(),
+        question_type: MDNSRecordType::AAAA,
+        question_class: MDNSQueryClass::IN,
+        unicast_response: false,
+    });
+    let sent = Instant::now();
+    let sent_datetime = OffsetDateTime::now_utc();
+    let query_id = sender.send(&query)?;
+
+    let mut addresses: Vec<ResolvedAddress> = vec![];
+    while sent.elapsed() < timeout {
+        // The query goes out from an ephemeral port, so responders answer it by unicast (RFC 6762 - 6.7).
+        // Responses to the group, like those to the same query of another host, count too.
+        let (payload, source) = match sender.receive(RECEIVE_TIMEOUT)? {
+            Some(response) => response,
+            None => match listener.receive()? {
+                Some(d) => (d.payload, d.source),
+                None => continue
+            }
+        };
+        let latency = sent.elapsed();
+        let message = match parse_mdns_message(&payload) {
+            Ok(m) if m.header.is_response() => m,
+            _ => continue
+        };
+        let responder = source.ip();
+        for (address, ttl_seconds) in addresses_for(&message, name) {
+            if addresses.iter().any(|a| a.address == address && a.responder == responder) {
+                continue;
+            }
+            addresses.push(ResolvedAddress { address, ttl_seconds, responder, latency });
+        }
+    }
+    Ok(Resolution {
+        name: name.to_string(),
+        query_id,
+        sent: sent_datetime,
+        timeout,
+        addresses,
+    })
+}
@@ -0,0 +1,744 @@
+use crate::mdns::encoder::{encode_mdns_message, encode_rdata};
+use crate::mdns::mdns_message::MDNSMessage;
+use crate::mdns::parser::{escape_label, parse_mdns_message};
+use crate::mdns::query_sender::{QuerySender, QuerySenderConfig};
+use crate::mdns::service_browser::SERVICE_TYPE_ENUMERATION;
+use crate::mdns::socket_listener::RECEIVE_BUFFER_LENGTH;
+use crate::mdns::types::{MDNSAnswer, MDNSMessageHeader, MDNSQueryClass, MDNSQuestion, MDNSRData, MDNSRecordType};
+use std::collections::hash_map::RandomState;
+use std::fmt::{Display, Formatter};
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// TTL of address and SRV records, which name a host (RFC 6762 - 10).
+pub const HOST_RECORD_TTL: u32 = 120;
+/// TTL of the other records.
+pub const OTHER_RECORD_TTL: u32 = 4500;
+/// Legacy unicast responses must not carry a larger TTL (RFC 6762 - 6.7).
+const LEGACY_UNICAST_TTL: u32 = 10;
+
+/// The first probe is sent after a random delay of up to 250 ms, so hosts powered on together do not probe at once (RFC 6762 - 8.1).
+const PROBE_DELAY: Duration = Duration::from_millis(250);
+/// Probes are sent 250 ms apart, three of them (RFC 6762 - 8.1).
+const PROBE_INTERVAL: Duration = Duration::from_millis(250);
+const PROBE_COUNT: u32 = 3;
+/// Records are announced twice, one second apart (RFC 6762 - 8.3).
+const ANNOUNCE_INTERVAL: Duration = Duration::from_secs(1);
+const ANNOUNCE_COUNT: u32 = 2;
+/// A host losing a simultaneous probe tie-break waits this long before probing again (RFC 6762 - 8.2).
+const LOST_TIEBREAK_DELAY: Duration = Duration::from_secs(1);
+/// After fifteen conflicts within ten seconds, a host waits five seconds before each further probe (RFC 6762 - 8.1).
+const CONFLICT_LIMIT: usize = 15;
+const CONFLICT_WINDOW: Duration = Duration::from_secs(10);
+const CONFLICT_BACKOFF: Duration = Duration::from_secs(5);
+/// Multicast answers with shared records are delayed by 20 to 120 ms, as other hosts may answer too (RFC 6762 - 6).
+const SHARED_ANSWER_DELAY_MIN: Duration = Duration::from_millis(20);
+const SHARED_ANSWER_DELAY_MAX: Duration = Duration::from_millis(120);
+
+/// QR and AA bits, set in every mDNS response (RFC 6762 - 18).
+const RESPONSE_FLAGS: u16 = 0x8400;
+/// How long the responder thread waits for a datagram on each socket before checking its timers.
+const RECEIVE_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// A host name and the addresses it resolves to, e.g. `kitchen.local`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublishedHost {
+    pub name: String,
+    pub addresses: Vec<IpAddr>,
+}
+
+/// A DNS-SD service instance, e.g. instance `Kitchen` of type `_http._tcp.local`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublishedService {
+    /// The instance label as users write it, without the service type. Its dots are escaped in the record names.
+    pub instance: String,
+    pub service_type: String,
+    /// Target of the SRV record. When it is one of the published hosts, it follows that host when it is renamed.
+    pub host: String,
+    pub port: u16,
+    /// One key=value pair per line.
+    pub txt: String,
+}
+
+/// Everything a responder publishes.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PublishedRecords {
+    pub hosts: Vec<PublishedHost>,
+    pub services: Vec<PublishedService>,
+}
+
+/// Where a published name is in its life cycle (RFC 6762 - 8).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PublishState {
+    /// Checking that no other host uses the name.
+    Probing,
+    /// The name is ours, its records are being announced.
+    Announcing,
+    Announced,
+}
+
+impl Display for PublishState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let state = match self {
+            PublishState::Probing => "Probing",
+            PublishState::Announcing => "Announcing",
+            PublishState::Announced => "Announced",
+        };
+        write!(f, "{}", state)
+    }
+}
+
+/// State of one published host or service instance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublishedName {
+    /// The name as the user entered it.
+    pub requested: String,
+    /// The name as it is published, which differs from the requested one after conflicts.
+    pub name: String,
+    pub state: PublishState,
+    /// Number of conflicts which made the responder rename it.
+    pub conflicts: u32,
+}
+
+/// Where a message of the responder is sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Destination {
+    Multicast,
+    Unicast(SocketAddr),
+}
+
+pub struct OutgoingMessage {
+    pub message: MDNSMessage,
+    pub destination: Destination,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Definition {
+    Host(PublishedHost),
+    Service(PublishedService),
+}
+
+struct Entry {
+    definition: Definition,
+    conflicts: u32,
+    state: PublishState,
+    /// Probes or announcements sent in the current state.
+    sent: u32,
+    /// When the next probe or announcement is due.
+    next: Instant,
+}
+
+impl Entry {
+    fn new(definition: Definition, next: Instant) -> Self {
+        Self {
+            definition,
+            conflicts: 0,
+            state: PublishState::Probing,
+            sent: 0,
+            next,
+        }
+    }
+
+    fn name(&self) -> String {
+        Self::name_after_conflicts(&self.definition, self.conflicts)
+    }
+
+    /// Hosts are renamed `kitchen-2.local`, instances `Kitchen (2)`, as RFC 6762 - 9 and RFC 6763 - 4.1 suggest.
+    fn name_after_conflicts(definition: &Definition, conflicts: u32) -> String {
+        match definition {
+            Definition::Host(host) if conflicts == 0 => host.name.clone(),
+            Definition::Host(host) => match host.name.split_once('.') {
+                Some((label, domain)) => format!("{}-{}.{}", label, conflicts + 1, domain),
+                None => format!("{}-{}", host.name, conflicts + 1),
+            },
+            Definition::Service(service) if conflicts == 0 => format!("{}.{}", escape_label(&service.instance), service.service_type),
+            Definition::Service(service) => {
+                format!("{}.{}", escape_label(&format!("{} ({})", service.instance, conflicts + 1)), service.service_type)
+            }
+        }
+    }
+
+    /// Types of the records which only this host may have under the name.
+    fn unique_types(&self) -> &'static [MDNSRecordType] {
+        match self.definition {
+            Definition::Host(_) => &[MDNSRecordType::A, MDNSRecordType::AAAA],
+            Definition::Service(_) => &[MDNSRecordType::SRV, MDNSRecordType::TXT],
+        }
+    }
+
+    fn is_owned(&self) -> bool {
+        self.state != PublishState::Probing
+    }
+
+    fn restart_probing(&mut self, next: Instant) {
+        self.state = PublishState::Probing;
+        self.sent = 0;
+        self.next = next;
+    }
+}
+
+/// The responder logic without any I/O: it is fed the received messages and the passing time
+/// and returns the messages to send.
+pub struct ResponderCore {
+    entries: Vec<Entry>,
+    /// Port of the multicast group. Queries from other ports are legacy unicast queries (RFC 6762 - 6.7).
+    port: u16,
+    /// Picks a delay between the two durations, random unless a test replaces it.
+    random_delay: fn(Duration, Duration) -> Duration,
+    /// Multicast answers waiting for their delay, the answers to later queries are added to them.
+    pending_answers: Vec<MDNSAnswer>,
+    /// When the pending answers are sent.
+    pending_due: Option<Instant>,
+    /// When the recent conflicts happened, to rate limit probing.
+    conflict_times: Vec<Instant>,
+}
+
+impl ResponderCore {
+    pub fn new(port: u16) -> Self {
+        Self::with_random_delay(port, random_delay)
+    }
+
+    pub fn with_random_delay(port: u16, random_delay: fn(Duration, Duration) -> Duration) -> Self {
+        Self {
+            entries: vec![],
+            port,
+            random_delay,
+            pending_answers: vec![],
+            pending_due: None,
+            conflict_times: vec![],
+        }
+    }
+
+    /// Replaces the published records. New hosts and services are probed, removed ones are said goodbye to.
+    pub fn publish(&mut self, records: PublishedRecords, now: Instant) -> Vec<OutgoingMessage> {
+        let definitions: Vec<Definition> = records.hosts.into_iter().map(Definition::Host)
+            .chain(records.services.into_iter().map(Definition::Service))
+            .collect();
+        let removed: Vec<usize> = (0..self.entries.len()).filter(|i| !definitions.contains(&self.entries[*i].definition)).collect();
+        let mut goodbyes: Vec<MDNSAnswer> = removed.iter()
+            .filter(|i| self.entries[**i].is_owned())
+            .flat_map(|i| self.records(*i))
+            .collect();
+        self.entries.retain(|e| definitions.contains(&e.definition));
+        for definition in definitions {
+            if !self.entries.iter().any(|e| e.definition == definition) {
+                self.entries.push(Entry::new(definition, now + (self.random_delay)(Duration::ZERO, PROBE_DELAY)));
+            }
+        }
+        // Shared records, like the service type enumeration PTR, may still be published by the remaining services.
+        let remaining = self.owned_records();
+        goodbyes.retain(|g| !remaining.iter().any(|r| same_record(r, g)));
+        if goodbyes.is_empty() {
+            return vec![];
+        }
+        for goodbye in goodbyes.iter_mut() {
+            goodbye.ttl_seconds = 0;
+        }
+        vec![OutgoingMessage { message: response(0, vec![], goodbyes, vec![]), destination: Destination::Multicast }]
+    }
+
+    /// State of every published host and service.
+    pub fn status(&self) -> Vec<PublishedName> {
+        self.entries.iter().map(|e| PublishedName {
+            requested: Entry::name_after_conflicts(&e.definition, 0),
+            name: e.name(),
+            state: e.state,
+            conflicts: e.conflicts,
+        }).collect()
+    }
+
+    /// Sends the probes, announcements and delayed answers which are due.
+    pub fn poll(&mut self, now: Instant) -> Vec<OutgoingMessage> {
+        let mut probes = vec![];
+        let mut announcements = vec![];
+        for (index, entry) in self.entries.iter_mut().enumerate() {
+            if now < entry.next {
+                continue;
+            }
+            if entry.state == PublishState::Probing {
+                if entry.sent < PROBE_COUNT {
+                    // Only the first probe asks for unicast responses (RFC 6762 - 8.1).
+                    probes.push((index, entry.sent == 0));
+                    entry.sent += 1;
+                    entry.next = now + PROBE_INTERVAL;
+                    continue;
+                }
+                entry.state = PublishState::Announcing;
+                entry.sent = 0;
+            }
+            if entry.state == PublishState::Announcing {
+                announcements.push(index);
+                entry.sent += 1;
+                entry.next = now + ANNOUNCE_INTERVAL;
+                if entry.sent == ANNOUNCE_COUNT {
+                    entry.state = PublishState::Announced;
+                }
+            }
+        }
+
+        let mut outgoing = vec![];
+        if !probes.is_empty() {
+            let questions = probes.iter().map(|(index, unicast_response)| MDNSQuestion {
+                name: self.entries[*index].name(),
+                question_type: MDNSRecordType::ANY,
+                question_class: MDNSQueryClass::IN,
+                unicast_response: *unicast_response,
+            }).collect();
+            let authorities = probes.iter().flat_map(|(index, _)| self.records(*index)).collect();
+            let message = MDNSMessage {
+                header: header(0, 0),
+                questions,
+                answers: vec![],
+                authorities,
+                additionals: vec![],
+            };
+            outgoing.push(OutgoingMessage { message: with_counts(message), destination: Destination::Multicast });
+        }
+        if !announcements.is_empty() {
+            let mut answers = vec![];
+            for record in announcements.iter().flat_map(|index| self.records(*index)) {
+                push_unique(&mut answers, record);
+            }
+            outgoing.push(OutgoingMessage { message: response(0, vec![], answers, vec![]), destination: Destination::Multicast });
+        }
+        if self.pending_due.is_some_and(|due| now >= due) {
+            self.pending_due = None;
+            // Names lost to a conflict in the meantime are not answered for anymore.
+            let owned = self.owned_records();
+            let answers: Vec<MDNSAnswer> = std::mem::take(&mut self.pending_answers).into_iter()
+                .filter(|a| owned.iter().any(|o| same_record(o, a)))
+                .collect();
+            if !answers.is_empty() {
+                let additionals = self.additional_records(&answers, &owned);
+                outgoing.push(OutgoingMessage { message: response(0, vec![], answers, additionals), destination: Destination::Multicast });
+            }
+        }
+        outgoing
+    }
+
+    /// Handles a received message: responses are checked for conflicts, queries are answered.
+    pub fn handle(&mut self, message: &MDNSMessage, source: SocketAddr, now: Instant) -> Vec<OutgoingMessage> {
+        if message.header.is_response() {
+            self.detect_conflicts(message, now);
+            return vec![];
+        }
+        self.break_probe_ties(message, now);
+        self.answer(message, source, now)
+    }
+
+    /// Renames the names another host claims (RFC 6762 - 8.1 and 9). Records equal to ours are not a conflict,
+    /// this is also how our own looped back responses are recognized.
+    fn detect_conflicts(&mut self, message: &MDNSMessage, now: Instant) {
+        let received: Vec<&MDNSAnswer> = message.answers.iter().chain(message.additionals.iter())
+            .filter(|r| r.ttl_seconds > 0)
+            .collect();
+        let mut renamed_hosts = vec![];
+        for index in 0..self.entries.len() {
+            let name = self.entries[index].name();
+            let ours = self.records(index);
+            let entry = &self.entries[index];
+            let conflicting = received.iter().any(|r| {
+                r.name.eq_ignore_ascii_case(&name)
+                    && !ours.iter().any(|o| same_record(o, r))
+                    && (entry.state == PublishState::Probing || entry.unique_types().contains(&r.answer_type))
+            });
+            if !conflicting {
+                continue;
+            }
+            self.conflict_times.retain(|t| now.duration_since(*t) < CONFLICT_WINDOW);
+            self.conflict_times.push(now);
+            let next = match self.conflict_times.len() >= CONFLICT_LIMIT {
+                true => now + CONFLICT_BACKOFF,
+                false => now + (self.random_delay)(Duration::ZERO, PROBE_DELAY),
+            };
+            let entry = &mut self.entries[index];
+            entry.conflicts += 1;
+            entry.restart_probing(next);
+            println!("Name {} is already used by another host, probing for {} instead.", name, entry.name());
+            if let Definition::Host(host) = &entry.definition {
+                renamed_hosts.push(host.name.clone());
+            }
+        }
+        // Services pointing to a renamed host announce their new SRV target (RFC 6762 - 8.4).
+        for entry in self.entries.iter_mut() {
+            if let Definition::Service(service) = &entry.definition
+                && entry.is_owned()
+                && renamed_hosts.iter().any(|h| h.eq_ignore_ascii_case(&service.host)) {
+                entry.state = PublishState::Announcing;
+                entry.sent = 0;
+                entry.next = now;
+            }
+        }
+    }
+
+    /// Compares the records of a probe from another host with ours (RFC 6762 - 8.2). The host with the
+    /// lexicographically later records wins, the other one probes again a second later.
+    /// Equal records are our own probe looped back.
+    fn break_probe_ties(&mut self, message: &MDNSMessage, now: Instant) {
+        if message.authorities.is_empty() {
+            return;
+        }
+        for index in 0..self.entries.len() {
+            if self.entries[index].state != PublishState::Probing {
+                continue;
+            }
+            let name = self.entries[index].name();
+            let theirs = sorted_record_data(message.authorities.iter().filter(|r| r.name.eq_ignore_ascii_case(&name)));
+            if theirs.is_empty() {
+                continue;
+            }
+            let records = self.records(index);
+            let ours = sorted_record_data(records.iter().filter(|r| r.name.eq_ignore_ascii_case(&name)));
+            if ours < theirs {
+                println!("Lost the probe tie-break for {}, probing again.", name);
+                self.entries[index].restart_probing(now + LOST_TIEBREAK_DELAY);
+            }
+        }
+    }
+
+    /// Answers the questions about our records. QU questions are answered by unicast (RFC 6762 - 5.4),
+    /// queries from other ports than the group port get a legacy unicast response (RFC 6762 - 6.7).
+    /// Multicast answers with only unique records go out at once, the others wait for the delay of shared answers (RFC 6762 - 6).
+    fn answer(&mut self, message: &MDNSMessage, source: SocketAddr, now: Instant) -> Vec<OutgoingMessage> {
+        let legacy = source.port() != self.port;
+        let owned = self.owned_records();
+        let mut multicast = vec![];
+        let mut unicast = vec![];
+        for question in message.questions.iter() {
+            let matching = owned.iter().filter(|r| {
+                r.name.eq_ignore_ascii_case(&question.name)
+                    && (question.question_type == MDNSRecordType::ANY || question.question_type == r.answer_type)
+            });
+            for record in matching {
+                // Known answer suppression (RFC 6762 - 7.1).
+                let known = message.answers.iter().any(|k| same_record(k, record) && k.ttl_seconds >= record.ttl_seconds / 2);
+                if known {
+                    continue;
+                }
+                match question.unicast_response || legacy {
+                    true => push_unique(&mut unicast, record.clone()),
+                    false => push_unique(&mut multicast, record.clone()),
+                }
+            }
+        }
+
+        let mut outgoing = vec![];
+        if !unicast.is_empty() {
+            let additionals = self.additional_records(&unicast, &owned);
+            let message = match legacy {
+                true => response(message.header.query_identifier, message.questions.clone(), legacy_records(unicast), legacy_records(additionals)),
+                false => response(0, vec![], unicast, additionals),
+            };
+            outgoing.push(OutgoingMessage { message, destination: Destination::Unicast(source) });
+        }
+        if self.pending_due.is_some() || multicast.iter().any(|r| !r.cache_flush) {
+            for record in multicast {
+                push_unique(&mut self.pending_answers, record);
+            }
+            if self.pending_due.is_none() && !self.pending_answers.is_empty() {
+                self.pending_due = Some(now + (self.random_delay)(SHARED_ANSWER_DELAY_MIN, SHARED_ANSWER_DELAY_MAX));
+            }
+        } else if !multicast.is_empty() {
+            let additionals = self.additional_records(&multicast, &owned);
+            outgoing.push(OutgoingMessage { message: response(0, vec![], multicast, additionals), destination: Destination::Multicast });
+        }
+        outgoing
+    }
+
+    /// Records which save the querier another query: SRV, TXT and addresses of the instances pointed to
+    /// and addresses of the SRV targets (RFC 6763 - 12).
+    fn additional_records(&self, answers: &[MDNSAnswer], owned: &[MDNSAnswer]) -> Vec<MDNSAnswer> {
+        let mut names: Vec<String> = answers.iter().filter_map(record_target).collect();
+        let mut additionals: Vec<MDNSAnswer> = vec![];
+        let mut index = 0;
+        while index < names.len() {
+            for record in owned.iter() {
+                let wanted = matches!(record.answer_type, MDNSRecordType::SRV | MDNSRecordType::TXT | MDNSRecordType::A | MDNSRecordType::AAAA);
+                if !wanted || !record.name.eq_ignore_ascii_case(&names[index])
+                    || answers.iter().chain(additionals.iter()).any(|a| same_record(a, record)) {
+                    continue;
+                }
+                if record.answer_type == MDNSRecordType::SRV
+                    && let Some(target) = record_target(record) {
+                    names.push(target);
+                }
+                additionals.push(record.clone());
+            }
+            index += 1;
+        }
+        additionals
+    }
+
+    /// Records of the names which passed probing.
+    fn owned_records(&self) -> Vec<MDNSAnswer> {
+        (0..self.entries.len()).filter(|i| self.entries[*i].is_owned()).flat_map(|i| self.records(i)).collect()
+    }
+
+    /// The published name of a host, after renames.
+    fn host_name(&self, requested: &str) -> String {
+        self.entries.iter().find_map(|e| match &e.definition {
+            Definition::Host(host) if host.name.eq_ignore_ascii_case(requested) => Some(e.name()),
+            _ => None,
+        }).unwrap_or_else(|| requested.to_string())
+    }
+
+    fn records(&self, index: usize) -> Vec<MDNSAnswer> {
+        let entry = &self.entries[index];
+        let name = entry.name();
+        match &entry.definition {
+            Definition::Host(host) => host.addresses.iter().map(|address| {
+                let rdata = match address {
+                    IpAddr::V4(ipv4_address) => MDNSRData::A { ipv4_address: *ipv4_address },
+                    IpAddr::V6(ipv6_addr) => MDNSRData::AAAA { ipv6_addr: *ipv6_addr },
+                };
+                record(&name, rdata, HOST_RECORD_TTL, true)
+            }).collect(),
+            Definition::Service(service) => vec![
+                record(&service.service_type, MDNSRData::PTR { domain_name: name.clone() }, OTHER_RECORD_TTL, false),
+                record(&name, MDNSRData::SRV {
+                    priority: 0,
+                    weight: 0,
+                    port: service.port,
+                    target_domain_name: self.host_name(&service.host),
+                }, HOST_RECORD_TTL, true),
+                record(&name, MDNSRData::TXT { text: service.txt.clone() }, OTHER_RECORD_TTL, true),
+                // Makes the service type show up in service type enumeration (RFC 6763 - 9).
+                record(SERVICE_TYPE_ENUMERATION, MDNSRData::PTR { domain_name: service.service_type.clone() }, OTHER_RECORD_TTL, false),
+            ],
+        }
+    }
+}
+
+/// A uniformly distributed delay between the two durations, in whole milliseconds.
+fn random_delay(min: Duration, max: Duration) -> Duration {
+    // Every RandomState is seeded with new random keys, which is all the randomness the delays need.
+    let random = RandomState::new().build_hasher().finish();
+    let span = (max - min).as_millis() as u64 + 1;
+    min + Duration::from_millis(random % span)
+}
+
+fn record(name: &str, rdata: MDNSRData, ttl_seconds: u32, cache_flush: bool) -> MDNSAnswer {
+    let answer_type = match rdata {
+        MDNSRData::A { .. } => MDNSRecordType::A,
+        MDNSRData::AAAA { .. } => MDNSRecordType::AAAA,
+        MDNSRData::PTR { .. } => MDNSRecordType::PTR,
+        MDNSRData::SRV { .. } => MDNSRecordType::SRV,
+        MDNSRData::TXT { .. } => MDNSRecordType::TXT,
+        MDNSRData::CNAME { .. } => MDNSRecordType::CNAME,
+        MDNSRData::NSEC { .. } => MDNSRecordType::NSEC,
+        MDNSRData::ANY { .. } | MDNSRData::OTHER { .. } => MDNSRecordType::ANY,
+    };
+    MDNSAnswer {
+        name: name.to_string(),
+        answer_type,
+        answer_class: MDNSQueryClass::IN,
+        cache_flush,
+        ttl_seconds,
+        rd_length: 0,
+        rdata,
+    }
+}
+
+/// The name a PTR or SRV record points to.
+fn record_target(record: &MDNSAnswer) -> Option<String> {
+    match &record.rdata {
+        MDNSRData::PTR { domain_name } => Some(domain_name.clone()),
+        MDNSRData::SRV { target_domain_name, .. } => Some(target_domain_name.clone()),
+        _ => None,
+    }
+}
+
+/// Records are the same when their name, type and data match, whatever their TTL.
+fn same_record(a: &MDNSAnswer, b: &MDNSAnswer) -> bool {
+    a.name.eq_ignore_ascii_case(&b.name)
+        && a.answer_type == b.answer_type
+        && encode_rdata(&a.rdata).ok() == encode_rdata(&b.rdata).ok()
+}
+
+fn push_unique(records: &mut Vec<MDNSAnswer>, record: MDNSAnswer) {
+    if !records.iter().any(|r| same_record(r, &record)) {
+        records.push(record);
+    }
+}
+
+/// Class, type and data of the records in the order the probe tie-break compares them.
+fn sorted_record_data<'a>(records: impl Iterator<Item = &'a MDNSAnswer>) -> Vec<(u16, u16, Vec<u8>)> {
+    let mut data: Vec<(u16, u16, Vec<u8>)> = records
+        .map(|r| (r.answer_class.clone() as u16, r.answer_type as u16, encode_rdata(&r.rdata).unwrap_or_default()))
+        .collect();
+    data.sort();
+    data
+}
+
+/// Legacy resolvers do not know the cache flush bit and would cache the records too long.
+fn legacy_records(records: Vec<MDNSAnswer>) -> Vec<MDNSAnswer> {
+    records.into_iter().map(|r| MDNSAnswer {
+        cache_flush: false,
+        ttl_seconds: r.ttl_seconds.min(LEGACY_UNICAST_TTL),
+        ..r
+    }).collect()
+}
+
+fn header(query_identifier: u16, flags: u16) -> MDNSMessageHeader {
+    MDNSMessageHeader {
+        query_identifier,
+        flags,
+        question_count: 0,
+        answer_count: 0,
+        authority_count: 0,
+        additional_count: 0,
+    }
+}
+
+fn with_counts(mut message: MDNSMessage) -> MDNSMessage {
+    message.header.question_count = message.questions.len() as u16;
+    message.header.answer_count = message.answers.len() as u16;
+    message.header.authority_count = message.authorities.len() as u16;
+    message.header.additional_count = message.additionals.len() as u16;
+    message
+}
+
+fn response(query_identifier: u16, questions: Vec<MDNSQuestion>, answers: Vec<MDNSAnswer>, additionals: Vec<MDNSAnswer>) -> MDNSMessage {
+    with_counts(MDNSMessage {
+        header: header(query_identifier, RESPONSE_FLAGS),
+        questions,
+        answers,
+        authorities: vec![],
+        additionals,
+    })
+}
+
+enum ResponderCommand {
+    Publish(PublishedRecords),
+    Stop,
+}
+
+/// Publishes records on an interface from a background thread until it is stopped or dropped,
+/// which sends goodbye packets for them.
+pub struct Responder {
+    commands: Sender<ResponderCommand>,
+    status: Arc<Mutex<Vec<PublishedName>>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Responder {
+    /// Joins the multicast group of the interface and starts probing for the records.
+    pub fn start(config: &QuerySenderConfig, records: PublishedRecords) -> io::Result<Self> {
+        let sockets = open_group_sockets(config, RECEIVE_TIMEOUT)?;
+        let (commands, receiver) = mpsc::channel();
+        let status = Arc::new(Mutex::new(vec![]));
+        let thread_status = status.clone();
+        let port = config.group.port;
+        let thread = thread::Builder::new()
+            .name("mDNS responder".to_string())
+            .spawn(move || run(sockets, port, records, receiver, thread_status))?;
+        Ok(Self {
+            commands,
+            status,
+            thread: Some(thread),
+        })
+    }
+
+    /// Replaces the published records.
+    pub fn publish(&self, records: PublishedRecords) {
+        let _ = self.commands.send(ResponderCommand::Publish(records));
+    }
+
+    /// State of every published host and service.
+    pub fn status(&self) -> Vec<PublishedName> {
+        self.status.lock().unwrap().clone()
+    }
+
+    /// Sends goodbye packets for the published records and stops answering.
+    pub fn stop(&mut self) {
+        let _ = self.commands.send(ResponderCommand::Stop);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Responder {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn run(sockets: Vec<(UdpSocket, SocketAddr)>, port: u16, records: PublishedRecords, commands: Receiver<ResponderCommand>, status: Arc<Mutex<Vec<PublishedName>>>) {
+    let mut core = ResponderCore::new(port);
+    send(&sockets, core.publish(records, Instant::now()));
+    let mut buffer = vec![0u8; RECEIVE_BUFFER_LENGTH];
+    loop {
+        match commands.try_recv() {
+            Ok(ResponderCommand::Publish(records)) => send(&sockets, core.publish(records, Instant::now())),
+            Ok(ResponderCommand::Stop) | Err(TryRecvError::Disconnected) => {
+                send(&sockets, core.publish(PublishedRecords::default(), Instant::now()));
+                break;
+            }
+            Err(TryRecvError::Empty) => {}
+        }
+        for (socket, _) in sockets.iter() {
+            match socket.recv_from(&mut buffer) {
+                Ok((length, source)) => match parse_mdns_message(&buffer[..length]) {
+                    Ok(message) => send(&sockets, core.handle(&message, source, Instant::now())),
+                    Err(e) => println!("Responder could not parse a message from {}: {}", source, e),
+                },
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut => {}
+                Err(e) => println!("Responder could not receive: {}", e),
+            }
+        }
+        send(&sockets, core.poll(Instant::now()));
+        *status.lock().unwrap() = core.status();
+    }
+}
+
+/// Opens a socket per address family which joins the group and sends from its port, as a responder does.
+/// Returned with the group address of each socket.
+pub fn open_group_sockets(config: &QuerySenderConfig, read_timeout: Duration) -> io::Result<Vec<(UdpSocket, SocketAddr)>> {
+    let mut sockets = vec![];
+    if let Some(interface) = config.ipv4_interface {
+        let (socket, group) = QuerySender::open_ipv4(&config.group, interface, config.group.port)?;
+        socket.join_multicast_v4(&config.group.ipv4, &interface)?;
+        sockets.push((socket, group));
+    }
+    if let Some(interface_index) = config.ipv6_interface_index {
+        let (socket, group) = QuerySender::open_ipv6(&config.group, interface_index, config.group.port)?;
+        socket.join_multicast_v6(&config.group.ipv6, interface_index)?;
+        sockets.push((socket, group));
+    }
+    if sockets.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::AddrNotAvailable, "The interface has no address to join the mDNS group on."));
+    }
+    for (socket, _) in sockets.iter() {
+        socket.set_read_timeout(Some(read_timeout))?;
+    }
+    Ok(sockets)
+}
+
+/// Multicast messages go out on every socket, unicast ones on the socket of the destination's address family.
+fn send(sockets: &[(UdpSocket, SocketAddr)], outgoing: Vec<OutgoingMessage>) {
+    for message in outgoing {
+        let payload = match encode_mdns_message(&message.message) {
+            Ok(p) => p,
+            Err(e) => {
+                println!("Responder could not encode a message: {}", e);
+                continue;
+            }
+        };
+        for (socket, group) in sockets.iter() {
+            let destination = match message.destination {
+                Destination::Multicast => *group,
+                Destination::Unicast(address) if address.is_ipv4() == group.is_ipv4() => address,
+                Destination::Unicast(_) => continue,
+            };
+            if let Err(e) = socket.send_to(&payload, destination) {
+                println!("Responder could not send to {}: {}", destination, e);
+            }
+        }
+    }
+}
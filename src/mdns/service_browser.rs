@@ -0,0 +1,326 @@
+use crate::mdns::mdns_message::MDNSMessage;
+use crate::mdns::query_sender::Query;
+use crate::mdns::types::{MDNSAnswer, MDNSQueryClass, MDNSQuestion, MDNSRData, MDNSRecordType};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::net::IpAddr;
+use time::{Duration, OffsetDateTime};
+
+/// Name queried to enumerate the service types on the network (RFC 6763 - 9).
+pub const SERVICE_TYPE_ENUMERATION: &str = "_services._dns-sd._udp.local";
+
+const FIRST_BROWSE_INTERVAL: Duration = Duration::seconds(1);
+/// RFC 6762 - 5.2 lets the interval grow to an hour. It is capped at a minute, so instances which missed
+/// the first queries and do not announce themselves still show up soon.
+const MAX_BROWSE_INTERVAL: Duration = Duration::seconds(60);
+/// Resolve queries for an instance with missing records are repeated this often, at most `RESOLVE_ATTEMPTS` times.
+const RESOLVE_INTERVAL: Duration = Duration::seconds(1);
+const RESOLVE_ATTEMPTS: u32 = 3;
+/// Number of browse events kept for display.
+const MAX_EVENTS: usize = 1000;
+
+/// A service instance found while browsing, with what is known about it so far.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServiceInstance {
+    /// Full name of the instance, e.g. `Kitchen._googlecast._tcp.local`.
+    pub name: String,
+    /// Target host and port from the SRV record.
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    /// TXT data, one line per string.
+    pub txt: Option<String>,
+    pub addresses: Vec<IpAddr>,
+    /// Address the PTR record was last received from.
+    pub responder: IpAddr,
+    pub ttl_seconds: u32,
+    /// When the PTR record expires unless it is refreshed.
+    pub expires: OffsetDateTime,
+}
+
+impl ServiceInstance {
+    /// Whether the SRV, TXT and address records of the instance are known.
+    pub fn is_resolved(&self) -> bool {
+        self.host.is_some() && self.txt.is_some() && !self.addresses.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrowseEventKind {
+    Added,
+    Resolved,
+    /// The responder sent a goodbye (TTL 0) for the instance.
+    Removed,
+    /// The PTR record was not refreshed before its TTL ran out.
+    Expired,
+}
+
+impl Display for BrowseEventKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let kind = match self {
+            BrowseEventKind::Added => "Added",
+            BrowseEventKind::Resolved => "Resolved",
+            BrowseEventKind::Removed => "Removed",
+            BrowseEventKind::Expired => "Expired",
+        };
+        write!(f, "{}", kind)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrowseEvent {
+    pub time: OffsetDateTime,
+    pub kind: BrowseEventKind,
+    pub instance: String,
+}
+
+struct ResolveState {
+    attempts: u32,
+    last_attempt: OffsetDateTime,
+}
+
+/// Browses one service type: tracks the instances announced in captured responses, resolves their SRV, TXT
+/// and address records, and decides which queries are due (RFC 6763 - 4 and 5).
+/// The browser does not send anything itself, the caller sends the queries returned by `due_queries`.
+pub struct ServiceBrowser {
+    service_type: String,
+    /// Instances by lower case name.
+    instances: BTreeMap<String, ServiceInstance>,
+    /// Address records by lower case host name, with their expiry.
+    hosts: HashMap<String, Vec<(IpAddr, OffsetDateTime)>>,
+    events: VecDeque<BrowseEvent>,
+    next_browse: OffsetDateTime,
+    browse_interval: Duration,
+    resolving: HashMap<String, ResolveState>,
+}
+
+impl ServiceBrowser {
+    /// Browses the service type, e.g. `_googlecast._tcp.local`. The first browse query is due right away.
+    pub fn new(service_type: &str, now: OffsetDateTime) -> Self {
+        Self {
+            service_type: service_type.trim().trim_end_matches('.').to_string(),
+            instances: BTreeMap::new(),
+            hosts: HashMap::new(),
+            events: VecDeque::new(),
+            next_browse: now,
+            browse_interval: FIRST_BROWSE_INTERVAL,
+            resolving: HashMap::new(),
+        }
+    }
+
+    pub fn service_type(&self) -> &str {
+        &self.service_type
+    }
+
+    pub fn instances(&self) -> impl Iterator<Item = &ServiceInstance> {
+        self.instances.values()
+    }
+
+    /// Changes of the instances, oldest first.
+    pub fn events(&self) -> &VecDeque<BrowseEvent> {
+        &self.events
+    }
+
+    /// The instance name without the service type, e.g. `Kitchen`.
+    pub fn instance_label<'a>(&self, instance: &'a ServiceInstance) -> &'a str {
+        let label_length = instance.name.len().saturating_sub(self.service_type.len() + 1);
+        instance.name.get(..label_length).filter(|l| !l.is_empty()).unwrap_or(&instance.name)
+    }
+
+    /// Takes the records of a captured response into account. Answers and additional records are used alike.
+    pub fn observe(&mut self, message: &MDNSMessage, received: OffsetDateTime, source_ip: IpAddr) {
+        if !message.header.is_response() {
+            return;
+        }
+        let records: Vec<&MDNSAnswer> = message.answers.iter().chain(message.additionals.iter()).collect();
+        // PTR records first, so SRV and TXT records of a new instance in the same message are not missed.
+        for record in records.iter().filter(|r| r.answer_type == MDNSRecordType::PTR) {
+            self.observe_ptr(record, received, source_ip);
+        }
+        for record in records.iter().filter(|r| r.answer_type != MDNSRecordType::PTR) {
+            self.observe_record(record, received);
+        }
+        self.update_addresses(received);
+    }
+
+    fn observe_ptr(&mut self, record: &MDNSAnswer, received: OffsetDateTime, source_ip: IpAddr) {
+        let instance_name = match &record.rdata {
+            MDNSRData::PTR { domain_name } if record.name.eq_ignore_ascii_case(&self.service_type) => domain_name,
+            _ => return,
+        };
+        let key = instance_name.to_ascii_lowercase();
+        if record.ttl_seconds == 0 {
+            if let Some(instance) = self.instances.remove(&key) {
+                self.resolving.remove(&key);
+                self.push_event(received, BrowseEventKind::Removed, instance.name);
+            }
+            return;
+        }
+        let expires = received + Duration::seconds(record.ttl_seconds as i64);
+        match self.instances.get_mut(&key) {
+            Some(instance) => {
+                instance.responder = source_ip;
+                instance.ttl_seconds = record.ttl_seconds;
+                instance.expires = expires;
+            }
+            None => {
+                self.instances.insert(key, ServiceInstance {
+                    name: instance_name.clone(),
+                    host: None,
+                    port: None,
+                    txt: None,
+                    addresses: vec![],
+                    responder: source_ip,
+                    ttl_seconds: record.ttl_seconds,
+                    expires,
+                });
+                self.push_event(received, BrowseEventKind::Added, instance_name.clone());
+            }
+        }
+    }
+
+    fn observe_record(&mut self, record: &MDNSAnswer, received: OffsetDateTime) {
+        let removed = record.ttl_seconds == 0;
+        match &record.rdata {
+            MDNSRData::SRV { port, target_domain_name, .. } => {
+                if let Some(instance) = self.instances.get_mut(&record.name.to_ascii_lowercase()) {
+                    instance.host = if removed { None } else { Some(target_domain_name.clone()) };
+                    instance.port = if removed { None } else { Some(*port) };
+                }
+            }
+            MDNSRData::TXT { text } => {
+                if let Some(instance) = self.instances.get_mut(&record.name.to_ascii_lowercase()) {
+                    instance.txt = if removed { None } else { Some(text.clone()) };
+                }
+            }
+            MDNSRData::A { ipv4_address } => self.observe_address(&record.name, IpAddr::V4(*ipv4_address), record.ttl_seconds, received),
+            MDNSRData::AAAA { ipv6_addr } => self.observe_address(&record.name, IpAddr::V6(*ipv6_addr), record.ttl_seconds, received),
+            _ => {}
+        }
+    }
+
+    fn observe_address(&mut self, host: &str, address: IpAddr, ttl_seconds: u32, received: OffsetDateTime) {
+        let addresses = self.hosts.entry(host.to_ascii_lowercase()).or_default();
+        addresses.retain(|(a, _)| *a != address);
+        if ttl_seconds > 0 {
+            addresses.push((address, received + Duration::seconds(ttl_seconds as i64)));
+        }
+    }
+
+    /// Copies the host addresses into the instances and reports instances which became resolved.
+    fn update_addresses(&mut self, now: OffsetDateTime) {
+        let mut resolved = vec![];
+        for instance in self.instances.values_mut() {
+            let was_resolved = instance.is_resolved();
+            instance.addresses = instance.host.as_ref()
+                .and_then(|h| self.hosts.get(&h.to_ascii_lowercase()))
+                .map(|addresses| addresses.iter().map(|(a, _)| *a).collect())
+                .unwrap_or_default();
+            if !was_resolved && instance.is_resolved() {
+                resolved.push(instance.name.clone());
+            }
+        }
+        for name in resolved {
+            self.push_event(now, BrowseEventKind::Resolved, name);
+        }
+    }
+
+    /// Drops instances and addresses whose records were not refreshed in time.
+    pub fn expire(&mut self, now: OffsetDateTime) {
+        let expired: Vec<String> = self.instances.iter().filter(|(_, i)| i.expires <= now).map(|(k, _)| k.clone()).collect();
+        for key in expired {
+            if let Some(instance) = self.instances.remove(&key) {
+                self.resolving.remove(&key);
+                self.push_event(now, BrowseEventKind::Expired, instance.name);
+            }
+        }
+        for addresses in self.hosts.values_mut() {
+            addresses.retain(|(_, expires)| *expires > now);
+        }
+        self.hosts.retain(|_, addresses| !addresses.is_empty());
+        self.update_addresses(now);
+    }
+
+    /// The queries to send now: the browse query when its interval has passed, with the known instances as known
+    /// answers, and resolve queries for instances with missing records.
+    pub fn due_queries(&mut self, now: OffsetDateTime) -> Vec<Query> {
+        let mut queries = vec![];
+        if now >= self.next_browse {
+            let mut query = Query::new(&self.service_type, MDNSRecordType::PTR, false);
+            query.known_answers = self.known_answers(now);
+            queries.push(query);
+            self.next_browse = now + self.browse_interval;
+            self.browse_interval = (self.browse_interval * 2i32).min(MAX_BROWSE_INTERVAL);
+        }
+        for (key, instance) in self.instances.iter() {
+            if instance.is_resolved() {
+                continue;
+            }
+            let state = self.resolving.entry(key.clone()).or_insert(ResolveState { attempts: 0, last_attempt: now - RESOLVE_INTERVAL });
+            if state.attempts >= RESOLVE_ATTEMPTS || now < state.last_attempt + RESOLVE_INTERVAL {
+                continue;
+            }
+            state.attempts += 1;
+            state.last_attempt = now;
+            let mut questions = vec![];
+            if instance.host.is_none() {
+                questions.push(question(&instance.name, MDNSRecordType::SRV));
+            }
+            if instance.txt.is_none() {
+                questions.push(question(&instance.name, MDNSRecordType::TXT));
+            }
+            if let Some(host) = &instance.host
+                && instance.addresses.is_empty() {
+                questions.push(question(host, MDNSRecordType::A));
+                questions.push(question(host, MDNSRecordType::AAAA));
+            }
+            queries.push(Query { questions, known_answers: vec![] });
+        }
+        queries
+    }
+
+    /// PTR records of the instances which still have more than half of their TTL left (RFC 6762 - 7.1).
+    fn known_answers(&self, now: OffsetDateTime) -> Vec<MDNSAnswer> {
+        self.instances.values()
+            .filter(|i| (i.expires - now).whole_seconds() > i.ttl_seconds as i64 / 2)
+            .map(|i| MDNSAnswer {
+                name: self.service_type.clone(),
+                answer_type: MDNSRecordType::PTR,
+                answer_class: MDNSQueryClass::IN,
+                cache_flush: false,
+                ttl_seconds: (i.expires - now).whole_seconds() as u32,
+                rd_length: 0,
+                rdata: MDNSRData::PTR { domain_name: i.name.clone() },
+            })
+            .collect()
+    }
+
+    fn push_event(&mut self, time: OffsetDateTime, kind: BrowseEventKind, instance: String) {
+        if self.events.len() >= MAX_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(BrowseEvent { time, kind, instance });
+    }
+}
+
+/// Service types listed in a response to the service type enumeration query.
+pub fn enumerated_service_types(message: &MDNSMessage) -> Vec<String> {
+    if !message.header.is_response() {
+        return vec![];
+    }
+    message.answers.iter()
+        .filter(|a| a.name.eq_ignore_ascii_case(SERVICE_TYPE_ENUMERATION))
+        .filter_map(|a| match &a.rdata {
+            MDNSRData::PTR { domain_name } => Some(domain_name.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn question(name: &str, record_type: MDNSRecordType) -> MDNSQuestion {
+    MDNSQuestion {
+        name: name.to_string(),
+        question_type: record_type,
+        question_class: MDNSQueryClass::IN,
+        unicast_response: false,
+    }
+}
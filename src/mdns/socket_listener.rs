@@ -0,0 +1,301 @@
+use pnet::datalink::{self, NetworkInterface};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6, UdpSocket};
+use std::time::Duration;
+
+pub const MDNS_PORT: u16 = 5353;
+pub const MDNS_IPV4_GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+pub const MDNS_IPV6_GROUP: Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 0xfb);
+pub const LLMNR_PORT: u16 = 5355;
+pub const LLMNR_IPV4_GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 252);
+pub const LLMNR_IPV6_GROUP: Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 1, 3);
+
+/// Largest datagram we expect; mDNS messages are limited to 9000 bytes by RFC 6762 - 17.
+pub(crate) const RECEIVE_BUFFER_LENGTH: usize = 9000;
+
+/// Multicast groups of a protocol and the port its sockets bind to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MulticastGroup {
+    pub port: u16,
+    pub ipv4: Ipv4Addr,
+    pub ipv6: Ipv6Addr,
+}
+
+impl MulticastGroup {
+    pub const MDNS: MulticastGroup = MulticastGroup { port: MDNS_PORT, ipv4: MDNS_IPV4_GROUP, ipv6: MDNS_IPV6_GROUP };
+    pub const LLMNR: MulticastGroup = MulticastGroup { port: LLMNR_PORT, ipv4: LLMNR_IPV4_GROUP, ipv6: LLMNR_IPV6_GROUP };
+}
+
+/// Describes which groups the listener joins and where.
+#[derive(Debug, Clone)]
+pub struct SocketListenerConfig {
+    pub groups: Vec<MulticastGroup>,
+    /// Address of the interface to join the IPv4 group on. No IPv4 socket is opened when missing.
+    pub ipv4_interface: Option<Ipv4Addr>,
+    /// Index of the interface to join the IPv6 group on. No IPv6 socket is opened when missing.
+    pub ipv6_interface_index: Option<u32>,
+    pub read_timeout: Duration,
+}
+
+impl SocketListenerConfig {
+    pub fn for_interface(interface: &NetworkInterface) -> Self {
+        let ipv4_interface = interface.ips.iter().find_map(|network| match network.ip() {
+            IpAddr::V4(ip) => Some(ip),
+            IpAddr::V6(_) => None,
+        });
+        let has_ipv6 = interface.ips.iter().any(|network| network.is_ipv6());
+        Self {
+            groups: vec![MulticastGroup::MDNS, MulticastGroup::LLMNR],
+            ipv4_interface,
+            ipv6_interface_index: if has_ipv6 { Some(interface.index) } else { None },
+            read_timeout: Duration::from_millis(100),
+        }
+    }
+}
+
+/// A datagram received on one of the listener sockets.
+pub struct ReceivedDatagram {
+    pub payload: Vec<u8>,
+    pub source: SocketAddr,
+    /// Destination address recovered from IP_PKTINFO. Falls back to the group address where the platform does not report it.
+    pub destination: SocketAddr,
+    /// IP TTL or IPv6 hop limit, where the platform reports it.
+    pub ttl: Option<u8>,
+    /// Index of the interface the datagram arrived on, where the platform reports it.
+    pub interface_index: Option<u32>,
+}
+
+/// Whether the destination address, TTL and interface of received datagrams are read from the socket.
+/// Elsewhere than on Linux the destination is taken to be the group and the TTL is unknown.
+pub const PACKET_INFO_AVAILABLE: bool = cfg!(target_os = "linux");
+
+/// A socket of the listener, the group it joined and the interface it joined it on.
+struct ListenerSocket {
+    socket: UdpSocket,
+    group: SocketAddr,
+    interface_index: Option<u32>,
+}
+
+/// Listens for mDNS traffic like an ordinary mDNS stack does, by joining the multicast groups on a UDP socket.
+/// Unlike raw datalink capture this needs no special privileges, but it only sees traffic delivered to the host.
+pub struct SocketListener {
+    sockets: Vec<ListenerSocket>,
+    next_socket: usize,
+    buffer: Vec<u8>,
+}
+
+impl SocketListener {
+    /// Opens a socket per group and address family. Groups whose port cannot be bound are skipped,
+    /// opening only fails when no socket could be opened at all.
+    pub fn open(config: &SocketListenerConfig) -> io::Result<Self> {
+        let family_count = config.ipv4_interface.is_some() as u32 + config.ipv6_interface_index.is_some() as u32;
+        if family_count == 0 || config.groups.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::AddrNotAvailable, "The interface has no address to join the mDNS group on."));
+        }
+        // The sockets are polled in turns, together they wait at most the configured timeout.
+        let read_timeout = config.read_timeout / (family_count * config.groups.len() as u32);
+
+        let mut sockets = vec![];
+        let mut first_error = None;
+        for group in config.groups.iter() {
+            let mut results = vec![];
+            if let Some(interface) = config.ipv4_interface {
+                results.push(Self::open_ipv4(group, interface, read_timeout));
+            }
+            if let Some(interface_index) = config.ipv6_interface_index {
+                results.push(Self::open_ipv6(group, interface_index, read_timeout));
+            }
+            for result in results {
+                match result {
+                    Ok(socket) => sockets.push(socket),
+                    Err(e) => {
+                        println!("Could not listen on port {}: {}", group.port, e);
+                        first_error.get_or_insert(e);
+                    }
+                }
+            }
+        }
+        if sockets.is_empty()
+            && let Some(e) = first_error {
+            return Err(e);
+        }
+
+        Ok(SocketListener {
+            sockets,
+            next_socket: 0,
+            buffer: vec![0u8; RECEIVE_BUFFER_LENGTH],
+        })
+    }
+
+    fn open_ipv4(group: &MulticastGroup, interface: Ipv4Addr, read_timeout: Duration) -> io::Result<ListenerSocket> {
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+        Self::set_reuse(&socket)?;
+        socket.bind(&SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, group.port)).into())?;
+        socket.join_multicast_v4(&group.ipv4, &interface)?;
+        socket.set_read_timeout(Some(read_timeout))?;
+        enable_packet_info(&socket, false)?;
+        // The socket is bound to the port on all interfaces, the index tells apart datagrams which arrived elsewhere.
+        let interface_index = datalink::interfaces().into_iter()
+            .find(|i| i.ips.iter().any(|network| network.ip() == IpAddr::V4(interface)))
+            .map(|i| i.index);
+        Ok(ListenerSocket {
+            socket: socket.into(),
+            group: SocketAddr::V4(SocketAddrV4::new(group.ipv4, group.port)),
+            interface_index,
+        })
+    }
+
+    fn open_ipv6(group: &MulticastGroup, interface_index: u32, read_timeout: Duration) -> io::Result<ListenerSocket> {
+        let socket = Socket::new(Domain::IPV6, Type::DGRAM, Some(Protocol::UDP))?;
+        socket.set_only_v6(true)?;
+        Self::set_reuse(&socket)?;
+        socket.bind(&SocketAddr::V6(SocketAddrV6::new(Ipv6Addr::UNSPECIFIED, group.port, 0, 0)).into())?;
+        socket.join_multicast_v6(&group.ipv6, interface_index)?;
+        socket.set_read_timeout(Some(read_timeout))?;
+        enable_packet_info(&socket, true)?;
+        Ok(ListenerSocket {
+            socket: socket.into(),
+            group: SocketAddr::V6(SocketAddrV6::new(group.ipv6, group.port, 0, interface_index)),
+            interface_index: Some(interface_index),
+        })
+    }
+
+    /// Returns the next datagram or `None` when nothing arrived within the read timeout.
+    /// Datagrams which arrived on another interface than the groups were joined on are dropped.
+    pub fn receive(&mut self) -> io::Result<Option<ReceivedDatagram>> {
+        for _ in 0..self.sockets.len() {
+            let listener_socket = &self.sockets[self.next_socket];
+            self.next_socket = (self.next_socket + 1) % self.sockets.len();
+            match receive_datagram(&listener_socket.socket, listener_socket.group, &mut self.buffer) {
+                Ok(datagram) if Self::arrived_elsewhere(listener_socket.interface_index, datagram.interface_index) => continue,
+                Ok(datagram) => return Ok(Some(datagram)),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(None)
+    }
+
+    fn arrived_elsewhere(joined_on: Option<u32>, arrived_on: Option<u32>) -> bool {
+        matches!((joined_on, arrived_on), (Some(joined), Some(arrived)) if joined != arrived)
+    }
+
+    /// Other mDNS and LLMNR stacks on the host (Bonjour, Avahi, systemd-resolved) already own ports 5353 and 5355.
+    pub fn set_reuse(socket: &Socket) -> io::Result<()> {
+        socket.set_reuse_address(true)?;
+        #[cfg(unix)]
+        socket.set_reuse_port(true)?;
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn enable_packet_info(socket: &Socket, ipv6: bool) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+    let options = if ipv6 {
+        [(libc::IPPROTO_IPV6, libc::IPV6_RECVPKTINFO), (libc::IPPROTO_IPV6, libc::IPV6_RECVHOPLIMIT)]
+    } else {
+        [(libc::IPPROTO_IP, libc::IP_PKTINFO), (libc::IPPROTO_IP, libc::IP_RECVTTL)]
+    };
+    for (level, name) in options {
+        let enabled: libc::c_int = 1;
+        let result = unsafe {
+            libc::setsockopt(
+                socket.as_raw_fd(),
+                level,
+                name,
+                &enabled as *const libc::c_int as *const libc::c_void,
+                size_of::<libc::c_int>() as libc::socklen_t,
+            )
+        };
+        if result != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn enable_packet_info(_socket: &Socket, _ipv6: bool) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn receive_datagram(socket: &UdpSocket, group: SocketAddr, buffer: &mut [u8]) -> io::Result<ReceivedDatagram> {
+    use std::os::fd::AsRawFd;
+    use std::{mem, ptr};
+
+    let mut source: libc::sockaddr_storage = unsafe { mem::zeroed() };
+    let mut iov = libc::iovec {
+        iov_base: buffer.as_mut_ptr() as *mut libc::c_void,
+        iov_len: buffer.len(),
+    };
+    // u64 elements keep the control buffer aligned for cmsghdr.
+    let mut control = [0u64; 32];
+    let mut message: libc::msghdr = unsafe { mem::zeroed() };
+    message.msg_name = &mut source as *mut libc::sockaddr_storage as *mut libc::c_void;
+    message.msg_namelen = size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+    message.msg_iov = &mut iov;
+    message.msg_iovlen = 1;
+    message.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+    message.msg_controllen = size_of_val(&control);
+
+    let length = unsafe { libc::recvmsg(socket.as_raw_fd(), &mut message, 0) };
+    if length < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut destination = group;
+    let mut ttl = None;
+    let mut interface_index = None;
+    unsafe {
+        let mut header = libc::CMSG_FIRSTHDR(&message);
+        while !header.is_null() {
+            let data = libc::CMSG_DATA(header);
+            match ((*header).cmsg_level, (*header).cmsg_type) {
+                (libc::IPPROTO_IP, libc::IP_PKTINFO) => {
+                    let info = ptr::read_unaligned(data as *const libc::in_pktinfo);
+                    destination.set_ip(IpAddr::V4(Ipv4Addr::from(u32::from_be(info.ipi_addr.s_addr))));
+                    interface_index = Some(info.ipi_ifindex as u32);
+                }
+                (libc::IPPROTO_IP, libc::IP_TTL) => {
+                    ttl = Some(ptr::read_unaligned(data as *const libc::c_int) as u8);
+                }
+                (libc::IPPROTO_IPV6, libc::IPV6_PKTINFO) => {
+                    let info = ptr::read_unaligned(data as *const libc::in6_pktinfo);
+                    destination.set_ip(IpAddr::V6(Ipv6Addr::from(info.ipi6_addr.s6_addr)));
+                    interface_index = Some(info.ipi6_ifindex);
+                }
+                (libc::IPPROTO_IPV6, libc::IPV6_HOPLIMIT) => {
+                    ttl = Some(ptr::read_unaligned(data as *const libc::c_int) as u8);
+                }
+                _ => {}
+            }
+            header = libc::CMSG_NXTHDR(&message, header);
+        }
+    }
+
+    let source = unsafe { socket2::SockAddr::new(source, message.msg_namelen) }
+        .as_socket()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Unexpected source address family."))?;
+    Ok(ReceivedDatagram {
+        payload: buffer[..length as usize].to_vec(),
+        source,
+        destination,
+        ttl,
+        interface_index,
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn receive_datagram(socket: &UdpSocket, group: SocketAddr, buffer: &mut [u8]) -> io::Result<ReceivedDatagram> {
+    let (length, source) = socket.recv_from(buffer)?;
+    Ok(ReceivedDatagram {
+        payload: buffer[..length].to_vec(),
+        source,
+        destination: group,
+        ttl: None,
+        interface_index: None,
+    })
+}
@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+/// Streams beyond this are dropped, least recently used first, so half-seen connections cannot pile up.
+const MAX_STREAMS: usize = 1024;
+
+/// Length of the prefix in front of every DNS message sent over TCP.
+const LENGTH_PREFIX: usize = 2;
+
+/// One direction of a TCP connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StreamKey {
+    pub source: SocketAddr,
+    pub destination: SocketAddr,
+}
+
+/// A TCP segment as far as reassembly is concerned.
+#[derive(Debug, Clone, Copy)]
+pub struct TcpSegment<'a> {
+    pub sequence: u32,
+    pub syn: bool,
+    /// FIN or RST, after which the stream is forgotten.
+    pub closes: bool,
+    pub payload: &'a [u8],
+}
+
+struct Stream {
+    /// Sequence number of the next expected byte.
+    next_sequence: u32,
+    /// Set when the start of the next message is unknown, because the stream was first seen without its SYN or
+    /// a segment went missing. Data is dropped until the next SYN.
+    desynced: bool,
+    buffer: Vec<u8>,
+    last_used: u64,
+}
+
+impl Stream {
+    fn take_messages(&mut self) -> Vec<Vec<u8>> {
+        let mut messages = vec![];
+        while self.buffer.len() >= LENGTH_PREFIX {
+            let length = u16::from_be_bytes([self.buffer[0], self.buffer[1]]) as usize;
+            if self.buffer.len() < LENGTH_PREFIX + length {
+                break;
+            }
+            messages.push(self.buffer[LENGTH_PREFIX..LENGTH_PREFIX + length].to_vec());
+            self.buffer.drain(..LENGTH_PREFIX + length);
+        }
+        messages
+    }
+}
+
+/// Reassembles DNS messages sent over TCP, where every message is prefixed with its length (RFC 1035 - 4.2.2).
+/// Only in-order data is used and retransmitted bytes are skipped. A stream first seen mid-connection, or one
+/// where a segment went missing, is not reassembled until the connection is set up again with a SYN.
+#[derive(Default)]
+pub struct TcpReassembler {
+    streams: HashMap<StreamKey, Stream>,
+    segments: u64,
+}
+
+impl TcpReassembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a segment and returns the DNS messages it completed, without their length prefix.
+    pub fn push(&mut self, key: StreamKey, segment: TcpSegment) -> Vec<Vec<u8>> {
+        if segment.payload.is_empty() && !segment.syn {
+            if segment.closes {
+                self.streams.remove(&key);
+            }
+            return vec![];
+        }
+        self.segments += 1;
+        if !self.streams.contains_key(&key) && self.streams.len() >= MAX_STREAMS {
+            self.evict_least_recently_used();
+        }
+        let stream = self.streams.entry(key).or_insert(Stream {
+            next_sequence: 0,
+            desynced: true,
+            buffer: vec![],
+            last_used: 0,
+        });
+        stream.last_used = self.segments;
+
+        // The SYN occupies one sequence number in front of the data.
+        let sequence = if segment.syn { segment.sequence.wrapping_add(1) } else { segment.sequence };
+        if segment.syn {
+            stream.next_sequence = sequence;
+            stream.desynced = false;
+            stream.buffer.clear();
+        }
+        let offset = sequence.wrapping_sub(stream.next_sequence) as i32;
+        if offset > 0 {
+            stream.desynced = true;
+            stream.buffer.clear();
+        }
+
+        let mut messages = vec![];
+        if !stream.desynced {
+            let data = segment.payload.get(offset.unsigned_abs() as usize..).unwrap_or(&[]);
+            if !data.is_empty() {
+                stream.next_sequence = sequence.wrapping_add(segment.payload.len() as u32);
+            }
+            stream.buffer.extend_from_slice(data);
+            messages = stream.take_messages();
+        }
+        if segment.closes {
+            self.streams.remove(&key);
+        }
+        messages
+    }
+
+    fn evict_least_recently_used(&mut self) {
+        let oldest = self.streams.iter().min_by_key(|(_, s)| s.last_used).map(|(k, _)| *k);
+        if let Some(key) = oldest {
+            self.streams.remove(&key);
+        }
+    }
+}
@@ -0,0 +1,195 @@
+#[cfg(test)]
+pub mod capture_filter_tests {
+    use crate::mdns::capture::LinkType;
+    use crate::mdns::capture_filter::{BpfInstruction, CaptureFilter};
+    use crate::mdns::packet_builder::build_udp_packet;
+    use crate::mdns::packet_source::{LivePacketSource, PacketSource};
+    use pnet::packet::ethernet::EthernetPacket;
+    use pnet::packet::ipv4::Ipv4Packet;
+    use pnet::packet::udp::UdpPacket;
+    use pnet::packet::Packet;
+    use std::io;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+    use std::time::Duration;
+
+    const PAYLOAD: [u8; 12] = [0; 12];
+
+    const ETHERNET_IPV4_HEADER: [u8; 14] = [1, 0, 0x5e, 0, 0, 0xfb, 2, 0, 0, 0, 0, 1, 0x08, 0x00];
+    const ETHERNET_IPV6_HEADER: [u8; 14] = [0x33, 0x33, 0, 0, 0, 0xfb, 2, 0, 0, 0, 0, 1, 0x86, 0xdd];
+
+    /// Runs a program the way the kernel does for frames without ancillary VLAN data.
+    fn run(program: &[BpfInstruction], frame: &[u8]) -> u32 {
+        let load = |offset: u32, size: usize| -> Option<u32> {
+            let bytes = frame.get(offset as usize..offset as usize + size)?;
+            Some(bytes.iter().fold(0u32, |value, byte| value << 8 | *byte as u32))
+        };
+        let (mut a, mut x, mut pc) = (0u32, 0u32, 0usize);
+        loop {
+            let instruction = program[pc];
+            pc += 1;
+            let k = instruction.k;
+            match instruction.code {
+                // Ancillary data reads as zero, i.e. no VLAN tag was stripped by the driver.
+                0x20 if k >= 0xffff_f000 => a = 0,
+                0x20 => match load(k, 4) { Some(v) => a = v, None => return 0 },
+                0x28 => match load(k, 2) { Some(v) => a = v, None => return 0 },
+                0x30 => match load(k, 1) { Some(v) => a = v, None => return 0 },
+                0x48 => match load(x + k, 2) { Some(v) => a = v, None => return 0 },
+                0xb1 => match load(k, 1) { Some(v) => x = 4 * (v & 0xf), None => return 0 },
+                0x54 => a &= k,
+                0x05 => pc += k as usize,
+                0x15 => pc += if a == k { instruction.jt } else { instruction.jf } as usize,
+                0x45 => pc += if a & k != 0 { instruction.jt } else { instruction.jf } as usize,
+                0x06 => return k,
+                code => panic!("Unexpected instruction {:#x}", code),
+            }
+        }
+    }
+
+    fn accepts(expression: &str, link_type: LinkType, frame: &[u8]) -> bool {
+        let filter = expression.parse::<CaptureFilter>().unwrap();
+        run(&filter.compile(link_type).unwrap(), frame) > 0
+    }
+
+    fn ipv4_packet(source: Ipv4Addr, destination_port: u16) -> Vec<u8> {
+        let source = SocketAddr::new(IpAddr::V4(source), 50000);
+        let destination = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(224, 0, 0, 251)), destination_port);
+        build_udp_packet(source, destination, 255, &PAYLOAD).unwrap()
+    }
+
+    fn ipv6_packet(destination_port: u16) -> Vec<u8> {
+        let source = SocketAddr::new(IpAddr::V6(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 2)), 50000);
+        let destination = SocketAddr::new(IpAddr::V6(Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 1, 3)), destination_port);
+        build_udp_packet(source, destination, 255, &PAYLOAD).unwrap()
+    }
+
+    fn ethernet(header: &[u8], packet: &[u8]) -> Vec<u8> {
+        let mut frame = header.to_vec();
+        frame.extend_from_slice(packet);
+        frame
+    }
+
+    fn vlan_tagged(id: u16, packet: &[u8]) -> Vec<u8> {
+        let mut frame = ETHERNET_IPV4_HEADER[..12].to_vec();
+        frame.extend_from_slice(&[0x81, 0x00]);
+        frame.extend_from_slice(&id.to_be_bytes());
+        frame.extend_from_slice(&[0x08, 0x00]);
+        frame.extend_from_slice(packet);
+        frame
+    }
+
+    #[test]
+    fn compile__when_decoded_protocols__accepts_mdns_and_llmnr_only() {
+        let program = CaptureFilter::for_decoded_protocols(false).compile(LinkType::Ethernet).unwrap();
+        let source = Ipv4Addr::new(10, 0, 0, 1);
+
+        assert!(run(&program, &ethernet(&ETHERNET_IPV4_HEADER, &ipv4_packet(source, 5353))) > 0);
+        assert!(run(&program, &ethernet(&ETHERNET_IPV6_HEADER, &ipv6_packet(5355))) > 0);
+        assert_eq!(run(&program, &ethernet(&ETHERNET_IPV4_HEADER, &ipv4_packet(source, 53))), 0);
+    }
+
+    #[test]
+    fn compile__when_unicast_dns_decoded__accepts_port_53() {
+        let program = CaptureFilter::for_decoded_protocols(true).compile(LinkType::Ethernet).unwrap();
+
+        assert!(run(&program, &ethernet(&ETHERNET_IPV4_HEADER, &ipv4_packet(Ipv4Addr::new(10, 0, 0, 1), 53))) > 0);
+    }
+
+    #[test]
+    fn compile__when_host_clause__matches_source_or_destination() {
+        let frame = ethernet(&ETHERNET_IPV4_HEADER, &ipv4_packet(Ipv4Addr::new(10, 0, 0, 1), 5353));
+
+        assert!(accepts("udp port 5353 and host 10.0.0.1", LinkType::Ethernet, &frame));
+        assert!(accepts("host 224.0.0.251", LinkType::Ethernet, &frame));
+        assert!(!accepts("udp port 5353 and host 10.0.0.2", LinkType::Ethernet, &frame));
+    }
+
+    #[test]
+    fn compile__when_ipv6_host_clause__compares_whole_address() {
+        let frame = ethernet(&ETHERNET_IPV6_HEADER, &ipv6_packet(5355));
+
+        assert!(accepts("udp port 5355 and host ff02::1:3", LinkType::Ethernet, &frame));
+        assert!(!accepts("udp port 5355 and host ff02::1:4", LinkType::Ethernet, &frame));
+    }
+
+    #[test]
+    fn compile__when_vlan_tag_in_frame__checks_vlan_id() {
+        let frame = vlan_tagged(10, &ipv4_packet(Ipv4Addr::new(10, 0, 0, 1), 5353));
+
+        assert!(accepts("udp port 5353", LinkType::Ethernet, &frame));
+        assert!(accepts("udp port 5353 and vlan 10", LinkType::Ethernet, &frame));
+        assert!(!accepts("udp port 5353 and vlan 20", LinkType::Ethernet, &frame));
+    }
+
+    #[test]
+    fn compile__when_vlan_required_and_frame_untagged__rejects_frame() {
+        let frame = ethernet(&ETHERNET_IPV4_HEADER, &ipv4_packet(Ipv4Addr::new(10, 0, 0, 1), 5353));
+
+        assert!(!accepts("udp port 5353 and vlan", LinkType::Ethernet, &frame));
+    }
+
+    #[test]
+    fn compile__when_raw_ip__checks_ip_version() {
+        assert!(accepts("udp port 5355 and ip6", LinkType::RawIp, &ipv6_packet(5355)));
+        assert!(!accepts("udp port 5353 and ip6", LinkType::RawIp, &ipv4_packet(Ipv4Addr::new(10, 0, 0, 1), 5353)));
+    }
+
+    #[test]
+    fn compile__when_tcp_port_only__rejects_udp() {
+        let frame = ethernet(&ETHERNET_IPV4_HEADER, &ipv4_packet(Ipv4Addr::new(10, 0, 0, 1), 53));
+
+        assert!(!accepts("tcp port 53", LinkType::Ethernet, &frame));
+        assert!(accepts("tcp port 53 or udp port 53", LinkType::Ethernet, &frame));
+    }
+
+    #[test]
+    fn from_str__when_invalid__returns_error() {
+        for expression in ["", "udp 5353", "port 70000", "host local", "ip and ip6", "port 5353 or host 10.0.0.1", "vlan 5000", "udp port 5353 and"] {
+            assert!(expression.parse::<CaptureFilter>().is_err(), "{} was accepted", expression);
+        }
+    }
+
+    #[test]
+    fn to_string__when_parsed__returns_normalized_expression() {
+        let filter = "UDP port 5353,5355 or tcp port 53 and vlan 10 and host 10.0.0.1".parse::<CaptureFilter>().unwrap();
+
+        assert_eq!(filter.to_string(), "udp port 5353,5355 or tcp port 53 and host 10.0.0.1 and vlan 10");
+        assert_eq!(filter.to_string().parse::<CaptureFilter>().unwrap(), filter);
+    }
+
+    #[test]
+    fn next_frame__when_filter_attached_on_loopback__returns_matching_frames_only() {
+        let interface = match pnet::datalink::interfaces().into_iter().find(|i| i.is_loopback()) {
+            Some(i) => i,
+            None => return,
+        };
+        let filter = "udp port 53538".parse::<CaptureFilter>().unwrap();
+        let mut source = match LivePacketSource::open(&interface, Duration::from_millis(100), Some(&filter)) {
+            Ok(s) => s,
+            // Raw capture needs privileges the test environment may not have.
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => return,
+            Err(e) => panic!("{}", e),
+        };
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        sender.send_to(&PAYLOAD, "127.0.0.1:53539").unwrap();
+        sender.send_to(&PAYLOAD, "127.0.0.1:53538").unwrap();
+
+        let mut destination_ports = vec![];
+        for _ in 0..10 {
+            match source.next_frame() {
+                Ok(Some(frame)) => {
+                    let ethernet = EthernetPacket::new(frame.data).unwrap();
+                    let ip_packet = Ipv4Packet::new(ethernet.payload()).unwrap();
+                    destination_ports.push(UdpPacket::new(ip_packet.payload()).unwrap().get_destination());
+                }
+                Ok(None) => break,
+                Err(e) if e.kind() == io::ErrorKind::TimedOut || e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => panic!("{}", e),
+            }
+        }
+        assert!(source.filter_attached());
+        assert!(!destination_ports.is_empty());
+        assert!(destination_ports.iter().all(|p| *p == 53538));
+    }
+}
@@ -0,0 +1,239 @@
+#[cfg(test)]
+pub mod conflict_test_tests {
+    use crate::mdns::conflict_test::{run_conflict_test, target_addresses, ConflictMode, ConflictObserver, ConflictTestConfig};
+    use crate::mdns::mdns_message::MDNSMessage;
+    use crate::mdns::tests::loopback::loopback::{announced_responder, loopback_config};
+    use crate::mdns::types::{MDNSAnswer, MDNSMessageHeader, MDNSQueryClass, MDNSQuestion, MDNSRData, MDNSRecordType};
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+    use std::time::Duration;
+
+    const TARGET: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 20));
+    const TARGET_IPV6: IpAddr = IpAddr::V6(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 0x20));
+
+    /// Address the responder on loopback publishes for device.local.
+    const DEVICE_ADDRESS: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 20);
+
+    fn a_record(name: &str, last_octet: u8, cache_flush: bool) -> MDNSAnswer {
+        MDNSAnswer {
+            name: name.to_string(),
+            answer_type: MDNSRecordType::A,
+            answer_class: MDNSQueryClass::IN,
+            cache_flush,
+            ttl_seconds: 120,
+            rd_length: 0,
+            rdata: MDNSRData::A { ipv4_address: Ipv4Addr::new(192, 168, 1, last_octet) },
+        }
+    }
+
+    fn config(mode: ConflictMode) -> ConflictTestConfig {
+        ConflictTestConfig {
+            targets: vec![TARGET, TARGET_IPV6],
+            record: a_record("device.local", 20, true),
+            mode,
+            duration: Duration::from_secs(10),
+        }
+    }
+
+    fn message(flags: u16, questions: Vec<MDNSQuestion>, answers: Vec<MDNSAnswer>, authorities: Vec<MDNSAnswer>) -> MDNSMessage {
+        MDNSMessage {
+            header: MDNSMessageHeader {
+                query_identifier: 0,
+                flags,
+                question_count: questions.len() as u16,
+                answer_count: answers.len() as u16,
+                authority_count: authorities.len() as u16,
+                additional_count: 0,
+            },
+            questions,
+            answers,
+            authorities,
+            additionals: vec![],
+        }
+    }
+
+    fn probe(name: &str) -> MDNSMessage {
+        let question = MDNSQuestion { name: name.to_string(), question_type: MDNSRecordType::ANY, question_class: MDNSQueryClass::IN, unicast_response: true };
+        message(0, vec![question], vec![], vec![a_record(name, 20, false)])
+    }
+
+    fn announcement(name: &str, cache_flush: bool) -> MDNSMessage {
+        message(0x8400, vec![], vec![a_record(name, 20, cache_flush)], vec![])
+    }
+
+    fn ms(milliseconds: u64) -> Duration {
+        Duration::from_millis(milliseconds)
+    }
+
+    #[test]
+    fn injection__when_announcement_mode__claims_the_name_with_other_data() {
+        let mut observer = ConflictObserver::new(config(ConflictMode::Announcement));
+
+        let injection = observer.injection();
+
+        assert!(injection.header.is_response());
+        assert_eq!(injection.answers[0].name, "device.local");
+        assert_eq!(injection.answers[0].rdata.to_string(), "A (192.168.1.21)");
+        assert!(injection.answers[0].cache_flush);
+    }
+
+    #[test]
+    fn injection__when_probe_mode__sends_multicast_probe_with_record_in_authority_section() {
+        let mut observer = ConflictObserver::new(config(ConflictMode::Probe));
+
+        let injection = observer.injection();
+
+        assert!(!injection.header.is_response());
+        assert_eq!(injection.questions[0].question_type, MDNSRecordType::ANY);
+        assert!(!injection.questions[0].unicast_response);
+        assert_eq!(injection.authorities[0].rdata.to_string(), "A (192.168.1.21)");
+    }
+
+    #[test]
+    fn report__when_target_reprobes_renames_and_announces__passes() {
+        let mut observer = ConflictObserver::new(config(ConflictMode::Announcement));
+        observer.injection();
+
+        let defense = observer.observe(&probe("device.local"), TARGET, ms(100));
+        observer.observe(&probe("device-2.local"), TARGET, ms(400));
+        observer.observe(&probe("device-2.local"), TARGET, ms(650));
+        observer.observe(&probe("device-2.local"), TARGET, ms(900));
+        observer.observe(&announcement("device-2.local", true), TARGET, ms(1150));
+
+        let report = observer.report();
+        assert!(defense.unwrap().answers[0].rdata.to_string() == "A (192.168.1.21)");
+        assert!(observer.is_complete());
+        assert!(report.passed(), "{:?}", report.checks);
+        assert_eq!(report.new_name.as_deref(), Some("device-2.local"));
+        assert_eq!(report.checks.len(), 4);
+        assert!(report.timeline.iter().any(|t| t.contains("Target announced device-2.local")));
+    }
+
+    #[test]
+    fn report__when_target_keeps_announcing_its_name__fails_rename_checks() {
+        let mut observer = ConflictObserver::new(config(ConflictMode::Announcement));
+        observer.injection();
+
+        observer.observe(&announcement("device.local", true), TARGET, ms(50));
+
+        let report = observer.report();
+        assert!(!report.passed());
+        assert!(!report.checks[0].passed);
+        assert_eq!(report.checks[0].detail, "No probe was seen.");
+        assert!(!report.checks[1].passed);
+        assert!(!observer.is_complete());
+    }
+
+    #[test]
+    fn report__when_new_name_is_probed_too_fast__fails_probe_timing_check() {
+        let mut observer = ConflictObserver::new(config(ConflictMode::Announcement));
+        observer.injection();
+
+        for (at, name) in [(10, "device-2.local"), (110, "device-2.local"), (210, "device-2.local")] {
+            observer.observe(&probe(name), TARGET, ms(at));
+        }
+        observer.observe(&announcement("device-2.local", false), TARGET, ms(300));
+
+        let report = observer.report();
+        assert!(report.checks[0].passed);
+        assert!(report.checks[1].passed);
+        assert!(!report.checks[2].passed);
+        assert_eq!(report.checks[2].detail, "3 probes, the shortest gap was 100.0 ms.");
+        assert!(!report.checks[3].passed);
+    }
+
+    #[test]
+    fn observe__when_message_comes_from_another_host__ignores_it() {
+        let mut observer = ConflictObserver::new(config(ConflictMode::Announcement));
+        observer.injection();
+
+        let defense = observer.observe(&probe("device.local"), IpAddr::V4(Ipv4Addr::new(192, 168, 1, 99)), ms(100));
+
+        assert!(defense.is_none());
+        assert_eq!(observer.report().checks[0].detail, "No probe was seen.");
+    }
+
+    #[test]
+    fn observe__when_target_renames_over_its_other_address__evaluates_it() {
+        let mut observer = ConflictObserver::new(config(ConflictMode::Announcement));
+        observer.injection();
+
+        observer.observe(&probe("device-2.local"), TARGET_IPV6, ms(100));
+
+        assert_eq!(observer.report().new_name.as_deref(), Some("device-2.local"));
+    }
+
+    #[test]
+    fn target_addresses__when_response_gives_addresses__returns_source_and_announced_addresses() {
+        let mut response = announcement("device.local", true);
+        response.additionals.push(MDNSAnswer {
+            answer_type: MDNSRecordType::AAAA,
+            rdata: MDNSRData::AAAA { ipv6_addr: Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 0x20) },
+            ..a_record("device.local", 20, true)
+        });
+
+        let addresses = target_addresses(TARGET, &response);
+
+        assert_eq!(addresses, vec![TARGET, TARGET_IPV6]);
+    }
+
+    #[test]
+    fn report__when_target_defends_probe_and_keeps_name__passes() {
+        let mut observer = ConflictObserver::new(config(ConflictMode::Probe));
+        observer.injection();
+
+        observer.observe(&announcement("device.local", true), TARGET, ms(30));
+
+        let report = observer.report();
+        assert!(report.passed(), "{:?}", report.checks);
+        assert_eq!(report.checks[0].detail, "Answered after 30.0 ms.");
+        assert!(!observer.is_complete());
+    }
+
+    #[test]
+    fn report__when_target_gives_up_name_on_probe__fails() {
+        let mut observer = ConflictObserver::new(config(ConflictMode::Probe));
+        observer.injection();
+
+        observer.observe(&probe("device-2.local"), TARGET, ms(30));
+
+        let report = observer.report();
+        assert!(!report.checks[0].passed);
+        assert!(!report.checks[1].passed);
+        assert_eq!(report.checks[1].detail, "Moved the record to device-2.local.");
+    }
+
+    fn loopback_test(mode: ConflictMode) -> ConflictTestConfig {
+        ConflictTestConfig {
+            targets: vec![IpAddr::V4(Ipv4Addr::LOCALHOST)],
+            record: MDNSAnswer {
+                rdata: MDNSRData::A { ipv4_address: DEVICE_ADDRESS },
+                ..a_record("device.local", 20, true)
+            },
+            mode,
+            duration: Duration::from_secs(3),
+        }
+    }
+
+    #[test]
+    fn run_conflict_test__when_responder_on_loopback_gets_conflicting_announcement__reports_pass() {
+        let port = 53550;
+        let responder = announced_responder(port, "device.local", vec![IpAddr::V4(DEVICE_ADDRESS)]);
+
+        let report = run_conflict_test(&loopback_config(port), loopback_test(ConflictMode::Announcement)).unwrap();
+
+        assert!(report.passed(), "{:?}\n{:?}", report.checks, report.timeline);
+        assert_eq!(report.new_name.as_deref(), Some("device-2.local"));
+        assert_eq!(responder.status()[0].name, "device-2.local");
+    }
+
+    #[test]
+    fn run_conflict_test__when_responder_on_loopback_gets_conflicting_probe__reports_pass() {
+        let port = 53551;
+        let responder = announced_responder(port, "device.local", vec![IpAddr::V4(DEVICE_ADDRESS)]);
+
+        let report = run_conflict_test(&loopback_config(port), loopback_test(ConflictMode::Probe)).unwrap();
+
+        assert!(report.passed(), "{:?}\n{:?}", report.checks, report.timeline);
+        assert_eq!(responder.status()[0].name, "device.local");
+    }
+}
@@ -0,0 +1,150 @@
+#[cfg(test)]
+pub mod encoder_tests {
+    use crate::mdns::encoder::{encode_mdns_message, EncodeError};
+    use crate::mdns::mdns_message::MDNSMessage;
+    use crate::mdns::parser::parse_mdns_message;
+    use crate::mdns::tests::parser_tests::parser_tests::RESOLVE_SPOTIFY_MDNS_PAYLOAD;
+    use crate::mdns::types::{MDNSAnswer, MDNSMessageHeader, MDNSQueryClass, MDNSQuestion, MDNSRData, MDNSRecordType};
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    fn answer(name: &str, answer_type: MDNSRecordType, rdata: MDNSRData) -> MDNSAnswer {
+        MDNSAnswer {
+            name: name.to_string(),
+            answer_type,
+            answer_class: MDNSQueryClass::IN,
+            cache_flush: false,
+            ttl_seconds: 120,
+            rd_length: 0,
+            rdata,
+        }
+    }
+
+    fn message(questions: Vec<MDNSQuestion>, answers: Vec<MDNSAnswer>) -> MDNSMessage {
+        MDNSMessage {
+            header: MDNSMessageHeader {
+                query_identifier: 0,
+                flags: 0x8400,
+                question_count: 0,
+                answer_count: 0,
+                authority_count: 0,
+                additional_count: 0,
+            },
+            questions,
+            answers,
+            authorities: vec![],
+            additionals: vec![],
+        }
+    }
+
+    #[test]
+    fn encode_mdns_message__when_parsed_query_encoded__returns_original_bytes() {
+        let message = parse_mdns_message(&RESOLVE_SPOTIFY_MDNS_PAYLOAD).unwrap();
+
+        let bytes = encode_mdns_message(&message).unwrap();
+
+        assert_eq!(bytes, RESOLVE_SPOTIFY_MDNS_PAYLOAD);
+    }
+
+    #[test]
+    fn encode_mdns_message__when_parsed_back__returns_same_records() {
+        let question = MDNSQuestion {
+            name: "_googlecast._tcp.local".to_string(),
+            question_type: MDNSRecordType::PTR,
+            question_class: MDNSQueryClass::IN,
+            unicast_response: true,
+        };
+        let mut srv = answer("Kitchen._googlecast._tcp.local", MDNSRecordType::SRV, MDNSRData::SRV {
+            priority: 0,
+            weight: 5,
+            port: 8009,
+            target_domain_name: "kitchen.local".to_string(),
+        });
+        srv.cache_flush = true;
+        let answers = vec![
+            answer("_googlecast._tcp.local", MDNSRecordType::PTR, MDNSRData::PTR { domain_name: "Kitchen._googlecast._tcp.local".to_string() }),
+            srv,
+            answer("kitchen.local", MDNSRecordType::A, MDNSRData::A { ipv4_address: Ipv4Addr::new(192, 168, 1, 20) }),
+            answer("kitchen.local", MDNSRecordType::AAAA, MDNSRData::AAAA { ipv6_addr: Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 0x20) }),
+        ];
+
+        let bytes = encode_mdns_message(&message(vec![question], answers)).unwrap();
+        let parsed = parse_mdns_message(&bytes).unwrap();
+
+        assert_eq!(parsed.header.flags, 0x8400);
+        assert!(parsed.questions[0].unicast_response);
+        assert_eq!(parsed.questions[0].name, "_googlecast._tcp.local");
+        assert_eq!(parsed.answers.len(), 4);
+        assert!(!parsed.answers[0].cache_flush);
+        assert!(parsed.answers[1].cache_flush);
+        assert_eq!(parsed.answers[0].rdata.to_string(), "PTR (Kitchen._googlecast._tcp.local)");
+        assert_eq!(parsed.answers[1].rdata.to_string(), "SRV (0, 5, 8009, kitchen.local)");
+        // Priority, weight and port, the label "kitchen" and a pointer to "local".
+        assert_eq!(parsed.answers[1].rd_length, 6 + 8 + 2);
+        assert_eq!(parsed.answers[2].rdata.to_string(), "A (192.168.1.20)");
+        assert_eq!(parsed.answers[3].rdata.to_string(), "AAAA (fe80::20)");
+    }
+
+    #[test]
+    fn encode_mdns_message__when_names_repeat__compresses_them() {
+        let answers = vec![
+            answer("kitchen.local", MDNSRecordType::A, MDNSRData::A { ipv4_address: Ipv4Addr::new(192, 168, 1, 20) }),
+            answer("kitchen.local", MDNSRecordType::A, MDNSRData::A { ipv4_address: Ipv4Addr::new(192, 168, 1, 21) }),
+        ];
+
+        let bytes = encode_mdns_message(&message(vec![], answers)).unwrap();
+
+        // Header, the first name (15 bytes) and the second name as a pointer to offset 12, each followed by 14 bytes of record.
+        assert_eq!(bytes.len(), 12 + 15 + 14 + 2 + 14);
+        assert_eq!(&bytes[12 + 15 + 14..12 + 15 + 14 + 2], &[0xc0, 12]);
+    }
+
+    #[test]
+    fn encode_mdns_message__when_txt_has_lines__writes_one_string_per_line() {
+        let answers = vec![answer("Kitchen._googlecast._tcp.local", MDNSRecordType::TXT, MDNSRData::TXT { text: "id=1\nfn=Kitchen".to_string() })];
+
+        let bytes = encode_mdns_message(&message(vec![], answers)).unwrap();
+
+        assert!(bytes.ends_with(&[0, 16, 4, b'i', b'd', b'=', b'1', 10, b'f', b'n', b'=', b'K', b'i', b't', b'c', b'h', b'e', b'n']));
+    }
+
+    #[test]
+    fn encode_mdns_message__when_instance_has_escaped_dot__keeps_the_dot_in_the_label() {
+        let name = "Printer 2\\.1._ipp._tcp.local";
+        let answers = vec![answer("_ipp._tcp.local", MDNSRecordType::PTR, MDNSRData::PTR { domain_name: name.to_string() })];
+
+        let bytes = encode_mdns_message(&message(vec![], answers)).unwrap();
+        let parsed = parse_mdns_message(&bytes).unwrap();
+
+        assert!(bytes.ends_with(&[11, b'P', b'r', b'i', b'n', b't', b'e', b'r', b' ', b'2', b'.', b'1', 0xc0, 12]));
+        assert_eq!(parsed.answers[0].rdata.to_string(), format!("PTR ({})", name));
+    }
+
+    #[test]
+    fn encode_mdns_message__when_label_too_long__returns_error() {
+        let name = format!("{}.local", "a".repeat(64));
+        let question = MDNSQuestion {
+            name: name.clone(),
+            question_type: MDNSRecordType::A,
+            question_class: MDNSQueryClass::IN,
+            unicast_response: false,
+        };
+
+        let result = encode_mdns_message(&message(vec![question], vec![]));
+
+        assert_eq!(result, Err(EncodeError::LabelTooLong(name)));
+    }
+
+    #[test]
+    fn encode_mdns_message__when_name_has_empty_label__returns_error() {
+        let question = MDNSQuestion {
+            name: "kitchen..local".to_string(),
+            question_type: MDNSRecordType::A,
+            question_class: MDNSQueryClass::IN,
+            unicast_response: false,
+        };
+
+        let result = encode_mdns_message(&message(vec![question], vec![]));
+
+        assert_eq!(result, Err(EncodeError::EmptyLabel("kitchen..local".to_string())));
+    }
+}
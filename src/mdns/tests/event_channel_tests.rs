@@ -0,0 +1,92 @@
+#[cfg(test)]
+pub mod event_channel_tests {
+    use crate::mdns::event_channel::{bounded, BackpressurePolicy};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    /// Stop flag which is never set.
+    static RUNNING: AtomicBool = AtomicBool::new(false);
+
+    #[test]
+    fn send__when_drop_oldest_and_full__evicts_the_oldest_event()
+    {
+        let (sender, receiver) = bounded(2, BackpressurePolicy::DropOldest);
+
+        sender.send(1, &RUNNING);
+        sender.send(2, &RUNNING);
+        sender.send(3, &RUNNING);
+
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!(receiver.stats().dropped, 1);
+    }
+
+    #[test]
+    fn send__when_drop_newest_and_full__discards_the_new_event()
+    {
+        let (sender, receiver) = bounded(2, BackpressurePolicy::DropNewest);
+
+        sender.send(1, &RUNNING);
+        sender.send(2, &RUNNING);
+        sender.send(3, &RUNNING);
+
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(receiver.stats().dropped, 1);
+    }
+
+    #[test]
+    fn send__when_block_and_full__waits_for_the_receiver()
+    {
+        let (sender, receiver) = bounded(1, BackpressurePolicy::Block);
+        sender.send(1, &RUNNING);
+
+        let producer = thread::spawn(move || {
+            sender.send(2, &RUNNING);
+        });
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(receiver.stats().queued, 1);
+
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(1)), Some(1));
+        producer.join().unwrap();
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(1)), Some(2));
+        assert_eq!(receiver.stats().dropped, 0);
+    }
+
+    #[test]
+    fn send__when_receiver_is_dropped__returns_false()
+    {
+        let (sender, receiver) = bounded(1, BackpressurePolicy::Block);
+        sender.send(1, &RUNNING);
+        drop(receiver);
+
+        assert!(!sender.send(2, &RUNNING));
+    }
+
+    #[test]
+    fn recv_timeout__when_sender_is_dropped__drains_and_ends()
+    {
+        let (sender, receiver) = bounded(4, BackpressurePolicy::Block);
+        sender.send(1, &RUNNING);
+        drop(sender);
+
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(1)), Some(1));
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(1)), None);
+    }
+
+    #[test]
+    fn send__when_block_and_stop_is_requested__gives_up_waiting()
+    {
+        let (sender, receiver) = bounded(1, BackpressurePolicy::Block);
+        sender.send(1, &RUNNING);
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let producer_stop = stop.clone();
+        let producer = thread::spawn(move || sender.send(2, &producer_stop));
+        thread::sleep(Duration::from_millis(50));
+        stop.store(true, Ordering::SeqCst);
+
+        assert!(!producer.join().unwrap());
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), vec![1]);
+    }
+}
@@ -0,0 +1,193 @@
+#[cfg(test)]
+pub mod frame_decoder_tests {
+    use crate::mdns::capture::{capture, decode_frame, CaptureOptions, CaptureStats, FrameDecoder, LinkType};
+    use crate::mdns::event_channel::BackpressurePolicy;
+    use crate::mdns::mdns_message::{MDNSMessageReceivedEvent, Protocol};
+    use crate::mdns::packet_builder::build_udp_packet;
+    use crate::mdns::packet_source::ScriptedPacketSource;
+    use crate::mdns::socket_listener::LLMNR_IPV4_GROUP;
+    use crate::mdns::tests::parser_tests::parser_tests::RESOLVE_SPOTIFY_MDNS_PAYLOAD;
+    use pnet::packet::ip::IpNextHeaderProtocols;
+    use pnet::packet::ipv4::MutableIpv4Packet;
+    use pnet::packet::tcp::{MutableTcpPacket, TcpFlags};
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use time::OffsetDateTime;
+
+    const CLIENT: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 10);
+    const RESOLVER: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 1);
+
+    fn dns_query_packet() -> Vec<u8> {
+        let source = SocketAddr::new(IpAddr::V4(CLIENT), 40000);
+        let destination = SocketAddr::new(IpAddr::V4(RESOLVER), 53);
+        build_udp_packet(source, destination, 64, &RESOLVE_SPOTIFY_MDNS_PAYLOAD).unwrap()
+    }
+
+    /// An IPv4 packet carrying a TCP segment from the client to the resolver. Checksums are left empty, they are not verified.
+    fn tcp_packet(sequence: u32, flags: u8, payload: &[u8]) -> Vec<u8> {
+        let mut buffer = vec![0u8; 20 + 20 + payload.len()];
+        {
+            let mut tcp_packet = MutableTcpPacket::new(&mut buffer[20..]).unwrap();
+            tcp_packet.set_source(40000);
+            tcp_packet.set_destination(53);
+            tcp_packet.set_sequence(sequence);
+            tcp_packet.set_data_offset(5);
+            tcp_packet.set_flags(flags);
+            tcp_packet.set_payload(payload);
+        }
+        let mut ip_packet = MutableIpv4Packet::new(&mut buffer).unwrap();
+        ip_packet.set_version(4);
+        ip_packet.set_header_length(5);
+        ip_packet.set_total_length((40 + payload.len()) as u16);
+        ip_packet.set_ttl(64);
+        ip_packet.set_next_level_protocol(IpNextHeaderProtocols::Tcp);
+        ip_packet.set_source(CLIENT);
+        ip_packet.set_destination(RESOLVER);
+        buffer
+    }
+
+    fn decode(decoder: &mut FrameDecoder, packet: &[u8]) -> Vec<MDNSMessageReceivedEvent> {
+        decoder.decode(LinkType::RawIp, packet, OffsetDateTime::now_utc(), &Arc::from("eth0"))
+    }
+
+    /// Runs the frames through a capture with unicast DNS decoding and returns the delivered events and the final counters.
+    fn capture_frames(frames: Vec<Vec<u8>>) -> (Vec<MDNSMessageReceivedEvent>, CaptureStats) {
+        let source = frames.into_iter()
+            .fold(ScriptedPacketSource::new(), |source, frame| source.with_frame(LinkType::RawIp, frame, OffsetDateTime::now_utc()));
+        let options = CaptureOptions {
+            backpressure: BackpressurePolicy::Block,
+            unicast_dns: true,
+            ..Default::default()
+        };
+        let (handle, receiver) = capture(source, "scripted".to_string(), "script", options).unwrap();
+        // The channel closes once the source is exhausted and the capture thread is done counting.
+        let events = std::iter::from_fn(|| receiver.recv_timeout(Duration::from_secs(5))).collect();
+        (events, handle.stats())
+    }
+
+    #[test]
+    fn decode__when_unicast_dns_disabled__ignores_port_53() {
+        let mut decoder = FrameDecoder::new(false);
+
+        assert!(decode(&mut decoder, &dns_query_packet()).is_empty());
+    }
+
+    #[test]
+    fn decode__when_local_query_sent_to_unicast_dns__flags_it() {
+        let mut decoder = FrameDecoder::new(true);
+
+        let events = decode(&mut decoder, &dns_query_packet());
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].protocol, Protocol::UnicastDns);
+        assert!(events[0].message.is_local_query());
+        assert!(events[0].diagnostics.iter().any(|d| d.contains(".local")));
+    }
+
+    #[test]
+    fn decode__when_dns_message_split_over_tcp_segments__returns_reassembled_message() {
+        let mut data = (RESOLVE_SPOTIFY_MDNS_PAYLOAD.len() as u16).to_be_bytes().to_vec();
+        data.extend_from_slice(&RESOLVE_SPOTIFY_MDNS_PAYLOAD);
+        let mut decoder = FrameDecoder::new(true);
+
+        decode(&mut decoder, &tcp_packet(99, TcpFlags::SYN, &[]));
+        let first = decode(&mut decoder, &tcp_packet(100, TcpFlags::ACK, &data[..20]));
+        let second = decode(&mut decoder, &tcp_packet(120, TcpFlags::ACK | TcpFlags::PSH, &data[20..]));
+
+        assert!(first.is_empty());
+        assert_eq!(second.len(), 1);
+        assert_eq!(second[0].protocol, Protocol::UnicastDns);
+        assert_eq!(second[0].metadata.destination_port, 53);
+        assert_eq!(second[0].message.questions[0].name, "_spotify-connect._tcp.local");
+    }
+
+    #[test]
+    fn stats__when_frames_decoded__counts_messages_and_other_frames() {
+        let (_, stats) = capture_frames(vec![dns_query_packet(), vec![0u8; 8]]);
+
+        assert_eq!(stats.ipv4, 1);
+        assert_eq!(stats.ipv6, 0);
+        assert_eq!(stats.messages, 1);
+        assert_eq!(stats.other_frames, 1);
+        assert_eq!(stats.checksum_errors, 0);
+    }
+
+    #[test]
+    fn stats__when_tcp_segment_is_not_dns_or_truncated__counts_other_frames() {
+        let mut other_port = tcp_packet(100, TcpFlags::ACK, &[1, 2, 3]);
+        // The destination port follows the source port at the start of the TCP header.
+        other_port[22..24].copy_from_slice(&80u16.to_be_bytes());
+        // Ten bytes are too few for a TCP header.
+        let mut truncated = tcp_packet(100, TcpFlags::ACK, &[]);
+        truncated.truncate(30);
+        truncated[2..4].copy_from_slice(&30u16.to_be_bytes());
+
+        let (events, stats) = capture_frames(vec![other_port, truncated]);
+
+        assert!(events.is_empty());
+        assert_eq!(stats.ipv4, 2);
+        assert_eq!(stats.other_frames, 2);
+    }
+
+    #[test]
+    fn stats__when_payload_is_not_dns__counts_parse_error() {
+        let source = SocketAddr::new(IpAddr::V4(CLIENT), 40000);
+        let destination = SocketAddr::new(IpAddr::V4(RESOLVER), 53);
+        let packet = build_udp_packet(source, destination, 64, &[1, 2, 3]).unwrap();
+
+        let (events, stats) = capture_frames(vec![packet]);
+
+        assert!(events.is_empty());
+        assert_eq!(stats.parse_errors, 1);
+    }
+
+    #[test]
+    fn stats__when_udp_checksum_corrupted__counts_checksum_error() {
+        let mut packet = dns_query_packet();
+        // The UDP checksum follows the 20 byte IPv4 header and the ports and length.
+        packet[26] ^= 0xff;
+
+        let (events, stats) = capture_frames(vec![packet]);
+
+        assert_eq!(stats.checksum_errors, 1);
+        assert_eq!(events.len(), 1);
+        assert!(events[0].diagnostics.iter().any(|d| d.contains("checksum")));
+    }
+
+    #[test]
+    fn decode__when_ipv4_total_length_is_zero__uses_the_rest_of_the_packet() {
+        let mut packet = dns_query_packet();
+        // Offloaded packets captured on the sending host leave the total length at bytes 2 and 3 empty.
+        packet[2] = 0;
+        packet[3] = 0;
+        let mut decoder = FrameDecoder::new(true);
+
+        let events = decode(&mut decoder, &packet);
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].message.questions[0].name, "_spotify-connect._tcp.local");
+    }
+
+    #[test]
+    fn decode_frame__when_llmnr_port__returns_llmnr_message() {
+        let source = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 10), 50000));
+        let destination = SocketAddr::V4(SocketAddrV4::new(LLMNR_IPV4_GROUP, 5355));
+
+        let packet = build_udp_packet(source, destination, 1, &RESOLVE_SPOTIFY_MDNS_PAYLOAD).unwrap();
+        let event = decode_frame(LinkType::RawIp, &packet, OffsetDateTime::now_utc(), &Arc::from("eth0")).unwrap();
+
+        assert_eq!(event.protocol, Protocol::Llmnr);
+        assert_eq!(event.message.questions[0].name, "_spotify-connect._tcp.local");
+    }
+
+    #[test]
+    fn decode_frame__when_neither_mdns_nor_llmnr_port__returns_none() {
+        let source = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 10), 50000));
+        let destination = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 1), 5354));
+
+        let packet = build_udp_packet(source, destination, 64, &RESOLVE_SPOTIFY_MDNS_PAYLOAD).unwrap();
+
+        assert!(decode_frame(LinkType::RawIp, &packet, OffsetDateTime::now_utc(), &Arc::from("eth0")).is_none());
+    }
+}
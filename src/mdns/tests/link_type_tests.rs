@@ -0,0 +1,150 @@
+#[cfg(test)]
+pub mod link_type_tests {
+    use crate::mdns::capture::{decode_frame, LinkType};
+    use crate::mdns::mdns_message::MDNSMessageReceivedEvent;
+    use crate::mdns::packet_builder::build_udp_packet;
+    use crate::mdns::tests::parser_tests::parser_tests::RESOLVE_SPOTIFY_MDNS_PAYLOAD;
+    use pnet::util::MacAddr;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+    use std::sync::Arc;
+    use time::OffsetDateTime;
+
+    const IPV4_SOURCE: Ipv4Addr = Ipv4Addr::new(10, 8, 0, 2);
+    const IPV6_SOURCE: Ipv6Addr = Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 0x2);
+
+    fn ipv4_packet() -> Vec<u8> {
+        let source = SocketAddr::new(IpAddr::V4(IPV4_SOURCE), 5353);
+        let destination = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(224, 0, 0, 251)), 5353);
+        build_udp_packet(source, destination, 255, &RESOLVE_SPOTIFY_MDNS_PAYLOAD).unwrap()
+    }
+
+    fn ipv6_packet() -> Vec<u8> {
+        let source = SocketAddr::new(IpAddr::V6(IPV6_SOURCE), 5353);
+        let destination = SocketAddr::new(IpAddr::V6(Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 0xfb)), 5353);
+        build_udp_packet(source, destination, 255, &RESOLVE_SPOTIFY_MDNS_PAYLOAD).unwrap()
+    }
+
+    fn with_header(header: &[u8], packet: &[u8]) -> Vec<u8> {
+        let mut frame = header.to_vec();
+        frame.extend_from_slice(packet);
+        frame
+    }
+
+    fn decode(link_type: LinkType, frame: &[u8]) -> Option<MDNSMessageReceivedEvent> {
+        decode_frame(link_type, frame, OffsetDateTime::now_utc(), &Arc::from("tun0"))
+    }
+
+    #[test]
+    fn decode_frame__when_raw_ipv6__returns_message() {
+        let event = decode(LinkType::RawIp, &ipv6_packet()).unwrap();
+
+        assert_eq!(event.message.questions[0].name, "_spotify-connect._tcp.local");
+        assert_eq!(event.source_ip, IpAddr::V6(IPV6_SOURCE));
+    }
+
+    #[test]
+    fn decode_frame__when_ethernet_ipv6__returns_message() {
+        let header = [0x33, 0x33, 0, 0, 0, 0xFB, 2, 0, 0, 0, 0, 1, 0x86, 0xDD];
+
+        let event = decode(LinkType::Ethernet, &with_header(&header, &ipv6_packet())).unwrap();
+
+        assert_eq!(event.source_ip, IpAddr::V6(IPV6_SOURCE));
+    }
+
+    #[test]
+    fn decode_frame__when_ethernet_vlan_tagged__returns_message() {
+        let header = [1, 0, 0x5E, 0, 0, 0xFB, 2, 0, 0, 0, 0, 1, 0x81, 0x00, 0x00, 0x0A, 0x08, 0x00];
+
+        let event = decode(LinkType::Ethernet, &with_header(&header, &ipv4_packet())).unwrap();
+
+        assert_eq!(event.source_ip, IpAddr::V4(IPV4_SOURCE));
+    }
+
+    #[test]
+    fn decode_frame__when_ethernet_with_zeroed_header__returns_message() {
+        let event = decode(LinkType::Ethernet, &with_header(&[0; 14], &ipv4_packet())).unwrap();
+
+        assert_eq!(event.source_ip, IpAddr::V4(IPV4_SOURCE));
+    }
+
+    #[test]
+    fn decode_frame__when_ethernet_ipv4__returns_packet_metadata() {
+        let header = [1, 0, 0x5E, 0, 0, 0xFB, 2, 0, 0, 0, 0, 1, 0x08, 0x00];
+        let mut packet = ipv4_packet();
+        // DSCP 46 (expedited forwarding) and identification 0x1234.
+        packet[1] = 46 << 2;
+        packet[4] = 0x12;
+        packet[5] = 0x34;
+        let frame = with_header(&header, &packet);
+
+        let metadata = decode(LinkType::Ethernet, &frame).unwrap().metadata;
+
+        assert_eq!(metadata.source_mac, Some(MacAddr::new(2, 0, 0, 0, 0, 1)));
+        assert_eq!(metadata.destination_mac, Some(MacAddr::new(1, 0, 0x5E, 0, 0, 0xFB)));
+        assert_eq!(metadata.ttl, Some(255));
+        assert_eq!(metadata.dscp, Some(46));
+        assert_eq!(metadata.ip_identification, Some(0x1234));
+        assert_eq!(metadata.source_port, 5353);
+        assert_eq!(metadata.destination_port, 5353);
+        assert_eq!(metadata.frame_length, frame.len());
+    }
+
+    #[test]
+    fn decode_frame__when_raw_ipv6__returns_hop_limit_without_macs() {
+        let metadata = decode(LinkType::RawIp, &ipv6_packet()).unwrap().metadata;
+
+        assert_eq!(metadata.source_mac, None);
+        assert_eq!(metadata.ttl, Some(255));
+        assert_eq!(metadata.ip_identification, None);
+    }
+
+    #[test]
+    fn decode_frame__when_ethernet_not_ip__returns_none() {
+        let header = [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 2, 0, 0, 0, 0, 1, 0x08, 0x06];
+
+        assert!(decode(LinkType::Ethernet, &with_header(&header, &ipv4_packet())).is_none());
+    }
+
+    #[test]
+    fn decode_frame__when_linux_sll__returns_message() {
+        let header = [0, 0, 0, 1, 0, 6, 2, 0, 0, 0, 0, 1, 0, 0, 0x08, 0x00];
+
+        let event = decode(LinkType::LinuxSll, &with_header(&header, &ipv4_packet())).unwrap();
+
+        assert_eq!(event.source_ip, IpAddr::V4(IPV4_SOURCE));
+    }
+
+    #[test]
+    fn decode_frame__when_linux_sll2__returns_message() {
+        let header = [0x86, 0xDD, 0, 0, 0, 0, 0, 3, 0, 1, 0, 6, 2, 0, 0, 0, 0, 1, 0, 0];
+
+        let event = decode(LinkType::LinuxSll2, &with_header(&header, &ipv6_packet())).unwrap();
+
+        assert_eq!(event.source_ip, IpAddr::V6(IPV6_SOURCE));
+    }
+
+    #[test]
+    fn decode_frame__when_bsd_loopback__returns_message() {
+        // AF_INET6 on macOS, in host byte order.
+        let header = 30u32.to_le_bytes();
+
+        let event = decode(LinkType::Null, &with_header(&header, &ipv6_packet())).unwrap();
+
+        assert_eq!(event.source_ip, IpAddr::V6(IPV6_SOURCE));
+    }
+
+    #[test]
+    fn from_pcap__when_written_link_type__returns_same_link_type() {
+        for link_type in [LinkType::Ethernet, LinkType::RawIp, LinkType::LinuxSll, LinkType::LinuxSll2, LinkType::Null] {
+            assert_eq!(LinkType::from_pcap(link_type.to_pcap()), Some(link_type));
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn for_interface__when_loopback__returns_ethernet() {
+        let interface = pnet::datalink::interfaces().into_iter().find(|i| i.is_loopback()).unwrap();
+
+        assert_eq!(LinkType::for_interface(&interface), LinkType::Ethernet);
+    }
+}
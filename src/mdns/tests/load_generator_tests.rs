@@ -0,0 +1,122 @@
+#[cfg(test)]
+pub mod load_generator_tests {
+    use crate::mdns::load_generator::{LoadGenerator, LoadProfile, LoadRun, MixEntry};
+    use crate::mdns::parser::parse_mdns_message;
+    use crate::mdns::tests::loopback::loopback::{loopback_config, loopback_listener};
+    use crate::mdns::types::{MDNSRData, MDNSRecordType};
+    use std::time::{Duration, Instant};
+
+    fn profile(mix: Vec<MixEntry>, announcement_percent: u8, names: &[&str]) -> LoadProfile {
+        LoadProfile {
+            rate: 100.0,
+            duration: Duration::from_secs(1),
+            announcement_percent,
+            mix,
+            names: names.iter().map(|n| n.to_string()).collect(),
+        }
+    }
+
+    fn entry(record_type: MDNSRecordType, weight: u32) -> MixEntry {
+        MixEntry { record_type, weight }
+    }
+
+    #[test]
+    fn next_message__when_mix_is_weighted__follows_the_weights_evenly() {
+        let mix = vec![entry(MDNSRecordType::A, 2), entry(MDNSRecordType::PTR, 1), entry(MDNSRecordType::AAAA, 0)];
+        let mut generator = LoadGenerator::new(profile(mix, 0, &["host.local"])).unwrap();
+
+        let types: Vec<MDNSRecordType> = (0..6).map(|_| generator.next_message().questions[0].question_type).collect();
+
+        assert_eq!(types, vec![
+            MDNSRecordType::A, MDNSRecordType::PTR, MDNSRecordType::A,
+            MDNSRecordType::A, MDNSRecordType::PTR, MDNSRecordType::A,
+        ]);
+    }
+
+    #[test]
+    fn next_message__when_announcement_percent_set__sends_that_share_of_announcements() {
+        let mut generator = LoadGenerator::new(profile(vec![entry(MDNSRecordType::A, 1)], 25, &["host.local"])).unwrap();
+
+        let messages: Vec<_> = (0..200).map(|_| generator.next_message()).collect();
+
+        let announcements: Vec<_> = messages.iter().filter(|m| m.header.is_response()).collect();
+        assert_eq!(announcements.len(), 50);
+        assert!(messages[0..4].iter().filter(|m| m.header.is_response()).count() == 1);
+        let record = &announcements[0].answers[0];
+        assert!(record.cache_flush);
+        assert_eq!(record.ttl_seconds, 120);
+        assert!(matches!(record.rdata, MDNSRData::A { .. }));
+    }
+
+    #[test]
+    fn next_message__when_name_has_placeholder__uses_names_in_turn_with_message_number() {
+        let mut generator = LoadGenerator::new(profile(vec![entry(MDNSRecordType::A, 1)], 0, &["dev-{n}.local", "", "fixed.local"])).unwrap();
+
+        let names: Vec<String> = (0..3).map(|_| generator.next_message().questions[0].name.clone()).collect();
+
+        assert_eq!(names, vec!["dev-0.local", "fixed.local", "dev-2.local"]);
+    }
+
+    #[test]
+    fn next_message__when_announcing_ptr__does_not_set_cache_flush() {
+        let mut generator = LoadGenerator::new(profile(vec![entry(MDNSRecordType::PTR, 1)], 100, &["_http._tcp.local"])).unwrap();
+
+        let message = generator.next_message();
+
+        let record = &message.answers[0];
+        assert!(!record.cache_flush);
+        assert_eq!(record.rdata.to_string(), "PTR (Load 0._http._tcp.local)");
+    }
+
+    #[test]
+    fn next_message__when_any_is_in_the_mix__only_queries_it() {
+        let mut generator = LoadGenerator::new(profile(vec![entry(MDNSRecordType::ANY, 1)], 100, &["host.local"])).unwrap();
+
+        let message = generator.next_message();
+
+        assert!(!message.header.is_response());
+        assert_eq!(message.questions[0].question_type, MDNSRecordType::ANY);
+    }
+
+    #[test]
+    fn messages_due__when_time_passes__follows_the_rate_until_the_duration_is_over() {
+        let load = LoadProfile { rate: 200.0, duration: Duration::from_millis(500), ..profile(vec![entry(MDNSRecordType::A, 1)], 0, &["host.local"]) };
+        let generator = LoadGenerator::new(load).unwrap();
+
+        let due: Vec<u64> = [0, 4, 5, 250, 499, 500, 2000].iter().map(|ms| generator.messages_due(Duration::from_millis(*ms))).collect();
+
+        assert_eq!(due, vec![1, 1, 2, 51, 100, 100, 100]);
+    }
+
+    #[test]
+    fn new__when_no_type_or_name__returns_error() {
+        assert!(LoadGenerator::new(profile(vec![entry(MDNSRecordType::A, 0)], 0, &["host.local"])).is_err());
+        assert!(LoadGenerator::new(profile(vec![entry(MDNSRecordType::A, 1)], 0, &[" "])).is_err());
+    }
+
+    #[test]
+    fn start__when_run_on_loopback__group_receives_every_sent_message() {
+        let port = 53554;
+        let mut listener = loopback_listener(port);
+        let load = LoadProfile {
+            rate: 200.0,
+            duration: Duration::from_millis(500),
+            ..profile(vec![entry(MDNSRecordType::A, 1), entry(MDNSRecordType::TXT, 1)], 50, &["storm-{n}.local"])
+        };
+
+        let run = LoadRun::start(&loopback_config(port), load).unwrap();
+        let mut received = 0;
+        let deadline = Instant::now() + Duration::from_millis(800);
+        while Instant::now() < deadline {
+            if let Some(datagram) = listener.receive().unwrap() {
+                assert!(parse_mdns_message(&datagram.payload).is_ok());
+                received += 1;
+            }
+        }
+
+        let stats = run.stats();
+        assert!(stats.finished);
+        assert_eq!(stats.errors, 0);
+        assert_eq!(received, stats.sent());
+    }
+}
@@ -0,0 +1,52 @@
+#[cfg(test)]
+pub mod loopback {
+    use crate::mdns::query_sender::QuerySenderConfig;
+    use crate::mdns::responder::{PublishState, PublishedHost, PublishedRecords, Responder};
+    use crate::mdns::socket_listener::{MulticastGroup, SocketListener, SocketListenerConfig};
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::time::{Duration, Instant};
+
+    /// The mDNS group on another port than 5353, so the tests do not interfere with the mDNS stack of the host.
+    /// Every test uses a port of its own, as the tests run in parallel.
+    pub fn loopback_group(port: u16) -> MulticastGroup {
+        MulticastGroup { port, ..MulticastGroup::MDNS }
+    }
+
+    /// Sends to the group on loopback.
+    pub fn loopback_config(port: u16) -> QuerySenderConfig {
+        QuerySenderConfig {
+            group: loopback_group(port),
+            ipv4_interface: Some(Ipv4Addr::LOCALHOST),
+            ipv6_interface_index: None,
+        }
+    }
+
+    pub fn loopback_listener_config(port: u16) -> SocketListenerConfig {
+        SocketListenerConfig {
+            groups: vec![loopback_group(port)],
+            ipv4_interface: Some(Ipv4Addr::LOCALHOST),
+            ipv6_interface_index: None,
+            read_timeout: Duration::from_millis(100),
+        }
+    }
+
+    /// Stands in for a responder or a browser, it joins the group on loopback like one would.
+    pub fn loopback_listener(port: u16) -> SocketListener {
+        SocketListener::open(&loopback_listener_config(port)).unwrap()
+    }
+
+    /// A responder publishing the host on loopback, returned once its records are announced.
+    pub fn announced_responder(port: u16, name: &str, addresses: Vec<IpAddr>) -> Responder {
+        let records = PublishedRecords {
+            hosts: vec![PublishedHost { name: name.to_string(), addresses }],
+            services: vec![],
+        };
+        let responder = Responder::start(&loopback_config(port), records).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !responder.status().iter().any(|s| s.state == PublishState::Announced) {
+            assert!(Instant::now() < deadline, "Records were not announced in time.");
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        responder
+    }
+}
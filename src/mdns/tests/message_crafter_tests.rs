@@ -0,0 +1,144 @@
+#[cfg(test)]
+pub mod message_crafter_tests {
+    use crate::mdns::encoder::encode_mdns_message;
+    use crate::mdns::mdns_message::MDNSMessage;
+    use crate::mdns::message_crafter::{format_hex, parse_hex, send_crafted, CraftedMessage, CraftedRecord, RecordSection};
+    use crate::mdns::parser::parse_mdns_message;
+    use crate::mdns::tests::loopback::loopback::{loopback_config, loopback_listener};
+    use crate::mdns::types::{MDNSAnswer, MDNSHeaderFlags, MDNSMessageHeader, MDNSQueryClass, MDNSRData, MDNSRecordType};
+    use std::net::Ipv4Addr;
+    use std::time::{Duration, Instant};
+
+    fn record(section: RecordSection, record_type: MDNSRecordType, data: &str, hex: bool) -> CraftedRecord {
+        CraftedRecord {
+            section,
+            name: "device.local".to_string(),
+            record_type,
+            record_class: MDNSQueryClass::IN,
+            cache_flush: true,
+            ttl_seconds: 120,
+            data: data.to_string(),
+            hex,
+        }
+    }
+
+    fn response() -> MDNSMessage {
+        let answer = |answer_type, rdata| MDNSAnswer {
+            name: "device.local".to_string(),
+            answer_type,
+            answer_class: MDNSQueryClass::IN,
+            cache_flush: true,
+            ttl_seconds: 120,
+            rd_length: 0,
+            rdata,
+        };
+        MDNSMessage {
+            header: MDNSMessageHeader {
+                query_identifier: 0,
+                flags: 0x8400,
+                question_count: 0,
+                answer_count: 0,
+                authority_count: 0,
+                additional_count: 0,
+            },
+            questions: vec![],
+            answers: vec![answer(MDNSRecordType::A, MDNSRData::A { ipv4_address: Ipv4Addr::new(10, 0, 0, 7) })],
+            authorities: vec![],
+            // NSEC data is kept raw by the parser, the crafter edits it as hex.
+            additionals: vec![answer(MDNSRecordType::NSEC, MDNSRData::OTHER { raw: vec![0xc0, 0x0c, 0x00, 0x01, 0x40] })],
+        }
+    }
+
+    #[test]
+    fn parse_hex__when_copied_from_hex_dump__ignores_separators_and_prefixes() {
+        assert_eq!(parse_hex("00 0a:FF-0x10\n7f"), Ok(vec![0x00, 0x0a, 0xff, 0x10, 0x7f]));
+        assert_eq!(parse_hex(""), Ok(vec![]));
+    }
+
+    #[test]
+    fn parse_hex__when_not_hex__returns_error() {
+        assert!(parse_hex("0g").is_err());
+        assert!(parse_hex("abc").is_err());
+    }
+
+    #[test]
+    fn format_hex__when_longer_than_a_line__writes_sixteen_bytes_per_line() {
+        let bytes: Vec<u8> = (0..18).collect();
+
+        let hex = format_hex(&bytes);
+
+        assert_eq!(hex, "00 01 02 03 04 05 06 07 08 09 0a 0b 0c 0d 0e 0f\n10 11");
+        assert_eq!(parse_hex(&hex), Ok(bytes));
+    }
+
+    #[test]
+    fn header_flags__when_split_and_joined__returns_same_flags() {
+        let flags = MDNSHeaderFlags::from_u16(0x8400);
+
+        assert!(flags.response && flags.authoritative);
+        assert!(!flags.truncated && !flags.recursion_desired);
+        assert_eq!(flags.opcode, 0);
+        for value in [0x0000, 0x8400, 0x0200, 0xffff, 0x7a5b] {
+            assert_eq!(MDNSHeaderFlags::from_u16(value).to_u16(), value);
+        }
+    }
+
+    #[test]
+    fn from_message__when_encoded_again__returns_the_original_bytes() {
+        let original = encode_mdns_message(&response()).unwrap();
+        let parsed = parse_mdns_message(&original).unwrap();
+
+        let crafted = CraftedMessage::from_message(&parsed);
+
+        assert_eq!(crafted.records.len(), 2);
+        assert_eq!(crafted.records[0].data, "10.0.0.7");
+        assert!(!crafted.records[0].hex);
+        assert_eq!(crafted.records[1].section, RecordSection::Additional);
+        assert_eq!(crafted.records[1].data, "c0 0c 00 01 40");
+        assert!(crafted.records[1].hex);
+        assert_eq!(crafted.encode().unwrap(), original);
+    }
+
+    #[test]
+    fn encode__when_record_data_is_hex__puts_it_on_the_wire_for_any_type() {
+        let mut crafted = CraftedMessage::query("device.local", MDNSRecordType::MX);
+        crafted.flags.response = true;
+        crafted.records.push(record(RecordSection::Authority, MDNSRecordType::MX, "00 0a 00", true));
+
+        let message = parse_mdns_message(&crafted.encode().unwrap()).unwrap();
+
+        assert!(message.header.is_response());
+        assert_eq!(message.header.authority_count, 1);
+        let authority = &message.authorities[0];
+        assert_eq!(authority.answer_type, MDNSRecordType::MX);
+        assert_eq!(authority.rd_length, 3);
+        assert!(matches!(&authority.rdata, MDNSRData::OTHER { raw } if raw == &vec![0x00, 0x0a, 0x00]));
+    }
+
+    #[test]
+    fn to_message__when_record_data_is_invalid__names_the_record() {
+        let mut crafted = CraftedMessage::query("device.local", MDNSRecordType::A);
+        crafted.records.push(record(RecordSection::Answer, MDNSRecordType::A, "not an address", false));
+
+        let error = crafted.to_message().err().unwrap();
+
+        assert!(error.starts_with("Record 1 (device.local)"), "{}", error);
+    }
+
+    #[test]
+    fn send_crafted__when_sent_on_loopback__group_receives_the_bytes_as_is() {
+        let port = 53555;
+        let mut listener = loopback_listener(port);
+        // Not a valid message, the crafter sends whatever it is given.
+        let payload = vec![0xde, 0xad, 0xbe, 0xef];
+
+        send_crafted(&loopback_config(port), &payload).unwrap();
+
+        let deadline = Instant::now() + Duration::from_millis(500);
+        let mut received = None;
+        while received.is_none() && Instant::now() < deadline {
+            received = listener.receive().unwrap().map(|d| d.payload);
+        }
+        assert_eq!(received, Some(payload));
+    }
+}
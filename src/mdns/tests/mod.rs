@@ -1 +1,20 @@
-pub mod parser_tests;
\ No newline at end of file
+pub mod parser_tests;
+pub mod event_channel_tests;
+pub mod pcap_reader_tests;
+pub mod pcapng_writer_tests;
+pub mod socket_listener_tests;
+pub mod link_type_tests;
+pub mod packet_source_tests;
+pub mod tcp_reassembly_tests;
+pub mod frame_decoder_tests;
+pub mod capture_filter_tests;
+pub mod encoder_tests;
+pub mod query_sender_tests;
+pub mod service_browser_tests;
+pub mod responder_tests;
+pub mod resolver_tests;
+pub mod conflict_test_tests;
+pub mod replay_tests;
+pub mod load_generator_tests;
+pub mod message_crafter_tests;
+pub mod loopback;
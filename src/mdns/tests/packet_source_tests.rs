@@ -0,0 +1,58 @@
+#[cfg(test)]
+pub mod packet_source_tests {
+    use crate::mdns::capture::{capture, CaptureOptions, LinkType};
+    use crate::mdns::event_channel::BackpressurePolicy;
+    use crate::mdns::packet_builder::build_udp_packet;
+    use crate::mdns::packet_source::ScriptedPacketSource;
+    use crate::mdns::tests::parser_tests::parser_tests::RESOLVE_SPOTIFY_MDNS_PAYLOAD;
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use std::time::Duration;
+    use time::OffsetDateTime;
+
+    // 2024-12-01 10:00:00 UTC
+    const START: i64 = 1_733_047_200;
+
+    fn at(seconds: i64) -> OffsetDateTime {
+        OffsetDateTime::from_unix_timestamp(START + seconds).unwrap()
+    }
+
+    fn udp_packet(source_port: u16, destination_port: u16) -> Vec<u8> {
+        let source = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10)), source_port);
+        let destination = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(224, 0, 0, 251)), destination_port);
+        build_udp_packet(source, destination, 255, &RESOLVE_SPOTIFY_MDNS_PAYLOAD).unwrap()
+    }
+
+    #[test]
+    fn capture__when_scripted_frames__delivers_mdns_messages_in_order() {
+        let source = ScriptedPacketSource::new()
+            .with_frame(LinkType::RawIp, udp_packet(5353, 5353), at(0))
+            .with_frame(LinkType::RawIp, udp_packet(40000, 53), at(1))
+            .with_frame(LinkType::RawIp, udp_packet(5353, 5353), at(2));
+        let options = CaptureOptions {
+            backpressure: BackpressurePolicy::Block,
+            ..Default::default()
+        };
+
+        let (_handle, events) = capture(source, "scripted".to_string(), "script", options).unwrap();
+
+        let first = events.recv_timeout(Duration::from_secs(5)).unwrap();
+        let second = events.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(first.received_datetime, at(0));
+        assert_eq!(second.received_datetime, at(2));
+        assert_eq!(&*first.interface_name, "script");
+        // The frame which is not mDNS is skipped and the channel closes once the source is exhausted.
+        assert!(events.recv_timeout(Duration::from_secs(5)).is_none());
+    }
+
+    #[test]
+    fn capture__when_source_exhausted__stops_running() {
+        let source = ScriptedPacketSource::new()
+            .with_frame(LinkType::RawIp, udp_packet(5353, 5353), at(0));
+
+        let (handle, events) = capture(source, "scripted".to_string(), "script", CaptureOptions::default()).unwrap();
+
+        assert!(events.recv_timeout(Duration::from_secs(5)).is_some());
+        assert!(events.recv_timeout(Duration::from_secs(5)).is_none());
+        assert!(!handle.is_running());
+    }
+}
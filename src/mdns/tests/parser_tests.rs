@@ -1,6 +1,7 @@
 #[cfg(test)]
 pub mod parser_tests {
-    use crate::mdns::parser::{parse_mdns_header, parse_mdns_message, parse_name, ByteReader};
+    use crate::mdns::parser::{parse_mdns_header, parse_mdns_message, parse_mdns_message_with_diagnostics, parse_name, parse_rdata, ByteReader, ParseError};
+    use crate::mdns::types::MDNSRecordType;
 
     #[test]
     fn parse_header__reads_the_header_and_only_the_header()
@@ -39,7 +40,7 @@ pub mod parser_tests {
             byte_index: 0
         };
 
-        let result = parse_name(&mut reader);
+        let result = parse_name(&mut reader).unwrap();
 
         assert_eq!(reader.byte_index, 29);
         assert_eq!(result, "_spotify-connect._tcp.local");
@@ -53,7 +54,7 @@ pub mod parser_tests {
             byte_index: 0
         };
 
-        let result = parse_name(&mut reader);
+        let result = parse_name(&mut reader).unwrap();
 
         assert_eq!(reader.byte_index, 18);
         assert_eq!(result, "_spotify-connect");
@@ -67,7 +68,7 @@ pub mod parser_tests {
             byte_index: 18
         };
 
-        let result = parse_name(&mut reader);
+        let result = parse_name(&mut reader).unwrap();
 
         assert_eq!(reader.byte_index, 20);
         assert_eq!(result, "_spotify-connect");
@@ -81,13 +82,13 @@ pub mod parser_tests {
             byte_index: 0
         };
 
-        let result = parse_name(&mut reader);
+        let result = parse_name(&mut reader).unwrap();
 
         assert_eq!(reader.byte_index, 7);
     }
 
     // id: 0, flags: 0, question_count: 1, answer_count: 0, authority_count: 0, additional_count: 0, label: _spotify-connect_tcplocal
-    const RESOLVE_SPOTIFY_MDNS_PAYLOAD: [u8; 45] = [0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 16, 95, 115, 112, 111, 116, 105, 102, 121, 45, 99, 111, 110, 110, 101, 99, 116, 4, 95, 116, 99, 112, 5, 108, 111, 99, 97, 108, 0, 0, 12, 0, 1];
+    pub const RESOLVE_SPOTIFY_MDNS_PAYLOAD: [u8; 45] = [0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 16, 95, 115, 112, 111, 116, 105, 102, 121, 45, 99, 111, 110, 110, 101, 99, 116, 4, 95, 116, 99, 112, 5, 108, 111, 99, 97, 108, 0, 0, 12, 0, 1];
 
     // const ANSWER_MACHINE1_MDNS_PAYLOAD: [u8; 114] = [0, 0, 132, 0, 0, 0, 0, 1, 0, 0, 0, 4, 5, 95, 104, 116, 116, 112, 4, 95, 116, 99, 112, 5, 108, 111, 99, 97, 108, 0, 0, 12, 0, 1, 0, 0, 17, 148, 0, 6, 3, 104, 117, 98, 192, 12, 192, 40, 0, 47, 128, 1, 0, 0, 0, 120, 0, 8, 192, 40, 0, 4, 0, 0, 0, 8, 192, 40, 0, 1, 128, 1, 0, 0, 0, 120, 0, 4, 192, 168, 100, 24, 192, 40, 0, 33, 128, 1, 0, 0, 0, 120, 0, 8, 0, 0, 0, 0, 216, 71, 192, 40, 192, 40, 0, 16, 128, 1, 0, 0, 17, 148, 0, 0];
 
@@ -155,4 +156,60 @@ pub mod parser_tests {
         assert_eq!(mdns_message.answers.len(), 5);
         // TODO: Complete the assert section.
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn parse_mdns_message__when_truncated__returns_error() {
+        let result = parse_mdns_message(&RESOLVE_SPOTIFY_MDNS_PAYLOAD[..30]);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn parse_name__when_pointer_points_to_itself__returns_error()
+    {
+        let mut reader = ByteReader {
+            bytes: [0, 0, 192, 2].try_into().unwrap(),
+            byte_index: 2
+        };
+
+        let result = parse_name(&mut reader);
+
+        assert_eq!(result, Err(ParseError::InvalidPointer(2)));
+    }
+
+    #[test]
+    fn parse_mdns_message_with_diagnostics__when_trailing_bytes__reports_them() {
+        let mut payload = RESOLVE_SPOTIFY_MDNS_PAYLOAD.to_vec();
+        payload.extend_from_slice(&[1, 2, 3]);
+
+        let (_, diagnostics) = parse_mdns_message_with_diagnostics(&payload).unwrap();
+
+        assert_eq!(diagnostics, vec!["3 trailing bytes after the last record.".to_string()]);
+    }
+
+    #[test]
+    fn parse_mdns_message__when_additional_records_present__decodes_them() {
+        // MDNS_ANSWER_1 with its last two answers counted as additional records.
+        let mut payload = MDNS_ANSWER_1.to_vec();
+        payload[7] = 3;
+        payload[11] = 2;
+
+        let (message, diagnostics) = parse_mdns_message_with_diagnostics(&payload).unwrap();
+
+        assert_eq!(message.answers.len(), 3);
+        assert_eq!(message.additionals.len(), 2);
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn parse_rdata__when_txt_has_several_strings__returns_one_line_per_string() {
+        let mut reader = ByteReader {
+            bytes: vec![4, b'i', b'd', b'=', b'1', 0, 3, b'a', b'=', b'b'],
+            byte_index: 0
+        };
+
+        let rdata = parse_rdata(&mut reader, MDNSRecordType::TXT, 10).unwrap();
+
+        assert_eq!(rdata.to_string(), "TXT (id=1, , a=b)");
+    }
+}
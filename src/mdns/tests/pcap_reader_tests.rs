@@ -0,0 +1,173 @@
+#[cfg(test)]
+pub mod pcap_reader_tests {
+    use crate::mdns::capture::{decode_frame, LinkType};
+    use crate::mdns::pcap_reader::PcapReader;
+    use crate::mdns::tests::parser_tests::parser_tests::RESOLVE_SPOTIFY_MDNS_PAYLOAD;
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::sync::Arc;
+    use time::OffsetDateTime;
+
+    fn ipv4_udp_packet(payload: &[u8]) -> Vec<u8> {
+        let total_length = (20 + 8 + payload.len()) as u16;
+        let mut packet = vec![0x45, 0, (total_length >> 8) as u8, total_length as u8, 0, 0, 0, 0, 255, 17, 0, 0, 192, 168, 1, 10, 224, 0, 0, 251];
+        let udp_length = (8 + payload.len()) as u16;
+        packet.extend_from_slice(&[0x14, 0xE9, 0x14, 0xE9, (udp_length >> 8) as u8, udp_length as u8, 0, 0]);
+        packet.extend_from_slice(payload);
+        packet
+    }
+
+    fn ethernet_frame(payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0x01, 0x00, 0x5E, 0x00, 0x00, 0xFB, 0x02, 0x00, 0x00, 0x00, 0x00, 0x01, 0x08, 0x00];
+        frame.extend_from_slice(&ipv4_udp_packet(payload));
+        frame
+    }
+
+    fn pcap_file(big_endian: bool, magic: u32, link_type: u32, seconds: u32, fraction: u32, frame: &[u8]) -> Vec<u8> {
+        let u16_bytes = |v: u16| if big_endian { v.to_be_bytes() } else { v.to_le_bytes() };
+        let u32_bytes = |v: u32| if big_endian { v.to_be_bytes() } else { v.to_le_bytes() };
+        let mut file = vec![];
+        file.extend_from_slice(&u32_bytes(magic));
+        file.extend_from_slice(&u16_bytes(2));
+        file.extend_from_slice(&u16_bytes(4));
+        file.extend_from_slice(&u32_bytes(0));
+        file.extend_from_slice(&u32_bytes(0));
+        file.extend_from_slice(&u32_bytes(65535));
+        file.extend_from_slice(&u32_bytes(link_type));
+        file.extend_from_slice(&u32_bytes(seconds));
+        file.extend_from_slice(&u32_bytes(fraction));
+        file.extend_from_slice(&u32_bytes(frame.len() as u32));
+        file.extend_from_slice(&u32_bytes(frame.len() as u32));
+        file.extend_from_slice(frame);
+        file
+    }
+
+    fn pcapng_block(block_type: u32, body: &[u8]) -> Vec<u8> {
+        let mut padded = body.to_vec();
+        while !padded.len().is_multiple_of(4) {
+            padded.push(0);
+        }
+        let length = (padded.len() + 12) as u32;
+        let mut block = vec![];
+        block.extend_from_slice(&block_type.to_le_bytes());
+        block.extend_from_slice(&length.to_le_bytes());
+        block.extend_from_slice(&padded);
+        block.extend_from_slice(&length.to_le_bytes());
+        block
+    }
+
+    fn pcapng_file(link_type: u16, ticks: u64, frame: &[u8]) -> Vec<u8> {
+        let mut section_header = vec![];
+        section_header.extend_from_slice(&0x1A2B3C4Du32.to_le_bytes());
+        section_header.extend_from_slice(&1u16.to_le_bytes());
+        section_header.extend_from_slice(&0u16.to_le_bytes());
+        section_header.extend_from_slice(&(-1i64).to_le_bytes());
+
+        let mut interface_description = vec![];
+        interface_description.extend_from_slice(&link_type.to_le_bytes());
+        interface_description.extend_from_slice(&0u16.to_le_bytes());
+        interface_description.extend_from_slice(&0u32.to_le_bytes());
+        // if_tsresol = 10^-9
+        interface_description.extend_from_slice(&[9, 0, 1, 0, 9, 0, 0, 0]);
+        interface_description.extend_from_slice(&[0, 0, 0, 0]);
+
+        let mut enhanced_packet = vec![];
+        enhanced_packet.extend_from_slice(&0u32.to_le_bytes());
+        enhanced_packet.extend_from_slice(&((ticks >> 32) as u32).to_le_bytes());
+        enhanced_packet.extend_from_slice(&(ticks as u32).to_le_bytes());
+        enhanced_packet.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        enhanced_packet.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        enhanced_packet.extend_from_slice(frame);
+
+        let mut file = pcapng_block(0x0A0D0D0A, &section_header);
+        file.extend(pcapng_block(1, &interface_description));
+        // A name resolution block which has to be skipped.
+        file.extend(pcapng_block(4, &[0, 0, 0, 0]));
+        file.extend(pcapng_block(6, &enhanced_packet));
+        file
+    }
+
+    #[test]
+    fn next_frame__when_little_endian_pcap__reads_frame_and_microsecond_timestamp()
+    {
+        let frame = ethernet_frame(&RESOLVE_SPOTIFY_MDNS_PAYLOAD);
+        let file = pcap_file(false, 0xA1B2C3D4, 1, 1_700_000_000, 250_000, &frame);
+        let mut reader = PcapReader::new(file.as_slice()).unwrap();
+
+        let captured = reader.next_frame().unwrap().unwrap();
+
+        assert_eq!(captured.link_type, 1);
+        assert_eq!(captured.data, frame);
+        assert_eq!(captured.timestamp, Some(OffsetDateTime::from_unix_timestamp_nanos(1_700_000_000_250_000_000).unwrap()));
+        assert!(reader.next_frame().unwrap().is_none());
+    }
+
+    #[test]
+    fn next_frame__when_big_endian_nanosecond_pcap__reads_nanosecond_timestamp()
+    {
+        let frame = ethernet_frame(&RESOLVE_SPOTIFY_MDNS_PAYLOAD);
+        let file = pcap_file(true, 0xA1B23C4D, 1, 1_700_000_000, 123_456_789, &frame);
+        let mut reader = PcapReader::new(file.as_slice()).unwrap();
+
+        let captured = reader.next_frame().unwrap().unwrap();
+
+        assert_eq!(captured.data, frame);
+        assert_eq!(captured.timestamp, Some(OffsetDateTime::from_unix_timestamp_nanos(1_700_000_000_123_456_789).unwrap()));
+    }
+
+    #[test]
+    fn next_frame__when_pcapng__uses_interface_link_type_and_resolution()
+    {
+        let packet = ipv4_udp_packet(&RESOLVE_SPOTIFY_MDNS_PAYLOAD);
+        let file = pcapng_file(101, 1_700_000_000_123_456_789, &packet);
+        let mut reader = PcapReader::new(file.as_slice()).unwrap();
+
+        let captured = reader.next_frame().unwrap().unwrap();
+
+        assert_eq!(captured.link_type, 101);
+        assert_eq!(captured.data, packet);
+        assert_eq!(captured.timestamp, Some(OffsetDateTime::from_unix_timestamp_nanos(1_700_000_000_123_456_789).unwrap()));
+        assert!(reader.next_frame().unwrap().is_none());
+    }
+
+    #[test]
+    fn next_frame__when_record_is_truncated__returns_error()
+    {
+        let frame = ethernet_frame(&RESOLVE_SPOTIFY_MDNS_PAYLOAD);
+        let file = pcap_file(false, 0xA1B2C3D4, 1, 0, 0, &frame);
+        let mut reader = PcapReader::new(&file[..file.len() - 1]).unwrap();
+
+        assert!(reader.next_frame().is_err());
+    }
+
+    #[test]
+    fn new__when_not_a_capture_file__returns_error()
+    {
+        assert!(PcapReader::new([1u8, 2, 3, 4, 5, 6, 7, 8].as_slice()).is_err());
+    }
+
+    #[test]
+    fn decode_frame__when_ethernet_frame_from_file__keeps_the_file_timestamp()
+    {
+        let frame = ethernet_frame(&RESOLVE_SPOTIFY_MDNS_PAYLOAD);
+        let timestamp = OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+
+        let event = decode_frame(LinkType::Ethernet, &frame, timestamp, &Arc::from("eth0")).unwrap();
+
+        assert_eq!(event.received_datetime, timestamp);
+        assert_eq!(event.source_ip, IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10)));
+        assert_eq!(event.destination_ip, IpAddr::V4(Ipv4Addr::new(224, 0, 0, 251)));
+        assert_eq!(event.message.questions.first().unwrap().name, "_spotify-connect._tcp.local");
+        assert_eq!(&event.frame[..], &frame[..]);
+        assert!(event.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn decode_frame__when_raw_ip_frame__decodes_the_message()
+    {
+        let packet = ipv4_udp_packet(&RESOLVE_SPOTIFY_MDNS_PAYLOAD);
+
+        let event = decode_frame(LinkType::RawIp, &packet, OffsetDateTime::UNIX_EPOCH, &Arc::from("tun0")).unwrap();
+
+        assert_eq!(event.message.questions.len(), 1);
+    }
+}
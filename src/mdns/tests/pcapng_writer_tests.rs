@@ -0,0 +1,90 @@
+#[cfg(test)]
+pub mod pcapng_writer_tests {
+    use crate::mdns::pcap_reader::PcapReader;
+    use crate::mdns::pcapng_writer::PcapNgWriter;
+    use time::OffsetDateTime;
+
+    #[test]
+    fn write_packet__output_can_be_read_back_with_nanosecond_timestamps()
+    {
+        let timestamp = OffsetDateTime::from_unix_timestamp_nanos(1_700_000_000_123_456_789).unwrap();
+        let mut writer = PcapNgWriter::new(vec![]).unwrap();
+        writer.write_packet(1, "eth0", timestamp, &[1, 2, 3, 4, 5], Some("2 authority and 0 additional records were not decoded.")).unwrap();
+        writer.write_packet(101, "tun0", timestamp, &[0x45, 0, 0], None).unwrap();
+        writer.write_packet(1, "eth0", timestamp, &[6], None).unwrap();
+        let file = writer.finish().unwrap();
+
+        let mut reader = PcapReader::new(file.as_slice()).unwrap();
+        let first = reader.next_frame().unwrap().unwrap();
+        let second = reader.next_frame().unwrap().unwrap();
+        let third = reader.next_frame().unwrap().unwrap();
+
+        assert_eq!(first.data, vec![1, 2, 3, 4, 5]);
+        assert_eq!(first.link_type, 1);
+        assert_eq!(first.timestamp, Some(timestamp));
+        assert_eq!(second.data, vec![0x45, 0, 0]);
+        assert_eq!(second.link_type, 101);
+        assert_eq!(third.data, vec![6]);
+        assert_eq!(third.link_type, 1);
+        assert!(reader.next_frame().unwrap().is_none());
+    }
+
+    #[test]
+    fn write_packet__writes_one_interface_description_per_interface()
+    {
+        let mut writer = PcapNgWriter::new(vec![]).unwrap();
+        writer.write_packet(1, "eth0", OffsetDateTime::UNIX_EPOCH, &[1], None).unwrap();
+        writer.write_packet(1, "eth0", OffsetDateTime::UNIX_EPOCH, &[2], None).unwrap();
+        writer.write_packet(1, "eth1", OffsetDateTime::UNIX_EPOCH, &[3], None).unwrap();
+        let file = writer.finish().unwrap();
+
+        let interface_description_blocks = count_blocks(&file, 1);
+
+        assert_eq!(interface_description_blocks, 2);
+    }
+
+    #[test]
+    fn write_packet__stores_the_comment_as_packet_option()
+    {
+        let mut writer = PcapNgWriter::new(vec![]).unwrap();
+        writer.write_packet(1, "eth0", OffsetDateTime::UNIX_EPOCH, &[1], Some("trailing bytes")).unwrap();
+        let file = writer.finish().unwrap();
+
+        let comment = b"trailing bytes";
+        assert!(file.windows(comment.len()).any(|w| w == comment));
+    }
+
+    #[test]
+    fn write_packet__when_comment_exceeds_option_length__truncates_it_and_keeps_the_file_readable()
+    {
+        let comment = "x".repeat(70_000);
+        let mut writer = PcapNgWriter::new(vec![]).unwrap();
+        writer.write_packet(1, "eth0", OffsetDateTime::UNIX_EPOCH, &[1], Some(&comment)).unwrap();
+        writer.write_packet(1, "eth0", OffsetDateTime::UNIX_EPOCH, &[2], None).unwrap();
+        let file = writer.finish().unwrap();
+
+        let mut reader = PcapReader::new(file.as_slice()).unwrap();
+        let first = reader.next_frame().unwrap().unwrap();
+        let second = reader.next_frame().unwrap().unwrap();
+
+        assert_eq!(first.data, vec![1]);
+        assert_eq!(second.data, vec![2]);
+        assert!(reader.next_frame().unwrap().is_none());
+        let truncated_option_header = [1, 0, 0xff, 0xff];
+        assert!(file.windows(4).any(|w| w == truncated_option_header));
+    }
+
+    fn count_blocks(file: &[u8], block_type: u32) -> usize {
+        let mut offset = 0;
+        let mut count = 0;
+        while offset + 8 <= file.len() {
+            let current_type = u32::from_le_bytes(file[offset..offset + 4].try_into().unwrap());
+            let length = u32::from_le_bytes(file[offset + 4..offset + 8].try_into().unwrap()) as usize;
+            if current_type == block_type {
+                count += 1;
+            }
+            offset += length;
+        }
+        count
+    }
+}
@@ -0,0 +1,117 @@
+#[cfg(test)]
+pub mod query_sender_tests {
+    use crate::mdns::mdns_message::MDNSMessage;
+    use crate::mdns::parser::parse_mdns_message;
+    use crate::mdns::query_sender::{ContinuousQuery, Query, QuerySender, QueryTag, SentQueryLog};
+    use crate::mdns::socket_listener::MDNS_IPV4_GROUP;
+    use crate::mdns::tests::loopback::loopback::{loopback_config, loopback_listener};
+    use crate::mdns::types::{MDNSAnswer, MDNSMessageHeader, MDNSQueryClass, MDNSRData, MDNSRecordType};
+    use std::net::{SocketAddr, SocketAddrV4};
+    use std::time::Duration;
+    use time::OffsetDateTime;
+
+    fn ptr_answer(name: &str, instance: &str) -> MDNSAnswer {
+        MDNSAnswer {
+            name: name.to_string(),
+            answer_type: MDNSRecordType::PTR,
+            answer_class: MDNSQueryClass::IN,
+            cache_flush: false,
+            ttl_seconds: 4500,
+            rd_length: 0,
+            rdata: MDNSRData::PTR { domain_name: instance.to_string() },
+        }
+    }
+
+    fn response(answers: Vec<MDNSAnswer>) -> MDNSMessage {
+        MDNSMessage {
+            header: MDNSMessageHeader {
+                query_identifier: 0,
+                flags: 0x8400,
+                question_count: 0,
+                answer_count: answers.len() as u16,
+                authority_count: 0,
+                additional_count: 0,
+            },
+            questions: vec![],
+            answers,
+            authorities: vec![],
+            additionals: vec![],
+        }
+    }
+
+    #[test]
+    fn send__when_sent_on_loopback__responder_receives_query_with_known_answers() {
+        let port = 53540;
+        let mut listener = loopback_listener(port);
+        let sender = QuerySender::open(&loopback_config(port), SentQueryLog::new()).unwrap();
+        let mut query = Query::new("_googlecast._tcp.local", MDNSRecordType::PTR, true);
+        query.known_answers.push(ptr_answer("_googlecast._tcp.local", "Kitchen._googlecast._tcp.local"));
+
+        let id = sender.send(&query).unwrap();
+
+        let datagram = (0..20).find_map(|_| listener.receive().unwrap()).expect("No query received.");
+        assert_eq!(id, 1);
+        assert_ne!(datagram.source.port(), port);
+        assert_eq!(datagram.destination, SocketAddr::V4(SocketAddrV4::new(MDNS_IPV4_GROUP, port)));
+        let message = parse_mdns_message(&datagram.payload).unwrap();
+        assert!(!message.header.is_response());
+        assert_eq!(message.questions[0].name, "_googlecast._tcp.local");
+        assert_eq!(message.questions[0].question_type, MDNSRecordType::PTR);
+        assert!(message.questions[0].unicast_response);
+        assert_eq!(message.answers[0].rdata.to_string(), "PTR (Kitchen._googlecast._tcp.local)");
+    }
+
+    #[test]
+    fn start__when_continuous__repeats_query_until_stopped() {
+        let port = 53541;
+        let mut listener = loopback_listener(port);
+        let sender = QuerySender::open(&loopback_config(port), SentQueryLog::new()).unwrap();
+        let query = Query::new("kitchen.local", MDNSRecordType::A, false);
+
+        let mut continuous_query = ContinuousQuery::start(sender, &query, Duration::from_millis(50)).unwrap();
+        let received = (0..30).filter_map(|_| listener.receive().unwrap()).take(3).count();
+        continuous_query.stop();
+
+        assert_eq!(received, 3);
+    }
+
+    #[test]
+    fn tag__when_response_answers_sent_query__returns_reply() {
+        let port = 53542;
+        let log = SentQueryLog::new();
+        let sender = QuerySender::open(&loopback_config(port), log.clone()).unwrap();
+        let id = sender.send(&Query::new("_googlecast._tcp.local", MDNSRecordType::PTR, false)).unwrap();
+
+        let tag = log.tag(&response(vec![ptr_answer("_GoogleCast._tcp.local", "Kitchen._googlecast._tcp.local")]), OffsetDateTime::now_utc());
+
+        assert_eq!(tag, Some(QueryTag::Reply(id)));
+    }
+
+    #[test]
+    fn tag__when_query_looped_back__returns_sent() {
+        let port = 53543;
+        let log = SentQueryLog::new();
+        let sender = QuerySender::open(&loopback_config(port), log.clone()).unwrap();
+        let query = Query::new("kitchen.local", MDNSRecordType::A, false);
+        let id = sender.send(&query).unwrap();
+
+        let tag = log.tag(&query.to_message(), OffsetDateTime::now_utc());
+
+        assert_eq!(tag, Some(QueryTag::Sent(id)));
+    }
+
+    #[test]
+    fn tag__when_response_is_unrelated_or_late__returns_none() {
+        let port = 53544;
+        let log = SentQueryLog::new();
+        let sender = QuerySender::open(&loopback_config(port), log.clone()).unwrap();
+        sender.send(&Query::new("_googlecast._tcp.local", MDNSRecordType::PTR, false)).unwrap();
+        let answer = ptr_answer("_googlecast._tcp.local", "Kitchen._googlecast._tcp.local");
+
+        let unrelated = log.tag(&response(vec![ptr_answer("_airplay._tcp.local", "Kitchen._airplay._tcp.local")]), OffsetDateTime::now_utc());
+        let late = log.tag(&response(vec![answer]), OffsetDateTime::now_utc() + time::Duration::minutes(1));
+
+        assert_eq!(unrelated, None);
+        assert_eq!(late, None);
+    }
+}
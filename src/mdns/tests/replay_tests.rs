@@ -0,0 +1,244 @@
+#[cfg(test)]
+pub mod replay_tests {
+    use crate::mdns::capture::{udp_datagram, LinkType};
+    use crate::mdns::encoder::encode_mdns_message;
+    use crate::mdns::mdns_message::MDNSMessage;
+    use crate::mdns::packet_builder::build_udp_packet;
+    use crate::mdns::parser::parse_mdns_message;
+    use crate::mdns::replay::{replay_frame, rewrite_payload, schedule, Replay, ReplayOptions, ReplayPacket, ReplayRecording, ReplaySource, ReplayTiming};
+    use crate::mdns::socket_listener::SocketListener;
+    use crate::mdns::tests::loopback::loopback::{loopback_group, loopback_listener};
+    use crate::mdns::types::{MDNSAnswer, MDNSMessageHeader, MDNSQueryClass, MDNSRData, MDNSRecordType};
+    use pnet::util::MacAddr;
+    use std::io;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+    use std::time::{Duration, Instant};
+    use time::OffsetDateTime;
+
+    fn response(name: &str, ttl_seconds: u32) -> Vec<u8> {
+        let message = MDNSMessage {
+            header: MDNSMessageHeader {
+                query_identifier: 0,
+                flags: 0x8400,
+                question_count: 0,
+                answer_count: 0,
+                authority_count: 0,
+                additional_count: 0,
+            },
+            questions: vec![],
+            answers: vec![MDNSAnswer {
+                name: name.to_string(),
+                answer_type: MDNSRecordType::A,
+                answer_class: MDNSQueryClass::IN,
+                cache_flush: true,
+                ttl_seconds,
+                rd_length: 0,
+                rdata: MDNSRData::A { ipv4_address: Ipv4Addr::new(10, 0, 0, 7) },
+            }],
+            authorities: vec![],
+            additionals: vec![],
+        };
+        encode_mdns_message(&message).unwrap()
+    }
+
+    fn frame(source_port: u16, destination_port: u16, payload: &[u8]) -> Vec<u8> {
+        let source = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 20)), source_port);
+        let destination = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(224, 0, 0, 251)), destination_port);
+        build_udp_packet(source, destination, 255, payload).unwrap()
+    }
+
+    fn packet(offset_millis: u64) -> ReplayPacket {
+        ReplayPacket {
+            offset: Duration::from_millis(offset_millis),
+            source: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 20)), 5353),
+            ttl: Some(255),
+            payload: response("printer.local", 120),
+        }
+    }
+
+    fn answer_ttls(payload: &[u8]) -> Vec<u32> {
+        parse_mdns_message(payload).unwrap().answers.iter().map(|a| a.ttl_seconds).collect()
+    }
+
+    #[test]
+    fn push_frame__when_frames_carry_mdns_and_other_udp__keeps_mdns_with_offsets_from_first() {
+        let payload = response("printer.local", 120);
+        let start = OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+        let mut recording = ReplayRecording::new();
+
+        assert!(recording.push_frame(LinkType::RawIp, &frame(5353, 5353, &payload), start));
+        assert!(!recording.push_frame(LinkType::RawIp, &frame(40000, 53, &payload), start + time::Duration::milliseconds(100)));
+        assert!(recording.push_frame(LinkType::RawIp, &frame(40000, 5353, &payload), start + time::Duration::milliseconds(250)));
+
+        let packets = recording.into_packets();
+        assert_eq!(packets.len(), 2);
+        assert_eq!(packets[0].offset, Duration::ZERO);
+        assert_eq!(packets[1].offset, Duration::from_millis(250));
+        assert_eq!(packets[1].source, SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 20)), 40000));
+        assert_eq!(packets[1].ttl, Some(255));
+        assert_eq!(packets[1].payload, payload);
+    }
+
+    #[test]
+    fn push_frame__when_timestamp_goes_back__keeps_order_with_previous_offset() {
+        let payload = response("printer.local", 120);
+        let start = OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+        let mut recording = ReplayRecording::new();
+
+        recording.push_frame(LinkType::RawIp, &frame(5353, 5353, &payload), start);
+        recording.push_frame(LinkType::RawIp, &frame(5353, 5353, &payload), start + time::Duration::milliseconds(300));
+        recording.push_frame(LinkType::RawIp, &frame(5353, 5353, &payload), start + time::Duration::milliseconds(100));
+
+        let offsets: Vec<Duration> = recording.into_packets().iter().map(|p| p.offset).collect();
+        assert_eq!(offsets, vec![Duration::ZERO, Duration::from_millis(300), Duration::from_millis(300)]);
+    }
+
+    #[test]
+    fn schedule__when_original_timing__uses_captured_offsets() {
+        let packets = vec![packet(0), packet(40), packet(1000)];
+
+        let times = schedule(&packets, ReplayTiming::Original);
+
+        assert_eq!(times, vec![Duration::ZERO, Duration::from_millis(40), Duration::from_millis(1000)]);
+    }
+
+    #[test]
+    fn schedule__when_fixed_rate__spaces_packets_evenly() {
+        let packets = vec![packet(0), packet(40), packet(1000)];
+
+        let times = schedule(&packets, ReplayTiming::FixedRate(4.0));
+
+        assert_eq!(times, vec![Duration::ZERO, Duration::from_millis(250), Duration::from_millis(500)]);
+    }
+
+    #[test]
+    fn rewrite_payload__when_record_ttl_set__replaces_ttl_of_every_record() {
+        let payload = response("printer.local", 120);
+
+        let rewritten = rewrite_payload(&payload, Some(0));
+
+        assert_eq!(answer_ttls(&rewritten), vec![0]);
+    }
+
+    #[test]
+    fn rewrite_payload__when_payload_has_opt_and_multi_string_txt__changes_only_the_record_ttls() {
+        let payload: Vec<u8> = [
+            // Header: response with one answer and one additional record.
+            &[0, 0, 0x84, 0, 0, 0, 0, 1, 0, 0, 0, 1][..],
+            // TXT record of printer.local with the strings "a=1" and "b=2", TTL 4500.
+            &[7, b'p', b'r', b'i', b'n', b't', b'e', b'r', 5, b'l', b'o', b'c', b'a', b'l', 0],
+            &[0, 16, 0x80, 1, 0, 0, 0x11, 0x94, 0, 8, 3, b'a', b'=', b'1', 3, b'b', b'=', b'2'],
+            // OPT record for a 1440 byte payload with the DO bit set.
+            &[0, 0, 41, 0x05, 0xa0, 0, 0, 0x80, 0, 0, 0],
+        ].concat();
+
+        let rewritten = rewrite_payload(&payload, Some(60));
+
+        let mut expected = payload.clone();
+        // The TTL of the TXT record follows the name, type and class.
+        expected[31..35].copy_from_slice(&60u32.to_be_bytes());
+        assert_eq!(rewritten, expected);
+    }
+
+    #[test]
+    fn rewrite_payload__when_record_ttl_missing__returns_payload_unchanged() {
+        let payload = response("printer.local", 120);
+
+        assert_eq!(rewrite_payload(&payload, None), payload);
+        assert_eq!(rewrite_payload(&[1, 2, 3], Some(10)), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn replay_frame__when_ethernet_ipv4__addresses_group_mac_and_decodes_back() {
+        let payload = response("printer.local", 120);
+        let source = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 1, 2, 3)), 5353);
+        let group = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(224, 0, 0, 251)), 5353);
+        let source_mac = MacAddr::new(0x02, 0, 0, 0, 0, 0x01);
+
+        let frame = replay_frame(LinkType::Ethernet, source_mac, source, group, 1, &payload).unwrap();
+
+        assert_eq!(&frame[0..6], &[0x01, 0x00, 0x5e, 0x00, 0x00, 0xfb]);
+        assert_eq!(&frame[6..12], &source_mac.octets());
+        let datagram = udp_datagram(LinkType::Ethernet, &frame).unwrap();
+        assert_eq!(datagram.source, source);
+        assert_eq!(datagram.destination, group);
+        assert_eq!(datagram.ttl, Some(1));
+        assert_eq!(datagram.payload, payload.as_slice());
+    }
+
+    #[test]
+    fn replay_frame__when_ethernet_ipv6__addresses_group_mac() {
+        let source = SocketAddr::new(IpAddr::V6(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1)), 5353);
+        let group = SocketAddr::new(IpAddr::V6(Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 0xfb)), 5353);
+
+        let frame = replay_frame(LinkType::Ethernet, MacAddr::zero(), source, group, 255, &[0; 12]).unwrap();
+
+        assert_eq!(&frame[0..6], &[0x33, 0x33, 0x00, 0x00, 0x00, 0xfb]);
+        assert_eq!(&frame[12..14], &[0x86, 0xdd]);
+    }
+
+    /// Source and payload of the datagrams received by the listener until the deadline.
+    fn receive_for(listener: &mut SocketListener, duration: Duration) -> Vec<(SocketAddr, Vec<u8>)> {
+        let deadline = Instant::now() + duration;
+        let mut datagrams = vec![];
+        while Instant::now() < deadline {
+            if let Some(datagram) = listener.receive().unwrap() {
+                datagrams.push((datagram.source, datagram.payload));
+            }
+        }
+        datagrams
+    }
+
+    #[test]
+    fn start__when_sent_from_interface_on_loopback__group_receives_rewritten_payloads() {
+        let port = 53552;
+        let interface = match pnet::datalink::interfaces().into_iter().find(|i| i.is_loopback()) {
+            Some(i) => i,
+            None => return,
+        };
+        let mut listener = loopback_listener(port);
+        let options = ReplayOptions {
+            timing: ReplayTiming::FixedRate(50.0),
+            source: ReplaySource::Interface,
+            ip_ttl: None,
+            record_ttl: Some(30),
+        };
+
+        let mut replay = Replay::start(&interface, loopback_group(port), vec![packet(0), packet(5000)], options).unwrap();
+        let datagrams = receive_for(&mut listener, Duration::from_millis(500));
+
+        assert_eq!(datagrams.len(), 2);
+        assert!(datagrams.iter().all(|(_, payload)| answer_ttls(payload) == vec![30]));
+        let status = replay.status();
+        assert_eq!((status.sent, status.total, status.finished), (2, 2, true));
+        replay.stop();
+    }
+
+    #[test]
+    fn start__when_source_rewritten_on_loopback__group_receives_packet_from_new_address() {
+        let port = 53553;
+        let interface = match pnet::datalink::interfaces().into_iter().find(|i| i.is_loopback()) {
+            Some(i) => i,
+            None => return,
+        };
+        let mut listener = loopback_listener(port);
+        // Linux drops multicast received from loopback sources, a documentation address is used instead.
+        let options = ReplayOptions {
+            timing: ReplayTiming::Original,
+            source: ReplaySource::Address(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 9))),
+            ip_ttl: Some(255),
+            record_ttl: None,
+        };
+
+        let replay = match Replay::start(&interface, loopback_group(port), vec![packet(0)], options) {
+            Ok(r) => r,
+            // Writing to the link layer needs privileges the test environment may not have.
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => return,
+            Err(e) => panic!("{}", e),
+        };
+        let datagrams = receive_for(&mut listener, Duration::from_millis(300));
+
+        assert_eq!(datagrams, vec![(SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 9)), 5353), packet(0).payload)]);
+        assert_eq!(replay.status().sent, 1);
+    }
+}
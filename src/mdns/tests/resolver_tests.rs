@@ -0,0 +1,80 @@
+#[cfg(test)]
+pub mod resolver_tests {
+    use crate::mdns::mdns_message::MDNSMessage;
+    use crate::mdns::query_sender::SentQueryLog;
+    use crate::mdns::resolver::{resolve, Resolution};
+    use crate::mdns::tests::loopback::loopback::{announced_responder, loopback_config};
+    use crate::mdns::types::{MDNSAnswer, MDNSMessageHeader, MDNSQueryClass, MDNSRData, MDNSRecordType};
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+    use std::time::{Duration, Instant};
+    use time::OffsetDateTime;
+
+    fn address_response(name: &str) -> MDNSMessage {
+        MDNSMessage {
+            header: MDNSMessageHeader {
+                query_identifier: 0,
+                flags: 0x8400,
+                question_count: 0,
+                answer_count: 1,
+                authority_count: 0,
+                additional_count: 0,
+            },
+            questions: vec![],
+            answers: vec![MDNSAnswer {
+                name: name.to_string(),
+                answer_type: MDNSRecordType::A,
+                answer_class: MDNSQueryClass::IN,
+                cache_flush: true,
+                ttl_seconds: 120,
+                rd_length: 4,
+                rdata: MDNSRData::A { ipv4_address: Ipv4Addr::new(10, 0, 0, 7) },
+            }],
+            authorities: vec![],
+            additionals: vec![],
+        }
+    }
+
+    #[test]
+    fn resolve__when_responder_publishes_name__returns_its_addresses_with_responder_and_latency() {
+        let port = 53548;
+        let ipv6 = IpAddr::V6(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1));
+        let _responder = announced_responder(port, "printer.local", vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 7)), ipv6]);
+        let log = SentQueryLog::new();
+
+        let timeout = Duration::from_millis(500);
+        let resolution = resolve(&loopback_config(port), log, "PRINTER.local", timeout).unwrap();
+
+        assert_eq!(resolution.query_id, 1);
+        let addresses: Vec<IpAddr> = resolution.addresses.iter().map(|a| a.address).collect();
+        assert_eq!(addresses, vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 7)), ipv6]);
+        assert_eq!(resolution.addresses[0].responder, IpAddr::V4(Ipv4Addr::LOCALHOST));
+        // The answer is a legacy unicast response, its TTL is capped at ten seconds (RFC 6762 - 6.7).
+        assert_eq!(resolution.addresses[0].ttl_seconds, 10);
+        assert!(resolution.addresses[0].latency < timeout);
+    }
+
+    #[test]
+    fn resolve__when_nobody_answers__returns_no_address_after_timeout() {
+        let port = 53549;
+        let started = Instant::now();
+
+        let resolution = resolve(&loopback_config(port), SentQueryLog::new(), "nobody.local", Duration::from_millis(200)).unwrap();
+
+        assert!(resolution.addresses.is_empty());
+        assert!(started.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[test]
+    fn is_answered_by__when_response_arrives_late_within_the_timeout__matches_by_time_and_name() {
+        let sent = OffsetDateTime::from_unix_timestamp(1_733_047_200).unwrap();
+        let resolution = Resolution { name: "printer.local".to_string(), query_id: 1, sent, timeout: Duration::from_secs(30), addresses: vec![] };
+
+        let late = resolution.is_answered_by(&address_response("PRINTER.local"), sent + time::Duration::seconds(10));
+        let after_timeout = resolution.is_answered_by(&address_response("printer.local"), sent + time::Duration::seconds(31));
+        let other_name = resolution.is_answered_by(&address_response("scanner.local"), sent + time::Duration::seconds(1));
+
+        assert!(late);
+        assert!(!after_timeout);
+        assert!(!other_name);
+    }
+}
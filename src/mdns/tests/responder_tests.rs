@@ -0,0 +1,477 @@
+#[cfg(test)]
+pub mod responder_tests {
+    use crate::mdns::mdns_message::MDNSMessage;
+    use crate::mdns::parser::parse_mdns_message;
+    use crate::mdns::query_sender::{Query, QuerySender, SentQueryLog};
+    use crate::mdns::responder::{Destination, OutgoingMessage, PublishState, PublishedHost, PublishedRecords, PublishedService, Responder, ResponderCore};
+    use crate::mdns::socket_listener::{SocketListener, MDNS_PORT};
+    use crate::mdns::tests::loopback::loopback::{announced_responder, loopback_config, loopback_listener};
+    use crate::mdns::types::{MDNSAnswer, MDNSMessageHeader, MDNSQueryClass, MDNSQuestion, MDNSRData, MDNSRecordType};
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use std::time::{Duration, Instant};
+
+    fn host(name: &str, address: [u8; 4]) -> PublishedHost {
+        PublishedHost { name: name.to_string(), addresses: vec![IpAddr::V4(Ipv4Addr::from(address))] }
+    }
+
+    fn service(instance: &str, host: &str) -> PublishedService {
+        PublishedService {
+            instance: instance.to_string(),
+            service_type: "_http._tcp.local".to_string(),
+            host: host.to_string(),
+            port: 8080,
+            txt: "path=/".to_string(),
+        }
+    }
+
+    fn records(hosts: Vec<PublishedHost>, services: Vec<PublishedService>) -> PublishedRecords {
+        PublishedRecords { hosts, services }
+    }
+
+    fn a_record(name: &str, address: [u8; 4], ttl_seconds: u32) -> MDNSAnswer {
+        MDNSAnswer {
+            name: name.to_string(),
+            answer_type: MDNSRecordType::A,
+            answer_class: MDNSQueryClass::IN,
+            cache_flush: true,
+            ttl_seconds,
+            rd_length: 0,
+            rdata: MDNSRData::A { ipv4_address: Ipv4Addr::from(address) },
+        }
+    }
+
+    fn message(flags: u16, questions: Vec<MDNSQuestion>, answers: Vec<MDNSAnswer>, authorities: Vec<MDNSAnswer>) -> MDNSMessage {
+        MDNSMessage {
+            header: MDNSMessageHeader {
+                query_identifier: 0,
+                flags,
+                question_count: questions.len() as u16,
+                answer_count: answers.len() as u16,
+                authority_count: authorities.len() as u16,
+                additional_count: 0,
+            },
+            questions,
+            answers,
+            authorities,
+            additionals: vec![],
+        }
+    }
+
+    fn question(name: &str, question_type: MDNSRecordType, unicast_response: bool) -> MDNSQuestion {
+        MDNSQuestion { name: name.to_string(), question_type, question_class: MDNSQueryClass::IN, unicast_response }
+    }
+
+    fn querier() -> SocketAddr {
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 5)), MDNS_PORT)
+    }
+
+    /// A core which always picks the shortest of its random delays.
+    fn core() -> ResponderCore {
+        ResponderCore::with_random_delay(MDNS_PORT, |min, _| min)
+    }
+
+    /// Runs the core through probing and announcing, returns the time when it is done.
+    fn announce(core: &mut ResponderCore, published: PublishedRecords, start: Instant) -> Instant {
+        core.publish(published, start);
+        let mut now = start;
+        while core.status().iter().any(|s| s.state != PublishState::Announced) {
+            core.poll(now);
+            now += Duration::from_millis(250);
+        }
+        now
+    }
+
+    fn names(records: &[MDNSAnswer]) -> Vec<String> {
+        records.iter().map(|r| format!("{} {}", r.name, r.answer_type)).collect()
+    }
+
+    #[test]
+    fn poll__when_host_published__sends_three_probes_then_two_announcements() {
+        let mut core = core();
+        let start = Instant::now();
+        core.publish(records(vec![host("kitchen.local", [10, 0, 0, 1])], vec![]), start);
+
+        let first = core.poll(start);
+        let early = core.poll(start + Duration::from_millis(100));
+        let second = core.poll(start + Duration::from_millis(250));
+        let third = core.poll(start + Duration::from_millis(500));
+        let announcement = core.poll(start + Duration::from_millis(750));
+        let state_after_first_announcement = core.status()[0].state;
+        let second_announcement = core.poll(start + Duration::from_millis(1750));
+
+        let probe = &first[0].message;
+        assert!(!probe.header.is_response());
+        assert_eq!(probe.questions[0].name, "kitchen.local");
+        assert_eq!(probe.questions[0].question_type, MDNSRecordType::ANY);
+        assert!(probe.questions[0].unicast_response);
+        assert_eq!(probe.authorities[0].rdata.to_string(), "A (10.0.0.1)");
+        assert!(early.is_empty());
+        assert!(!second[0].message.questions[0].unicast_response);
+        assert_eq!(third.len(), 1);
+        assert!(announcement[0].message.header.is_response());
+        assert!(announcement[0].message.answers[0].cache_flush);
+        assert_eq!(announcement[0].message.answers[0].ttl_seconds, 120);
+        assert_eq!(announcement[0].destination, Destination::Multicast);
+        assert_eq!(state_after_first_announcement, PublishState::Announcing);
+        assert_eq!(second_announcement.len(), 1);
+        assert_eq!(core.status()[0].state, PublishState::Announced);
+    }
+
+    #[test]
+    fn poll__when_host_published__waits_the_random_delay_before_the_first_probe() {
+        let mut core = ResponderCore::with_random_delay(MDNS_PORT, |_, max| max);
+        let start = Instant::now();
+        core.publish(records(vec![host("kitchen.local", [10, 0, 0, 1])], vec![]), start);
+
+        let during_delay = core.poll(start + Duration::from_millis(249));
+        let after_delay = core.poll(start + Duration::from_millis(250));
+
+        assert!(during_delay.is_empty());
+        assert!(after_delay[0].message.questions[0].unicast_response);
+    }
+
+    #[test]
+    fn poll__when_service_published__announces_ptr_srv_txt_and_enumeration() {
+        let mut core = core();
+        let start = Instant::now();
+        core.publish(records(vec![], vec![service("Kitchen", "kitchen.local")]), start);
+        for step in 0..3 {
+            core.poll(start + Duration::from_millis(250 * step));
+        }
+
+        let announcement = core.poll(start + Duration::from_millis(750));
+
+        let answers = &announcement[0].message.answers;
+        assert_eq!(names(answers), vec![
+            "_http._tcp.local PTR",
+            "Kitchen._http._tcp.local SRV",
+            "Kitchen._http._tcp.local TXT",
+            "_services._dns-sd._udp.local PTR",
+        ]);
+        assert_eq!(answers[1].rdata.to_string(), "SRV (0, 0, 8080, kitchen.local)");
+        assert!(!answers[0].cache_flush);
+        assert!(answers[1].cache_flush);
+    }
+
+    #[test]
+    fn poll__when_instance_has_dot__escapes_it_in_the_record_names() {
+        let mut core = core();
+        let start = Instant::now();
+        core.publish(records(vec![], vec![service("Printer 2.1", "printer.local")]), start);
+        for step in 0..3 {
+            core.poll(start + Duration::from_millis(250 * step));
+        }
+
+        let announcement = core.poll(start + Duration::from_millis(750));
+
+        let answers = &announcement[0].message.answers;
+        assert_eq!(answers[0].rdata.to_string(), "PTR (Printer 2\\.1._http._tcp.local)");
+        assert_eq!(answers[1].name, "Printer 2\\.1._http._tcp.local");
+        assert_eq!(core.status()[0].name, "Printer 2\\.1._http._tcp.local");
+    }
+
+    #[test]
+    fn handle__when_response_claims_probed_name__renames_host() {
+        let mut core = core();
+        let start = Instant::now();
+        core.publish(records(vec![host("kitchen.local", [10, 0, 0, 1])], vec![]), start);
+        core.poll(start);
+
+        let now = start + Duration::from_millis(100);
+        core.handle(&message(0x8400, vec![], vec![a_record("kitchen.local", [10, 0, 0, 2], 120)], vec![]), querier(), now);
+        let probe = core.poll(now);
+
+        let status = &core.status()[0];
+        assert_eq!(status.requested, "kitchen.local");
+        assert_eq!(status.name, "kitchen-2.local");
+        assert_eq!(status.conflicts, 1);
+        assert_eq!(status.state, PublishState::Probing);
+        assert_eq!(probe[0].message.questions[0].name, "kitchen-2.local");
+    }
+
+    #[test]
+    fn handle__when_fifteen_conflicts_happen_within_ten_seconds__waits_five_seconds_before_probing_again() {
+        let mut core = core();
+        let mut now = Instant::now();
+        core.publish(records(vec![host("kitchen.local", [10, 0, 0, 1])], vec![]), now);
+        for _ in 0..15 {
+            core.poll(now);
+            let name = core.status()[0].name.clone();
+            core.handle(&message(0x8400, vec![], vec![a_record(&name, [10, 0, 0, 2], 120)], vec![]), querier(), now);
+            now += Duration::from_millis(100);
+        }
+        let last_conflict = now - Duration::from_millis(100);
+
+        let during_backoff = core.poll(last_conflict + Duration::from_millis(4999));
+        let after_backoff = core.poll(last_conflict + Duration::from_secs(5));
+
+        assert_eq!(core.status()[0].conflicts, 15);
+        assert!(during_backoff.is_empty());
+        assert_eq!(after_backoff[0].message.questions[0].name, "kitchen-16.local");
+    }
+
+    #[test]
+    fn handle__when_announced_srv_conflicts__renames_instance_and_probes_again() {
+        let mut core = core();
+        let now = announce(&mut core, records(vec![], vec![service("Kitchen", "kitchen.local")]), Instant::now());
+        let other_srv = MDNSAnswer {
+            name: "Kitchen._http._tcp.local".to_string(),
+            answer_type: MDNSRecordType::SRV,
+            answer_class: MDNSQueryClass::IN,
+            cache_flush: true,
+            ttl_seconds: 120,
+            rd_length: 0,
+            rdata: MDNSRData::SRV { priority: 0, weight: 0, port: 80, target_domain_name: "other.local".to_string() },
+        };
+
+        core.handle(&message(0x8400, vec![], vec![other_srv], vec![]), querier(), now);
+
+        let status = &core.status()[0];
+        assert_eq!(status.name, "Kitchen (2)._http._tcp.local");
+        assert_eq!(status.state, PublishState::Probing);
+    }
+
+    #[test]
+    fn handle__when_own_records_loop_back__keeps_names() {
+        let mut core = core();
+        let start = Instant::now();
+        core.publish(records(vec![host("kitchen.local", [10, 0, 0, 1])], vec![]), start);
+        let probe = core.poll(start).remove(0).message;
+        core.handle(&probe, querier(), start);
+        let now = announce(&mut core, records(vec![host("kitchen.local", [10, 0, 0, 1])], vec![]), start);
+        let announcement = message(0x8400, vec![], vec![a_record("kitchen.local", [10, 0, 0, 1], 120)], vec![]);
+
+        core.handle(&announcement, querier(), now);
+
+        let status = &core.status()[0];
+        assert_eq!(status.name, "kitchen.local");
+        assert_eq!(status.conflicts, 0);
+        assert_eq!(status.state, PublishState::Announced);
+    }
+
+    #[test]
+    fn handle__when_simultaneous_probe_has_later_data__waits_a_second_before_probing_again() {
+        let mut core = core();
+        let start = Instant::now();
+        core.publish(records(vec![host("kitchen.local", [10, 0, 0, 1])], vec![]), start);
+        core.poll(start);
+        let other_probe = message(0, vec![question("kitchen.local", MDNSRecordType::ANY, true)], vec![], vec![a_record("kitchen.local", [10, 0, 0, 9], 120)]);
+
+        core.handle(&other_probe, querier(), start + Duration::from_millis(10));
+        let during_delay = core.poll(start + Duration::from_millis(250));
+        let after_delay = core.poll(start + Duration::from_millis(1010));
+
+        assert!(during_delay.is_empty());
+        assert!(after_delay[0].message.questions[0].unicast_response);
+        assert_eq!(core.status()[0].name, "kitchen.local");
+    }
+
+    #[test]
+    fn handle__when_simultaneous_probe_has_earlier_data__keeps_probing() {
+        let mut core = core();
+        let start = Instant::now();
+        core.publish(records(vec![host("kitchen.local", [10, 0, 0, 5])], vec![]), start);
+        core.poll(start);
+        let other_probe = message(0, vec![question("kitchen.local", MDNSRecordType::ANY, true)], vec![], vec![a_record("kitchen.local", [10, 0, 0, 1], 120)]);
+
+        core.handle(&other_probe, querier(), start + Duration::from_millis(10));
+        let next_probe = core.poll(start + Duration::from_millis(250));
+
+        assert_eq!(next_probe.len(), 1);
+    }
+
+    #[test]
+    fn handle__when_question_has_qu_bit__answers_by_unicast_with_additional_records() {
+        let mut core = core();
+        let published = records(vec![host("kitchen.local", [10, 0, 0, 1])], vec![service("Kitchen", "kitchen.local")]);
+        let now = announce(&mut core, published, Instant::now());
+        let query = message(0, vec![question("_http._tcp.local", MDNSRecordType::PTR, true)], vec![], vec![]);
+
+        let outgoing = core.handle(&query, querier(), now);
+
+        assert_eq!(outgoing.len(), 1);
+        assert_eq!(outgoing[0].destination, Destination::Unicast(querier()));
+        let response = &outgoing[0].message;
+        assert_eq!(names(&response.answers), vec!["_http._tcp.local PTR"]);
+        assert_eq!(names(&response.additionals), vec![
+            "Kitchen._http._tcp.local SRV",
+            "Kitchen._http._tcp.local TXT",
+            "kitchen.local A",
+        ]);
+        assert_eq!(response.header.additional_count, 3);
+    }
+
+    #[test]
+    fn handle__when_question_is_multicast__answers_to_the_group() {
+        let mut core = core();
+        let now = announce(&mut core, records(vec![host("kitchen.local", [10, 0, 0, 1])], vec![]), Instant::now());
+        let query = message(0, vec![question("KITCHEN.local", MDNSRecordType::A, false)], vec![], vec![]);
+
+        let outgoing = core.handle(&query, querier(), now);
+
+        assert_eq!(outgoing[0].destination, Destination::Multicast);
+        assert_eq!(outgoing[0].message.answers[0].rdata.to_string(), "A (10.0.0.1)");
+    }
+
+    #[test]
+    fn handle__when_multicast_question_asks_for_shared_record__answers_after_the_delay_together_with_later_answers() {
+        let mut core = core();
+        let published = records(vec![host("kitchen.local", [10, 0, 0, 1])], vec![service("Kitchen", "kitchen.local")]);
+        let now = announce(&mut core, published, Instant::now());
+        let browse = message(0, vec![question("_http._tcp.local", MDNSRecordType::PTR, false)], vec![], vec![]);
+        let enumerate = message(0, vec![question("_services._dns-sd._udp.local", MDNSRecordType::PTR, false)], vec![], vec![]);
+
+        let first = core.handle(&browse, querier(), now);
+        let second = core.handle(&enumerate, querier(), now + Duration::from_millis(10));
+        let before_delay = core.poll(now + Duration::from_millis(19));
+        let after_delay = core.poll(now + Duration::from_millis(20));
+
+        assert!(first.is_empty());
+        assert!(second.is_empty());
+        assert!(before_delay.is_empty());
+        assert_eq!(after_delay.len(), 1);
+        assert_eq!(after_delay[0].destination, Destination::Multicast);
+        assert_eq!(names(&after_delay[0].message.answers), vec!["_http._tcp.local PTR", "_services._dns-sd._udp.local PTR"]);
+        assert_eq!(after_delay[0].message.header.additional_count, 3);
+    }
+
+    #[test]
+    fn handle__when_known_answer_has_half_the_ttl__suppresses_the_answer() {
+        let mut core = core();
+        let now = announce(&mut core, records(vec![host("kitchen.local", [10, 0, 0, 1])], vec![]), Instant::now());
+        let fresh = message(0, vec![question("kitchen.local", MDNSRecordType::A, false)], vec![a_record("kitchen.local", [10, 0, 0, 1], 60)], vec![]);
+        let stale = message(0, vec![question("kitchen.local", MDNSRecordType::A, false)], vec![a_record("kitchen.local", [10, 0, 0, 1], 59)], vec![]);
+
+        let suppressed = core.handle(&fresh, querier(), now);
+        let answered = core.handle(&stale, querier(), now);
+
+        assert!(suppressed.is_empty());
+        assert_eq!(answered.len(), 1);
+    }
+
+    #[test]
+    fn handle__when_query_comes_from_another_port__sends_legacy_unicast_response() {
+        let mut core = core();
+        let now = announce(&mut core, records(vec![host("kitchen.local", [10, 0, 0, 1])], vec![]), Instant::now());
+        let mut query = message(0, vec![question("kitchen.local", MDNSRecordType::A, false)], vec![], vec![]);
+        query.header.query_identifier = 0x1234;
+        let resolver = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 5)), 40000);
+
+        let outgoing = core.handle(&query, resolver, now);
+
+        assert_eq!(outgoing[0].destination, Destination::Unicast(resolver));
+        let response = &outgoing[0].message;
+        assert_eq!(response.header.query_identifier, 0x1234);
+        assert_eq!(response.questions[0].name, "kitchen.local");
+        assert_eq!(response.answers[0].ttl_seconds, 10);
+        assert!(!response.answers[0].cache_flush);
+    }
+
+    #[test]
+    fn handle__when_name_is_still_probed__does_not_answer() {
+        let mut core = core();
+        let start = Instant::now();
+        core.publish(records(vec![host("kitchen.local", [10, 0, 0, 1])], vec![]), start);
+        core.poll(start);
+        let query = message(0, vec![question("kitchen.local", MDNSRecordType::A, false)], vec![], vec![]);
+
+        let outgoing = core.handle(&query, querier(), start);
+
+        assert!(outgoing.is_empty());
+    }
+
+    #[test]
+    fn publish__when_service_removed__sends_goodbye_for_its_records_only() {
+        let mut core = core();
+        let kitchen = service("Kitchen", "kitchen.local");
+        let hall = service("Hall", "kitchen.local");
+        let now = announce(&mut core, records(vec![], vec![kitchen.clone(), hall]), Instant::now());
+
+        let outgoing: Vec<OutgoingMessage> = core.publish(records(vec![], vec![kitchen]), now);
+
+        let goodbye = &outgoing[0].message;
+        assert_eq!(outgoing[0].destination, Destination::Multicast);
+        assert!(goodbye.header.is_response());
+        // The enumeration PTR is still published for Kitchen.
+        assert_eq!(names(&goodbye.answers), vec![
+            "_http._tcp.local PTR",
+            "Hall._http._tcp.local SRV",
+            "Hall._http._tcp.local TXT",
+        ]);
+        assert!(goodbye.answers.iter().all(|a| a.ttl_seconds == 0));
+        assert_eq!(core.status().len(), 1);
+    }
+
+    fn wait_until_announced(responder: &Responder) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            let status = responder.status();
+            if !status.is_empty() && status.iter().all(|s| s.state == PublishState::Announced) {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        panic!("Records were not announced in time.");
+    }
+
+    /// Messages received by the listener until the deadline.
+    fn receive_for(listener: &mut SocketListener, duration: Duration) -> Vec<MDNSMessage> {
+        let deadline = Instant::now() + duration;
+        let mut messages = vec![];
+        while Instant::now() < deadline {
+            if let Some(datagram) = listener.receive().unwrap()
+                && let Ok(message) = parse_mdns_message(&datagram.payload) {
+                messages.push(message);
+            }
+        }
+        messages
+    }
+
+    #[test]
+    fn start__when_two_instances_run_on_loopback__both_answer_a_browse_query() {
+        let port = 53545;
+        let alpha = Responder::start(&loopback_config(port), records(vec![host("alpha.local", [127, 0, 0, 1])], vec![service("Alpha", "alpha.local")])).unwrap();
+        let beta = Responder::start(&loopback_config(port), records(vec![host("beta.local", [127, 0, 0, 1])], vec![service("Beta", "beta.local")])).unwrap();
+        wait_until_announced(&alpha);
+        wait_until_announced(&beta);
+        let sender = QuerySender::open(&loopback_config(port), SentQueryLog::new()).unwrap();
+
+        sender.send(&Query::new("_http._tcp.local", MDNSRecordType::PTR, false)).unwrap();
+
+        // The query comes from an ephemeral port, so the instances answer it by unicast.
+        let instances: Vec<String> = (0..25).filter_map(|_| sender.receive(Duration::from_millis(20)).unwrap())
+            .filter_map(|(payload, _)| parse_mdns_message(&payload).ok())
+            .filter(|m| m.header.is_response())
+            .flat_map(|m| m.answers.into_iter().map(|a| a.rdata.to_string()))
+            .collect();
+        assert!(instances.contains(&"PTR (Alpha._http._tcp.local)".to_string()));
+        assert!(instances.contains(&"PTR (Beta._http._tcp.local)".to_string()));
+    }
+
+    #[test]
+    fn start__when_second_instance_claims_published_host_name__renames_it() {
+        let port = 53546;
+        let first = announced_responder(port, "shared.local", vec![IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1))]);
+
+        let second = announced_responder(port, "shared.local", vec![IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2))]);
+
+        assert_eq!(first.status()[0].name, "shared.local");
+        assert_eq!(second.status()[0].name, "shared-2.local");
+        assert_eq!(second.status()[0].conflicts, 1);
+    }
+
+    #[test]
+    fn stop__when_records_were_announced__sends_goodbye() {
+        let port = 53547;
+        let mut responder = announced_responder(port, "leaving.local", vec![IpAddr::V4(Ipv4Addr::LOCALHOST)]);
+        let mut listener = loopback_listener(port);
+
+        responder.stop();
+
+        let goodbye = receive_for(&mut listener, Duration::from_millis(300)).into_iter()
+            .find(|m| m.header.is_response())
+            .expect("No goodbye received.");
+        assert_eq!(goodbye.answers[0].name, "leaving.local");
+        assert_eq!(goodbye.answers[0].ttl_seconds, 0);
+    }
+}
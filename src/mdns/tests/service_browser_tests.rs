@@ -0,0 +1,145 @@
+#[cfg(test)]
+pub mod service_browser_tests {
+    use crate::mdns::encoder::encode_mdns_message;
+    use crate::mdns::mdns_message::MDNSMessage;
+    use crate::mdns::parser::parse_mdns_message;
+    use crate::mdns::service_browser::{enumerated_service_types, BrowseEventKind, ServiceBrowser, SERVICE_TYPE_ENUMERATION};
+    use crate::mdns::types::{MDNSAnswer, MDNSMessageHeader, MDNSQueryClass, MDNSRData, MDNSRecordType};
+    use std::net::{IpAddr, Ipv4Addr};
+    use time::{Duration, OffsetDateTime};
+
+    const START: i64 = 1_733_047_200;
+    const SERVICE_TYPE: &str = "_googlecast._tcp.local";
+    const INSTANCE: &str = "Kitchen._googlecast._tcp.local";
+    const RESPONDER: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 20));
+
+    fn at(seconds: i64) -> OffsetDateTime {
+        OffsetDateTime::from_unix_timestamp(START + seconds).unwrap()
+    }
+
+    fn record(name: &str, answer_type: MDNSRecordType, ttl_seconds: u32, rdata: MDNSRData) -> MDNSAnswer {
+        MDNSAnswer {
+            name: name.to_string(),
+            answer_type,
+            answer_class: MDNSQueryClass::IN,
+            cache_flush: false,
+            ttl_seconds,
+            rd_length: 0,
+            rdata,
+        }
+    }
+
+    fn ptr(ttl_seconds: u32) -> MDNSAnswer {
+        record(SERVICE_TYPE, MDNSRecordType::PTR, ttl_seconds, MDNSRData::PTR { domain_name: INSTANCE.to_string() })
+    }
+
+    /// A response with the PTR answer and the SRV, TXT and A records in the additional section, as responders send it.
+    /// It is encoded and parsed again, so the browser sees what the parser makes of the wire format.
+    fn response(answers: Vec<MDNSAnswer>, additionals: Vec<MDNSAnswer>) -> MDNSMessage {
+        let message = MDNSMessage {
+            header: MDNSMessageHeader {
+                query_identifier: 0,
+                flags: 0x8400,
+                question_count: 0,
+                answer_count: 0,
+                authority_count: 0,
+                additional_count: 0,
+            },
+            questions: vec![],
+            answers,
+            authorities: vec![],
+            additionals,
+        };
+        parse_mdns_message(&encode_mdns_message(&message).unwrap()).unwrap()
+    }
+
+    fn full_response() -> MDNSMessage {
+        response(vec![ptr(4500)], vec![
+            record(INSTANCE, MDNSRecordType::SRV, 120, MDNSRData::SRV { priority: 0, weight: 0, port: 8009, target_domain_name: "kitchen.local".to_string() }),
+            record(INSTANCE, MDNSRecordType::TXT, 4500, MDNSRData::TXT { text: "id=1\nfn=Kitchen".to_string() }),
+            record("kitchen.local", MDNSRecordType::A, 120, MDNSRData::A { ipv4_address: Ipv4Addr::new(192, 168, 1, 20) }),
+        ])
+    }
+
+    #[test]
+    fn observe__when_response_has_additional_records__resolves_instance() {
+        let mut browser = ServiceBrowser::new(SERVICE_TYPE, at(0));
+
+        browser.observe(&full_response(), at(0), RESPONDER);
+
+        let instance = browser.instances().next().unwrap();
+        assert_eq!(browser.instance_label(instance), "Kitchen");
+        assert_eq!(instance.host.as_deref(), Some("kitchen.local"));
+        assert_eq!(instance.port, Some(8009));
+        assert_eq!(instance.txt.as_deref(), Some("id=1\nfn=Kitchen"));
+        assert_eq!(instance.addresses, vec![RESPONDER]);
+        assert!(instance.is_resolved());
+        let kinds: Vec<BrowseEventKind> = browser.events().iter().map(|e| e.kind).collect();
+        assert_eq!(kinds, vec![BrowseEventKind::Added, BrowseEventKind::Resolved]);
+    }
+
+    #[test]
+    fn observe__when_goodbye_received__removes_instance() {
+        let mut browser = ServiceBrowser::new(SERVICE_TYPE, at(0));
+        browser.observe(&full_response(), at(0), RESPONDER);
+
+        browser.observe(&response(vec![ptr(0)], vec![]), at(5), RESPONDER);
+
+        assert_eq!(browser.instances().count(), 0);
+        assert_eq!(browser.events().back().unwrap().kind, BrowseEventKind::Removed);
+    }
+
+    #[test]
+    fn expire__when_ttl_passed__removes_instance() {
+        let mut browser = ServiceBrowser::new(SERVICE_TYPE, at(0));
+        browser.observe(&response(vec![ptr(120)], vec![]), at(0), RESPONDER);
+
+        browser.expire(at(119));
+        let before = browser.instances().count();
+        browser.expire(at(120));
+
+        assert_eq!(before, 1);
+        assert_eq!(browser.instances().count(), 0);
+        assert_eq!(browser.events().back().unwrap().kind, BrowseEventKind::Expired);
+    }
+
+    #[test]
+    fn due_queries__when_instance_unresolved__asks_for_missing_records() {
+        let mut browser = ServiceBrowser::new(SERVICE_TYPE, at(0));
+        browser.observe(&response(vec![ptr(4500)], vec![]), at(0), RESPONDER);
+
+        let queries = browser.due_queries(at(0));
+
+        assert_eq!(queries.len(), 2);
+        assert_eq!(queries[0].questions[0].name, SERVICE_TYPE);
+        assert_eq!(queries[0].known_answers.len(), 1);
+        let resolve: Vec<MDNSRecordType> = queries[1].questions.iter().map(|q| q.question_type).collect();
+        assert_eq!(resolve, vec![MDNSRecordType::SRV, MDNSRecordType::TXT]);
+    }
+
+    #[test]
+    fn due_queries__when_called_repeatedly__doubles_browse_interval() {
+        let mut browser = ServiceBrowser::new(SERVICE_TYPE, at(0));
+
+        let due: Vec<i64> = (0..8).filter(|s| !browser.due_queries(at(*s)).is_empty()).collect();
+
+        assert_eq!(due, vec![0, 1, 3, 7]);
+    }
+
+    #[test]
+    fn due_queries__when_instance_resolved__sends_no_resolve_query() {
+        let mut browser = ServiceBrowser::new(SERVICE_TYPE, at(0));
+        browser.observe(&full_response(), at(0), RESPONDER);
+
+        let queries = browser.due_queries(at(0) + Duration::milliseconds(10));
+
+        assert_eq!(queries.len(), 1);
+    }
+
+    #[test]
+    fn enumerated_service_types__when_enumeration_response__returns_types() {
+        let message = response(vec![record(SERVICE_TYPE_ENUMERATION, MDNSRecordType::PTR, 4500, MDNSRData::PTR { domain_name: SERVICE_TYPE.to_string() })], vec![]);
+
+        assert_eq!(enumerated_service_types(&message), vec![SERVICE_TYPE.to_string()]);
+    }
+}
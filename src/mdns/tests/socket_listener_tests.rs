@@ -0,0 +1,117 @@
+#[cfg(test)]
+pub mod socket_listener_tests {
+    use crate::mdns::capture::{decode_frame, LinkType};
+    use crate::mdns::packet_builder::build_udp_packet;
+    use crate::mdns::packet_source::{PacketSource, SocketPacketSource};
+    use crate::mdns::mdns_message;
+    use crate::mdns::socket_listener::{MulticastGroup, SocketListener, LLMNR_IPV4_GROUP, MDNS_IPV4_GROUP};
+    use crate::mdns::tests::loopback::loopback::loopback_listener_config;
+    use crate::mdns::tests::parser_tests::parser_tests::RESOLVE_SPOTIFY_MDNS_PAYLOAD;
+    use pnet::packet::ipv4::Ipv4Packet;
+    use pnet::packet::udp::UdpPacket;
+    use pnet::packet::Packet;
+    use socket2::{Domain, Protocol, Socket, Type};
+    use std::io;
+    use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+    use std::sync::Arc;
+    use time::OffsetDateTime;
+
+    fn loopback_sender() -> UdpSocket {
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP)).unwrap();
+        socket.set_multicast_if_v4(&Ipv4Addr::LOCALHOST).unwrap();
+        socket.set_multicast_loop_v4(true).unwrap();
+        socket.bind(&SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0)).into()).unwrap();
+        socket.into()
+    }
+
+    #[test]
+    fn receive__when_multicast_sent_on_loopback__returns_datagram_with_destination_group() {
+        let port = 53531;
+        let mut listener = SocketListener::open(&loopback_listener_config(port)).unwrap();
+        let sender = loopback_sender();
+        let sender_address = sender.local_addr().unwrap();
+
+        sender.send_to(&RESOLVE_SPOTIFY_MDNS_PAYLOAD, SocketAddrV4::new(MDNS_IPV4_GROUP, port)).unwrap();
+
+        let datagram = (0..20).find_map(|_| listener.receive().unwrap()).expect("No datagram received.");
+        assert_eq!(datagram.payload, RESOLVE_SPOTIFY_MDNS_PAYLOAD);
+        assert_eq!(datagram.source, sender_address);
+        assert_eq!(datagram.destination, SocketAddr::V4(SocketAddrV4::new(MDNS_IPV4_GROUP, port)));
+        assert!(datagram.ttl.is_some());
+    }
+
+    #[test]
+    fn receive__when_multicast_sent_on_loopback__reports_the_loopback_interface() {
+        let port = 53556;
+        let mut listener = SocketListener::open(&loopback_listener_config(port)).unwrap();
+        let sender = loopback_sender();
+        let loopback_index = pnet::datalink::interfaces().into_iter().find(|i| i.is_loopback()).map(|i| i.index);
+
+        sender.send_to(&RESOLVE_SPOTIFY_MDNS_PAYLOAD, SocketAddrV4::new(MDNS_IPV4_GROUP, port)).unwrap();
+
+        let datagram = (0..20).find_map(|_| listener.receive().unwrap()).expect("No datagram received.");
+        assert_eq!(datagram.interface_index, loopback_index);
+    }
+
+    #[test]
+    fn next_frame__when_multicast_sent_on_loopback__returns_decodable_raw_ip_frame() {
+        let port = 53533;
+        let mut source = SocketPacketSource::open(&loopback_listener_config(port)).unwrap();
+        let sender = loopback_sender();
+        let sender_address = sender.local_addr().unwrap();
+
+        sender.send_to(&RESOLVE_SPOTIFY_MDNS_PAYLOAD, SocketAddrV4::new(MDNS_IPV4_GROUP, port)).unwrap();
+
+        let frame = loop {
+            match source.next_frame() {
+                Ok(Some(f)) => break f,
+                Ok(None) => panic!("Socket source ended."),
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => continue,
+                Err(e) => panic!("{}", e),
+            }
+        };
+        assert_eq!(frame.link_type, LinkType::RawIp);
+        // The test port is not decoded as mDNS, so the synthesized headers are checked directly.
+        let ip_packet = Ipv4Packet::new(frame.data).unwrap();
+        assert_eq!(ip_packet.get_source(), Ipv4Addr::LOCALHOST);
+        assert_eq!(ip_packet.get_destination(), MDNS_IPV4_GROUP);
+        let udp_packet = UdpPacket::new(ip_packet.payload()).unwrap();
+        assert_eq!(udp_packet.get_source(), sender_address.port());
+        assert_eq!(udp_packet.get_destination(), port);
+        assert_eq!(udp_packet.payload(), RESOLVE_SPOTIFY_MDNS_PAYLOAD);
+    }
+
+    #[test]
+    fn receive__when_nothing_sent__returns_none() {
+        let mut listener = SocketListener::open(&loopback_listener_config(53532)).unwrap();
+
+        assert!(listener.receive().unwrap().is_none());
+    }
+
+    #[test]
+    fn build_udp_packet__when_decoded_as_raw_ip__returns_original_message() {
+        let source = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 10), 5353));
+        let destination = SocketAddr::V4(SocketAddrV4::new(MDNS_IPV4_GROUP, 5353));
+
+        let packet = build_udp_packet(source, destination, 255, &RESOLVE_SPOTIFY_MDNS_PAYLOAD).unwrap();
+        let event = decode_frame(LinkType::RawIp, &packet, OffsetDateTime::now_utc(), &Arc::from("eth0")).unwrap();
+
+        assert_eq!(event.protocol, mdns_message::Protocol::Mdns);
+        assert_eq!(event.message.questions[0].name, "_spotify-connect._tcp.local");
+        assert_eq!(event.source_ip, source.ip());
+        assert_eq!(event.destination_ip, destination.ip());
+    }
+
+    #[test]
+    fn receive__when_sent_to_second_group__returns_datagram_with_its_destination() {
+        let mut config = loopback_listener_config(53534);
+        config.groups.push(MulticastGroup { port: 53535, ..MulticastGroup::LLMNR });
+        let mut listener = SocketListener::open(&config).unwrap();
+        let sender = loopback_sender();
+
+        sender.send_to(&RESOLVE_SPOTIFY_MDNS_PAYLOAD, SocketAddrV4::new(LLMNR_IPV4_GROUP, 53535)).unwrap();
+
+        let datagram = (0..20).find_map(|_| listener.receive().unwrap()).expect("No datagram received.");
+        assert_eq!(datagram.destination, SocketAddr::V4(SocketAddrV4::new(LLMNR_IPV4_GROUP, 53535)));
+    }
+}
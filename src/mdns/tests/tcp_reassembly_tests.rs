@@ -0,0 +1,110 @@
+#[cfg(test)]
+pub mod tcp_reassembly_tests {
+    use crate::mdns::tcp_reassembly::{StreamKey, TcpReassembler, TcpSegment};
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+    const SEQUENCE: u32 = 1000;
+
+    fn key() -> StreamKey {
+        StreamKey {
+            source: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10)), 40000),
+            destination: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1)), 53),
+        }
+    }
+
+    fn segment(sequence: u32, payload: &[u8]) -> TcpSegment<'_> {
+        TcpSegment { sequence, syn: false, closes: false, payload }
+    }
+
+    fn syn(sequence: u32) -> TcpSegment<'static> {
+        TcpSegment { sequence, syn: true, closes: false, payload: &[] }
+    }
+
+    fn with_length_prefix(message: &[u8]) -> Vec<u8> {
+        let mut data = (message.len() as u16).to_be_bytes().to_vec();
+        data.extend_from_slice(message);
+        data
+    }
+
+    #[test]
+    fn push__when_message_split_across_segments__returns_it_once_complete() {
+        let data = with_length_prefix(&[1, 2, 3, 4, 5]);
+        let mut reassembler = TcpReassembler::new();
+        reassembler.push(key(), syn(SEQUENCE - 1));
+
+        let first = reassembler.push(key(), segment(SEQUENCE, &data[..3]));
+        let second = reassembler.push(key(), segment(SEQUENCE + 3, &data[3..]));
+
+        assert!(first.is_empty());
+        assert_eq!(second, vec![vec![1, 2, 3, 4, 5]]);
+    }
+
+    #[test]
+    fn push__when_segment_holds_two_messages__returns_both() {
+        let mut data = with_length_prefix(&[1, 2]);
+        data.extend(with_length_prefix(&[3]));
+        let mut reassembler = TcpReassembler::new();
+        reassembler.push(key(), syn(SEQUENCE - 1));
+
+        let messages = reassembler.push(key(), segment(SEQUENCE, &data));
+
+        assert_eq!(messages, vec![vec![1, 2], vec![3]]);
+    }
+
+    #[test]
+    fn push__when_segment_retransmitted__skips_the_repeated_bytes() {
+        let data = with_length_prefix(&[1, 2, 3, 4]);
+        let mut reassembler = TcpReassembler::new();
+        reassembler.push(key(), syn(SEQUENCE - 1));
+        reassembler.push(key(), segment(SEQUENCE, &data[..3]));
+
+        reassembler.push(key(), segment(SEQUENCE, &data[..3]));
+        let messages = reassembler.push(key(), segment(SEQUENCE + 1, &data[1..]));
+
+        assert_eq!(messages, vec![vec![1, 2, 3, 4]]);
+    }
+
+    #[test]
+    fn push__when_segment_missing__drops_data_until_the_next_syn() {
+        // Read from the middle, the last three bytes look like a message of its own.
+        let data = with_length_prefix(&[1, 2, 0, 1, 7]);
+        let next = with_length_prefix(&[9]);
+        let mut reassembler = TcpReassembler::new();
+        reassembler.push(key(), syn(SEQUENCE - 1));
+        reassembler.push(key(), segment(SEQUENCE, &data[..2]));
+
+        let after_gap = reassembler.push(key(), segment(SEQUENCE + 4, &data[4..]));
+        let following = reassembler.push(key(), segment(SEQUENCE + 8, &next));
+        reassembler.push(key(), syn(2 * SEQUENCE - 1));
+        let after_syn = reassembler.push(key(), segment(2 * SEQUENCE, &next));
+
+        assert!(after_gap.is_empty());
+        assert!(following.is_empty());
+        assert_eq!(after_syn, vec![vec![9]]);
+    }
+
+    #[test]
+    fn push__when_stream_first_seen_without_syn__drops_its_data() {
+        let data = with_length_prefix(&[1, 2, 0, 1, 7]);
+        let mut reassembler = TcpReassembler::new();
+
+        let messages = reassembler.push(key(), segment(SEQUENCE, &data[4..]));
+        let next = reassembler.push(key(), segment(SEQUENCE + 3, &with_length_prefix(&[9])));
+
+        assert!(messages.is_empty());
+        assert!(next.is_empty());
+    }
+
+    #[test]
+    fn push__when_stream_closed__forgets_buffered_data() {
+        let data = with_length_prefix(&[1, 2, 3, 4]);
+        let mut reassembler = TcpReassembler::new();
+        reassembler.push(key(), syn(SEQUENCE - 1));
+        reassembler.push(key(), segment(SEQUENCE, &data[..3]));
+
+        reassembler.push(key(), TcpSegment { sequence: SEQUENCE + 3, syn: false, closes: true, payload: &[] });
+        let messages = reassembler.push(key(), segment(SEQUENCE + 3, &data[3..]));
+
+        assert!(messages.is_empty());
+    }
+}
@@ -54,7 +54,7 @@ pub enum MDNSRData {
     AAAA { ipv6_addr: Ipv6Addr }, // Maps a hostname to an IPv6 address
     PTR { domain_name: String }, // Service discovery
     SRV { priority: u16, weight: u16, port: u16, target_domain_name: String }, // Service instance details
-    TXT { text: String }, // Service metadata TODO: This is often key-value pair
+    TXT { text: String }, // Service metadata, one line per string. These are often key=value pairs (RFC 6763 - 6.3)
     CNAME { canonical_domain_name: String }, // Alias for a domain name
     NSEC { raw: Vec<u8> }, // Next domain name, Bitmap indicating available record types TODO: implement strongly typed NSEC data
     ANY { raw: Vec<u8> }, // No specific RDATA; used in queries.
@@ -68,7 +68,7 @@ impl Display for MDNSRData {
             MDNSRData::AAAA { ipv6_addr } => { write!(f, "AAAA ({})", ipv6_addr) }
             MDNSRData::PTR { domain_name } => { write!(f, "PTR ({})", domain_name) }
             MDNSRData::SRV { priority, weight, port, target_domain_name } => { write!(f, "SRV ({}, {}, {}, {})", priority, weight, port, target_domain_name) }
-            MDNSRData::TXT { text } => { write!(f, "TXT ({})", text) }
+            MDNSRData::TXT { text } => { write!(f, "TXT ({})", text.replace('\n', ", ")) }
             MDNSRData::CNAME { canonical_domain_name } => { write!(f, "CNAME ({})", canonical_domain_name) }
             MDNSRData::NSEC { .. } => { write!(f, "NSEC") }
             MDNSRData::ANY { .. } => { write!(f, "ANY") }
@@ -77,7 +77,69 @@ impl Display for MDNSRData {
     }
 }
 
+impl MDNSRData {
+    /// Reads record data of the given type as it is written in zone files, e.g. `0 0 8009 host.local` for SRV.
+    /// TXT data is taken as is, every line is one string.
+    pub fn from_text(record_type: MDNSRecordType, text: &str) -> Result<MDNSRData, String> {
+        let text = text.trim();
+        match record_type {
+            MDNSRecordType::A => text.parse().map(|ipv4_address| MDNSRData::A { ipv4_address }).map_err(|_| format!("{} is not an IPv4 address.", text)),
+            MDNSRecordType::AAAA => text.parse().map(|ipv6_addr| MDNSRData::AAAA { ipv6_addr }).map_err(|_| format!("{} is not an IPv6 address.", text)),
+            MDNSRecordType::PTR | MDNSRecordType::CNAME if text.is_empty() => Err("The name is empty.".to_string()),
+            MDNSRecordType::PTR => Ok(MDNSRData::PTR { domain_name: text.to_string() }),
+            MDNSRecordType::CNAME => Ok(MDNSRData::CNAME { canonical_domain_name: text.to_string() }),
+            MDNSRecordType::SRV => {
+                let fields: Vec<&str> = text.split_whitespace().collect();
+                let number = |field: &str| field.parse::<u16>().map_err(|_| format!("{} is not a number between 0 and 65535.", field));
+                match fields[..] {
+                    [priority, weight, port, target] => Ok(MDNSRData::SRV {
+                        priority: number(priority)?,
+                        weight: number(weight)?,
+                        port: number(port)?,
+                        target_domain_name: target.to_string(),
+                    }),
+                    _ => Err("SRV data is written as: priority weight port target.".to_string()),
+                }
+            }
+            MDNSRecordType::TXT => Ok(MDNSRData::TXT { text: text.to_string() }),
+            _ => Err(format!("{} records cannot be written as text.", record_type)),
+        }
+    }
+
+    /// Writes the data the way `from_text` reads it, `None` for data kept as raw bytes.
+    pub fn to_text(&self) -> Option<String> {
+        let text = match self {
+            MDNSRData::A { ipv4_address } => ipv4_address.to_string(),
+            MDNSRData::AAAA { ipv6_addr } => ipv6_addr.to_string(),
+            MDNSRData::PTR { domain_name } => domain_name.clone(),
+            MDNSRData::SRV { priority, weight, port, target_domain_name } => format!("{} {} {} {}", priority, weight, port, target_domain_name),
+            MDNSRData::TXT { text } => text.clone(),
+            MDNSRData::CNAME { canonical_domain_name } => canonical_domain_name.clone(),
+            MDNSRData::NSEC { .. } | MDNSRData::ANY { .. } | MDNSRData::OTHER { .. } => return None,
+        };
+        Some(text)
+    }
+}
+
 impl MDNSRecordType {
+    pub const ALL: [MDNSRecordType; 15] = [
+        MDNSRecordType::A,
+        MDNSRecordType::NS,
+        MDNSRecordType::CNAME,
+        MDNSRecordType::SOA,
+        MDNSRecordType::PTR,
+        MDNSRecordType::MX,
+        MDNSRecordType::TXT,
+        MDNSRecordType::AAAA,
+        MDNSRecordType::SRV,
+        MDNSRecordType::NSEC,
+        MDNSRecordType::OPT,
+        MDNSRecordType::ANY,
+        MDNSRecordType::AXFR,
+        MDNSRecordType::MAILB,
+        MDNSRecordType::MAILA,
+    ];
+
     pub fn from_u16(value: u16) -> Option<Self> {
         match value {
             1 => Some(Self::A),
@@ -121,11 +183,13 @@ impl MDNSQueryClass {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct MDNSQuestion{
     pub name: String,
     pub question_type: MDNSRecordType,
-    pub question_class: MDNSQueryClass
+    pub question_class: MDNSQueryClass,
+    /// The QU bit, the top bit of the class, asking for a unicast response (RFC 6762 - 5.4).
+    pub unicast_response: bool
 }
 
 #[derive(Clone)]
@@ -133,6 +197,8 @@ pub struct MDNSAnswer{
     pub name: String,
     pub answer_type: MDNSRecordType,
     pub answer_class: MDNSQueryClass,
+    /// The top bit of the class, telling caches to drop other records of the same name and type (RFC 6762 - 10.2).
+    pub cache_flush: bool,
     pub ttl_seconds: u32,
     pub rd_length: u16,
     pub rdata: MDNSRData
@@ -162,4 +228,59 @@ impl Display for MDNSMessageHeader {
 }
 
 impl MDNSMessageHeader {
-}
\ No newline at end of file
+    /// The QR bit, set in responses.
+    pub fn is_response(&self) -> bool {
+        self.flags & 0x8000 != 0
+    }
+}
+
+/// The flags field of the header split into its parts (RFC 1035 - 4.1.1, RFC 2535 - 6.1 for AD and CD).
+/// mDNS only uses QR, AA and TC, the others must be zero in multicast messages (RFC 6762 - 18).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MDNSHeaderFlags {
+    pub response: bool,
+    /// Four bits, 0 for standard queries.
+    pub opcode: u8,
+    pub authoritative: bool,
+    pub truncated: bool,
+    pub recursion_desired: bool,
+    pub recursion_available: bool,
+    /// The reserved bit.
+    pub zero: bool,
+    pub authentic_data: bool,
+    pub checking_disabled: bool,
+    /// Four bits, 0 when there was no error.
+    pub response_code: u8,
+}
+
+impl MDNSHeaderFlags {
+    pub fn from_u16(flags: u16) -> Self {
+        let bit = |position: u16| flags & (1 << position) != 0;
+        Self {
+            response: bit(15),
+            opcode: ((flags >> 11) & 0xf) as u8,
+            authoritative: bit(10),
+            truncated: bit(9),
+            recursion_desired: bit(8),
+            recursion_available: bit(7),
+            zero: bit(6),
+            authentic_data: bit(5),
+            checking_disabled: bit(4),
+            response_code: (flags & 0xf) as u8,
+        }
+    }
+
+    pub fn to_u16(self) -> u16 {
+        let bit = |set: bool, position: u16| if set { 1 << position } else { 0 };
+        bit(self.response, 15)
+            | ((self.opcode as u16 & 0xf) << 11)
+            | bit(self.authoritative, 10)
+            | bit(self.truncated, 9)
+            | bit(self.recursion_desired, 8)
+            | bit(self.recursion_available, 7)
+            | bit(self.zero, 6)
+            | bit(self.authentic_data, 5)
+            | bit(self.checking_disabled, 4)
+            | (self.response_code as u16 & 0xf)
+    }
+}
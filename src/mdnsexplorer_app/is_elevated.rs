@@ -1,17 +1,19 @@
-#![cfg(windows)]
-
-use std::mem;
-use winapi::shared::minwindef::DWORD;
-use winapi::shared::minwindef::LPVOID;
-use winapi::um::processthreadsapi::GetCurrentProcess;
-use winapi::um::processthreadsapi::OpenProcessToken;
-use winapi::um::securitybaseapi::GetTokenInformation;
-use winapi::um::winnt::TokenElevation;
-use winapi::um::winnt::HANDLE;
-use winapi::um::winnt::TOKEN_ELEVATION;
-use winapi::um::winnt::TOKEN_QUERY;
+/// Capability bit as numbered in linux/capability.h. Opening an AF_PACKET socket needs it, CAP_NET_ADMIN alone is not enough.
+const CAP_NET_RAW: u32 = 13;
 
+#[cfg(windows)]
 pub fn is_elevated() -> bool {
+    use std::mem;
+    use winapi::shared::minwindef::DWORD;
+    use winapi::shared::minwindef::LPVOID;
+    use winapi::um::processthreadsapi::GetCurrentProcess;
+    use winapi::um::processthreadsapi::OpenProcessToken;
+    use winapi::um::securitybaseapi::GetTokenInformation;
+    use winapi::um::winnt::TokenElevation;
+    use winapi::um::winnt::HANDLE;
+    use winapi::um::winnt::TOKEN_ELEVATION;
+    use winapi::um::winnt::TOKEN_QUERY;
+
     // based on https://stackoverflow.com/a/8196291
     unsafe {
         let mut current_token_ptr: HANDLE = mem::zeroed();
@@ -35,4 +37,31 @@ pub fn is_elevated() -> bool {
         }
     }
     false
-}
\ No newline at end of file
+}
+
+/// Raw capture on Linux needs root or CAP_NET_RAW, which can be granted to the binary with `setcap`.
+#[cfg(target_os = "linux")]
+pub fn is_elevated() -> bool {
+    let effective_uid = unsafe { libc::geteuid() };
+    let status = std::fs::read_to_string("/proc/self/status").unwrap_or_default();
+    has_capture_privileges(effective_uid, &status)
+}
+
+/// Other platforms give access to BPF devices through file permissions, which are only known once capture starts.
+#[cfg(not(any(windows, target_os = "linux")))]
+pub fn is_elevated() -> bool {
+    true
+}
+
+/// Whether a process with the given effective uid and `/proc/self/status` content may open a raw capture.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub fn has_capture_privileges(effective_uid: u32, status: &str) -> bool {
+    if effective_uid == 0 {
+        return true;
+    }
+    let required = 1u64 << CAP_NET_RAW;
+    status.lines()
+        .filter_map(|line| line.strip_prefix("CapEff:"))
+        .filter_map(|capabilities| u64::from_str_radix(capabilities.trim(), 16).ok())
+        .any(|capabilities| capabilities & required != 0)
+}
@@ -1,14 +1,13 @@
-use crate::mdns::capture;
-use crate::mdnsexplorer_ui::mdns_message_table::MdnsMessageOverview;
-use crate::mdnsexplorer_ui::mdnsexplorer_ui::{MdnsExplorerUi, ViewModel};
+use crate::mdns::capture_filter::CaptureFilter;
+use crate::mdnsexplorer_ui::mdnsexplorer_ui::{CaptureSource, MdnsExplorerUi};
 use std;
-use std::process;
+use std::env;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
-use std::thread;
-use std::time::SystemTime;
 use pnet::datalink::{interfaces, NetworkInterface};
 use crate::mdnsexplorer_ui::confirmation_dialogue_ui::ConfirmationDialogueUi;
-use crate::mdnsexplorer_ui::interface_chooser_ui::InterfaceChooserUi;
+use crate::mdnsexplorer_ui::interface_chooser_ui::{InterfaceChoice, InterfaceChooserUi};
+use crate::mdnsexplorer_app::is_elevated::is_elevated;
 
 pub struct MDNSExplorerApplication {
 }
@@ -23,73 +22,66 @@ impl MDNSExplorerApplication {
     pub fn run(&mut self) {
         println!("Starting Mdns Explorer");
 
-        #[cfg(windows)]
-        {
-            use crate::mdnsexplorer_app::is_elevated::is_elevated;
-            if !is_elevated()
-            {
-                ConfirmationDialogueUi::run(
-                    "Missing Admin Privileges",
-                    "Administrator privileges are missing. Please, make sure you are running the program as administrator."
-                );
-                process::exit(0);
-            }
+        let interfaces = interfaces();
+        let raw_capture_available = is_elevated();
+        // A capture file passed on the command line is opened directly, no privileges are needed for that.
+        if let Some(path) = env::args_os().nth(1) {
+            Self::run_explorer(interfaces, CaptureSource::File(PathBuf::from(path)), None, raw_capture_available);
+            return;
         }
 
-        let interface = Self::run_interface_chooser();
-        let view_model = Arc::new(Mutex::new(ViewModel {
-            mdns_message_overview_entries: vec![],
-            is_paused: false
-        }));
-        thread::scope(|s| {
-            s.spawn(|| {
-                capture::start(&interface, |mdns_message| {
-                    let now = SystemTime::now();
-                    match view_model.lock() {
-                        Ok(mut m) => {
-                            let duration = now.elapsed().unwrap();
-                            // println!("Locking the view_model took {} ms.", duration.as_millis());
+        if !raw_capture_available && !Self::confirm_socket_listener() {
+            return;
+        }
+
+        let choice = match Self::run_interface_chooser(&interfaces, raw_capture_available) {
+            Some(i) => i,
+            None => {
+                println!("No interface was picked.");
+                return;
+            }
+        };
+        Self::run_explorer(interfaces, CaptureSource::Interface(choice.interface, choice.backend), choice.capture_filter, raw_capture_available);
+    }
 
-                            if !m.is_paused
-                            {
-                                let model = MdnsMessageOverview::new(
-                                    mdns_message.received_datetime.time(),
-                                    mdns_message.message.clone(),
-                                    mdns_message.source_ip,
-                                    mdns_message.destination_ip
-                                );
-                                m.mdns_message_overview_entries.push(model);
-                            }
-                        }
-                        Err(_) => {
-                            panic!("Could not lock Mdns message overview");
-                        }
-                    }
-                });
-            });
-            MdnsExplorerUi::run(&view_model, &interface.description);
-            println!("Stopping Mdns Explorer");
+    /// Explains how to enable raw capture and asks whether to continue with the socket listener instead.
+    fn confirm_socket_listener() -> bool {
+        #[cfg(windows)]
+        let (title, message) = (
+            "Missing Admin Privileges",
+            "Administrator privileges are missing, so raw capture is not available. Restart MDNS Explorer as administrator, or continue with the socket listener, which only shows mDNS traffic delivered to this host.".to_string()
+        );
+        #[cfg(not(windows))]
+        let (title, message) = (
+            "Missing Capture Privileges",
+            format!(
+                "Raw capture needs root or the CAP_NET_RAW capability. Grant it with\n\nsudo setcap cap_net_raw,cap_net_admin=eip {}\n\nand restart, or continue with the socket listener, which only shows mDNS traffic delivered to this host.",
+                env::current_exe().map(|p| p.display().to_string()).unwrap_or_else(|_| "mdnsexplorer".to_string())
+            )
+        );
+        let answer = ConfirmationDialogueUi::ask(title, &message, &["Continue with socket listener", "Quit"]);
+        answer == Some(0)
+    }
 
-            // Since the capture thread could potentially be blocked when awaiting packets,
-            // this is the only way to properly end the program.
-            process::exit(0);
-        });
+    fn run_explorer(interfaces: Vec<NetworkInterface>, source: CaptureSource, capture_filter: Option<CaptureFilter>, raw_capture_available: bool) {
+        MdnsExplorerUi::run(interfaces, source, capture_filter, raw_capture_available);
+        println!("Stopping Mdns Explorer");
     }
 
-    fn run_interface_chooser() -> NetworkInterface
+    fn run_interface_chooser(interfaces: &[NetworkInterface], raw_capture_available: bool) -> Option<InterfaceChoice>
     {
-        let interfaces = interfaces();
         let picked_interface = Arc::new(Mutex::new(None));
         InterfaceChooserUi::run(
-            interfaces.clone(),
-            picked_interface.clone()
+            interfaces.to_vec(),
+            picked_interface.clone(),
+            raw_capture_available
         );
         let interface = match picked_interface.lock() {
-            Ok(i) => i.clone().expect("No interface was picked."),
+            Ok(i) => i.clone(),
             Err(_) => {
                 panic!("Could not lock picked interface.");
             }
         };
         return interface;
     }
-}
\ No newline at end of file
+}
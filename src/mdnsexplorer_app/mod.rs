@@ -1,2 +1,3 @@
 pub mod mdnsexplorer_app;
 mod is_elevated;
+mod tests;
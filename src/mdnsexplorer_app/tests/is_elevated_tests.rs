@@ -0,0 +1,38 @@
+#[cfg(test)]
+pub mod is_elevated_tests {
+    use crate::mdnsexplorer_app::is_elevated::has_capture_privileges;
+
+    const UNPRIVILEGED_STATUS: &str = "Name:\tmdnsexplorer\nUid:\t1000\t1000\t1000\t1000\nCapInh:\t0000000000000000\nCapPrm:\t0000000000000000\nCapEff:\t0000000000000000\nCapBnd:\t000001ffffffffff\n";
+
+    #[test]
+    fn has_capture_privileges__when_root__returns_true() {
+        assert!(has_capture_privileges(0, UNPRIVILEGED_STATUS));
+    }
+
+    #[test]
+    fn has_capture_privileges__when_no_effective_capabilities__returns_false() {
+        assert!(!has_capture_privileges(1000, UNPRIVILEGED_STATUS));
+    }
+
+    #[test]
+    fn has_capture_privileges__when_cap_net_raw_effective__returns_true() {
+        // Set by `setcap cap_net_raw=eip`.
+        let status = UNPRIVILEGED_STATUS.replace("CapEff:\t0000000000000000", "CapEff:\t0000000000002000");
+
+        assert!(has_capture_privileges(1000, &status));
+    }
+
+    #[test]
+    fn has_capture_privileges__when_cap_net_raw_only_permitted__returns_false() {
+        let status = UNPRIVILEGED_STATUS.replace("CapPrm:\t0000000000000000", "CapPrm:\t0000000000002000");
+
+        assert!(!has_capture_privileges(1000, &status));
+    }
+
+    #[test]
+    fn has_capture_privileges__when_only_cap_net_admin_effective__returns_false() {
+        let status = UNPRIVILEGED_STATUS.replace("CapEff:\t0000000000000000", "CapEff:\t0000000000001000");
+
+        assert!(!has_capture_privileges(1000, &status));
+    }
+}
@@ -1,30 +1,43 @@
+use std::sync::{Arc, Mutex};
 use eframe::egui;
 use egui::{Vec2, ViewportCommand};
 
 pub struct ConfirmationDialogueUi {
-    message: String
+    message: String,
+    options: Vec<String>,
+    answer: Arc<Mutex<Option<usize>>>
 }
 
 impl ConfirmationDialogueUi {
-    pub fn run(title: &str, message: &str) {
+    /// Shows the message with a button per option.
+    /// Returns the index of the clicked option, or `None` when the window was closed without choosing.
+    pub fn ask(title: &str, message: &str, options: &[&str]) -> Option<usize> {
         let builder = egui::ViewportBuilder::default()
             .with_maximize_button(false)
-            .with_inner_size(Vec2::new(250.0, 120.0))
+            .with_inner_size(Vec2::new(450.0, 220.0))
             .with_close_button(true)
             .with_always_on_top();
-        let options = eframe::NativeOptions {
+        let native_options = eframe::NativeOptions {
             viewport: builder,
             ..Default::default()
         };
+        let answer = Arc::new(Mutex::new(None));
+        let dialogue_answer = answer.clone();
         let _ = eframe::run_native(
             title,
-            options,
+            native_options,
             Box::new(|_| {
                 Ok(Box::<ConfirmationDialogueUi>::new(ConfirmationDialogueUi {
-                    message: message.to_string()
+                    message: message.to_string(),
+                    options: options.iter().map(|o| o.to_string()).collect(),
+                    answer: dialogue_answer
                 }))
             }),
         );
+        match answer.lock() {
+            Ok(a) => *a,
+            Err(_) => None
+        }
     }
 }
 
@@ -36,10 +49,17 @@ impl eframe::App for ConfirmationDialogueUi {
             ui.vertical_centered(|ui| {
                 ui.label(&self.message);
 
-                if ui.button("OK").clicked()
-                {
-                    ctx.send_viewport_cmd(ViewportCommand::Close)
-                }
+                ui.horizontal(|ui| {
+                    for (index, option) in self.options.iter().enumerate() {
+                        if ui.button(option).clicked()
+                        {
+                            if let Ok(mut answer) = self.answer.lock() {
+                                *answer = Some(index);
+                            }
+                            ctx.send_viewport_cmd(ViewportCommand::Close)
+                        }
+                    }
+                });
             })
         });
     }
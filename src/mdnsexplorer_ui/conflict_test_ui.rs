@@ -0,0 +1,193 @@
+use crate::mdns::conflict_test::{run_conflict_test, target_addresses, ConflictMode, ConflictReport, ConflictTestConfig, DEFAULT_TEST_DURATION};
+use crate::mdns::query_sender::QuerySenderConfig;
+use crate::mdns::types::MDNSAnswer;
+use crate::mdnsexplorer_ui::mdns_message_table::MdnsMessageOverview;
+use eframe::egui;
+use std::io;
+use std::net::IpAddr;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+/// Window certifying the name conflict behavior of a device (RFC 6762 - 9) against one of the unique records it announced.
+pub struct ConflictTestWindow {
+    /// Addresses of the device under test and the unique records taken from one of its responses.
+    target: Option<(Vec<IpAddr>, Vec<MDNSAnswer>)>,
+    record_index: usize,
+    mode: ConflictMode,
+    duration_seconds: f64,
+    /// Report of the test running in the background.
+    pending: Option<Receiver<io::Result<ConflictReport>>>,
+    report: Option<ConflictReport>,
+    error: Option<String>,
+}
+
+impl ConflictTestWindow {
+    pub fn new() -> Self {
+        Self {
+            target: None,
+            record_index: 0,
+            mode: ConflictMode::Announcement,
+            duration_seconds: DEFAULT_TEST_DURATION.as_secs_f64(),
+            pending: None,
+            report: None,
+            error: None,
+        }
+    }
+
+    /// `config` is where the conflict is sent, `None` for capture files. The tested record is taken from the `focused` message.
+    /// Returns true when the user closed the window.
+    pub fn render(&mut self, ctx: &egui::Context, config: Option<QuerySenderConfig>, focused: Option<&MdnsMessageOverview>) -> bool {
+        self.poll_pending();
+        let mut closed = false;
+        egui::Window::new("Conflict test")
+            .collapsible(false)
+            .resizable(true)
+            .show(ctx, |ui| {
+                let unique_records = focused.map(Self::unique_records).unwrap_or_default();
+                let use_focused = ui.add_enabled(!unique_records.is_empty(), egui::Button::new("Test the selected message"))
+                    .on_disabled_hover_text("Select a response with unique records, those with the cache flush bit set.");
+                if use_focused.clicked()
+                    && let Some(overview) = focused {
+                    self.target = Some((target_addresses(overview.source_ip, &overview.message), unique_records));
+                    self.record_index = 0;
+                }
+                if let Some((addresses, records)) = &self.target {
+                    egui::Grid::new("conflict_test_settings").num_columns(2).show(ui, |ui| {
+                        ui.label("Device");
+                        ui.label(addresses.iter().map(|a| a.to_string()).collect::<Vec<String>>().join(", "));
+                        ui.end_row();
+                        ui.label("Record");
+                        ui.vertical(|ui| {
+                            for (index, record) in records.iter().enumerate() {
+                                ui.radio_value(&mut self.record_index, index, format!("{} {} => {}", record.answer_type, record.name, record.rdata));
+                            }
+                        });
+                        ui.end_row();
+                        ui.label("Send");
+                        ui.horizontal(|ui| {
+                            for mode in [ConflictMode::Announcement, ConflictMode::Probe] {
+                                ui.radio_value(&mut self.mode, mode, mode.to_string());
+                            }
+                        });
+                        ui.end_row();
+                        ui.label("Observe for");
+                        ui.add(egui::DragValue::new(&mut self.duration_seconds).range(1.0..=60.0).speed(0.5).suffix(" s"));
+                        ui.end_row();
+                    });
+                }
+                ui.horizontal(|ui| {
+                    let running = self.pending.is_some();
+                    let label = if running { "Running…" } else { "Start" };
+                    let start = ui.add_enabled(config.is_some() && self.target.is_some() && !running, egui::Button::new(label))
+                        .on_disabled_hover_text("Conflicts are sent on the captured interface, select one instead of a capture file.");
+                    if start.clicked()
+                        && let Some(config) = config {
+                        self.start(config);
+                    }
+                    if ui.button("Close").clicked() {
+                        closed = true;
+                    }
+                });
+                if let Some(error) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+                if let Some(report) = &self.report {
+                    Self::render_report(ui, report);
+                }
+            });
+        if self.pending.is_some() {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+        closed
+    }
+
+    /// Records only the responder may have, those it announces with the cache flush bit.
+    fn unique_records(overview: &MdnsMessageOverview) -> Vec<MDNSAnswer> {
+        if !overview.message.header.is_response() {
+            return vec![];
+        }
+        let message = &overview.message;
+        message.answers.iter().chain(message.additionals.iter())
+            .filter(|r| r.cache_flush && r.ttl_seconds > 0)
+            .cloned()
+            .collect()
+    }
+
+    fn start(&mut self, config: QuerySenderConfig) {
+        let (targets, record) = match &self.target {
+            Some((addresses, records)) => match records.get(self.record_index) {
+                Some(record) => (addresses.clone(), record.clone()),
+                None => return
+            },
+            None => return
+        };
+        let test = ConflictTestConfig {
+            targets,
+            record,
+            mode: self.mode,
+            duration: Duration::from_secs_f64(self.duration_seconds),
+        };
+        let (sender, receiver) = mpsc::channel();
+        let spawned = thread::Builder::new()
+            .name("mDNS conflict test".to_string())
+            .spawn(move || {
+                let _ = sender.send(run_conflict_test(&config, test));
+            });
+        match spawned {
+            Ok(_) => {
+                self.pending = Some(receiver);
+                self.report = None;
+                self.error = None;
+            }
+            Err(e) => self.error = Some(format!("Could not start the test: {}", e)),
+        }
+    }
+
+    fn poll_pending(&mut self) {
+        let result = match &self.pending {
+            Some(receiver) => match receiver.try_recv() {
+                Ok(result) => result,
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => Err(io::Error::other("The test thread stopped unexpectedly.")),
+            },
+            None => return
+        };
+        self.pending = None;
+        match result {
+            Ok(report) => self.report = Some(report),
+            Err(e) => self.error = Some(format!("The test failed to run: {}", e)),
+        }
+    }
+
+    fn render_report(ui: &mut egui::Ui, report: &ConflictReport) {
+        ui.separator();
+        let (verdict, color) = match report.passed() {
+            true => ("PASS", egui::Color32::from_rgb(0, 160, 0)),
+            false => ("FAIL", ui.visuals().error_fg_color),
+        };
+        ui.horizontal(|ui| {
+            ui.colored_label(color, egui::RichText::new(verdict).strong());
+            ui.label(format!("{} for {}", report.mode, report.record));
+        });
+        if let Some(new_name) = &report.new_name {
+            ui.label(format!("The device moved the record to {}.", new_name));
+        }
+        egui::Grid::new("conflict_test_checks").num_columns(3).striped(true).show(ui, |ui| {
+            for check in report.checks.iter() {
+                match check.passed {
+                    true => ui.colored_label(egui::Color32::from_rgb(0, 160, 0), "✔"),
+                    false => ui.colored_label(ui.visuals().error_fg_color, "✘"),
+                };
+                ui.label(&check.requirement);
+                ui.label(&check.detail);
+                ui.end_row();
+            }
+        });
+        egui::CollapsingHeader::new("Timeline").default_open(false).show(ui, |ui| {
+            for event in report.timeline.iter() {
+                ui.monospace(event);
+            }
+        });
+    }
+}
@@ -2,28 +2,47 @@ use std::sync::{Arc, Mutex};
 use eframe::egui;
 use egui::{Vec2, ViewportCommand};
 use pnet::datalink::NetworkInterface;
+use crate::mdns::capture::CaptureBackend;
+use crate::mdns::capture_filter::CaptureFilter;
+use crate::mdns::socket_listener::PACKET_INFO_AVAILABLE;
+
+/// What the user picked in the interface chooser.
+#[derive(Clone)]
+pub struct InterfaceChoice {
+    pub interface: NetworkInterface,
+    pub backend: CaptureBackend,
+    /// Filter for raw capture, `None` lets the decoded protocols through.
+    pub capture_filter: Option<CaptureFilter>,
+}
 
 pub struct InterfaceChooserUi {
     interfaces: Vec<NetworkInterface>,
-    picked_interface: Arc<Mutex<Option<NetworkInterface>>>
+    picked_interface: Arc<Mutex<Option<InterfaceChoice>>>,
+    backend: CaptureBackend,
+    raw_capture_available: bool,
+    capture_filter: String
 }
 
 impl InterfaceChooserUi {
-    pub fn run(interfaces: Vec<NetworkInterface>, picked_interface: Arc<Mutex<Option<NetworkInterface>>>) {
+    pub fn run(interfaces: Vec<NetworkInterface>, picked_interface: Arc<Mutex<Option<InterfaceChoice>>>, raw_capture_available: bool) {
         let builder = egui::ViewportBuilder::default()
             .with_maximize_button(false)
-            .with_inner_size(Vec2::new(400.0, 250.0))
+            .with_inner_size(Vec2::new(400.0, 300.0))
             .with_close_button(false)
             .with_always_on_top();
         let options = eframe::NativeOptions {
             viewport: builder,
             ..Default::default()
         };
+        let backend = match raw_capture_available {
+            true => CaptureBackend::RawCapture,
+            false => CaptureBackend::SocketListener,
+        };
         let _ = eframe::run_native(
             "Choose interface",
             options,
             Box::new(|_| {
-                Ok(Box::<InterfaceChooserUi>::new(InterfaceChooserUi { interfaces, picked_interface }))
+                Ok(Box::<InterfaceChooserUi>::new(InterfaceChooserUi { interfaces, picked_interface, backend, raw_capture_available, capture_filter: String::new() }))
             }),
         );
     }
@@ -34,11 +53,47 @@ impl eframe::App for InterfaceChooserUi {
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.heading("MDNS Explorer");
             ui.separator();
+            ui.horizontal(|ui| {
+                ui.add_enabled_ui(self.raw_capture_available, |ui| {
+                    ui.radio_value(&mut self.backend, CaptureBackend::RawCapture, CaptureBackend::RawCapture.to_string())
+                        .on_disabled_hover_text("Raw capture needs elevated privileges.");
+                });
+                ui.radio_value(&mut self.backend, CaptureBackend::SocketListener, CaptureBackend::SocketListener.to_string())
+                    .on_hover_text(socket_listener_hover_text());
+            });
+            let raw_capture = self.backend == CaptureBackend::RawCapture;
+            ui.add_enabled_ui(raw_capture, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Capture filter");
+                    ui.add(egui::TextEdit::singleline(&mut self.capture_filter).hint_text("udp port 5353,5355"))
+                        .on_hover_text("Applied by the kernel on Linux. Supports port, udp port, tcp port, host, ip, ip6 and vlan clauses joined with 'and', port clauses also with 'or'. Leave empty to let the decoded protocols through.");
+                });
+            });
+            let capture_filter = match (raw_capture, self.capture_filter.trim()) {
+                (false, _) | (true, "") => Ok(None),
+                (true, expression) => expression.parse::<CaptureFilter>().map(Some),
+            };
+            if let Err(e) = &capture_filter {
+                ui.colored_label(ui.visuals().error_fg_color, e.to_string());
+            }
+            ui.separator();
             ui.vertical_centered(|ui| {
                 for interface in self.interfaces.iter() {
+                    let capture_filter = match &capture_filter {
+                        Ok(f) => f,
+                        Err(_) => {
+                            ui.add_enabled(false, egui::Button::new(&interface.description));
+                            continue;
+                        }
+                    };
                     if ui.button(&interface.description).clicked() {
+                        let choice = InterfaceChoice {
+                            interface: interface.clone(),
+                            backend: self.backend,
+                            capture_filter: capture_filter.clone(),
+                        };
                         match self.picked_interface.try_lock() {
-                            Ok(mut picked_interface) => { *picked_interface = Some(interface.clone()); },
+                            Ok(mut picked_interface) => { *picked_interface = Some(choice); },
                             Err(e) => { panic!("Unable to lock picked interface."); }
                         }
                         ctx.send_viewport_cmd(ViewportCommand::Close)
@@ -48,3 +103,11 @@ impl eframe::App for InterfaceChooserUi {
         });
     }
 }
+
+/// Describes the socket listener, and what it cannot tell about a message on this platform.
+pub fn socket_listener_hover_text() -> &'static str {
+    match PACKET_INFO_AVAILABLE {
+        true => "Receives only the mDNS traffic delivered to this host, but needs no privileges.",
+        false => "Receives only the mDNS traffic delivered to this host, but needs no privileges. On this platform the destination of every message is shown as the group and its TTL as 255, they are not reported to the listener.",
+    }
+}
@@ -0,0 +1,154 @@
+use crate::mdns::load_generator::{LoadProfile, LoadRun, LoadStats, MixEntry};
+use crate::mdns::query_sender::QuerySenderConfig;
+use crate::mdns::types::MDNSRecordType;
+use eframe::egui;
+use std::time::Duration;
+
+/// Record types offered in the traffic mix.
+const MIX_TYPES: [MDNSRecordType; 7] = [
+    MDNSRecordType::A,
+    MDNSRecordType::AAAA,
+    MDNSRecordType::PTR,
+    MDNSRecordType::SRV,
+    MDNSRecordType::TXT,
+    MDNSRecordType::CNAME,
+    MDNSRecordType::ANY,
+];
+
+/// Window sending a storm of synthetic queries and announcements, to see how responders cope with it.
+pub struct LoadGeneratorWindow {
+    rate: f64,
+    duration_seconds: f64,
+    announcement_percent: u8,
+    /// Weight of every type of `MIX_TYPES`, zero leaves the type out.
+    weights: [u32; MIX_TYPES.len()],
+    /// One name per line.
+    names: String,
+    run: Option<LoadRun>,
+    error: Option<String>,
+}
+
+impl LoadGeneratorWindow {
+    pub fn new() -> Self {
+        Self {
+            rate: 100.0,
+            duration_seconds: 10.0,
+            announcement_percent: 20,
+            weights: [3, 1, 2, 0, 0, 0, 0],
+            names: "loadtest-{n}.local\n_http._tcp.local".to_string(),
+            run: None,
+            error: None,
+        }
+    }
+
+    /// `config` is where the messages are sent, `None` for capture files. Returns true when the user closed the window.
+    pub fn render(&mut self, ctx: &egui::Context, config: Option<QuerySenderConfig>) -> bool {
+        let mut closed = false;
+        egui::Window::new("Load generator")
+            .collapsible(false)
+            .resizable(true)
+            .show(ctx, |ui| {
+                let running = self.run.as_ref().is_some_and(|r| !r.stats().finished);
+                ui.add_enabled_ui(!running, |ui| self.render_profile(ui));
+                ui.horizontal(|ui| {
+                    let start = ui.add_enabled(config.is_some() && !running, egui::Button::new("Start"))
+                        .on_disabled_hover_text("Messages are sent on the captured interface, select one instead of a capture file.");
+                    if start.clicked()
+                        && let Some(config) = &config {
+                        self.start(config);
+                    }
+                    if ui.add_enabled(running, egui::Button::new("Stop")).clicked()
+                        && let Some(run) = &mut self.run {
+                        run.stop();
+                    }
+                    if ui.button("Close").clicked() {
+                        closed = true;
+                    }
+                });
+                if let Some(error) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+                if let Some(run) = &self.run {
+                    ui.separator();
+                    Self::render_stats(ui, &run.stats(), self.duration_seconds);
+                }
+            });
+        if self.run.as_ref().is_some_and(|r| !r.stats().finished) {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+        closed
+    }
+
+    fn render_profile(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("load_generator_profile").num_columns(2).show(ui, |ui| {
+            ui.label("Rate");
+            ui.add(egui::DragValue::new(&mut self.rate).range(1.0..=100000.0).speed(1.0).suffix(" messages/s"));
+            ui.end_row();
+            ui.label("Duration");
+            ui.add(egui::DragValue::new(&mut self.duration_seconds).range(1.0..=3600.0).speed(1.0).suffix(" s"));
+            ui.end_row();
+            ui.label("Announcements");
+            ui.add(egui::Slider::new(&mut self.announcement_percent, 0..=100).suffix(" %"))
+                .on_hover_text("Share of unsolicited responses, the rest are queries.");
+            ui.end_row();
+            ui.label("Record types");
+            ui.vertical(|ui| {
+                for (record_type, weight) in MIX_TYPES.iter().zip(self.weights.iter_mut()) {
+                    ui.horizontal(|ui| {
+                        ui.add(egui::DragValue::new(weight).range(0..=100));
+                        ui.label(record_type.to_string());
+                    });
+                }
+            }).response.on_hover_text("Relative weight of every type, zero leaves the type out.");
+            ui.end_row();
+            ui.label("Names");
+            ui.add(egui::TextEdit::multiline(&mut self.names).desired_rows(3).desired_width(250.0))
+                .on_hover_text("One name per line, used in turn. {n} is replaced by the number of the message.");
+            ui.end_row();
+        });
+    }
+
+    fn render_stats(ui: &mut egui::Ui, stats: &LoadStats, duration_seconds: f64) {
+        let progress = (stats.elapsed.as_secs_f64() / duration_seconds).min(1.0) as f32;
+        let text = match stats.finished {
+            true => format!("Finished after {:.1} s", stats.elapsed.as_secs_f64()),
+            false => format!("{:.1} of {:.0} s", stats.elapsed.as_secs_f64(), duration_seconds),
+        };
+        ui.add(egui::ProgressBar::new(progress).text(text));
+        egui::Grid::new("load_generator_stats").num_columns(2).show(ui, |ui| {
+            ui.label("Current rate");
+            ui.label(format!("{:.0} messages/s", stats.current_rate));
+            ui.end_row();
+            ui.label("Sent");
+            ui.label(format!("{} ({} queries, {} announcements)", stats.sent(), stats.queries, stats.announcements));
+            ui.end_row();
+            ui.label("Errors");
+            ui.label(stats.errors.to_string());
+            ui.end_row();
+        });
+        if let Some(error) = &stats.last_error {
+            ui.colored_label(ui.visuals().error_fg_color, format!("Last error: {}", error));
+        }
+    }
+
+    fn start(&mut self, config: &QuerySenderConfig) {
+        let profile = LoadProfile {
+            rate: self.rate,
+            duration: Duration::from_secs_f64(self.duration_seconds),
+            announcement_percent: self.announcement_percent,
+            mix: MIX_TYPES.iter().zip(self.weights.iter())
+                .map(|(record_type, weight)| MixEntry { record_type: *record_type, weight: *weight })
+                .collect(),
+            names: self.names.lines().map(|n| n.to_string()).collect(),
+        };
+        // A previous run is stopped by dropping it.
+        self.run = None;
+        match LoadRun::start(config, profile) {
+            Ok(run) => {
+                self.run = Some(run);
+                self.error = None;
+            }
+            Err(e) => self.error = Some(format!("Could not start: {}", e)),
+        }
+    }
+}
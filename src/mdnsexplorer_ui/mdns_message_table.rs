@@ -1,23 +1,154 @@
 use std::cmp;
 use std::cmp::max;
-use std::net::Ipv4Addr;
-use crate::mdns::mdns_message::MDNSMessage;
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::net::IpAddr;
+use std::sync::Arc;
+use crate::mdns::capture::LinkType;
+use crate::mdns::mdns_message::{MDNSMessage, MDNSMessageReceivedEvent, PacketMetadata, Protocol};
+use crate::mdns::pcapng_writer::PcapNgWriter;
+use crate::mdns::query_sender::QueryTag;
+use crate::mdnsexplorer_ui::message_store::MessageStore;
+use crate::mdnsexplorer_ui::timestamp_format::TimestampFormat;
 use crate::mdns::types::{MDNSAnswer, MDNSQuestion};
 use eframe::egui;
-use time::Time;
+use time::OffsetDateTime;
 
 #[derive(Clone)]
 pub struct MdnsMessageOverview {
-    utc_time: Time,
-    message: MDNSMessage,
-    source_ip: Ipv4Addr,
-    destination_ip: Ipv4Addr,
+    pub received_datetime: OffsetDateTime,
+    pub protocol: Protocol,
+    pub message: MDNSMessage,
+    pub source_ip: IpAddr,
+    pub destination_ip: IpAddr,
+    pub metadata: PacketMetadata,
+    pub frame: Arc<[u8]>,
+    pub link_type: LinkType,
+    pub interface_name: Arc<str>,
+    pub diagnostics: Vec<String>,
+    /// Set when the message is a query sent by this application or a reply to one.
+    pub query_tag: Option<QueryTag>,
 }
 
 impl MdnsMessageOverview {
-    pub fn new(utc_time: Time, message: MDNSMessage, source_ip: Ipv4Addr, destination_ip: Ipv4Addr) -> Self {
-        Self { utc_time, message, source_ip: source_ip, destination_ip: destination_ip }
+    pub fn new(event: MDNSMessageReceivedEvent) -> Self {
+        Self {
+            received_datetime: event.received_datetime,
+            protocol: event.protocol,
+            message: event.message,
+            source_ip: event.source_ip,
+            destination_ip: event.destination_ip,
+            metadata: event.metadata,
+            frame: event.frame,
+            link_type: event.link_type,
+            interface_name: event.interface_name,
+            diagnostics: event.diagnostics,
+            query_tag: None,
+        }
+    }
+}
+
+impl MdnsMessageOverview {
+    /// Rough number of bytes the message occupies in memory, used to enforce the retention limit.
+    pub fn approximate_size(&self) -> usize {
+        let questions: usize = self.message.questions.iter().map(|q| size_of::<MDNSQuestion>() + q.name.len()).sum();
+        let message = &self.message;
+        let records = message.answers.iter().chain(message.authorities.iter()).chain(message.additionals.iter());
+        let answers: usize = records.map(|a| size_of::<MDNSAnswer>() + a.name.len() + a.rd_length as usize).sum();
+        let diagnostics: usize = self.diagnostics.iter().map(|d| d.len()).sum();
+        size_of::<Self>() + self.frame.len() + questions + answers + diagnostics
+    }
+
+    /// A `.local` name asked for over unicast DNS, which points to a misconfigured resolver on the sending host.
+    pub fn is_misdirected_local_query(&self) -> bool {
+        self.protocol == Protocol::UnicastDns && self.message.is_local_query()
     }
+
+    /// Writes the frame of the message, with its diagnostics as packet comment.
+    pub fn write_to<W: Write>(&self, writer: &mut PcapNgWriter<W>) -> io::Result<()> {
+        let comment = self.diagnostics.join(" ");
+        writer.write_packet(
+            self.link_type.to_pcap(),
+            &self.interface_name,
+            self.received_datetime,
+            &self.frame,
+            if comment.is_empty() { None } else { Some(&comment) }
+        )
+    }
+}
+
+/// Columns which can be shown in addition to the time, source and message columns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionalColumn {
+    DestinationIp,
+    SourceMac,
+    DestinationMac,
+    SourcePort,
+    DestinationPort,
+    Ttl,
+    Dscp,
+    IpIdentification,
+    FrameLength,
+}
+
+impl OptionalColumn {
+    pub const ALL: [OptionalColumn; 9] = [
+        OptionalColumn::DestinationIp,
+        OptionalColumn::SourceMac,
+        OptionalColumn::DestinationMac,
+        OptionalColumn::SourcePort,
+        OptionalColumn::DestinationPort,
+        OptionalColumn::Ttl,
+        OptionalColumn::Dscp,
+        OptionalColumn::IpIdentification,
+        OptionalColumn::FrameLength,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            OptionalColumn::DestinationIp => "Destination IP",
+            OptionalColumn::SourceMac => "Source MAC",
+            OptionalColumn::DestinationMac => "Destination MAC",
+            OptionalColumn::SourcePort => "Source port",
+            OptionalColumn::DestinationPort => "Destination port",
+            OptionalColumn::Ttl => "TTL / Hop limit",
+            OptionalColumn::Dscp => "DSCP",
+            OptionalColumn::IpIdentification => "IP ID",
+            OptionalColumn::FrameLength => "Frame length",
+        }
+    }
+
+    /// The value shown for the message, "-" where the capture source does not provide it.
+    pub fn value(self, overview: &MdnsMessageOverview) -> String {
+        let metadata = &overview.metadata;
+        let optional = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+        match self {
+            OptionalColumn::DestinationIp => overview.destination_ip.to_string(),
+            OptionalColumn::SourceMac => optional(metadata.source_mac.map(|m| m.to_string())),
+            OptionalColumn::DestinationMac => optional(metadata.destination_mac.map(|m| m.to_string())),
+            OptionalColumn::SourcePort => metadata.source_port.to_string(),
+            OptionalColumn::DestinationPort => metadata.destination_port.to_string(),
+            OptionalColumn::Ttl => optional(metadata.ttl.map(|t| t.to_string())),
+            OptionalColumn::Dscp => optional(metadata.dscp.map(|d| d.to_string())),
+            OptionalColumn::IpIdentification => optional(metadata.ip_identification.map(|i| format!("0x{:04x}", i))),
+            OptionalColumn::FrameLength => metadata.frame_length.to_string(),
+        }
+    }
+}
+
+/// Text of a row, formatted once when the message is appended to the table.
+struct FormattedRow {
+    /// Sequence number of the message in the store.
+    sequence: u64,
+    time: String,
+    protocol: String,
+    source_ip: String,
+    questions: String,
+    answers: String,
+    height: f32,
+    /// Rows of misdirected `.local` queries are highlighted.
+    highlighted: bool,
+    query_tag: Option<QueryTag>,
 }
 
 pub struct MdnsMessageTable {
@@ -27,10 +158,23 @@ pub struct MdnsMessageTable {
     num_rows: usize,
     scroll_to_row_slider: usize,
     scroll_to_row: Option<usize>,
-    selection: std::collections::HashSet<usize>,
+    /// Sequence numbers of the selected messages, which stay valid while old messages are evicted.
+    selection: std::collections::HashSet<u64>,
     checked: bool,
     reversed: bool,
-    overviews: Vec<MdnsMessageOverview>
+    /// Formatted rows of the displayed messages in the store, in sequence order.
+    rows: VecDeque<FormattedRow>,
+    /// Sequence number of the next store message `sync` looks at.
+    next_sequence: u64,
+    /// Messages of protocols which are not in the list are not displayed.
+    visible_protocols: Vec<Protocol>,
+    /// Visible optional columns, kept in the order of `OptionalColumn::ALL`.
+    optional_columns: Vec<OptionalColumn>,
+    /// Sequence number of the message which was selected last, its details are shown below the table.
+    focused_row: Option<u64>,
+    timestamp_format: TimestampFormat,
+    /// Receive time of the last formatted row, the reference for delta timestamps.
+    previous_received: Option<OffsetDateTime>
 }
 
 impl Default for MdnsMessageTable {
@@ -45,13 +189,19 @@ impl Default for MdnsMessageTable {
             selection: Default::default(),
             checked: false,
             reversed: false,
-            overviews: Default::default()
+            rows: Default::default(),
+            next_sequence: 0,
+            visible_protocols: Protocol::ALL.to_vec(),
+            optional_columns: vec![],
+            focused_row: None,
+            timestamp_format: TimestampFormat::Utc,
+            previous_received: None
         }
     }
 }
 
 impl MdnsMessageTable {
-    pub fn new(a: Vec<MdnsMessageOverview>) -> Self {
+    pub fn new() -> Self {
         Self {
             striped: true,
             resizable: true,
@@ -62,19 +212,113 @@ impl MdnsMessageTable {
             selection: Default::default(),
             checked: false,
             reversed: false,
-            overviews: a
+            rows: Default::default(),
+            next_sequence: 0,
+            visible_protocols: Protocol::ALL.to_vec(),
+            optional_columns: vec![],
+            focused_row: None,
+            timestamp_format: TimestampFormat::Utc,
+            previous_received: None
         }
     }
 
-    pub fn render(&mut self, ui: &mut egui::Ui, reset: bool) {
+    /// Catches up with the store: formats messages appended since the last call and drops rows of evicted messages.
+    /// Selected messages which are no longer in the store get unselected.
+    pub fn sync(&mut self, store: &MessageStore) {
+        let store_first = store.first_sequence();
+        while self.rows.front().is_some_and(|r| r.sequence < store_first) {
+            self.rows.pop_front();
+        }
+        self.next_sequence = max(self.next_sequence, store_first);
+        let skipped = (self.next_sequence - store_first) as usize;
+        for (index, overview) in store.messages().iter().enumerate().skip(skipped) {
+            if !self.visible_protocols.contains(&overview.protocol) {
+                continue;
+            }
+            let time = self.timestamp_format.format(overview.received_datetime, store.session_start(), self.previous_received);
+            self.rows.push_back(Self::format_row(store_first + index as u64, overview, time));
+            self.previous_received = Some(overview.received_datetime);
+        }
+        self.next_sequence = store_first + store.messages().len() as u64;
+        self.selection.retain(|s| *s >= store_first);
+    }
+
+    /// Number of rows displayed after the last `sync`.
+    pub fn row_count(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn timestamp_format(&self) -> TimestampFormat {
+        self.timestamp_format
+    }
+
+    pub fn set_timestamp_format(&mut self, timestamp_format: TimestampFormat) {
+        self.timestamp_format = timestamp_format;
+        self.invalidate();
+    }
+
+    pub fn is_protocol_visible(&self, protocol: Protocol) -> bool {
+        self.visible_protocols.contains(&protocol)
+    }
+
+    /// Shows or hides the messages of the protocol. Hidden messages stay in the store.
+    pub fn set_protocol_visible(&mut self, protocol: Protocol, visible: bool) {
+        self.visible_protocols.retain(|p| *p != protocol);
+        if visible {
+            self.visible_protocols.push(protocol);
+        }
+        self.invalidate();
+    }
+
+    /// Formats all rows again on the next `sync`.
+    fn invalidate(&mut self) {
+        self.rows.clear();
+        self.next_sequence = 0;
+        self.previous_received = None;
+    }
+
+    /// The selected messages in the order they are displayed.
+    pub fn selected_overviews<'s>(&self, store: &'s MessageStore) -> Vec<&'s MdnsMessageOverview> {
+        let mut selected_rows: Vec<u64> = self.selection.iter().copied().collect();
+        selected_rows.sort();
+        selected_rows.iter().filter_map(|s| store.get(*s)).collect()
+    }
+
+    /// Replaces the selection with the given messages and focuses the first of them.
+    pub fn select_rows(&mut self, sequences: &[u64]) {
+        self.selection = sequences.iter().copied().collect();
+        self.focused_row = sequences.first().copied();
+    }
+
+    /// The last selected message, if it is still selected.
+    pub fn focused_overview<'s>(&self, store: &'s MessageStore) -> Option<&'s MdnsMessageOverview> {
+        self.focused_row.filter(|s| self.selection.contains(s)).and_then(|s| store.get(s))
+    }
+
+    pub fn is_column_visible(&self, column: OptionalColumn) -> bool {
+        self.optional_columns.contains(&column)
+    }
+
+    pub fn set_column_visible(&mut self, column: OptionalColumn, visible: bool) {
+        self.optional_columns.retain(|c| *c != column);
+        if visible {
+            self.optional_columns.push(column);
+            self.optional_columns.sort_by_key(|c| OptionalColumn::ALL.iter().position(|a| a == c));
+        }
+    }
+
+    /// Renders the rows formatted by the last `sync` with the same store. Only the visible rows are laid out.
+    pub fn render(&mut self, ui: &mut egui::Ui, store: &MessageStore, reset: bool) {
         use egui_extras::{Column, TableBuilder};
         let available_height = ui.available_height();
         let mut table = TableBuilder::new(ui)
             .striped(self.striped)
             .resizable(self.resizable)
             .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
-            .column(Column::initial(120 as f32).resizable(true).auto_size_this_frame(false))
+            .column(Column::initial(230.0).resizable(true).auto_size_this_frame(false))
+            .column(Column::initial(60.0).resizable(true).auto_size_this_frame(false))
             .column(Column::initial(80 as f32).resizable(true).auto_size_this_frame(false))
+            .columns(Column::initial(110.0).resizable(true).auto_size_this_frame(false), self.optional_columns.len())
             .column(Column::initial(500 as f32).resizable(true).auto_size_this_frame(false))
             .column(Column::initial(500 as f32).resizable(true).auto_size_this_frame(false))
             .min_scrolled_height(0.0)
@@ -89,14 +333,23 @@ impl MdnsMessageTable {
             table.reset();
         }
 
+        let mut clicked_row = None;
         table
             .header(20.0, |mut header| {
                 header.col(|ui| {
-                    ui.strong("UTC Time");
+                    ui.strong(self.timestamp_format.column_title());
+                });
+                header.col(|ui| {
+                    ui.strong("Protocol");
                 });
                 header.col(|ui| {
                     ui.strong("Source IP");
                 });
+                for column in self.optional_columns.iter() {
+                    header.col(|ui| {
+                        ui.strong(column.title());
+                    });
+                }
                 header.col(|ui| {
                     ui.strong("Questions");
                 });
@@ -105,26 +358,66 @@ impl MdnsMessageTable {
                 });
             })
             .body(|body| {
-                let row_height = 30f32;
-                let _ = body.heterogeneous_rows(self.overviews.iter().map(|o| Self::get_row_height(&o)), | mut row| {
-                    let row_index = row.index();
-                    let overview = &self.overviews[row_index];
+                let row_heights = self.rows.iter().map(|r| r.height);
+                body.heterogeneous_rows(row_heights, |mut row| {
+                    let formatted_row = &self.rows[row.index()];
+                    let sequence = formatted_row.sequence;
+                    row.set_selected(self.selection.contains(&sequence));
+                    let cell = |ui: &mut egui::Ui, text: &str| match (formatted_row.highlighted, formatted_row.query_tag) {
+                        (true, _) => ui.colored_label(ui.visuals().warn_fg_color, text)
+                            .on_hover_text("Query for a .local name sent to unicast DNS instead of mDNS."),
+                        (false, Some(QueryTag::Sent(id))) => ui.colored_label(ui.visuals().hyperlink_color, text)
+                            .on_hover_text(format!("Query #{} sent from MDNS Explorer.", id)),
+                        (false, Some(QueryTag::Reply(id))) => ui.colored_label(ui.visuals().hyperlink_color, text)
+                            .on_hover_text(format!("Reply to query #{} sent from MDNS Explorer.", id)),
+                        (false, None) => ui.label(text),
+                    };
                     row.col(|ui| {
-                        ui.label(overview.utc_time.to_string());
+                        cell(ui, &formatted_row.time);
                     });
                     row.col(|ui| {
-                        ui.label(format!("{}", overview.source_ip.to_string()));
+                        cell(ui, &formatted_row.protocol);
                     });
-                    let questions = overview.message.questions.iter().map(|q| Self::format_question(q)).collect::<Vec<_>>();
                     row.col(|ui| {
-                        ui.label(questions.join("\n"));
+                        cell(ui, &formatted_row.source_ip);
                     });
-                    let answers = overview.message.answers.iter().map(|a| Self::format_answer(a)).collect::<Vec<_>>();
+                    if let Some(overview) = store.get(sequence) {
+                        for column in self.optional_columns.iter() {
+                            row.col(|ui| {
+                                cell(ui, &column.value(overview));
+                            });
+                        }
+                    }
                     row.col(|ui| {
-                        ui.label(answers.join("\n"));
+                        cell(ui, &formatted_row.questions);
                     });
+                    row.col(|ui| {
+                        cell(ui, &formatted_row.answers);
+                    });
+                    if row.response().clicked() {
+                        clicked_row = Some(sequence);
+                    }
                 });
             });
+        if let Some(sequence) = clicked_row {
+            self.toggle_row_selection(sequence);
+        }
+    }
+
+    fn format_row(sequence: u64, overview: &MdnsMessageOverview, time: String) -> FormattedRow {
+        let questions = overview.message.questions.iter().map(Self::format_question).collect::<Vec<_>>();
+        let answers = overview.message.answers.iter().map(Self::format_answer).collect::<Vec<_>>();
+        FormattedRow {
+            sequence,
+            time,
+            protocol: overview.protocol.to_string(),
+            source_ip: overview.source_ip.to_string(),
+            questions: questions.join("\n"),
+            answers: answers.join("\n"),
+            height: Self::get_row_height(overview),
+            highlighted: overview.is_misdirected_local_query(),
+            query_tag: overview.query_tag,
+        }
     }
 
     fn get_row_height(overview: &MdnsMessageOverview) -> f32
@@ -134,13 +427,12 @@ impl MdnsMessageTable {
         return max(len, 1) as f32 * default_height;
     }
 
-    fn toggle_row_selection(&mut self, row_index: usize, row_response: &egui::Response) {
-        if row_response.clicked() {
-            if self.selection.contains(&row_index) {
-                self.selection.remove(&row_index);
-            } else {
-                self.selection.insert(row_index);
-            }
+    fn toggle_row_selection(&mut self, sequence: u64) {
+        if self.selection.contains(&sequence) {
+            self.selection.remove(&sequence);
+        } else {
+            self.selection.insert(sequence);
+            self.focused_row = Some(sequence);
         }
     }
 
@@ -1,21 +1,103 @@
-use crate::mdnsexplorer_ui::mdns_message_table::{MdnsMessageOverview, MdnsMessageTable};
+use crate::mdns::capture;
+use crate::mdns::capture::{CaptureBackend, CaptureHandle, CaptureOptions};
+use crate::mdns::capture_filter::CaptureFilter;
+use crate::mdns::event_channel::{BackpressurePolicy, EventReceiver};
+use crate::mdns::mdns_message::{MDNSMessageReceivedEvent, Protocol};
+use crate::mdns::pcapng_writer::PcapNgWriter;
+use crate::mdns::query_sender::{ContinuousQuery, QuerySender, QuerySenderConfig, SentQueryLog};
+use crate::mdns::responder::Responder;
+use crate::mdnsexplorer_ui::interface_chooser_ui::socket_listener_hover_text;
+use crate::mdnsexplorer_ui::conflict_test_ui::ConflictTestWindow;
+use crate::mdnsexplorer_ui::load_generator_ui::LoadGeneratorWindow;
+use crate::mdnsexplorer_ui::mdns_message_table::{MdnsMessageOverview, MdnsMessageTable, OptionalColumn};
+use crate::mdnsexplorer_ui::message_crafter_ui::MessageCrafterWindow;
+use crate::mdnsexplorer_ui::message_detail_view::MessageDetailView;
+use crate::mdnsexplorer_ui::message_store::{MessageStore, RetentionPolicy, RetentionState};
+use crate::mdnsexplorer_ui::query_sender_ui::{QueryAction, QueryWindow};
+use crate::mdnsexplorer_ui::replay_ui::ReplayWindow;
+use crate::mdnsexplorer_ui::resolve_ui::{ResolveAction, ResolveWindow};
+use crate::mdnsexplorer_ui::responder_ui::{ResponderAction, ResponderWindow};
+use crate::mdnsexplorer_ui::service_browser_ui::ServiceBrowserView;
+use crate::mdnsexplorer_ui::timestamp_format::TimestampFormat;
+use crate::mdnsexplorer_ui::retention_settings_ui::{RetentionAction, RetentionSettingsWindow};
 use eframe::egui;
 use egui::{TextStyle, Vec2};
 use egui_extras::{Size, StripBuilder};
-use std::sync::{Arc, Mutex};
+use pnet::datalink::NetworkInterface;
+use std::io;
+use std::path::{Path, PathBuf};
 
-pub struct ViewModel {
-    pub mdns_message_overview_entries: Vec<MdnsMessageOverview>,
-    pub is_paused: bool
+/// Where the displayed messages come from.
+#[derive(Clone)]
+pub enum CaptureSource {
+    Interface(NetworkInterface, CaptureBackend),
+    File(PathBuf),
 }
 
-pub struct MdnsExplorerUi<'l> {
-    view_model: &'l Arc<Mutex<ViewModel>>,
-    interface_name: String
+/// Views shown in the main area of the window.
+#[derive(Clone, Copy, PartialEq)]
+enum Tab {
+    Messages,
+    ServiceBrowser,
 }
 
-impl MdnsExplorerUi<'_> {
-    pub fn run(view_model: &Arc<Mutex<ViewModel>>, interface_name: &str) {
+/// The file operation the path dialog was opened for.
+#[derive(Clone, Copy, PartialEq)]
+enum FileAction {
+    OpenCapture,
+    SaveCapture,
+    SaveSelection,
+}
+
+impl CaptureSource {
+    fn description(&self) -> String {
+        match self {
+            CaptureSource::Interface(interface, _) => interface.description.clone(),
+            CaptureSource::File(path) => path.display().to_string(),
+        }
+    }
+}
+
+pub struct MdnsExplorerUi {
+    /// Messages received so far. Only the UI thread touches the store, the capture thread hands messages over through the event channel.
+    message_store: MessageStore,
+    interfaces: Vec<NetworkInterface>,
+    source: CaptureSource,
+    /// Whether the process has the privileges needed for raw capture.
+    raw_capture_available: bool,
+    capture: Option<CaptureHandle>,
+    capture_events: Option<EventReceiver<MDNSMessageReceivedEvent>>,
+    capture_options: CaptureOptions,
+    /// Capture filter being edited, applied with Enter.
+    capture_filter_text: String,
+    capture_error: Option<String>,
+    message_table: MdnsMessageTable,
+    /// Action and path typed into the file window while it is shown.
+    file_dialog: Option<(FileAction, String)>,
+    /// Outcome of the last file operation.
+    file_status: Option<String>,
+    retention_window: Option<RetentionSettingsWindow>,
+    query_window: Option<QueryWindow>,
+    /// Queries sent from the query window, used to tag the captured replies.
+    query_log: SentQueryLog,
+    continuous_query: Option<ContinuousQuery>,
+    /// Outcome of the last query sent.
+    query_status: Option<String>,
+    tab: Tab,
+    service_browser: ServiceBrowserView,
+    responder_window: Option<ResponderWindow>,
+    /// Publishes the records of the responder window until stopped.
+    responder: Option<Responder>,
+    responder_error: Option<String>,
+    resolve_window: Option<ResolveWindow>,
+    conflict_test_window: Option<ConflictTestWindow>,
+    replay_window: Option<ReplayWindow>,
+    load_generator_window: Option<LoadGeneratorWindow>,
+    crafter_window: Option<MessageCrafterWindow>
+}
+
+impl MdnsExplorerUi {
+    pub fn run(interfaces: Vec<NetworkInterface>, source: CaptureSource, capture_filter: Option<CaptureFilter>, raw_capture_available: bool) {
         let builder = egui::ViewportBuilder::default()
             .with_maximize_button(true)
             .with_inner_size(Vec2::new(1300.0, 800.0));
@@ -27,62 +109,703 @@ impl MdnsExplorerUi<'_> {
             "MDNS Explorer",
             options,
             Box::new(|_| {
-                Ok(Box::<MdnsExplorerUi>::new(MdnsExplorerUi {
-                    view_model: view_model,
-                    interface_name: interface_name.to_string()
-                }))
+                let mut ui = MdnsExplorerUi {
+                    message_store: MessageStore::new(RetentionPolicy::default()),
+                    interfaces,
+                    source,
+                    raw_capture_available,
+                    capture: None,
+                    capture_events: None,
+                    capture_filter_text: capture_filter.as_ref().map(|f| f.to_string()).unwrap_or_default(),
+                    capture_options: CaptureOptions {
+                        filter: capture_filter,
+                        ..Default::default()
+                    },
+                    capture_error: None,
+                    message_table: MdnsMessageTable::new(),
+                    file_dialog: None,
+                    file_status: None,
+                    retention_window: None,
+                    query_window: None,
+                    query_log: SentQueryLog::new(),
+                    continuous_query: None,
+                    query_status: None,
+                    tab: Tab::Messages,
+                    service_browser: ServiceBrowserView::new(),
+                    responder_window: None,
+                    responder: None,
+                    responder_error: None,
+                    resolve_window: None,
+                    conflict_test_window: None,
+                    replay_window: None,
+                    load_generator_window: None,
+                    crafter_window: None
+                };
+                ui.start_capture();
+                Ok(Box::<MdnsExplorerUi>::new(ui))
             }),
         );
     }
 
-    fn get_overviews(&self) -> Vec<MdnsMessageOverview>
-    {
-        match self.view_model.lock() {
-            Ok(m) => {
-                m.mdns_message_overview_entries.clone()
+    fn start_capture(&mut self) {
+        self.stop_capture();
+        if let CaptureSource::File(_) = &self.source {
+            // A capture file is always read from the beginning, so the previous content would only be duplicated.
+            self.clear_messages();
+        }
+        let result = match &self.source {
+            CaptureSource::Interface(interface, CaptureBackend::RawCapture) => capture::start(interface, self.capture_options.clone()),
+            CaptureSource::Interface(interface, CaptureBackend::SocketListener) => capture::listen(interface, self.capture_options.clone()),
+            CaptureSource::File(path) => {
+                let options = CaptureOptions {
+                    backpressure: BackpressurePolicy::Block,
+                    ..self.capture_options.clone()
+                };
+                capture::open_file(path, options)
             }
-            Err(_) => {
-                panic!("Nope.")
+        };
+        match result {
+            Ok((handle, events)) => {
+                self.capture = Some(handle);
+                self.capture_events = Some(events);
+                self.capture_error = None;
+            }
+            Err(e) => {
+                self.capture_events = None;
+                let hint = match (&self.source, e.kind()) {
+                    (CaptureSource::Interface(_, CaptureBackend::RawCapture), io::ErrorKind::PermissionDenied) => {
+                        self.raw_capture_available = false;
+                        " Raw capture needs elevated privileges, the socket listener works without them."
+                    }
+                    _ => ""
+                };
+                self.capture_error = Some(format!("Could not start capture on {}: {}.{}", self.source.description(), e, hint));
+            }
+        }
+    }
+
+    fn stop_capture(&mut self) {
+        if let Some(mut handle) = self.capture.take() {
+            handle.stop();
+        }
+    }
+
+    fn clear_messages(&mut self) {
+        self.message_store.clear();
+    }
+
+    /// Moves the messages queued by the capture thread into the store.
+    fn receive_messages(&mut self) {
+        let events = match &self.capture_events {
+            Some(e) => e,
+            None => return
+        };
+        self.message_store.extend(events.try_iter().map(|event| {
+            let mut overview = MdnsMessageOverview::new(event);
+            overview.query_tag = self.query_log.tag(&overview.message, overview.received_datetime);
+            self.service_browser.observe(&overview.message, overview.received_datetime, overview.source_ip);
+            overview
+        }));
+    }
+
+    fn apply_retention(&mut self, policy: RetentionPolicy, spill_path: Option<PathBuf>) {
+        self.message_store.set_policy(policy);
+        let current_spill_path = self.message_store.state().spill_path;
+        match spill_path {
+            // Restarting the spill would truncate the session file.
+            Some(path) if current_spill_path.as_ref() == Some(&path) => {}
+            Some(path) => {
+                if let Err(e) = self.message_store.start_spill(&path) {
+                    self.file_status = Some(format!("Could not create session file {}: {}", path.display(), e));
+                }
             }
+            None => self.message_store.stop_spill(),
         }
     }
 
+    fn apply_capture_filter(&mut self) {
+        let filter = match self.capture_filter_text.trim() {
+            "" => None,
+            expression => match expression.parse::<CaptureFilter>() {
+                Ok(f) => Some(f),
+                Err(e) => {
+                    self.capture_error = Some(format!("Invalid capture filter: {}", e));
+                    return;
+                }
+            },
+        };
+        if filter != self.capture_options.filter {
+            self.capture_options.filter = filter;
+            self.start_capture();
+        }
+    }
+
+    fn default_backend(&self) -> CaptureBackend {
+        match self.raw_capture_available {
+            true => CaptureBackend::RawCapture,
+            false => CaptureBackend::SocketListener,
+        }
+    }
+
+    fn is_capturing(&self) -> bool {
+        self.capture.as_ref().is_some_and(|c| c.is_running())
+    }
+
     fn is_paused(&self) -> bool
     {
-        match self.view_model.lock() {
-            Ok(m) => {
-                m.is_paused
+        self.capture.as_ref().is_some_and(|c| c.is_paused())
+    }
+
+    fn pause(&mut self, is_paused: bool) {
+        if let Some(capture) = &self.capture {
+            if is_paused { capture.pause() } else { capture.resume() }
+        }
+    }
+
+    fn render_capture_controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let (current_interface, current_backend) = match &self.source {
+                CaptureSource::Interface(interface, backend) => (Some(interface.name.clone()), *backend),
+                CaptureSource::File(_) => (None, self.default_backend()),
+            };
+            let mut picked_interface = None;
+            egui::ComboBox::from_label("Interface")
+                .selected_text(match &self.source {
+                    CaptureSource::Interface(interface, _) => interface.description.clone(),
+                    CaptureSource::File(_) => "(capture file)".to_string(),
+                })
+                .show_ui(ui, |ui| {
+                    for interface in self.interfaces.iter() {
+                        if ui.selectable_label(current_interface.as_ref() == Some(&interface.name), &interface.description).clicked() {
+                            picked_interface = Some(interface.clone());
+                        }
+                    }
+                });
+            if let Some(interface) = picked_interface
+                && current_interface.as_ref() != Some(&interface.name) {
+                self.source = CaptureSource::Interface(interface, current_backend);
+                self.start_capture();
+            }
+
+            if let CaptureSource::Interface(interface, backend) = &self.source {
+                let mut picked_backend = *backend;
+                egui::ComboBox::from_label("Backend")
+                    .selected_text(picked_backend.to_string())
+                    .show_ui(ui, |ui| {
+                        ui.add_enabled_ui(self.raw_capture_available, |ui| {
+                            ui.selectable_value(&mut picked_backend, CaptureBackend::RawCapture, CaptureBackend::RawCapture.to_string())
+                                .on_disabled_hover_text("Raw capture needs elevated privileges.");
+                        });
+                        ui.selectable_value(&mut picked_backend, CaptureBackend::SocketListener, CaptureBackend::SocketListener.to_string())
+                            .on_hover_text(socket_listener_hover_text());
+                    });
+                if picked_backend != *backend {
+                    self.source = CaptureSource::Interface(interface.clone(), picked_backend);
+                    self.start_capture();
+                }
+            }
+
+            if self.is_capturing() {
+                if ui.button("Stop").clicked() {
+                    self.stop_capture();
+                }
+                let is_paused = self.is_paused();
+                let pause_button_label = if is_paused { "Unpause" } else { "Pause" };
+                if ui.button(pause_button_label).clicked() {
+                    self.pause(!is_paused);
+                }
+            } else if ui.button("Start").clicked() {
+                self.start_capture();
             }
-            Err(_) => {
-                panic!("Nope.")
+
+            let mut backpressure = self.capture_options.backpressure;
+            egui::ComboBox::from_label("When the UI falls behind")
+                .selected_text(backpressure.to_string())
+                .show_ui(ui, |ui| {
+                    for policy in [BackpressurePolicy::DropOldest, BackpressurePolicy::DropNewest, BackpressurePolicy::Block] {
+                        ui.selectable_value(&mut backpressure, policy, policy.to_string());
+                    }
+                });
+            if backpressure != self.capture_options.backpressure {
+                self.capture_options.backpressure = backpressure;
+                if self.is_capturing() {
+                    self.start_capture();
+                }
+            }
+
+            if let CaptureSource::Interface(_, CaptureBackend::RawCapture) = &self.source {
+                ui.label("Capture filter");
+                let filter_edit = ui.add(egui::TextEdit::singleline(&mut self.capture_filter_text).hint_text("udp port 5353,5355").desired_width(180.0))
+                    .on_hover_text("Applied by the kernel on Linux when Enter is pressed. Leave empty to let the decoded protocols through.");
+                if filter_edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    self.apply_capture_filter();
+                }
+            }
+
+            let socket_listener = matches!(self.source, CaptureSource::Interface(_, CaptureBackend::SocketListener));
+            let unicast_dns = ui.add_enabled(!socket_listener, egui::Checkbox::new(&mut self.capture_options.unicast_dns, "Unicast DNS (port 53)"))
+                .on_hover_text("Also decode DNS traffic on port 53 to spot .local queries sent to the unicast resolver.")
+                .on_disabled_hover_text("The socket listener only receives multicast traffic, use raw capture or a capture file.");
+            if unicast_dns.changed() && self.is_capturing() {
+                self.start_capture();
+            }
+        });
+
+        let status = match (&self.source, self.is_capturing()) {
+            (CaptureSource::Interface(interface, backend), true) => format!("Capturing on interface: {} ({})", interface.description, backend),
+            (CaptureSource::Interface(interface, _), false) => format!("Capture stopped on interface: {}", interface.description),
+            (CaptureSource::File(path), true) => format!("Reading capture file: {}", path.display()),
+            (CaptureSource::File(path), false) => format!("Capture file: {}", path.display()),
+        };
+        ui.label(status);
+        if let Some(filter) = self.capture.as_ref().and_then(|c| c.kernel_filter()) {
+            ui.label(format!("The kernel applies the capture filter \"{}\".", filter));
+        }
+        if let Some(events) = &self.capture_events {
+            let stats = events.stats();
+            if stats.dropped > 0 {
+                ui.colored_label(ui.visuals().warn_fg_color, format!("{} messages were dropped because the UI could not keep up.", stats.dropped));
             }
         }
+        if let Some(error) = &self.capture_error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+            if let CaptureSource::Interface(interface, CaptureBackend::RawCapture) = &self.source
+                && !self.raw_capture_available
+                && ui.button("Use socket listener").clicked() {
+                self.source = CaptureSource::Interface(interface.clone(), CaptureBackend::SocketListener);
+                self.start_capture();
+            }
+        }
+        ui.label(Self::format_retention(&self.message_store.state()));
+        let retained = self.message_store.messages().len();
+        if self.message_table.row_count() < retained {
+            ui.label(format!("Showing {} of {} messages, the others are hidden by the protocol filter.", self.message_table.row_count(), retained));
+        }
+        if let Some(status) = &self.file_status {
+            ui.label(status);
+        }
     }
 
-    fn pause(&mut self, is_paused: bool) {
-        match self.view_model.lock() {
-            Ok(mut m) => {
-                m.is_paused = is_paused
+    fn render_menu(&mut self, ui: &mut egui::Ui) {
+        egui::menu::bar(ui, |ui| {
+            ui.menu_button("File", |ui| {
+                if ui.button("Open capture…").clicked() {
+                    self.file_dialog = Some((FileAction::OpenCapture, String::new()));
+                    ui.close_menu();
+                }
+                if ui.button("Save capture…").clicked() {
+                    self.file_dialog = Some((FileAction::SaveCapture, String::new()));
+                    ui.close_menu();
+                }
+                if ui.button("Save selection…").clicked() {
+                    self.file_dialog = Some((FileAction::SaveSelection, String::new()));
+                    ui.close_menu();
+                }
+            });
+            ui.menu_button("Capture", |ui| {
+                if ui.button("Send query…").clicked() {
+                    self.query_window.get_or_insert_with(QueryWindow::new);
+                    ui.close_menu();
+                }
+                if ui.button("Craft message…").clicked() {
+                    self.crafter_window.get_or_insert_with(MessageCrafterWindow::new);
+                    ui.close_menu();
+                }
+                if ui.button("Resolve name…").clicked() {
+                    self.resolve_window.get_or_insert_with(ResolveWindow::new);
+                    ui.close_menu();
+                }
+                if ui.button("Publish records…").clicked() {
+                    let addresses = match &self.source {
+                        CaptureSource::Interface(interface, _) => interface.ips.iter().map(|network| network.ip()).collect(),
+                        CaptureSource::File(_) => vec![],
+                    };
+                    self.responder_window.get_or_insert_with(|| ResponderWindow::new(&addresses));
+                    ui.close_menu();
+                }
+                if ui.button("Conflict test…").clicked() {
+                    self.conflict_test_window.get_or_insert_with(ConflictTestWindow::new);
+                    ui.close_menu();
+                }
+                if ui.button("Replay…").clicked() {
+                    let interface_name = match &self.source {
+                        CaptureSource::Interface(interface, _) => Some(interface.name.clone()),
+                        CaptureSource::File(_) => None,
+                    };
+                    self.replay_window.get_or_insert_with(|| ReplayWindow::new(interface_name));
+                    ui.close_menu();
+                }
+                if ui.button("Load generator…").clicked() {
+                    self.load_generator_window.get_or_insert_with(LoadGeneratorWindow::new);
+                    ui.close_menu();
+                }
+                if ui.button("Retention…").clicked() {
+                    self.retention_window = Some(RetentionSettingsWindow::new(self.message_store.policy(), &self.message_store.state()));
+                    ui.close_menu();
+                }
+            });
+            ui.menu_button("View", |ui| {
+                ui.menu_button("Time format", |ui| {
+                    for timestamp_format in TimestampFormat::ALL {
+                        if ui.radio(self.message_table.timestamp_format() == timestamp_format, timestamp_format.to_string()).clicked() {
+                            self.message_table.set_timestamp_format(timestamp_format);
+                            ui.close_menu();
+                        }
+                    }
+                });
+                ui.menu_button("Protocols", |ui| {
+                    for protocol in Protocol::ALL {
+                        let mut visible = self.message_table.is_protocol_visible(protocol);
+                        if ui.checkbox(&mut visible, protocol.to_string()).changed() {
+                            self.message_table.set_protocol_visible(protocol, visible);
+                        }
+                    }
+                });
+                ui.menu_button("Columns", |ui| {
+                    for column in OptionalColumn::ALL {
+                        let mut visible = self.message_table.is_column_visible(column);
+                        if ui.checkbox(&mut visible, column.title()).changed() {
+                            self.message_table.set_column_visible(column, visible);
+                        }
+                    }
+                });
+            });
+        });
+    }
+
+    fn render_status_bar(&self, ui: &mut egui::Ui) {
+        let stats = match &self.capture {
+            Some(capture) => capture.stats(),
+            None => {
+                ui.label("No capture running.");
+                return;
             }
-            Err(_) => {
-                panic!("Nope.")
+        };
+        ui.horizontal_wrapped(|ui| {
+            ui.label(format!("Frames: {}", stats.frames_seen));
+            ui.separator();
+            ui.label(format!("IPv4: {}", stats.ipv4));
+            ui.label(format!("IPv6: {}", stats.ipv6));
+            ui.separator();
+            ui.label(format!("Messages: {}", stats.messages));
+            ui.label(format!("Other: {}", stats.other_frames));
+            ui.separator();
+            let errors = format!("Parse errors: {}  Checksum errors: {}", stats.parse_errors, stats.checksum_errors);
+            match stats.parse_errors + stats.checksum_errors {
+                0 => ui.label(errors),
+                _ => ui.colored_label(ui.visuals().warn_fg_color, errors),
             }
+            .on_hover_text("Checksum errors on frames sent by this host usually mean the network card computes the checksums.");
+            ui.separator();
+            match stats.kernel_drops {
+                Some(0) => ui.label("Kernel drops: 0"),
+                Some(drops) => ui.colored_label(ui.visuals().warn_fg_color, format!("Kernel drops: {}", drops)),
+                None => ui.label("Kernel drops: n/a"),
+            };
+            ui.separator();
+            ui.label(format!("Backlog: {} queued, {} dropped", stats.backlog.queued, stats.backlog.dropped));
+        });
+    }
+
+    fn format_retention(state: &RetentionState) -> String {
+        let mut text = format!("Retaining {} messages ({:.1} MB)", state.retained, state.bytes as f64 / (1024.0 * 1024.0));
+        if state.evicted > 0 {
+            text += &format!(", {} evicted", state.evicted);
+        }
+        if let Some(path) = &state.spill_path {
+            text += &format!(", {} written to {}", state.spilled, path.display());
+        }
+        if let Some(error) = &state.spill_error {
+            text += &format!(". {}", error);
+        }
+        text
+    }
+
+    fn render_retention_window(&mut self, ctx: &egui::Context) {
+        let action = match &mut self.retention_window {
+            Some(w) => w.render(ctx),
+            None => return
+        };
+        match action {
+            Some(RetentionAction::Apply(policy, spill_path)) => {
+                self.apply_retention(policy, spill_path);
+                self.retention_window = None;
+            }
+            Some(RetentionAction::OpenSession(path)) => {
+                self.source = CaptureSource::File(path);
+                self.start_capture();
+                self.retention_window = None;
+            }
+            Some(RetentionAction::Close) => self.retention_window = None,
+            None => {}
+        }
+    }
+
+    fn render_query_window(&mut self, ctx: &egui::Context) {
+        let interface = match &self.source {
+            CaptureSource::Interface(interface, _) => Some(interface.clone()),
+            CaptureSource::File(_) => None,
+        };
+        let running = self.continuous_query.as_ref().map(|q| q.id());
+        let focused = self.message_table.focused_overview(&self.message_store);
+        let action = match &mut self.query_window {
+            Some(w) => w.render(ctx, interface.is_some(), running, self.query_status.as_deref(), focused),
+            None => return
+        };
+        let (query, interval) = match action {
+            Some(QueryAction::Send(query)) => (query, None),
+            Some(QueryAction::Start(query, interval)) => (query, Some(interval)),
+            Some(QueryAction::Stop) => {
+                self.continuous_query = None;
+                self.query_status = running.map(|id| format!("Stopped query #{}.", id));
+                return;
+            }
+            Some(QueryAction::Close) => {
+                self.continuous_query = None;
+                self.query_window = None;
+                return;
+            }
+            None => return
+        };
+        let interface = match interface {
+            Some(i) => i,
+            None => return
+        };
+        self.continuous_query = None;
+        let result = QuerySender::open(&QuerySenderConfig::for_interface(&interface), self.query_log.clone()).and_then(|sender| match interval {
+            None => sender.send(&query).map(|id| format!("Sent query #{}.", id)),
+            Some(interval) => ContinuousQuery::start(sender, &query, interval).map(|continuous_query| {
+                let status = format!("Sending query #{} every {:.1} s.", continuous_query.id(), interval.as_secs_f64());
+                self.continuous_query = Some(continuous_query);
+                status
+            }),
+        });
+        self.query_status = Some(match result {
+            Ok(status) => status,
+            Err(e) => format!("Could not send the query on {}: {}", interface.description, e),
+        });
+    }
+
+    fn render_crafter_window(&mut self, ctx: &egui::Context) {
+        let config = self.sender_config();
+        let closed = match &mut self.crafter_window {
+            Some(w) => w.render(ctx, config),
+            None => return
+        };
+        if closed {
+            self.crafter_window = None;
+        }
+    }
+
+    /// Where the windows which put messages on the network send them, `None` for capture files.
+    fn sender_config(&self) -> Option<QuerySenderConfig> {
+        match &self.source {
+            CaptureSource::Interface(interface, _) => Some(QuerySenderConfig::for_interface(interface)),
+            CaptureSource::File(_) => None,
+        }
+    }
+
+    fn render_resolve_window(&mut self, ctx: &egui::Context) {
+        let config = self.sender_config();
+        let action = match &mut self.resolve_window {
+            Some(w) => w.render(ctx, config, &self.query_log, &self.message_store),
+            None => return
+        };
+        match action {
+            Some(ResolveAction::SelectMessages(sequences)) => {
+                self.tab = Tab::Messages;
+                self.message_table.select_rows(&sequences);
+            }
+            Some(ResolveAction::Close) => self.resolve_window = None,
+            None => {}
+        }
+    }
+
+    fn render_conflict_test_window(&mut self, ctx: &egui::Context) {
+        let config = self.sender_config();
+        let focused = self.message_table.focused_overview(&self.message_store);
+        let closed = match &mut self.conflict_test_window {
+            Some(w) => w.render(ctx, config, focused),
+            None => return
+        };
+        if closed {
+            self.conflict_test_window = None;
         }
     }
+
+    fn render_replay_window(&mut self, ctx: &egui::Context) {
+        let selected = self.message_table.selected_overviews(&self.message_store);
+        let closed = match &mut self.replay_window {
+            Some(w) => w.render(ctx, &self.interfaces, &selected),
+            None => return
+        };
+        // Closing the window stops a running replay.
+        if closed {
+            self.replay_window = None;
+        }
+    }
+
+    fn render_load_generator_window(&mut self, ctx: &egui::Context) {
+        let config = self.sender_config();
+        let closed = match &mut self.load_generator_window {
+            Some(w) => w.render(ctx, config),
+            None => return
+        };
+        // Closing the window stops a running load.
+        if closed {
+            self.load_generator_window = None;
+        }
+    }
+
+    fn render_responder_window(&mut self, ctx: &egui::Context) {
+        let interface = match &self.source {
+            CaptureSource::Interface(interface, _) => Some(interface.clone()),
+            CaptureSource::File(_) => None,
+        };
+        let status = self.responder.as_ref().map(|r| r.status());
+        let action = match &mut self.responder_window {
+            Some(w) => w.render(ctx, interface.is_some(), status.as_deref(), self.responder_error.as_deref()),
+            None => return
+        };
+        match action {
+            Some(ResponderAction::Publish(records)) => {
+                if let Some(responder) = &self.responder {
+                    responder.publish(records);
+                    return;
+                }
+                let interface = match interface {
+                    Some(i) => i,
+                    None => return
+                };
+                match Responder::start(&QuerySenderConfig::for_interface(&interface), records) {
+                    Ok(responder) => {
+                        self.responder = Some(responder);
+                        self.responder_error = None;
+                    }
+                    Err(e) => self.responder_error = Some(format!("Could not publish records on {}: {}", interface.description, e)),
+                }
+            }
+            Some(ResponderAction::Stop) => self.responder = None,
+            // The responder keeps publishing, the window can be opened again to change or stop it.
+            Some(ResponderAction::Close) => self.responder_window = None,
+            None => {}
+        }
+    }
+
+    fn render_file_window(&mut self, ctx: &egui::Context) {
+        let (action, mut path) = match self.file_dialog.take() {
+            Some(d) => d,
+            None => return
+        };
+        let (title, button) = match action {
+            FileAction::OpenCapture => ("Open capture", "Open"),
+            FileAction::SaveCapture => ("Save capture", "Save"),
+            FileAction::SaveSelection => ("Save selection", "Save"),
+        };
+        let mut confirm = false;
+        let mut cancel = false;
+        egui::Window::new(title)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                match action {
+                    FileAction::OpenCapture => ui.label("Path to a pcap or pcapng file:"),
+                    FileAction::SaveCapture | FileAction::SaveSelection => ui.label("Path of the pcapng file to write:"),
+                };
+                ui.text_edit_singleline(&mut path);
+                ui.horizontal(|ui| {
+                    confirm = ui.button(button).clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+        if confirm && !path.trim().is_empty() {
+            let path = PathBuf::from(path.trim());
+            match action {
+                FileAction::OpenCapture => {
+                    self.source = CaptureSource::File(path);
+                    self.start_capture();
+                }
+                FileAction::SaveCapture => {
+                    let overviews = self.message_store.messages().iter().collect::<Vec<_>>();
+                    self.file_status = Some(Self::save_capture(&path, &overviews));
+                }
+                FileAction::SaveSelection => {
+                    let overviews = self.message_table.selected_overviews(&self.message_store);
+                    self.file_status = Some(Self::save_capture(&path, &overviews));
+                }
+            }
+        } else if !cancel {
+            self.file_dialog = Some((action, path));
+        }
+    }
+
+    /// Writes the messages to a pcapng file and describes the outcome.
+    fn save_capture(path: &Path, overviews: &[&MdnsMessageOverview]) -> String {
+        match Self::write_pcapng(path, overviews) {
+            Ok(_) => format!("Saved {} messages to {}", overviews.len(), path.display()),
+            Err(e) => format!("Could not save {}: {}", path.display(), e),
+        }
+    }
+
+    fn write_pcapng(path: &Path, overviews: &[&MdnsMessageOverview]) -> io::Result<()> {
+        let mut writer = PcapNgWriter::create(path)?;
+        for overview in overviews {
+            overview.write_to(&mut writer)?;
+        }
+        writer.finish()?;
+        Ok(())
+    }
 }
 
-impl eframe::App for MdnsExplorerUi<'_> {
+impl eframe::App for MdnsExplorerUi {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.receive_messages();
+        egui::TopBottomPanel::top("menu").show(ctx, |ui| {
+            self.render_menu(ui);
+        });
+        self.render_file_window(ctx);
+        self.render_retention_window(ctx);
+        self.render_query_window(ctx);
+        self.render_crafter_window(ctx);
+        self.render_resolve_window(ctx);
+        self.render_responder_window(ctx);
+        self.render_conflict_test_window(ctx);
+        self.render_replay_window(ctx);
+        self.render_load_generator_window(ctx);
+        egui::TopBottomPanel::bottom("status").show(ctx, |ui| {
+            self.render_status_bar(ui);
+        });
+        self.service_browser.update();
+        if self.tab == Tab::Messages {
+            egui::TopBottomPanel::bottom("details")
+                .resizable(true)
+                .default_height(200.0)
+                .show(ctx, |ui| {
+                    let focused = self.message_table.focused_overview(&self.message_store);
+                    if let Some(overview) = focused
+                        && ui.button("Edit and resend").on_hover_text("Open the message in the crafter.").clicked() {
+                        self.crafter_window = Some(MessageCrafterWindow::from_overview(overview));
+                    }
+                    MessageDetailView::render(ui, focused);
+                });
+        }
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.heading("MDNS Explorer");
-            ui.label(format!("Capturing on interface: {}", self.interface_name));
-
-            let is_paused = self.is_paused();
-            let pause_button_label = if is_paused { "Unpause" } else { "Pause" };
-            if ui.button(pause_button_label).clicked() {
-                self.pause(!is_paused);
-            }
+            self.render_capture_controls(ui);
             ui.separator();
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.tab, Tab::Messages, "Messages");
+                ui.selectable_value(&mut self.tab, Tab::ServiceBrowser, "Service browser");
+            });
+            ui.separator();
+            if self.tab == Tab::ServiceBrowser {
+                let interface = match &self.source {
+                    CaptureSource::Interface(interface, _) => Some(interface),
+                    CaptureSource::File(_) => None,
+                };
+                self.service_browser.render(ui, interface, &self.query_log, &self.message_store);
+                return;
+            }
             let body_text_size = TextStyle::Body.resolve(ui.style()).size;
             let reset = false;
             StripBuilder::new(ui)
@@ -91,7 +814,8 @@ impl eframe::App for MdnsExplorerUi<'_> {
                 .vertical(|mut strip| {
                     strip.cell(|ui| {
                         egui::ScrollArea::horizontal().show(ui, |ui| {
-                            MdnsMessageTable::new(self.get_overviews()).render(ui, reset);
+                            self.message_table.sync(&self.message_store);
+                            self.message_table.render(ui, &self.message_store, reset);
                         });
                     });
                     strip.cell(|ui| {
@@ -105,4 +829,13 @@ impl eframe::App for MdnsExplorerUi<'_> {
         // That way user can keep the window open and see the incoming packets without interacting with the window.
         ctx.request_repaint();
     }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.continuous_query = None;
+        self.service_browser.stop();
+        self.responder = None;
+        self.replay_window = None;
+        self.load_generator_window = None;
+        self.stop_capture();
+    }
 }
@@ -0,0 +1,296 @@
+use crate::mdns::capture::udp_datagram;
+use crate::mdns::message_crafter::{format_hex, parse_hex, send_crafted, CraftedMessage, CraftedRecord, RecordSection};
+use crate::mdns::parser::parse_mdns_message_with_diagnostics;
+use crate::mdns::query_sender::QuerySenderConfig;
+use crate::mdns::types::{MDNSQueryClass, MDNSQuestion, MDNSRecordType};
+use crate::mdnsexplorer_ui::mdns_message_table::MdnsMessageOverview;
+use crate::mdnsexplorer_ui::message_detail_view::MessageDetailView;
+use eframe::egui;
+
+/// Window for building a message by hand, field by field or as raw hex, and sending it to the group.
+pub struct MessageCrafterWindow {
+    message: CraftedMessage,
+    /// Whether the raw hex is edited rather than the fields.
+    raw: bool,
+    hex: String,
+    /// The fields as they were decoded from the hex. Until they are edited, the hex is sent and kept as it is.
+    decoded: Option<CraftedMessage>,
+    status: Option<String>,
+    error: Option<String>,
+}
+
+impl MessageCrafterWindow {
+    pub fn new() -> Self {
+        Self::with_message(CraftedMessage::query("_services._dns-sd._udp.local", MDNSRecordType::PTR), None)
+    }
+
+    /// Starts from a captured message in raw mode, so the captured payload is sent as it is, with the parts
+    /// the fields cannot hold, like EDNS options.
+    pub fn from_overview(overview: &MdnsMessageOverview) -> Self {
+        let payload = udp_datagram(overview.link_type, &overview.frame).map(|d| d.payload.to_vec());
+        Self::with_message(CraftedMessage::from_message(&overview.message), payload)
+    }
+
+    fn with_message(message: CraftedMessage, payload: Option<Vec<u8>>) -> Self {
+        let (raw, hex) = match payload {
+            Some(p) => (true, format_hex(&p)),
+            None => (false, message.encode().map(|p| format_hex(&p)).unwrap_or_default()),
+        };
+        Self {
+            decoded: raw.then(|| message.clone()),
+            message,
+            raw,
+            hex,
+            status: None,
+            error: None,
+        }
+    }
+
+    /// `config` is where the message is sent, `None` for capture files. Returns true when the user closed the window.
+    pub fn render(&mut self, ctx: &egui::Context, config: Option<QuerySenderConfig>) -> bool {
+        let mut closed = false;
+        egui::Window::new("Message crafter")
+            .collapsible(false)
+            .resizable(true)
+            .default_width(700.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.selectable_label(!self.raw, "Fields").clicked() && self.raw {
+                        self.switch_to_fields();
+                    }
+                    if ui.selectable_label(self.raw, "Raw hex").clicked() && !self.raw {
+                        self.switch_to_raw();
+                    }
+                });
+                ui.separator();
+                egui::ScrollArea::vertical().max_height(350.0).id_salt("crafter_editor").show(ui, |ui| {
+                    match self.raw {
+                        true => {
+                            ui.add(egui::TextEdit::multiline(&mut self.hex)
+                                .font(egui::TextStyle::Monospace)
+                                .desired_rows(8)
+                                .desired_width(f32::INFINITY)
+                                .hint_text("00 00 00 00 00 01 …"));
+                        }
+                        false => self.render_fields(ui),
+                    }
+                });
+                ui.separator();
+                let payload = self.payload();
+                ui.strong("Decoded");
+                egui::ScrollArea::vertical().max_height(200.0).id_salt("crafter_decoded").show(ui, |ui| {
+                    match &payload {
+                        Ok(bytes) => Self::render_decoded(ui, bytes),
+                        Err(e) => {
+                            ui.colored_label(ui.visuals().error_fg_color, e);
+                        }
+                    }
+                });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    let send = ui.add_enabled(config.is_some() && payload.is_ok(), egui::Button::new("Send"))
+                        .on_disabled_hover_text("Messages are sent on the captured interface, select one instead of a capture file.");
+                    if send.clicked()
+                        && let (Some(config), Ok(bytes)) = (&config, &payload) {
+                        match send_crafted(config, bytes) {
+                            Ok(_) => {
+                                self.status = Some(format!("Sent {} bytes.", bytes.len()));
+                                self.error = None;
+                            }
+                            Err(e) => self.error = Some(format!("Could not send: {}", e)),
+                        }
+                    }
+                    if ui.button("Close").clicked() {
+                        closed = true;
+                    }
+                });
+                if let Some(error) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+                if let Some(status) = &self.status {
+                    ui.label(status);
+                }
+            });
+        closed
+    }
+
+    /// The bytes that would be sent, from the fields once they were edited, otherwise from the hex.
+    pub fn payload(&self) -> Result<Vec<u8>, String> {
+        match self.raw || self.decoded.as_ref() == Some(&self.message) {
+            true => parse_hex(&self.hex),
+            false => self.message.encode(),
+        }
+    }
+
+    /// The hex is only encoded again from the fields when they were edited.
+    fn switch_to_raw(&mut self) {
+        if self.decoded.as_ref() == Some(&self.message) {
+            self.raw = true;
+            self.error = None;
+            return;
+        }
+        match self.message.encode() {
+            Ok(payload) => {
+                self.hex = format_hex(&payload);
+                self.raw = true;
+                self.error = None;
+            }
+            Err(e) => self.error = Some(format!("Fix the fields first: {}", e)),
+        }
+    }
+
+    /// Only hex which decodes can be edited as fields.
+    fn switch_to_fields(&mut self) {
+        let decoded = parse_hex(&self.hex).and_then(|payload| parse_mdns_message_with_diagnostics(&payload).map_err(|e| e.to_string()));
+        match decoded {
+            Ok((message, _)) => {
+                self.message = CraftedMessage::from_message(&message);
+                self.decoded = Some(self.message.clone());
+                self.raw = false;
+                self.error = None;
+            }
+            Err(e) => self.error = Some(format!("The hex cannot be edited as fields: {}", e)),
+        }
+    }
+
+    fn render_fields(&mut self, ui: &mut egui::Ui) {
+        let message = &mut self.message;
+        ui.strong("Header");
+        egui::Grid::new("crafter_header").num_columns(2).show(ui, |ui| {
+            ui.label("ID");
+            ui.add(egui::DragValue::new(&mut message.id));
+            ui.end_row();
+            ui.label("Flags");
+            ui.horizontal_wrapped(|ui| {
+                let flags = &mut message.flags;
+                ui.checkbox(&mut flags.response, "QR");
+                ui.label("Opcode");
+                ui.add(egui::DragValue::new(&mut flags.opcode).range(0..=15));
+                ui.checkbox(&mut flags.authoritative, "AA");
+                ui.checkbox(&mut flags.truncated, "TC");
+                ui.checkbox(&mut flags.recursion_desired, "RD");
+                ui.checkbox(&mut flags.recursion_available, "RA");
+                ui.checkbox(&mut flags.zero, "Z");
+                ui.checkbox(&mut flags.authentic_data, "AD");
+                ui.checkbox(&mut flags.checking_disabled, "CD");
+                ui.label("RCODE");
+                ui.add(egui::DragValue::new(&mut flags.response_code).range(0..=15));
+                ui.monospace(format!("0x{:04x}", flags.to_u16()));
+            });
+            ui.end_row();
+        });
+
+        ui.separator();
+        ui.strong("Questions");
+        let mut removed = None;
+        egui::Grid::new("crafter_questions").num_columns(5).show(ui, |ui| {
+            for (index, question) in message.questions.iter_mut().enumerate() {
+                ui.add(egui::TextEdit::singleline(&mut question.name).desired_width(220.0));
+                Self::record_type_combo(ui, ("crafter_question_type", index), &mut question.question_type);
+                Self::class_combo(ui, ("crafter_question_class", index), &mut question.question_class);
+                ui.checkbox(&mut question.unicast_response, "QU");
+                if ui.button("Remove").clicked() {
+                    removed = Some(index);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(index) = removed {
+            message.questions.remove(index);
+        }
+        if ui.button("Add question").clicked() {
+            message.questions.push(MDNSQuestion {
+                name: String::new(),
+                question_type: MDNSRecordType::PTR,
+                question_class: MDNSQueryClass::IN,
+                unicast_response: false,
+            });
+        }
+
+        ui.separator();
+        ui.strong("Records");
+        let mut removed = None;
+        egui::Grid::new("crafter_records").num_columns(9).show(ui, |ui| {
+            for (index, record) in message.records.iter_mut().enumerate() {
+                egui::ComboBox::from_id_salt(("crafter_record_section", index))
+                    .selected_text(record.section.to_string())
+                    .show_ui(ui, |ui| {
+                        for section in RecordSection::ALL {
+                            ui.selectable_value(&mut record.section, section, section.to_string());
+                        }
+                    });
+                ui.add(egui::TextEdit::singleline(&mut record.name).desired_width(180.0));
+                Self::record_type_combo(ui, ("crafter_record_type", index), &mut record.record_type);
+                Self::class_combo(ui, ("crafter_record_class", index), &mut record.record_class);
+                ui.checkbox(&mut record.cache_flush, "Flush");
+                ui.add(egui::DragValue::new(&mut record.ttl_seconds).range(0..=u32::MAX).suffix(" s"));
+                // TXT data holds one string per line.
+                let data = match record.record_type == MDNSRecordType::TXT && !record.hex {
+                    true => egui::TextEdit::multiline(&mut record.data).desired_rows(2),
+                    false => egui::TextEdit::singleline(&mut record.data),
+                };
+                ui.add(data.desired_width(200.0).hint_text("data"))
+                    .on_hover_text("Written as in zone files, e.g. priority weight port target for SRV, or as hex.");
+                ui.checkbox(&mut record.hex, "Hex")
+                    .on_hover_text("Put the data on the wire as is, for any record type.");
+                if ui.button("Remove").clicked() {
+                    removed = Some(index);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(index) = removed {
+            message.records.remove(index);
+        }
+        if ui.button("Add record").clicked() {
+            let name = message.questions.first().map(|q| q.name.clone()).unwrap_or_default();
+            message.records.push(CraftedRecord {
+                section: RecordSection::Answer,
+                name,
+                record_type: MDNSRecordType::A,
+                record_class: MDNSQueryClass::IN,
+                cache_flush: true,
+                ttl_seconds: 120,
+                data: String::new(),
+                hex: false,
+            });
+        }
+    }
+
+    fn record_type_combo(ui: &mut egui::Ui, id: impl std::hash::Hash, record_type: &mut MDNSRecordType) {
+        egui::ComboBox::from_id_salt(id)
+            .selected_text(record_type.to_string())
+            .show_ui(ui, |ui| {
+                for t in MDNSRecordType::ALL {
+                    ui.selectable_value(record_type, t, t.to_string());
+                }
+            });
+    }
+
+    fn class_combo(ui: &mut egui::Ui, id: impl std::hash::Hash, class: &mut MDNSQueryClass) {
+        egui::ComboBox::from_id_salt(id)
+            .selected_text(format!("{:?}", class))
+            .show_ui(ui, |ui| {
+                for c in [MDNSQueryClass::IN, MDNSQueryClass::ANY] {
+                    let label = format!("{:?}", c);
+                    ui.selectable_value(class, c, label);
+                }
+            });
+    }
+
+    /// Shows what `parse_mdns_message` makes of the bytes.
+    fn render_decoded(ui: &mut egui::Ui, payload: &[u8]) {
+        let (message, diagnostics) = match parse_mdns_message_with_diagnostics(payload) {
+            Ok(m) => m,
+            Err(e) => {
+                ui.colored_label(ui.visuals().error_fg_color, format!("{} bytes, not decodable: {}", payload.len(), e));
+                return;
+            }
+        };
+        ui.label(format!("{} bytes", payload.len()));
+        ui.push_id("crafter_decoded_tree", |ui| MessageDetailView::render_message_tree(ui, &message));
+        for diagnostic in diagnostics {
+            ui.colored_label(ui.visuals().warn_fg_color, diagnostic);
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use crate::mdns::mdns_message::MDNSMessage;
+use crate::mdns::query_sender::QueryTag;
+use crate::mdns::types::{MDNSAnswer, MDNSHeaderFlags, MDNSQuestion};
+use crate::mdnsexplorer_ui::mdns_message_table::{MdnsMessageOverview, OptionalColumn};
+use crate::mdnsexplorer_ui::timestamp_format::{format_absolute, local_offset_at};
+use eframe::egui;
+
+/// Shows everything known about a single captured message.
+pub struct MessageDetailView;
+
+impl MessageDetailView {
+    pub fn render(ui: &mut egui::Ui, overview: Option<&MdnsMessageOverview>) {
+        let overview = match overview {
+            Some(o) => o,
+            None => {
+                ui.label("Select a row to see its details.");
+                return;
+            }
+        };
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            Self::render_message_tree(ui, &overview.message);
+            egui::CollapsingHeader::new("Capture")
+                .default_open(true)
+                .show(ui, |ui| Self::render_capture(ui, overview));
+        });
+    }
+
+    /// Header, questions and records of the message as a tree of collapsible sections.
+    pub fn render_message_tree(ui: &mut egui::Ui, message: &MDNSMessage) {
+        let header = &message.header;
+        egui::CollapsingHeader::new("Header")
+            .default_open(true)
+            .show(ui, |ui| {
+                Self::grid(ui, "message_header", |ui| {
+                    Self::field(ui, "ID", format!("{} (0x{:04x})", header.query_identifier, header.query_identifier));
+                    Self::field(ui, "Flags", format!("0x{:04x}", header.flags));
+                    Self::field(ui, "Questions", header.question_count.to_string());
+                    Self::field(ui, "Answers", header.answer_count.to_string());
+                    Self::field(ui, "Authority", header.authority_count.to_string());
+                    Self::field(ui, "Additional", header.additional_count.to_string());
+                });
+                egui::CollapsingHeader::new("Decoded flags")
+                    .show(ui, |ui| Self::render_flags(ui, MDNSHeaderFlags::from_u16(header.flags)));
+            });
+        egui::CollapsingHeader::new(format!("Questions ({})", message.questions.len()))
+            .id_salt("message_questions")
+            .default_open(true)
+            .show(ui, |ui| {
+                for (index, question) in message.questions.iter().enumerate() {
+                    Self::render_question(ui, index, question);
+                }
+            });
+        let sections = [("Answers", &message.answers), ("Authority", &message.authorities), ("Additional", &message.additionals)];
+        for (section, records) in sections {
+            egui::CollapsingHeader::new(format!("{} ({})", section, records.len()))
+                .id_salt(("message_section", section))
+                .default_open(!records.is_empty())
+                .show(ui, |ui| {
+                    for (index, record) in records.iter().enumerate() {
+                        Self::render_record(ui, (section, index), record);
+                    }
+                });
+        }
+    }
+
+    /// The bits of the flags field (RFC 1035 - 4.1.1).
+    fn render_flags(ui: &mut egui::Ui, flags: MDNSHeaderFlags) {
+        let set = |bit: bool| if bit { "1" } else { "0" }.to_string();
+        Self::grid(ui, "message_flags", |ui| {
+            Self::field(ui, "QR", format!("{} ({})", set(flags.response), if flags.response { "response" } else { "query" }));
+            Self::field(ui, "Opcode", flags.opcode.to_string());
+            Self::field(ui, "AA (authoritative)", set(flags.authoritative));
+            Self::field(ui, "TC (truncated)", set(flags.truncated));
+            Self::field(ui, "RD (recursion desired)", set(flags.recursion_desired));
+            Self::field(ui, "RA (recursion available)", set(flags.recursion_available));
+            Self::field(ui, "Z (reserved)", set(flags.zero));
+            Self::field(ui, "AD (authentic data)", set(flags.authentic_data));
+            Self::field(ui, "CD (checking disabled)", set(flags.checking_disabled));
+            Self::field(ui, "RCODE", flags.response_code.to_string());
+        });
+    }
+
+    fn render_question(ui: &mut egui::Ui, index: usize, question: &MDNSQuestion) {
+        egui::CollapsingHeader::new(format!("{} {}", question.question_type, question.name))
+            .id_salt(("message_question", index))
+            .show(ui, |ui| {
+                Self::grid(ui, ("message_question_fields", index), |ui| {
+                    Self::field(ui, "Name", question.name.clone());
+                    Self::field(ui, "Type", question.question_type.to_string());
+                    Self::field(ui, "Class", format!("{:?}", question.question_class));
+                    Self::field(ui, "Unicast response", question.unicast_response.to_string());
+                });
+            });
+    }
+
+    fn render_record(ui: &mut egui::Ui, id: (&str, usize), record: &MDNSAnswer) {
+        egui::CollapsingHeader::new(format!("{} {} => {}", record.answer_type, record.name, record.rdata))
+            .id_salt(("message_record", id))
+            .show(ui, |ui| {
+                Self::grid(ui, ("message_record_fields", id), |ui| {
+                    Self::field(ui, "Name", record.name.clone());
+                    Self::field(ui, "Type", record.answer_type.to_string());
+                    Self::field(ui, "Class", format!("{:?}", record.answer_class));
+                    Self::field(ui, "Cache flush", record.cache_flush.to_string());
+                    Self::field(ui, "TTL", format!("{} s", record.ttl_seconds));
+                    Self::field(ui, "RDLENGTH", record.rd_length.to_string());
+                    Self::field(ui, "RDATA", record.rdata.to_string());
+                });
+            });
+    }
+
+    fn render_capture(ui: &mut egui::Ui, overview: &MdnsMessageOverview) {
+        Self::grid(ui, "message_details", |ui| {
+            let received = overview.received_datetime;
+            Self::field(ui, "Received (UTC)", format_absolute(received));
+            Self::field(ui, "Received (local)", format_absolute(received.to_offset(local_offset_at(received))));
+            Self::field(ui, "Protocol", overview.protocol.to_string());
+            match overview.query_tag {
+                Some(QueryTag::Sent(id)) => Self::field(ui, "Own query", format!("Query #{} sent from MDNS Explorer", id)),
+                Some(QueryTag::Reply(id)) => Self::field(ui, "Own query", format!("Reply to query #{}", id)),
+                None => {}
+            }
+            Self::field(ui, "Interface", overview.interface_name.to_string());
+            Self::field(ui, "Link type", overview.link_type.to_string());
+            Self::field(ui, "Source IP", overview.source_ip.to_string());
+            for column in OptionalColumn::ALL {
+                Self::field(ui, column.title(), column.value(overview));
+            }
+            if !overview.diagnostics.is_empty() {
+                Self::field(ui, "Diagnostics", overview.diagnostics.join("\n"));
+            }
+        });
+    }
+
+    fn grid(ui: &mut egui::Ui, id: impl std::hash::Hash, add_contents: impl FnOnce(&mut egui::Ui)) {
+        egui::Grid::new(id)
+            .num_columns(2)
+            .striped(true)
+            .show(ui, add_contents);
+    }
+
+    fn field(ui: &mut egui::Ui, name: &str, value: String) {
+        ui.strong(name);
+        ui.label(value);
+        ui.end_row();
+    }
+}
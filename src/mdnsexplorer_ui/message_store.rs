@@ -0,0 +1,201 @@
+use crate::mdns::pcapng_writer::PcapNgWriter;
+use crate::mdnsexplorer_ui::mdns_message_table::MdnsMessageOverview;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use time::{Duration, OffsetDateTime};
+
+/// Limits for the messages kept in memory. `None` means unlimited.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetentionPolicy {
+    pub max_messages: Option<usize>,
+    /// Messages older than this, relative to the newest message, are evicted.
+    /// The newest message is used instead of the current time, so messages read from capture files are kept too.
+    pub max_age: Option<Duration>,
+    pub max_bytes: Option<usize>,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            max_messages: Some(100_000),
+            max_age: None,
+            max_bytes: Some(512 * 1024 * 1024),
+        }
+    }
+}
+
+/// What the store currently holds and what it has evicted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetentionState {
+    pub retained: usize,
+    pub bytes: usize,
+    pub evicted: u64,
+    pub spilled: u64,
+    pub spill_path: Option<PathBuf>,
+    pub spill_error: Option<String>,
+}
+
+/// Evicted messages are appended to a pcapng session file, which can be opened like any capture file.
+struct SessionSpill {
+    path: PathBuf,
+    writer: PcapNgWriter<BufWriter<File>>,
+    spilled: u64,
+}
+
+/// Ring buffer of the received messages which evicts the oldest ones according to the retention policy.
+/// Every message gets a sequence number, which stays the same while older messages are evicted.
+pub struct MessageStore {
+    messages: VecDeque<MdnsMessageOverview>,
+    bytes: usize,
+    policy: RetentionPolicy,
+    /// Number of messages removed from the front, which is the sequence number of the oldest retained message.
+    removed: u64,
+    evicted: u64,
+    spill: Option<SessionSpill>,
+    spill_error: Option<String>,
+    /// Receive time of the first message since the store was created or cleared.
+    session_start: Option<OffsetDateTime>,
+}
+
+impl MessageStore {
+    pub fn new(policy: RetentionPolicy) -> Self {
+        Self {
+            messages: VecDeque::new(),
+            bytes: 0,
+            policy,
+            removed: 0,
+            evicted: 0,
+            spill: None,
+            spill_error: None,
+            session_start: None,
+        }
+    }
+
+    /// Appends the messages and evicts what no longer fits the retention policy.
+    pub fn extend(&mut self, overviews: impl IntoIterator<Item = MdnsMessageOverview>) {
+        for overview in overviews {
+            if self.session_start.is_none() {
+                self.session_start = Some(overview.received_datetime);
+            }
+            self.bytes += overview.approximate_size();
+            self.messages.push_back(overview);
+        }
+        self.enforce_policy();
+    }
+
+    /// Drops all retained messages without spilling them.
+    pub fn clear(&mut self) {
+        self.removed += self.messages.len() as u64;
+        self.messages.clear();
+        self.bytes = 0;
+        self.session_start = None;
+    }
+
+    /// Receive time of the first message since the store was created or cleared, evicted or not.
+    pub fn session_start(&self) -> Option<OffsetDateTime> {
+        self.session_start
+    }
+
+    pub fn messages(&self) -> &VecDeque<MdnsMessageOverview> {
+        &self.messages
+    }
+
+    /// The message with the given sequence number, if it is still retained.
+    pub fn get(&self, sequence: u64) -> Option<&MdnsMessageOverview> {
+        let index = sequence.checked_sub(self.removed)?;
+        self.messages.get(index as usize)
+    }
+
+    /// Sequence number of the oldest retained message.
+    pub fn first_sequence(&self) -> u64 {
+        self.removed
+    }
+
+    pub fn policy(&self) -> RetentionPolicy {
+        self.policy
+    }
+
+    pub fn set_policy(&mut self, policy: RetentionPolicy) {
+        self.policy = policy;
+        self.enforce_policy();
+    }
+
+    /// Starts writing evicted messages to a new session file at the given path.
+    pub fn start_spill(&mut self, path: &Path) -> io::Result<()> {
+        self.stop_spill();
+        self.spill = Some(SessionSpill {
+            path: path.to_path_buf(),
+            writer: PcapNgWriter::create(path)?,
+            spilled: 0,
+        });
+        self.spill_error = None;
+        Ok(())
+    }
+
+    /// Finishes the session file. Evicted messages are discarded from now on.
+    pub fn stop_spill(&mut self) {
+        if let Some(spill) = self.spill.take()
+            && let Err(e) = spill.writer.finish() {
+            self.spill_error = Some(format!("Could not finish {}: {}", spill.path.display(), e));
+        }
+    }
+
+    pub fn state(&self) -> RetentionState {
+        RetentionState {
+            retained: self.messages.len(),
+            bytes: self.bytes,
+            evicted: self.evicted,
+            spilled: self.spill.as_ref().map_or(0, |s| s.spilled),
+            spill_path: self.spill.as_ref().map(|s| s.path.clone()),
+            spill_error: self.spill_error.clone(),
+        }
+    }
+
+    fn enforce_policy(&mut self) {
+        let mut evicted_any = false;
+        while self.exceeds_policy() {
+            let overview = match self.messages.pop_front() {
+                Some(o) => o,
+                None => break,
+            };
+            self.bytes -= overview.approximate_size();
+            self.removed += 1;
+            self.evicted += 1;
+            evicted_any = true;
+            self.spill_message(&overview);
+        }
+        // Flushing once per batch keeps the session file readable without writing every message separately.
+        if evicted_any
+            && let Some(spill) = &mut self.spill
+            && let Err(e) = spill.writer.flush() {
+            self.spill_error = Some(format!("Could not write {}: {}", spill.path.display(), e));
+            self.spill = None;
+        }
+    }
+
+    fn exceeds_policy(&self) -> bool {
+        let too_many = self.policy.max_messages.is_some_and(|max| self.messages.len() > max);
+        let too_large = self.policy.max_bytes.is_some_and(|max| self.bytes > max);
+        let too_old = match (self.policy.max_age, self.messages.front(), self.messages.back()) {
+            (Some(max_age), Some(oldest), Some(newest)) => newest.received_datetime - oldest.received_datetime > max_age,
+            _ => false,
+        };
+        too_many || too_large || too_old
+    }
+
+    fn spill_message(&mut self, overview: &MdnsMessageOverview) {
+        let spill = match &mut self.spill {
+            Some(s) => s,
+            None => return,
+        };
+        match overview.write_to(&mut spill.writer) {
+            Ok(_) => spill.spilled += 1,
+            Err(e) => {
+                self.spill_error = Some(format!("Could not write {}: {}", spill.path.display(), e));
+                self.spill = None;
+            }
+        }
+    }
+}
@@ -1,4 +1,17 @@
 pub mod mdnsexplorer_ui;
 pub mod mdns_message_table;
 pub mod interface_chooser_ui;
-pub mod confirmation_dialogue_ui;
\ No newline at end of file
+pub mod load_generator_ui;
+pub mod confirmation_dialogue_ui;
+pub mod conflict_test_ui;
+pub mod message_crafter_ui;
+pub mod message_detail_view;
+pub mod message_store;
+pub mod query_sender_ui;
+pub mod replay_ui;
+pub mod resolve_ui;
+pub mod responder_ui;
+pub mod retention_settings_ui;
+pub mod service_browser_ui;
+pub mod timestamp_format;
+mod tests;
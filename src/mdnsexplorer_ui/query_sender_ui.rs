@@ -0,0 +1,203 @@
+use crate::mdns::query_sender::Query;
+use crate::mdns::types::{MDNSAnswer, MDNSQueryClass, MDNSRData, MDNSRecordType};
+use crate::mdnsexplorer_ui::mdns_message_table::MdnsMessageOverview;
+use eframe::egui;
+use std::time::Duration;
+
+/// Types offered for the question.
+const QUESTION_TYPES: [MDNSRecordType; 8] = [
+    MDNSRecordType::PTR,
+    MDNSRecordType::SRV,
+    MDNSRecordType::TXT,
+    MDNSRecordType::A,
+    MDNSRecordType::AAAA,
+    MDNSRecordType::CNAME,
+    MDNSRecordType::NSEC,
+    MDNSRecordType::ANY,
+];
+
+/// Types whose data can be typed in as known answers.
+const KNOWN_ANSWER_TYPES: [MDNSRecordType; 6] = [
+    MDNSRecordType::PTR,
+    MDNSRecordType::SRV,
+    MDNSRecordType::TXT,
+    MDNSRecordType::A,
+    MDNSRecordType::AAAA,
+    MDNSRecordType::CNAME,
+];
+
+/// What the user decided in the query window.
+pub enum QueryAction {
+    Send(Query),
+    /// Send the query repeatedly with the given interval.
+    Start(Query, Duration),
+    Stop,
+    Close,
+}
+
+struct KnownAnswerRow {
+    name: String,
+    record_type: MDNSRecordType,
+    /// Record data as accepted by `MDNSRData::from_text`.
+    data: String,
+    ttl_seconds: u32,
+}
+
+/// Window for composing an mDNS query and sending it once or continuously.
+pub struct QueryWindow {
+    name: String,
+    record_type: MDNSRecordType,
+    unicast_response: bool,
+    known_answers: Vec<KnownAnswerRow>,
+    continuous: bool,
+    interval_seconds: f64,
+    error: Option<String>,
+}
+
+impl QueryWindow {
+    pub fn new() -> Self {
+        Self {
+            name: "_services._dns-sd._udp.local".to_string(),
+            record_type: MDNSRecordType::PTR,
+            unicast_response: false,
+            known_answers: vec![],
+            continuous: false,
+            interval_seconds: 1.0,
+            error: None,
+        }
+    }
+
+    /// `can_send` is false when there is no interface to send on, `running` is the id of the continuous query being sent.
+    /// Known answers can be copied from the `focused` message.
+    pub fn render(&mut self, ctx: &egui::Context, can_send: bool, running: Option<u32>, status: Option<&str>, focused: Option<&MdnsMessageOverview>) -> Option<QueryAction> {
+        let mut action = None;
+        egui::Window::new("Send query")
+            .collapsible(false)
+            .resizable(true)
+            .show(ctx, |ui| {
+                egui::Grid::new("query_question").num_columns(2).show(ui, |ui| {
+                    ui.label("Name");
+                    ui.add(egui::TextEdit::singleline(&mut self.name).desired_width(300.0));
+                    ui.end_row();
+                    ui.label("Type");
+                    Self::record_type_combo(ui, "query_type", &mut self.record_type, &QUESTION_TYPES);
+                    ui.end_row();
+                    ui.label("");
+                    ui.checkbox(&mut self.unicast_response, "Ask for a unicast response (QU)");
+                    ui.end_row();
+                });
+                ui.separator();
+                ui.label("Known answers");
+                let mut removed = None;
+                egui::Grid::new("query_known_answers").num_columns(5).show(ui, |ui| {
+                    for (index, row) in self.known_answers.iter_mut().enumerate() {
+                        ui.add(egui::TextEdit::singleline(&mut row.name).desired_width(200.0));
+                        Self::record_type_combo(ui, ("known_answer_type", index), &mut row.record_type, &KNOWN_ANSWER_TYPES);
+                        ui.add(egui::TextEdit::singleline(&mut row.data).desired_width(200.0).hint_text("data"))
+                            .on_hover_text("SRV data is written as: priority weight port target.");
+                        ui.add(egui::DragValue::new(&mut row.ttl_seconds).range(0..=u32::MAX).suffix(" s"));
+                        if ui.button("Remove").clicked() {
+                            removed = Some(index);
+                        }
+                        ui.end_row();
+                    }
+                });
+                if let Some(index) = removed {
+                    self.known_answers.remove(index);
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("Add known answer").clicked() {
+                        self.known_answers.push(KnownAnswerRow {
+                            name: self.name.clone(),
+                            record_type: self.record_type_for_known_answer(),
+                            data: String::new(),
+                            ttl_seconds: 4500,
+                        });
+                    }
+                    if ui.add_enabled(focused.is_some(), egui::Button::new("Add answers of the selected message")).clicked()
+                        && let Some(overview) = focused {
+                        self.known_answers.extend(overview.message.answers.iter().filter_map(Self::known_answer_row));
+                    }
+                });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.continuous, "Repeat every");
+                    ui.add_enabled(self.continuous, egui::DragValue::new(&mut self.interval_seconds).range(0.1..=3600.0).speed(0.1).suffix(" s"));
+                });
+                ui.horizontal(|ui| {
+                    let send_label = if self.continuous { "Start" } else { "Send" };
+                    let send = ui.add_enabled(can_send, egui::Button::new(send_label))
+                        .on_disabled_hover_text("Queries are sent on the captured interface, select one instead of a capture file.");
+                    if send.clicked() {
+                        match self.query() {
+                            Ok(query) if self.continuous => action = Some(QueryAction::Start(query, Duration::from_secs_f64(self.interval_seconds))),
+                            Ok(query) => action = Some(QueryAction::Send(query)),
+                            Err(e) => self.error = Some(e),
+                        }
+                    }
+                    if running.is_some() && ui.button("Stop").clicked() {
+                        action = Some(QueryAction::Stop);
+                    }
+                    if ui.button("Close").clicked() {
+                        action = Some(QueryAction::Close);
+                    }
+                });
+                if let Some(error) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+                if let Some(status) = status {
+                    ui.label(status);
+                }
+            });
+        action
+    }
+
+    fn record_type_combo(ui: &mut egui::Ui, id: impl std::hash::Hash, record_type: &mut MDNSRecordType, types: &[MDNSRecordType]) {
+        egui::ComboBox::from_id_salt(id)
+            .selected_text(record_type.to_string())
+            .show_ui(ui, |ui| {
+                for t in types {
+                    ui.selectable_value(record_type, *t, t.to_string());
+                }
+            });
+    }
+
+    fn record_type_for_known_answer(&self) -> MDNSRecordType {
+        match KNOWN_ANSWER_TYPES.contains(&self.record_type) {
+            true => self.record_type,
+            false => MDNSRecordType::PTR,
+        }
+    }
+
+    fn known_answer_row(answer: &MDNSAnswer) -> Option<KnownAnswerRow> {
+        let data = answer.rdata.to_text()?;
+        Some(KnownAnswerRow {
+            name: answer.name.clone(),
+            record_type: answer.answer_type,
+            data,
+            ttl_seconds: answer.ttl_seconds,
+        })
+    }
+
+    fn query(&mut self) -> Result<Query, String> {
+        let name = self.name.trim();
+        if name.is_empty() {
+            return Err("Enter the name to query.".to_string());
+        }
+        let mut query = Query::new(name, self.record_type, self.unicast_response);
+        for row in self.known_answers.iter() {
+            let rdata = MDNSRData::from_text(row.record_type, &row.data).map_err(|e| format!("Known answer {}: {}", row.name, e))?;
+            query.known_answers.push(MDNSAnswer {
+                name: row.name.trim().to_string(),
+                answer_type: row.record_type,
+                answer_class: MDNSQueryClass::IN,
+                cache_flush: false,
+                ttl_seconds: row.ttl_seconds,
+                rd_length: 0,
+                rdata,
+            });
+        }
+        self.error = None;
+        Ok(query)
+    }
+}
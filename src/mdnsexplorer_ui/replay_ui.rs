@@ -0,0 +1,230 @@
+use crate::mdns::replay::{read_replay_file, Replay, ReplayOptions, ReplayPacket, ReplayRecording, ReplaySource, ReplayTiming};
+use crate::mdns::socket_listener::MulticastGroup;
+use crate::mdnsexplorer_ui::mdns_message_table::MdnsMessageOverview;
+use eframe::egui;
+use pnet::datalink::NetworkInterface;
+use std::net::IpAddr;
+use std::path::Path;
+use std::time::Duration;
+
+/// Which source address the replayed packets get, as edited in the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SourceChoice {
+    Interface,
+    Original,
+    Address,
+}
+
+/// Window sending captured mDNS payloads again, to reproduce the traffic of another network in the lab.
+pub struct ReplayWindow {
+    /// Name of the interface the packets are sent on.
+    interface_name: Option<String>,
+    /// Where the packets came from and the packets themselves.
+    packets: Option<(String, Vec<ReplayPacket>)>,
+    path: String,
+    original_timing: bool,
+    rate: f64,
+    source: SourceChoice,
+    source_address: String,
+    rewrite_ip_ttl: bool,
+    ip_ttl: u8,
+    rewrite_record_ttl: bool,
+    record_ttl: u32,
+    replay: Option<Replay>,
+    error: Option<String>,
+}
+
+impl ReplayWindow {
+    /// Sends on the interface with the given name unless another one is picked.
+    pub fn new(interface_name: Option<String>) -> Self {
+        Self {
+            interface_name,
+            packets: None,
+            path: String::new(),
+            original_timing: true,
+            rate: 10.0,
+            source: SourceChoice::Interface,
+            source_address: String::new(),
+            rewrite_ip_ttl: false,
+            ip_ttl: 255,
+            rewrite_record_ttl: false,
+            record_ttl: 120,
+            replay: None,
+            error: None,
+        }
+    }
+
+    /// `selected` are the rows of the message table which can be replayed instead of a capture file.
+    /// Returns true when the user closed the window.
+    pub fn render(&mut self, ctx: &egui::Context, interfaces: &[NetworkInterface], selected: &[&MdnsMessageOverview]) -> bool {
+        let mut closed = false;
+        egui::Window::new("Replay")
+            .collapsible(false)
+            .resizable(true)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let use_selected = ui.add_enabled(!selected.is_empty(), egui::Button::new(format!("Use {} selected rows", selected.len())))
+                        .on_disabled_hover_text("Select the messages to replay in the table.");
+                    if use_selected.clicked() {
+                        self.use_selected(selected);
+                    }
+                    ui.label("or");
+                    ui.add(egui::TextEdit::singleline(&mut self.path).desired_width(250.0).hint_text("capture.pcapng"));
+                    if ui.button("Load").clicked() {
+                        self.load_file();
+                    }
+                });
+                match &self.packets {
+                    Some((description, packets)) => ui.label(format!("{} mDNS packets from {}.", packets.len(), description)),
+                    None => ui.label("Nothing to replay yet."),
+                };
+                ui.separator();
+                self.render_options(ui, interfaces);
+                ui.separator();
+                ui.horizontal(|ui| {
+                    let running = self.replay.as_ref().is_some_and(|r| !r.status().finished);
+                    let can_start = self.packets.as_ref().is_some_and(|(_, p)| !p.is_empty()) && self.interface_name.is_some();
+                    if ui.add_enabled(can_start && !running, egui::Button::new("Start")).clicked() {
+                        self.start(interfaces);
+                    }
+                    if ui.add_enabled(running, egui::Button::new("Stop")).clicked()
+                        && let Some(replay) = &mut self.replay {
+                        replay.stop();
+                    }
+                    if ui.button("Close").clicked() {
+                        closed = true;
+                    }
+                });
+                if let Some(replay) = &self.replay {
+                    let status = replay.status();
+                    let done = status.sent + status.skipped;
+                    let progress = if status.total == 0 { 1.0 } else { done as f32 / status.total as f32 };
+                    ui.add(egui::ProgressBar::new(progress).text(format!("Sent {} of {}", status.sent, status.total)));
+                    if status.skipped > 0 {
+                        ui.label(format!("Skipped {} packets of the other address family.", status.skipped));
+                    }
+                    if let Some(error) = &status.last_error {
+                        ui.colored_label(ui.visuals().error_fg_color, format!("Last error: {}", error));
+                    }
+                }
+                if let Some(error) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+            });
+        if self.replay.as_ref().is_some_and(|r| !r.status().finished) {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+        closed
+    }
+
+    fn render_options(&mut self, ui: &mut egui::Ui, interfaces: &[NetworkInterface]) {
+        egui::Grid::new("replay_options").num_columns(2).show(ui, |ui| {
+            ui.label("Interface");
+            let selected_text = interfaces.iter()
+                .find(|i| Some(&i.name) == self.interface_name.as_ref())
+                .map(|i| i.description.clone())
+                .unwrap_or_else(|| "(pick an interface)".to_string());
+            egui::ComboBox::from_id_salt("replay_interface")
+                .selected_text(selected_text)
+                .show_ui(ui, |ui| {
+                    for interface in interfaces.iter() {
+                        if ui.selectable_label(Some(&interface.name) == self.interface_name.as_ref(), &interface.description).clicked() {
+                            self.interface_name = Some(interface.name.clone());
+                        }
+                    }
+                });
+            ui.end_row();
+            ui.label("Timing");
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut self.original_timing, true, "Original");
+                ui.radio_value(&mut self.original_timing, false, "Fixed rate");
+                ui.add_enabled(!self.original_timing, egui::DragValue::new(&mut self.rate).range(0.1..=10000.0).speed(1.0).suffix(" packets/s"));
+            });
+            ui.end_row();
+            ui.label("Source address");
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut self.source, SourceChoice::Interface, "Interface");
+                ui.radio_value(&mut self.source, SourceChoice::Original, "Captured");
+                ui.radio_value(&mut self.source, SourceChoice::Address, "Rewrite to");
+                ui.add_enabled(self.source == SourceChoice::Address, egui::TextEdit::singleline(&mut self.source_address).desired_width(150.0).hint_text("192.168.1.50"));
+            }).response.on_hover_text("Captured and rewritten sources are written to the link layer, which needs elevated privileges.");
+            ui.end_row();
+            ui.label("IP TTL");
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.rewrite_ip_ttl, "Rewrite to");
+                ui.add_enabled(self.rewrite_ip_ttl, egui::DragValue::new(&mut self.ip_ttl).range(1..=255));
+            });
+            ui.end_row();
+            ui.label("Record TTL");
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.rewrite_record_ttl, "Rewrite to");
+                ui.add_enabled(self.rewrite_record_ttl, egui::DragValue::new(&mut self.record_ttl).range(0..=u32::MAX).suffix(" s"));
+            });
+            ui.end_row();
+        });
+    }
+
+    fn use_selected(&mut self, selected: &[&MdnsMessageOverview]) {
+        let mut recording = ReplayRecording::new();
+        for overview in selected {
+            recording.push_frame(overview.link_type, &overview.frame, overview.received_datetime);
+        }
+        self.packets = Some((format!("{} selected rows", selected.len()), recording.into_packets()));
+        self.error = None;
+    }
+
+    fn load_file(&mut self) {
+        let path = self.path.trim();
+        match read_replay_file(Path::new(path)) {
+            Ok(packets) => {
+                self.packets = Some((path.to_string(), packets));
+                self.error = None;
+            }
+            Err(e) => self.error = Some(format!("Could not read {}: {}", path, e)),
+        }
+    }
+
+    fn options(&self) -> Result<ReplayOptions, String> {
+        let source = match self.source {
+            SourceChoice::Interface => ReplaySource::Interface,
+            SourceChoice::Original => ReplaySource::Original,
+            SourceChoice::Address => match self.source_address.trim().parse::<IpAddr>() {
+                Ok(address) => ReplaySource::Address(address),
+                Err(_) => return Err(format!("{} is not an IP address.", self.source_address.trim())),
+            },
+        };
+        Ok(ReplayOptions {
+            timing: if self.original_timing { ReplayTiming::Original } else { ReplayTiming::FixedRate(self.rate) },
+            source,
+            ip_ttl: if self.rewrite_ip_ttl { Some(self.ip_ttl) } else { None },
+            record_ttl: if self.rewrite_record_ttl { Some(self.record_ttl) } else { None },
+        })
+    }
+
+    fn start(&mut self, interfaces: &[NetworkInterface]) {
+        let options = match self.options() {
+            Ok(o) => o,
+            Err(e) => {
+                self.error = Some(e);
+                return;
+            }
+        };
+        let interface = match interfaces.iter().find(|i| Some(&i.name) == self.interface_name.as_ref()) {
+            Some(i) => i,
+            None => return
+        };
+        let packets = match &self.packets {
+            Some((_, packets)) => packets.clone(),
+            None => return
+        };
+        // A running replay is stopped by dropping it.
+        self.replay = None;
+        match Replay::start(interface, MulticastGroup::MDNS, packets, options) {
+            Ok(replay) => {
+                self.replay = Some(replay);
+                self.error = None;
+            }
+            Err(e) => self.error = Some(format!("Could not replay on {}: {}", interface.description, e)),
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use crate::mdns::query_sender::{QuerySenderConfig, SentQueryLog};
+use crate::mdns::resolver::{resolve, Resolution};
+use crate::mdnsexplorer_ui::message_store::MessageStore;
+use crate::mdnsexplorer_ui::timestamp_format::format_absolute;
+use eframe::egui;
+use std::io;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+/// What the user decided in the resolve window.
+pub enum ResolveAction {
+    /// Select the captured messages with the given sequence numbers.
+    SelectMessages(Vec<u64>),
+    Close,
+}
+
+/// Window resolving a `.local` name to its addresses, in place of `avahi-resolve`.
+pub struct ResolveWindow {
+    name: String,
+    timeout_seconds: f64,
+    /// Result of the resolve running in the background.
+    pending: Option<Receiver<io::Result<Resolution>>>,
+    resolution: Option<Resolution>,
+    error: Option<String>,
+}
+
+impl ResolveWindow {
+    pub fn new() -> Self {
+        Self {
+            name: String::new(),
+            timeout_seconds: 2.0,
+            pending: None,
+            resolution: None,
+            error: None,
+        }
+    }
+
+    /// `config` is where the query is sent, `None` for capture files. Answering messages are looked up in the `store`.
+    pub fn render(&mut self, ctx: &egui::Context, config: Option<QuerySenderConfig>, log: &SentQueryLog, store: &MessageStore) -> Option<ResolveAction> {
+        self.poll_pending();
+        let mut action = None;
+        egui::Window::new("Resolve name")
+            .collapsible(false)
+            .resizable(true)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Name");
+                    ui.add(egui::TextEdit::singleline(&mut self.name).desired_width(250.0).hint_text("printer.local"));
+                    ui.label("Timeout");
+                    ui.add(egui::DragValue::new(&mut self.timeout_seconds).range(0.1..=30.0).speed(0.1).suffix(" s"));
+                });
+                ui.horizontal(|ui| {
+                    let resolving = self.pending.is_some();
+                    let label = if resolving { "Resolving…" } else { "Resolve" };
+                    let resolve = ui.add_enabled(config.is_some() && !resolving, egui::Button::new(label))
+                        .on_disabled_hover_text("Queries are sent on the captured interface, select one instead of a capture file.");
+                    if resolve.clicked()
+                        && let Some(config) = config {
+                        self.start(config, log.clone());
+                    }
+                    if ui.button("Close").clicked() {
+                        action = Some(ResolveAction::Close);
+                    }
+                });
+                if let Some(error) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+                if let Some(resolution) = &self.resolution
+                    && let Some(selected) = Self::render_resolution(ui, resolution, store) {
+                    action = Some(ResolveAction::SelectMessages(selected));
+                }
+            });
+        if self.pending.is_some() {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+        action
+    }
+
+    fn start(&mut self, config: QuerySenderConfig, log: SentQueryLog) {
+        let name = self.name.trim().to_string();
+        if name.is_empty() {
+            self.error = Some("Enter the name to resolve.".to_string());
+            return;
+        }
+        let timeout = Duration::from_secs_f64(self.timeout_seconds);
+        let (sender, receiver) = mpsc::channel();
+        let spawned = thread::Builder::new()
+            .name("mDNS resolve".to_string())
+            .spawn(move || {
+                let _ = sender.send(resolve(&config, log, &name, timeout));
+            });
+        match spawned {
+            Ok(_) => {
+                self.pending = Some(receiver);
+                self.error = None;
+            }
+            Err(e) => self.error = Some(format!("Could not start resolving: {}", e)),
+        }
+    }
+
+    fn poll_pending(&mut self) {
+        let result = match &self.pending {
+            Some(receiver) => match receiver.try_recv() {
+                Ok(result) => result,
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => Err(io::Error::other("The resolve thread stopped unexpectedly.")),
+            },
+            None => return
+        };
+        self.pending = None;
+        match result {
+            Ok(resolution) => self.resolution = Some(resolution),
+            Err(e) => self.error = Some(format!("Could not resolve: {}", e)),
+        }
+    }
+
+    /// Shows the addresses and the captured responses. Returns the responses when the user wants them selected.
+    fn render_resolution(ui: &mut egui::Ui, resolution: &Resolution, store: &MessageStore) -> Option<Vec<u64>> {
+        ui.separator();
+        if resolution.addresses.is_empty() {
+            ui.label(format!("No responder gave an address for {}.", resolution.name));
+        } else {
+            egui::Grid::new("resolved_addresses").num_columns(4).striped(true).show(ui, |ui| {
+                for title in ["Address", "TTL", "Responder", "Latency"] {
+                    ui.strong(title);
+                }
+                ui.end_row();
+                for address in resolution.addresses.iter() {
+                    ui.label(address.address.to_string());
+                    ui.label(format!("{} s", address.ttl_seconds));
+                    ui.label(address.responder.to_string());
+                    ui.label(format!("{:.1} ms", address.latency.as_secs_f64() * 1000.0));
+                    ui.end_row();
+                }
+            });
+        }
+
+        ui.separator();
+        let answering: Vec<(u64, String)> = store.messages().iter().enumerate()
+            .filter(|(_, overview)| resolution.is_answered_by(&overview.message, overview.received_datetime))
+            .map(|(index, overview)| {
+                let description = format!("{}  {}", format_absolute(overview.received_datetime), overview.source_ip);
+                (store.first_sequence() + index as u64, description)
+            })
+            .collect();
+        if answering.is_empty() {
+            ui.label(format!("No captured message answered query #{}, the capture may be stopped or filtered.", resolution.query_id));
+            return None;
+        }
+        ui.label(format!("Captured responses to query #{}:", resolution.query_id));
+        for (_, description) in answering.iter() {
+            ui.label(description);
+        }
+        match ui.button("Select them in the table").clicked() {
+            true => Some(answering.into_iter().map(|(sequence, _)| sequence).collect()),
+            false => None,
+        }
+    }
+}
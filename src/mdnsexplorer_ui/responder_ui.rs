@@ -0,0 +1,200 @@
+use crate::mdns::responder::{PublishState, PublishedHost, PublishedName, PublishedRecords, PublishedService};
+use eframe::egui;
+use std::net::IpAddr;
+
+/// What the user decided in the responder window.
+pub enum ResponderAction {
+    /// Publish the records, replacing the ones published before.
+    Publish(PublishedRecords),
+    /// Stop publishing, sending goodbye packets.
+    Stop,
+    Close,
+}
+
+struct HostRow {
+    name: String,
+    /// Addresses separated by commas or spaces.
+    addresses: String,
+}
+
+struct ServiceRow {
+    instance: String,
+    service_type: String,
+    host: String,
+    port: u16,
+    /// One key=value pair per line.
+    txt: String,
+}
+
+/// Window for editing the host names and DNS-SD services published by the responder.
+pub struct ResponderWindow {
+    hosts: Vec<HostRow>,
+    services: Vec<ServiceRow>,
+    error: Option<String>,
+}
+
+impl ResponderWindow {
+    /// Starts with a host name for the given addresses, usually those of the captured interface.
+    pub fn new(addresses: &[IpAddr]) -> Self {
+        Self {
+            hosts: vec![HostRow {
+                name: "mdnsexplorer.local".to_string(),
+                addresses: addresses.iter().map(|a| a.to_string()).collect::<Vec<_>>().join(", "),
+            }],
+            services: vec![ServiceRow {
+                instance: "MDNS Explorer".to_string(),
+                service_type: "_http._tcp.local".to_string(),
+                host: "mdnsexplorer.local".to_string(),
+                port: 8080,
+                txt: "path=/".to_string(),
+            }],
+            error: None,
+        }
+    }
+
+    /// `can_publish` is false when there is no interface to publish on, `status` is set while the responder runs.
+    pub fn render(&mut self, ctx: &egui::Context, can_publish: bool, status: Option<&[PublishedName]>, responder_error: Option<&str>) -> Option<ResponderAction> {
+        let mut action = None;
+        egui::Window::new("Publish records")
+            .collapsible(false)
+            .resizable(true)
+            .show(ctx, |ui| {
+                ui.strong("Hosts");
+                let mut removed = None;
+                egui::Grid::new("responder_hosts").num_columns(3).show(ui, |ui| {
+                    ui.label("Name");
+                    ui.label("Addresses");
+                    ui.end_row();
+                    for (index, row) in self.hosts.iter_mut().enumerate() {
+                        ui.add(egui::TextEdit::singleline(&mut row.name).desired_width(200.0));
+                        ui.add(egui::TextEdit::singleline(&mut row.addresses).desired_width(300.0).hint_text("192.168.1.10, fe80::1"));
+                        if ui.button("Remove").clicked() {
+                            removed = Some(index);
+                        }
+                        ui.end_row();
+                    }
+                });
+                if let Some(index) = removed {
+                    self.hosts.remove(index);
+                }
+                if ui.button("Add host").clicked() {
+                    self.hosts.push(HostRow { name: String::new(), addresses: String::new() });
+                }
+                ui.separator();
+                ui.strong("Services");
+                let mut removed = None;
+                egui::Grid::new("responder_services").num_columns(6).show(ui, |ui| {
+                    for title in ["Instance", "Type", "Host", "Port", "TXT"] {
+                        ui.label(title);
+                    }
+                    ui.end_row();
+                    for (index, row) in self.services.iter_mut().enumerate() {
+                        ui.add(egui::TextEdit::singleline(&mut row.instance).desired_width(150.0));
+                        ui.add(egui::TextEdit::singleline(&mut row.service_type).desired_width(150.0));
+                        ui.add(egui::TextEdit::singleline(&mut row.host).desired_width(150.0));
+                        ui.add(egui::DragValue::new(&mut row.port).range(0..=u16::MAX));
+                        ui.add(egui::TextEdit::multiline(&mut row.txt).desired_rows(1).desired_width(150.0))
+                            .on_hover_text("One key=value pair per line.");
+                        if ui.button("Remove").clicked() {
+                            removed = Some(index);
+                        }
+                        ui.end_row();
+                    }
+                });
+                if let Some(index) = removed {
+                    self.services.remove(index);
+                }
+                if ui.button("Add service").clicked() {
+                    let host = self.hosts.first().map(|h| h.name.clone()).unwrap_or_default();
+                    self.services.push(ServiceRow {
+                        instance: String::new(),
+                        service_type: "_http._tcp.local".to_string(),
+                        host,
+                        port: 80,
+                        txt: String::new(),
+                    });
+                }
+                ui.separator();
+                ui.horizontal(|ui| {
+                    let label = if status.is_some() { "Update" } else { "Publish" };
+                    let publish = ui.add_enabled(can_publish, egui::Button::new(label))
+                        .on_disabled_hover_text("Records are published on the captured interface, select one instead of a capture file.");
+                    if publish.clicked() {
+                        match self.records() {
+                            Ok(records) => action = Some(ResponderAction::Publish(records)),
+                            Err(e) => self.error = Some(e),
+                        }
+                    }
+                    if status.is_some() && ui.button("Stop").clicked() {
+                        action = Some(ResponderAction::Stop);
+                    }
+                    if ui.button("Close").clicked() {
+                        action = Some(ResponderAction::Close);
+                    }
+                });
+                if let Some(error) = self.error.as_deref().or(responder_error) {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+                if let Some(status) = status {
+                    Self::render_status(ui, status);
+                }
+            });
+        action
+    }
+
+    fn render_status(ui: &mut egui::Ui, status: &[PublishedName]) {
+        ui.separator();
+        egui::Grid::new("responder_status").num_columns(3).striped(true).show(ui, |ui| {
+            for title in ["Requested", "Published as", "State"] {
+                ui.strong(title);
+            }
+            ui.end_row();
+            for name in status {
+                ui.label(&name.requested);
+                match name.conflicts {
+                    0 => ui.label(&name.name),
+                    conflicts => ui.colored_label(ui.visuals().warn_fg_color, &name.name)
+                        .on_hover_text(format!("Renamed after {} conflicts with other hosts.", conflicts)),
+                };
+                match name.state {
+                    PublishState::Announced => ui.label(name.state.to_string()),
+                    _ => ui.colored_label(ui.visuals().weak_text_color(), name.state.to_string()),
+                };
+                ui.end_row();
+            }
+        });
+    }
+
+    fn records(&mut self) -> Result<PublishedRecords, String> {
+        let mut records = PublishedRecords::default();
+        for row in self.hosts.iter() {
+            let name = row.name.trim();
+            if name.is_empty() {
+                return Err("Enter a name for every host.".to_string());
+            }
+            let addresses = row.addresses.split([',', ' '])
+                .filter(|a| !a.is_empty())
+                .map(|a| a.parse::<IpAddr>().map_err(|_| format!("Host {}: {} is not an IP address.", name, a)))
+                .collect::<Result<Vec<IpAddr>, String>>()?;
+            if addresses.is_empty() {
+                return Err(format!("Enter at least one address for host {}.", name));
+            }
+            records.hosts.push(PublishedHost { name: name.to_string(), addresses });
+        }
+        for row in self.services.iter() {
+            let instance = row.instance.trim();
+            if instance.is_empty() || row.service_type.trim().is_empty() || row.host.trim().is_empty() {
+                return Err("Enter the instance, type and host of every service.".to_string());
+            }
+            records.services.push(PublishedService {
+                instance: instance.to_string(),
+                service_type: row.service_type.trim().to_string(),
+                host: row.host.trim().to_string(),
+                port: row.port,
+                txt: row.txt.trim().to_string(),
+            });
+        }
+        self.error = None;
+        Ok(records)
+    }
+}
@@ -0,0 +1,99 @@
+use crate::mdnsexplorer_ui::message_store::{RetentionPolicy, RetentionState};
+use eframe::egui;
+use std::path::PathBuf;
+use time::{Duration, OffsetDateTime};
+
+const BYTES_PER_MEGABYTE: usize = 1024 * 1024;
+
+/// What the user decided in the retention window.
+pub enum RetentionAction {
+    /// Use the policy and spill evicted messages to the path, or stop spilling when it is `None`.
+    Apply(RetentionPolicy, Option<PathBuf>),
+    /// Open a session file written earlier.
+    OpenSession(PathBuf),
+    Close,
+}
+
+/// Window for editing the retention policy. Values are only applied on confirmation.
+pub struct RetentionSettingsWindow {
+    limit_messages: bool,
+    max_messages: usize,
+    limit_age: bool,
+    max_age_minutes: u32,
+    limit_bytes: bool,
+    max_megabytes: usize,
+    spill: bool,
+    spill_path: String,
+}
+
+impl RetentionSettingsWindow {
+    pub fn new(policy: RetentionPolicy, state: &RetentionState) -> Self {
+        let defaults = RetentionPolicy::default();
+        let spill_path = match &state.spill_path {
+            Some(p) => p.display().to_string(),
+            None => Self::default_session_path().display().to_string(),
+        };
+        Self {
+            limit_messages: policy.max_messages.is_some(),
+            max_messages: policy.max_messages.or(defaults.max_messages).unwrap_or(100_000),
+            limit_age: policy.max_age.is_some(),
+            max_age_minutes: policy.max_age.map_or(60, |a| a.whole_minutes().max(1) as u32),
+            limit_bytes: policy.max_bytes.is_some(),
+            max_megabytes: policy.max_bytes.or(defaults.max_bytes).map_or(512, |b| b / BYTES_PER_MEGABYTE),
+            spill: state.spill_path.is_some(),
+            spill_path,
+        }
+    }
+
+    pub fn render(&mut self, ctx: &egui::Context) -> Option<RetentionAction> {
+        let mut action = None;
+        egui::Window::new("Retention")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("retention_settings").num_columns(2).show(ui, |ui| {
+                    ui.checkbox(&mut self.limit_messages, "Keep at most");
+                    ui.add_enabled(self.limit_messages, egui::DragValue::new(&mut self.max_messages).range(1..=10_000_000).suffix(" messages"));
+                    ui.end_row();
+                    ui.checkbox(&mut self.limit_age, "Keep the last");
+                    ui.add_enabled(self.limit_age, egui::DragValue::new(&mut self.max_age_minutes).range(1..=100_000).suffix(" minutes"));
+                    ui.end_row();
+                    ui.checkbox(&mut self.limit_bytes, "Use at most");
+                    ui.add_enabled(self.limit_bytes, egui::DragValue::new(&mut self.max_megabytes).range(1..=100_000).suffix(" MB"));
+                    ui.end_row();
+                });
+                ui.separator();
+                ui.checkbox(&mut self.spill, "Write evicted messages to a session file");
+                ui.add_enabled(self.spill, egui::TextEdit::singleline(&mut self.spill_path));
+                ui.horizontal(|ui| {
+                    if ui.button("Apply").clicked() {
+                        let spill_path = match self.spill && !self.spill_path.trim().is_empty() {
+                            true => Some(PathBuf::from(self.spill_path.trim())),
+                            false => None,
+                        };
+                        action = Some(RetentionAction::Apply(self.policy(), spill_path));
+                    }
+                    if ui.button("Open session file").clicked() && !self.spill_path.trim().is_empty() {
+                        action = Some(RetentionAction::OpenSession(PathBuf::from(self.spill_path.trim())));
+                    }
+                    if ui.button("Close").clicked() {
+                        action = Some(RetentionAction::Close);
+                    }
+                });
+            });
+        action
+    }
+
+    fn policy(&self) -> RetentionPolicy {
+        RetentionPolicy {
+            max_messages: if self.limit_messages { Some(self.max_messages) } else { None },
+            max_age: if self.limit_age { Some(Duration::minutes(self.max_age_minutes as i64)) } else { None },
+            max_bytes: if self.limit_bytes { Some(self.max_megabytes * BYTES_PER_MEGABYTE) } else { None },
+        }
+    }
+
+    fn default_session_path() -> PathBuf {
+        let started = OffsetDateTime::now_utc().unix_timestamp();
+        std::env::temp_dir().join(format!("mdnsexplorer-session-{}.pcapng", started))
+    }
+}
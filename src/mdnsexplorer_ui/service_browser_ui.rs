@@ -0,0 +1,156 @@
+use crate::mdns::mdns_message::MDNSMessage;
+use crate::mdns::query_sender::{Query, QuerySender, QuerySenderConfig, SentQueryLog};
+use crate::mdns::service_browser::{enumerated_service_types, ServiceBrowser, SERVICE_TYPE_ENUMERATION};
+use crate::mdns::types::MDNSRecordType;
+use crate::mdnsexplorer_ui::message_store::MessageStore;
+use crate::mdnsexplorer_ui::timestamp_format::format_absolute;
+use eframe::egui;
+use pnet::datalink::NetworkInterface;
+use std::collections::BTreeSet;
+use std::net::IpAddr;
+use time::OffsetDateTime;
+
+/// Number of browse events listed, newest first.
+const SHOWN_EVENTS: usize = 200;
+
+/// Tab which browses a DNS-SD service type and resolves its instances, like `dns-sd -B` and `-L` do.
+pub struct ServiceBrowserView {
+    service_type: String,
+    /// Service types seen in responses to the enumeration query.
+    service_types: BTreeSet<String>,
+    browser: Option<ServiceBrowser>,
+    sender: Option<QuerySender>,
+    error: Option<String>,
+}
+
+impl ServiceBrowserView {
+    pub fn new() -> Self {
+        Self {
+            service_type: "_googlecast._tcp.local".to_string(),
+            service_types: BTreeSet::new(),
+            browser: None,
+            sender: None,
+            error: None,
+        }
+    }
+
+    /// Passes a captured message to the running browse.
+    pub fn observe(&mut self, message: &MDNSMessage, received: OffsetDateTime, source_ip: IpAddr) {
+        self.service_types.extend(enumerated_service_types(message));
+        if let Some(browser) = &mut self.browser {
+            browser.observe(message, received, source_ip);
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.browser = None;
+        self.sender = None;
+    }
+
+    /// Starts browsing on the interface. Responses already in the store are taken into account right away.
+    fn start(&mut self, interface: &NetworkInterface, log: &SentQueryLog, store: &MessageStore) {
+        self.stop();
+        let sender = match QuerySender::open(&QuerySenderConfig::for_interface(interface), log.clone()) {
+            Ok(s) => s,
+            Err(e) => {
+                self.error = Some(format!("Could not send queries on {}: {}", interface.description, e));
+                return;
+            }
+        };
+        let now = OffsetDateTime::now_utc();
+        let mut browser = ServiceBrowser::new(&self.service_type, now);
+        for overview in store.messages() {
+            browser.observe(&overview.message, overview.received_datetime, overview.source_ip);
+        }
+        browser.expire(now);
+        self.browser = Some(browser);
+        self.sender = Some(sender);
+        self.error = None;
+    }
+
+    /// Sends the queries which are due. Called every frame, whichever tab is shown.
+    pub fn update(&mut self) {
+        let (browser, sender) = match (&mut self.browser, &self.sender) {
+            (Some(b), Some(s)) => (b, s),
+            _ => return
+        };
+        let now = OffsetDateTime::now_utc();
+        browser.expire(now);
+        for query in browser.due_queries(now) {
+            if let Err(e) = sender.send(&query) {
+                self.error = Some(format!("Could not send query: {}", e));
+            }
+        }
+    }
+
+    /// `interface` is where queries are sent, browsing is not possible for capture files.
+    pub fn render(&mut self, ui: &mut egui::Ui, interface: Option<&NetworkInterface>, log: &SentQueryLog, store: &MessageStore) {
+        ui.horizontal(|ui| {
+            ui.label("Service type");
+            ui.add(egui::TextEdit::singleline(&mut self.service_type).desired_width(250.0));
+            egui::ComboBox::from_id_salt("service_types")
+                .selected_text("Discovered types")
+                .show_ui(ui, |ui| {
+                    for service_type in self.service_types.iter() {
+                        ui.selectable_value(&mut self.service_type, service_type.clone(), service_type);
+                    }
+                });
+            let browsing = self.browser.is_some();
+            if browsing {
+                if ui.button("Stop").clicked() {
+                    self.stop();
+                }
+            } else if let Some(interface) = interface {
+                if ui.button("Browse").clicked() && !self.service_type.trim().is_empty() {
+                    self.start(interface, log, store);
+                }
+                if ui.button("Discover service types").clicked() {
+                    let query = Query::new(SERVICE_TYPE_ENUMERATION, MDNSRecordType::PTR, false);
+                    let result = QuerySender::open(&QuerySenderConfig::for_interface(interface), log.clone()).and_then(|s| s.send(&query));
+                    self.error = result.err().map(|e| format!("Could not send the enumeration query: {}", e));
+                }
+            } else {
+                ui.label("Browsing sends queries on the captured interface, select one instead of a capture file.");
+            }
+        });
+        if let Some(error) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+        let browser = match &self.browser {
+            Some(b) => b,
+            None => return
+        };
+        ui.separator();
+        ui.strong(format!("Instances of {}", browser.service_type()));
+        let now = OffsetDateTime::now_utc();
+        egui::ScrollArea::vertical().id_salt("service_instances").max_height(ui.available_height() * 0.6).show(ui, |ui| {
+            egui::Grid::new("service_instances_grid").num_columns(7).striped(true).show(ui, |ui| {
+                for title in ["Instance", "Host", "Port", "Addresses", "TXT", "Responder", "Expires in"] {
+                    ui.strong(title);
+                }
+                ui.end_row();
+                for instance in browser.instances() {
+                    let label = browser.instance_label(instance);
+                    match instance.is_resolved() {
+                        true => ui.label(label),
+                        false => ui.colored_label(ui.visuals().weak_text_color(), label).on_hover_text("Not resolved yet."),
+                    };
+                    ui.label(instance.host.as_deref().unwrap_or("-"));
+                    ui.label(instance.port.map_or("-".to_string(), |p| p.to_string()));
+                    ui.label(instance.addresses.iter().map(|a| a.to_string()).collect::<Vec<_>>().join("\n"));
+                    ui.label(instance.txt.as_deref().unwrap_or("-"));
+                    ui.label(instance.responder.to_string());
+                    ui.label(format!("{} s", (instance.expires - now).whole_seconds().max(0)));
+                    ui.end_row();
+                }
+            });
+        });
+        ui.separator();
+        ui.strong("Changes");
+        egui::ScrollArea::vertical().id_salt("browse_events").show(ui, |ui| {
+            for event in browser.events().iter().rev().take(SHOWN_EVENTS) {
+                ui.label(format!("{}  {}  {}", format_absolute(event.time), event.kind, event.instance));
+            }
+        });
+    }
+}
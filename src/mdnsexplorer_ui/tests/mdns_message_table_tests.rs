@@ -0,0 +1,72 @@
+#[cfg(test)]
+pub mod mdns_message_table_tests {
+    use crate::mdns::mdns_message::Protocol;
+    use crate::mdnsexplorer_ui::mdns_message_table::MdnsMessageTable;
+    use crate::mdnsexplorer_ui::message_store::{MessageStore, RetentionPolicy};
+    use crate::mdnsexplorer_ui::tests::message_store_tests::message_store_tests::{protocol_overview, START};
+
+    fn store_with_mixed_protocols() -> MessageStore {
+        let mut store = MessageStore::new(RetentionPolicy::default());
+        store.extend([
+            protocol_overview(Protocol::Mdns, 0),
+            protocol_overview(Protocol::Llmnr, 1),
+            protocol_overview(Protocol::Mdns, 2),
+        ]);
+        store
+    }
+
+    #[test]
+    fn sync__when_protocol_hidden__skips_its_messages() {
+        let store = store_with_mixed_protocols();
+        let mut table = MdnsMessageTable::new();
+
+        table.set_protocol_visible(Protocol::Llmnr, false);
+        table.sync(&store);
+
+        assert_eq!(table.row_count(), 2);
+    }
+
+    #[test]
+    fn sync__when_protocol_shown_again__formats_all_messages() {
+        let store = store_with_mixed_protocols();
+        let mut table = MdnsMessageTable::new();
+        table.set_protocol_visible(Protocol::Mdns, false);
+        table.sync(&store);
+
+        table.set_protocol_visible(Protocol::Mdns, true);
+        table.sync(&store);
+
+        assert_eq!(table.row_count(), 3);
+    }
+
+    #[test]
+    fn sync__when_messages_evicted__drops_their_rows() {
+        let mut store = store_with_mixed_protocols();
+        let mut table = MdnsMessageTable::new();
+        table.set_protocol_visible(Protocol::Llmnr, false);
+        table.sync(&store);
+
+        store.set_policy(RetentionPolicy { max_messages: Some(1), ..RetentionPolicy::default() });
+        store.extend([protocol_overview(Protocol::Llmnr, 3)]);
+        table.sync(&store);
+
+        assert_eq!(table.row_count(), 0);
+    }
+
+    #[test]
+    fn sync__when_selected_messages_evicted__unselects_them_and_keeps_the_focus() {
+        let mut store = store_with_mixed_protocols();
+        let mut table = MdnsMessageTable::new();
+        table.sync(&store);
+        table.select_rows(&[2, 0]);
+
+        store.set_policy(RetentionPolicy { max_messages: Some(2), ..RetentionPolicy::default() });
+        store.extend([protocol_overview(Protocol::Mdns, 3)]);
+        table.sync(&store);
+
+        let selected: Vec<i64> = table.selected_overviews(&store).iter().map(|o| o.received_datetime.unix_timestamp() - START).collect();
+        assert_eq!(selected, vec![2]);
+        let focused = table.focused_overview(&store).map(|o| o.received_datetime.unix_timestamp() - START);
+        assert_eq!(focused, Some(2));
+    }
+}
@@ -0,0 +1,32 @@
+#[cfg(test)]
+pub mod message_crafter_ui_tests {
+    use crate::mdns::capture::{decode_frame, LinkType};
+    use crate::mdns::packet_builder::build_udp_packet;
+    use crate::mdnsexplorer_ui::mdns_message_table::MdnsMessageOverview;
+    use crate::mdnsexplorer_ui::message_crafter_ui::MessageCrafterWindow;
+    use crate::mdnsexplorer_ui::tests::message_store_tests::message_store_tests::START;
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use std::sync::Arc;
+    use time::OffsetDateTime;
+
+    #[test]
+    fn from_overview__when_message_has_opt_record__sends_the_captured_payload() {
+        let payload: Vec<u8> = [
+            // Header: query with one question and one additional record.
+            &[0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 1][..],
+            // printer.local A, class IN.
+            &[7, b'p', b'r', b'i', b'n', b't', b'e', b'r', 5, b'l', b'o', b'c', b'a', b'l', 0, 0, 1, 0, 1],
+            // OPT record for a 1440 byte payload with the DO bit set.
+            &[0, 0, 41, 0x05, 0xa0, 0, 0, 0x80, 0, 0, 0],
+        ].concat();
+        let source = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10)), 5353);
+        let destination = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(224, 0, 0, 251)), 5353);
+        let packet = build_udp_packet(source, destination, 255, &payload).unwrap();
+        let received = OffsetDateTime::from_unix_timestamp(START).unwrap();
+        let overview = MdnsMessageOverview::new(decode_frame(LinkType::RawIp, &packet, received, &Arc::from("eth0")).unwrap());
+
+        let window = MessageCrafterWindow::from_overview(&overview);
+
+        assert_eq!(window.payload(), Ok(payload));
+    }
+}
@@ -0,0 +1,123 @@
+#[cfg(test)]
+pub mod message_store_tests {
+    use crate::mdns::capture::{decode_frame, LinkType};
+    use crate::mdns::mdns_message::Protocol;
+    use crate::mdns::packet_builder::build_udp_packet;
+    use crate::mdns::pcap_reader::PcapReader;
+    use crate::mdns::tests::parser_tests::parser_tests::RESOLVE_SPOTIFY_MDNS_PAYLOAD;
+    use crate::mdnsexplorer_ui::mdns_message_table::MdnsMessageOverview;
+    use crate::mdnsexplorer_ui::message_store::{MessageStore, RetentionPolicy};
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use std::sync::Arc;
+    use time::{Duration, OffsetDateTime};
+
+    // 2024-12-01 10:00:00 UTC
+    pub const START: i64 = 1_733_047_200;
+
+    const UNLIMITED: RetentionPolicy = RetentionPolicy {
+        max_messages: None,
+        max_age: None,
+        max_bytes: None,
+    };
+
+    fn overview(seconds: i64) -> MdnsMessageOverview {
+        protocol_overview(Protocol::Mdns, seconds)
+    }
+
+    /// A query received `seconds` after `START` on the port of the protocol.
+    pub fn protocol_overview(protocol: Protocol, seconds: i64) -> MdnsMessageOverview {
+        let source = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10)), protocol.port());
+        let destination = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(224, 0, 0, 251)), protocol.port());
+        let packet = build_udp_packet(source, destination, 255, &RESOLVE_SPOTIFY_MDNS_PAYLOAD).unwrap();
+        let received = OffsetDateTime::from_unix_timestamp(START + seconds).unwrap();
+        MdnsMessageOverview::new(decode_frame(LinkType::RawIp, &packet, received, &Arc::from("eth0")).unwrap())
+    }
+
+    fn retained_seconds(store: &MessageStore) -> Vec<i64> {
+        store.messages().iter().map(|o| o.received_datetime.unix_timestamp() - START).collect()
+    }
+
+    #[test]
+    fn extend__when_max_messages_exceeded__evicts_oldest() {
+        let mut store = MessageStore::new(RetentionPolicy { max_messages: Some(2), ..UNLIMITED });
+
+        store.extend((0..5).map(overview));
+
+        assert_eq!(retained_seconds(&store), vec![3, 4]);
+        assert_eq!(store.first_sequence(), 3);
+        assert_eq!(store.state().evicted, 3);
+    }
+
+    #[test]
+    fn extend__when_max_age_exceeded__evicts_messages_older_than_newest_minus_age() {
+        let mut store = MessageStore::new(RetentionPolicy { max_age: Some(Duration::seconds(60)), ..UNLIMITED });
+
+        store.extend([0, 30, 100, 150].map(overview));
+
+        assert_eq!(retained_seconds(&store), vec![100, 150]);
+    }
+
+    #[test]
+    fn extend__when_max_bytes_exceeded__keeps_messages_within_limit() {
+        let size = overview(0).approximate_size();
+        let mut store = MessageStore::new(RetentionPolicy { max_bytes: Some(size * 3), ..UNLIMITED });
+
+        store.extend((0..10).map(overview));
+
+        assert_eq!(retained_seconds(&store), vec![7, 8, 9]);
+        assert_eq!(store.state().bytes, size * 3);
+    }
+
+    #[test]
+    fn get__when_older_messages_evicted__returns_message_by_sequence() {
+        let mut store = MessageStore::new(RetentionPolicy { max_messages: Some(2), ..UNLIMITED });
+        store.extend((0..4).map(overview));
+
+        assert!(store.get(1).is_none());
+        assert_eq!(store.get(3).unwrap().received_datetime.unix_timestamp() - START, 3);
+        assert!(store.get(4).is_none());
+    }
+
+    #[test]
+    fn clear__when_messages_retained__advances_sequence_without_counting_evictions() {
+        let mut store = MessageStore::new(UNLIMITED);
+        store.extend((0..3).map(overview));
+
+        store.clear();
+
+        assert!(store.messages().is_empty());
+        assert_eq!(store.first_sequence(), 3);
+        assert_eq!(store.state().evicted, 0);
+        assert_eq!(store.state().bytes, 0);
+    }
+
+    #[test]
+    fn extend__when_spilling__writes_evicted_messages_to_readable_session_file() {
+        let path = std::env::temp_dir().join(format!("mdnsexplorer-session-test-{}.pcapng", std::process::id()));
+        let mut store = MessageStore::new(RetentionPolicy { max_messages: Some(1), ..UNLIMITED });
+        store.start_spill(&path).unwrap();
+
+        store.extend((0..3).map(overview));
+
+        assert_eq!(store.state().spilled, 2);
+        let mut reader = PcapReader::open(&path).unwrap();
+        let mut timestamps = vec![];
+        while let Some(frame) = reader.next_frame().unwrap() {
+            timestamps.push(frame.timestamp.unwrap().unix_timestamp() - START);
+        }
+        store.stop_spill();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(timestamps, vec![0, 1]);
+    }
+
+    #[test]
+    fn session_start__when_oldest_evicted__keeps_first_message_time() {
+        let mut store = MessageStore::new(RetentionPolicy { max_messages: Some(1), ..UNLIMITED });
+
+        store.extend((2..5).map(overview));
+
+        assert_eq!(store.session_start().map(|s| s.unix_timestamp() - START), Some(2));
+        store.clear();
+        assert_eq!(store.session_start(), None);
+    }
+}
@@ -0,0 +1,4 @@
+pub mod message_store_tests;
+pub mod timestamp_format_tests;
+pub mod mdns_message_table_tests;
+pub mod message_crafter_ui_tests;
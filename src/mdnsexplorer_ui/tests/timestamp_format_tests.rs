@@ -0,0 +1,49 @@
+#[cfg(test)]
+pub mod timestamp_format_tests {
+    use crate::mdnsexplorer_ui::timestamp_format::{format_absolute, format_seconds, TimestampFormat};
+    use crate::mdnsexplorer_ui::tests::message_store_tests::message_store_tests::START;
+    use time::{Duration, OffsetDateTime, UtcOffset};
+
+    fn at(seconds: i64, nanoseconds: i64) -> OffsetDateTime {
+        OffsetDateTime::from_unix_timestamp(START + seconds).unwrap() + Duration::nanoseconds(nanoseconds)
+    }
+
+    #[test]
+    fn format__when_utc__keeps_nanoseconds() {
+        let formatted = TimestampFormat::Utc.format(at(0, 123_456_789), None, None);
+
+        assert_eq!(formatted, "2024-12-01 10:00:00.123456789");
+    }
+
+    #[test]
+    fn format_absolute__when_offset_is_not_utc__uses_the_offset() {
+        let timestamp = at(0, 5_000).to_offset(UtcOffset::from_hms(2, 0, 0).unwrap());
+
+        assert_eq!(format_absolute(timestamp), "2024-12-01 12:00:00.000005000");
+    }
+
+    #[test]
+    fn format__when_since_start__counts_from_the_start() {
+        let formatted = TimestampFormat::SinceStart.format(at(12, 500_000), Some(at(0, 0)), Some(at(11, 0)));
+
+        assert_eq!(formatted, "12.000500000");
+    }
+
+    #[test]
+    fn format__when_delta__counts_from_the_previous_message() {
+        let formatted = TimestampFormat::Delta.format(at(12, 500_000), Some(at(0, 0)), Some(at(11, 0)));
+
+        assert_eq!(formatted, "+1.000500000");
+    }
+
+    #[test]
+    fn format__when_relative_without_reference__returns_zero() {
+        assert_eq!(TimestampFormat::SinceStart.format(at(3, 0), None, None), "0.000000000");
+        assert_eq!(TimestampFormat::Delta.format(at(3, 0), None, None), "+0.000000000");
+    }
+
+    #[test]
+    fn format_seconds__when_negative__keeps_the_sign() {
+        assert_eq!(format_seconds(Duration::milliseconds(-1_500)), "-1.500000000");
+    }
+}
@@ -0,0 +1,96 @@
+use std::fmt::{Display, Formatter};
+use time::{Duration, OffsetDateTime, UtcOffset};
+
+/// How the time column presents the receive timestamps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampFormat {
+    Utc,
+    Local,
+    /// Seconds since the first message of the session.
+    SinceStart,
+    /// Seconds since the previous message.
+    Delta,
+}
+
+impl TimestampFormat {
+    pub const ALL: [TimestampFormat; 4] = [TimestampFormat::Utc, TimestampFormat::Local, TimestampFormat::SinceStart, TimestampFormat::Delta];
+
+    pub fn column_title(self) -> &'static str {
+        match self {
+            TimestampFormat::Utc => "Time (UTC)",
+            TimestampFormat::Local => "Time (local)",
+            TimestampFormat::SinceStart => "Since start [s]",
+            TimestampFormat::Delta => "Delta [s]",
+        }
+    }
+
+    /// Formats `received` with nanosecond precision. `start` and `previous` are only used by the relative formats,
+    /// a missing reference is treated as the timestamp itself.
+    pub fn format(self, received: OffsetDateTime, start: Option<OffsetDateTime>, previous: Option<OffsetDateTime>) -> String {
+        match self {
+            TimestampFormat::Utc => format_absolute(received),
+            TimestampFormat::Local => format_absolute(received.to_offset(local_offset_at(received))),
+            TimestampFormat::SinceStart => format_seconds(received - start.unwrap_or(received)),
+            TimestampFormat::Delta => format!("+{}", format_seconds(received - previous.unwrap_or(received))),
+        }
+    }
+}
+
+impl Display for TimestampFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let format = match self {
+            TimestampFormat::Utc => "UTC",
+            TimestampFormat::Local => "Local time",
+            TimestampFormat::SinceStart => "Seconds since start",
+            TimestampFormat::Delta => "Delta to previous message",
+        };
+        write!(f, "{}", format)
+    }
+}
+
+/// Date and time of day with nanoseconds, e.g. `2024-12-01 10:00:00.123456789`.
+pub fn format_absolute(timestamp: OffsetDateTime) -> String {
+    format!(
+        "{} {:02}:{:02}:{:02}.{:09}",
+        timestamp.date(),
+        timestamp.hour(),
+        timestamp.minute(),
+        timestamp.second(),
+        timestamp.nanosecond()
+    )
+}
+
+/// Seconds with nanoseconds, e.g. `12.000500000`.
+pub fn format_seconds(duration: Duration) -> String {
+    let sign = if duration.is_negative() { "-" } else { "" };
+    let duration = duration.abs();
+    format!("{}{}.{:09}", sign, duration.whole_seconds(), duration.subsec_nanoseconds())
+}
+
+/// The local UTC offset in effect at the given time. Falls back to UTC when it cannot be determined.
+#[cfg(unix)]
+pub fn local_offset_at(timestamp: OffsetDateTime) -> UtcOffset {
+    let seconds = timestamp.unix_timestamp() as libc::time_t;
+    let mut local: libc::tm = unsafe { std::mem::zeroed() };
+    let result = unsafe { libc::localtime_r(&seconds, &mut local) };
+    if result.is_null() {
+        return UtcOffset::UTC;
+    }
+    UtcOffset::from_whole_seconds(local.tm_gmtoff as i32).unwrap_or(UtcOffset::UTC)
+}
+
+/// The current local UTC offset. Windows only reports the rules in effect now, which are applied to any timestamp.
+#[cfg(windows)]
+pub fn local_offset_at(_timestamp: OffsetDateTime) -> UtcOffset {
+    use winapi::um::timezoneapi::{GetTimeZoneInformation, TIME_ZONE_INFORMATION};
+    use winapi::um::winnt::TIME_ZONE_ID_DAYLIGHT;
+
+    let mut information: TIME_ZONE_INFORMATION = unsafe { std::mem::zeroed() };
+    let zone = unsafe { GetTimeZoneInformation(&mut information) };
+    // The bias is what has to be added to local time to get UTC, in minutes.
+    let mut bias = information.Bias;
+    if zone == TIME_ZONE_ID_DAYLIGHT {
+        bias += information.DaylightBias;
+    }
+    UtcOffset::from_whole_seconds(-bias * 60).unwrap_or(UtcOffset::UTC)
+}